@@ -35,14 +35,15 @@ use util::{ResultExt, debug_panic};
 use crate::InspectorElementRegistry;
 use crate::{
     Action, ActionBuildError, ActionRegistry, Any, AnyView, AnyWindowHandle, AppContext, Asset,
-    AssetSource, BackgroundExecutor, Bounds, ClipboardItem, CursorStyle, DispatchPhase, DisplayId,
-    EventEmitter, FocusHandle, FocusMap, ForegroundExecutor, Global, KeyBinding, KeyContext,
-    Keymap, Keystroke, LayoutId, Menu, MenuItem, OwnedMenu, PathPromptOptions, Pixels, Platform,
-    PlatformDisplay, PlatformKeyboardLayout, PlatformKeyboardMapper, Point, Priority,
-    PromptBuilder, PromptButton, PromptHandle, PromptLevel, Render, RenderImage,
-    RenderablePromptHandle, Reservation, SharedString, SubscriberSet,
-    Subscription, SvgRenderer, Task, TextSystem, Window, WindowAppearance, WindowHandle, WindowId,
-    WindowInvalidator, current_platform,
+    AssetSource, BackgroundExecutor, Bounds, ClipboardFormat, ClipboardItem, CursorStyle,
+    DispatchPhase, DisplayId, EventEmitter, FocusHandle, FocusMap, ForegroundExecutor, Global,
+    GpuError, GpuHandle, GpuMemoryUsage, KeyBinding, KeyContext, Keymap, Keystroke, LayoutId, Menu,
+    MenuItem, OwnedMenu, PathPromptOptions, Pixels, Platform, PlatformDisplay,
+    PlatformKeyboardLayout, PlatformKeyboardMapper, Point, PowerState, Priority, PromptBuilder,
+    PromptButton, PromptHandle, PromptLevel, Render, RenderImage, RenderPipelinePlugin,
+    RenderablePromptHandle, RendererSettings, Reservation, SharedString, SubscriberSet,
+    Subscription, SvgRenderer, SystemAppearance, Task, TextSystem, Window, WindowAppearance,
+    WindowHandle, WindowId, WindowInvalidator, current_platform,
     default_colors::{Colors, GlobalColors},
     hash, init_app_menus,
 };
@@ -177,6 +178,34 @@ impl Application {
         self
     }
 
+    /// Sets the directory searched for helper binaries by
+    /// [`Application::path_for_auxiliary_executable`], in addition to the directory containing
+    /// the application's executable. Defaults to `../libexec` relative to the executable.
+    pub fn with_auxiliary_executable_dir(self, dir: impl Into<PathBuf>) -> Self {
+        self.0
+            .borrow()
+            .platform
+            .set_auxiliary_executable_dir(dir.into());
+        self
+    }
+
+    /// Sets the settings the renderer starts with. Environment variable overrides still take
+    /// precedence, see [`RendererSettings`].
+    pub fn with_renderer_settings(self, settings: RendererSettings) -> Self {
+        self.0.borrow().platform.set_renderer_settings(settings);
+        self
+    }
+
+    /// Quits the application when the process receives a termination signal, such as SIGTERM,
+    /// SIGINT or SIGHUP on Unix or a console control event on Windows, after running the close
+    /// callbacks of its windows and the quit callbacks. Without it, the signal's default action
+    /// applies. A process can only have one such handler, so applications that install their own
+    /// shouldn't call this.
+    pub fn with_termination_signal_handler(self) -> Self {
+        self.0.borrow().platform.handle_termination_signals();
+        self
+    }
+
     /// Start the application. The provided callback will be called once the
     /// app is fully launched.
     pub fn run<F>(self, on_finish_launching: F)
@@ -191,6 +220,37 @@ impl Application {
         }));
     }
 
+    /// Starts the application without handing it the main thread, for applications that own
+    /// their main loop, such as game engines and test harnesses. Drive it with
+    /// [`Application::pump_events`] or [`Application::step_frame`] from then on. The provided
+    /// callback will be called during one of them, once the app is fully launched.
+    pub fn start<F>(&self, on_finish_launching: F)
+    where
+        F: 'static + FnOnce(&mut App),
+    {
+        let this = self.0.clone();
+        let platform = self.0.borrow().platform.clone();
+        platform.start(Box::new(move || {
+            let cx = &mut *this.borrow_mut();
+            on_finish_launching(cx);
+        }));
+    }
+
+    /// Processes the events of an application started with [`Application::start`], waiting at
+    /// most `timeout` for new ones. Returns false once the application has quit, after which it
+    /// mustn't be pumped again.
+    pub fn pump_events(&self, timeout: Option<Duration>) -> bool {
+        let platform = self.0.borrow().platform.clone();
+        platform.pump_events(timeout)
+    }
+
+    /// Processes the pending events of an application started with [`Application::start`]
+    /// without waiting for new ones, which lets its windows draw a frame if they need one.
+    /// Returns false once the application has quit.
+    pub fn step_frame(&self) -> bool {
+        self.pump_events(Some(Duration::ZERO))
+    }
+
     /// Register a handler to be invoked when the platform instructs the application
     /// to open one or more URLs.
     pub fn on_open_urls<F>(&self, mut callback: F) -> &Self
@@ -216,6 +276,59 @@ impl Application {
         self
     }
 
+    /// Makes this the only running instance of the application, so launching it again invokes the
+    /// [`Application::on_reopen`] handler of this instance. Returns false if another instance is
+    /// already running, in which case it has been asked to reopen and this one should exit.
+    pub fn claim_single_instance(&self) -> bool {
+        self.0.borrow().platform.claim_single_instance()
+    }
+
+    /// Register a handler to be invoked when the GPU device reports an error that wasn't
+    /// handled where it occurred, such as a validation failure or running out of memory.
+    /// Without a handler, these errors are logged.
+    pub fn on_gpu_error<F>(&self, mut callback: F) -> &Self
+    where
+        F: 'static + FnMut(GpuError, &mut App),
+    {
+        let this = Rc::downgrade(&self.0);
+        self.0
+            .borrow_mut()
+            .platform
+            .on_gpu_error(Box::new(move |error| {
+                if let Some(app) = this.upgrade() {
+                    callback(error, &mut app.borrow_mut());
+                }
+            }));
+        self
+    }
+
+    /// Register a handler to be invoked with each event winit delivers to a window, before GPUI
+    /// translates it, for platform-specific events GPUI doesn't handle. Returning true from the
+    /// handler consumes the event, so GPUI doesn't handle it either. Only platforms backed by
+    /// winit call the handler.
+    pub fn on_raw_winit_event<F>(&self, mut callback: F) -> &Self
+    where
+        F: 'static + FnMut(AnyWindowHandle, &winit::event::WindowEvent, &mut App) -> bool,
+    {
+        let this = Rc::downgrade(&self.0);
+        self.0
+            .borrow_mut()
+            .platform
+            .on_raw_winit_event(Box::new(move |window, event| {
+                this.upgrade()
+                    .is_some_and(|app| callback(window, event, &mut app.borrow_mut()))
+            }));
+        self
+    }
+
+    /// Sets the amount of GPU memory, in bytes, the renderer may use before it starts evicting
+    /// cached sprites and downscaling surfaces. Exceeding it is reported to the handler registered
+    /// with [`Application::on_gpu_error`]. Pass `None` to remove the budget.
+    pub fn set_gpu_memory_budget(&self, budget: Option<u64>) -> &Self {
+        self.0.borrow().platform.set_gpu_memory_budget(budget);
+        self
+    }
+
     /// Returns a handle to the [`BackgroundExecutor`] associated with this app, which can be used to spawn futures in the background.
     pub fn background_executor(&self) -> BackgroundExecutor {
         self.0.borrow().background_executor.clone()
@@ -617,6 +730,11 @@ pub struct App {
     pub(crate) keystroke_observers: SubscriberSet<(), KeystrokeObserver>,
     pub(crate) keystroke_interceptors: SubscriberSet<(), KeystrokeObserver>,
     pub(crate) keyboard_layout_observers: SubscriberSet<(), Handler>,
+    pub(crate) system_appearance_observers: SubscriberSet<(), Handler>,
+    pub(crate) display_observers: SubscriberSet<(), Handler>,
+    pub(crate) power_state_observers: SubscriberSet<(), Handler>,
+    pub(crate) text_scale_factor_observers: SubscriberSet<(), Handler>,
+    pub(crate) cursor_blink_observers: SubscriberSet<(), Handler>,
     pub(crate) release_listeners: SubscriberSet<EntityId, ReleaseListener>,
     pub(crate) global_observers: SubscriberSet<TypeId, Handler>,
     pub(crate) quit_observers: SubscriberSet<(), QuitHandler>,
@@ -696,6 +814,11 @@ impl App {
                 keystroke_observers: SubscriberSet::new(),
                 keystroke_interceptors: SubscriberSet::new(),
                 keyboard_layout_observers: SubscriberSet::new(),
+                system_appearance_observers: SubscriberSet::new(),
+                display_observers: SubscriberSet::new(),
+                power_state_observers: SubscriberSet::new(),
+                text_scale_factor_observers: SubscriberSet::new(),
+                cursor_blink_observers: SubscriberSet::new(),
                 global_observers: SubscriberSet::new(),
                 quit_observers: SubscriberSet::new(),
                 restart_observers: SubscriberSet::new(),
@@ -733,6 +856,71 @@ impl App {
             }
         }));
 
+        platform.on_system_appearance_changed(Box::new({
+            let app = Rc::downgrade(&app);
+            move || {
+                if let Some(app) = app.upgrade() {
+                    let cx = &mut app.borrow_mut();
+                    cx.system_appearance_observers
+                        .clone()
+                        .retain(&(), move |callback| (callback)(cx));
+                }
+            }
+        }));
+
+        platform.on_displays_changed(Box::new({
+            let app = Rc::downgrade(&app);
+            move || {
+                if let Some(app) = app.upgrade() {
+                    let cx = &mut app.borrow_mut();
+                    cx.display_observers
+                        .clone()
+                        .retain(&(), move |callback| (callback)(cx));
+                }
+            }
+        }));
+
+        platform.on_power_state_changed(Box::new({
+            let app = Rc::downgrade(&app);
+            move || {
+                if let Some(app) = app.upgrade() {
+                    let cx = &mut app.borrow_mut();
+                    cx.power_state_observers
+                        .clone()
+                        .retain(&(), move |callback| (callback)(cx));
+                }
+            }
+        }));
+
+        platform.on_text_scale_factor_changed(Box::new({
+            let app = Rc::downgrade(&app);
+            move || {
+                if let Some(app) = app.upgrade() {
+                    let cx = &mut app.borrow_mut();
+                    let text_scale_factor = cx.platform.text_scale_factor();
+                    for window in cx.windows.values_mut().flatten() {
+                        window.text_scale_factor = text_scale_factor;
+                        window.refresh();
+                    }
+                    cx.text_scale_factor_observers
+                        .clone()
+                        .retain(&(), move |callback| (callback)(cx));
+                }
+            }
+        }));
+
+        platform.on_cursor_blink(Box::new({
+            let app = Rc::downgrade(&app);
+            move || {
+                if let Some(app) = app.upgrade() {
+                    let cx = &mut app.borrow_mut();
+                    cx.cursor_blink_observers
+                        .clone()
+                        .retain(&(), move |callback| (callback)(cx));
+                }
+            }
+        }));
+
         platform.on_quit(Box::new({
             let cx = app.clone();
             move || {
@@ -795,6 +983,45 @@ impl App {
         subscription
     }
 
+    /// Returns the GPU memory the renderer has allocated, if the platform tracks it.
+    pub fn gpu_memory_usage(&self) -> Option<GpuMemoryUsage> {
+        self.platform.gpu_memory_usage()
+    }
+
+    /// Returns a handle to the GPU device the UI is rendered with, for running compute or render
+    /// jobs on it without a window, or `None` until the device is ready and on platforms that
+    /// don't render with wgpu. See [`GpuHandle`] for how the jobs synchronize with the UI.
+    pub fn gpu_handle(&self) -> Option<GpuHandle> {
+        self.platform.gpu_handle()
+    }
+
+    /// Presents the frames of `windows` together, so windows mirroring each other flip on the
+    /// same refresh. Each frame is drawn and submitted as usual, and the frames are presented back
+    /// to back once every window has drawn. Passing a single window stops synchronizing it.
+    pub fn synchronize_presents(&self, windows: &[AnyWindowHandle]) {
+        self.platform.synchronize_presents(windows);
+    }
+
+    /// Registers `plugin` to draw the primitives painted for it with
+    /// [`Window::paint_plugin_primitive`], replacing any plugin of the same type. Plugins are
+    /// shared by every window, and stay registered when the renderers are recreated.
+    pub fn register_render_pipeline_plugin<P: RenderPipelinePlugin>(&self, plugin: P) {
+        self.platform
+            .register_render_pipeline_plugin(TypeId::of::<P>(), Arc::new(plugin));
+    }
+
+    /// Returns the renderer settings in effect, including any environment variable overrides.
+    pub fn renderer_settings(&self) -> RendererSettings {
+        self.platform.renderer_settings()
+    }
+
+    /// Changes the renderer settings. Open windows are redrawn with the new settings.
+    pub fn update_renderer_settings(&self, update: impl FnOnce(&mut RendererSettings)) {
+        let mut settings = self.platform.renderer_settings();
+        update(&mut settings);
+        self.platform.set_renderer_settings(settings);
+    }
+
     /// Gracefully quit the application via the platform's standard routine.
     pub fn quit(&self) {
         self.platform.quit();
@@ -1068,6 +1295,111 @@ impl App {
         self.platform.window_appearance()
     }
 
+    /// Returns the accent and title bar colors the OS uses for window chrome.
+    pub fn system_appearance(&self) -> SystemAppearance {
+        self.platform.system_appearance()
+    }
+
+    /// Invokes a handler when the OS accent or title bar colors change. Windows are notified
+    /// through [`Window::observe_window_appearance`] as well.
+    pub fn observe_system_appearance<F>(&self, mut callback: F) -> Subscription
+    where
+        F: 'static + FnMut(&mut App),
+    {
+        let (subscription, activate) = self.system_appearance_observers.insert(
+            (),
+            Box::new(move |cx| {
+                callback(cx);
+                true
+            }),
+        );
+        activate();
+        subscription
+    }
+
+    /// Invokes a handler when displays are connected or disconnected, or change their bounds.
+    /// Windows left without a display are moved onto the primary display first.
+    pub fn observe_displays<F>(&self, mut callback: F) -> Subscription
+    where
+        F: 'static + FnMut(&mut App),
+    {
+        let (subscription, activate) = self.display_observers.insert(
+            (),
+            Box::new(move |cx| {
+                callback(cx);
+                true
+            }),
+        );
+        activate();
+        subscription
+    }
+
+    /// Returns whether the system is on battery, in power saver mode or thermally throttled.
+    pub fn power_state(&self) -> PowerState {
+        self.platform.power_state()
+    }
+
+    /// Invokes a handler when [`App::power_state`] changes.
+    pub fn observe_power_state<F>(&self, mut callback: F) -> Subscription
+    where
+        F: 'static + FnMut(&mut App),
+    {
+        let (subscription, activate) = self.power_state_observers.insert(
+            (),
+            Box::new(move |cx| {
+                callback(cx);
+                true
+            }),
+        );
+        activate();
+        subscription
+    }
+
+    /// Returns the user's preferred text size as a multiple of the default, such as 1.25 when
+    /// large text is turned on. Windows multiply their rem size by it.
+    pub fn text_scale_factor(&self) -> f32 {
+        self.platform.text_scale_factor()
+    }
+
+    /// Invokes a handler when [`App::text_scale_factor`] changes.
+    pub fn observe_text_scale_factor<F>(&self, mut callback: F) -> Subscription
+    where
+        F: 'static + FnMut(&mut App),
+    {
+        let (subscription, activate) = self.text_scale_factor_observers.insert(
+            (),
+            Box::new(move |cx| {
+                callback(cx);
+                true
+            }),
+        );
+        activate();
+        subscription
+    }
+
+    /// Returns whether a blinking caret should currently be shown. The caret blinks at the
+    /// system's rate, and is shown while keys are being pressed and after focus changes.
+    pub fn cursor_blink_visible(&self) -> bool {
+        self.platform.cursor_blink_visible()
+    }
+
+    /// Invokes a handler when [`App::cursor_blink_visible`] changes, so editors can repaint their
+    /// caret.
+    pub fn observe_cursor_blink<F>(&self, mut callback: F) -> Subscription
+    where
+        F: 'static + FnMut(&mut App),
+    {
+        let (subscription, activate) = self.cursor_blink_observers.insert(
+            (),
+            Box::new(move |cx| {
+                callback(cx);
+                true
+            }),
+        );
+        activate();
+        subscription
+    }
+
     /// Writes data to the primary selection buffer.
     /// Only available on Linux.
     #[cfg(any(target_os = "linux", target_os = "freebsd"))]
@@ -1092,6 +1424,15 @@ impl App {
         self.platform.read_from_clipboard()
     }
 
+    /// Reads only the given formats from the platform clipboard, so that data in other formats,
+    /// such as a large image, isn't converted when it won't be used.
+    pub fn read_from_clipboard_formats(
+        &self,
+        formats: &[ClipboardFormat],
+    ) -> Option<ClipboardItem> {
+        self.platform.read_from_clipboard_formats(formats)
+    }
+
     /// Writes credentials to the platform keychain.
     pub fn write_credentials(
         &self,
@@ -2412,7 +2753,7 @@ pub struct KeystrokeEvent {
     pub context_stack: Vec<KeyContext>,
 }
 
-struct NullHttpClient;
+pub(crate) struct NullHttpClient;
 
 impl HttpClient for NullHttpClient {
     fn type_name(&self) -> &'static str {
@@ -1,7 +1,7 @@
 use crate::{DevicePixels, Pixels, Result, SharedString, Size, size};
 use smallvec::SmallVec;
 
-use image::{Delay, Frame};
+use image::{Delay, Frame, imageops};
 use std::{
     borrow::Cow,
     fmt,
@@ -36,6 +36,8 @@ pub struct ImageId(pub usize);
 pub(crate) struct RenderImageParams {
     pub(crate) image_id: ImageId,
     pub(crate) frame_index: usize,
+    // how many times the frame is halved, 0 for its full size
+    pub(crate) mip_level: u32,
 }
 
 /// A cached and processed image, in BGRA format
@@ -86,6 +88,26 @@ impl RenderImage {
             .map(|v| (v.0 as f32 / self.scale_factor).into())
     }
 
+    /// The smallest mip level of a frame, at which it's a pixel wide or tall.
+    pub(crate) fn max_mip_level(&self, frame_index: usize) -> u32 {
+        let size = self.size(frame_index);
+        size.width.0.max(size.height.0).max(1).ilog2()
+    }
+
+    /// Downscales a frame by `2^mip_level`, filtering every pixel it covers.
+    pub(crate) fn mip_level(
+        &self,
+        frame_index: usize,
+        mip_level: u32,
+    ) -> Option<(Size<DevicePixels>, Vec<u8>)> {
+        let buffer = self.data.get(frame_index)?.buffer();
+        let (width, height) = buffer.dimensions();
+        let width = (width >> mip_level).max(1);
+        let height = (height >> mip_level).max(1);
+        let mip = imageops::resize(buffer, width, height, imageops::FilterType::Triangle);
+        Some((size(width.into(), height.into()), mip.into_raw()))
+    }
+
     /// Get the delay of this frame from the previous
     pub fn delay(&self, frame_index: usize) -> Delay {
         self.data[frame_index].delay()
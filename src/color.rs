@@ -835,6 +835,15 @@ impl Background {
             BackgroundTag::PatternSlash => self.solid.is_transparent(),
         }
     }
+
+    /// Returns whether the background hides everything behind it.
+    pub fn is_opaque(&self) -> bool {
+        match self.tag {
+            BackgroundTag::Solid => self.solid.is_opaque(),
+            BackgroundTag::LinearGradient => self.colors.iter().all(|c| c.color.is_opaque()),
+            BackgroundTag::PatternSlash => false,
+        }
+    }
 }
 
 impl From<Hsla> for Background {
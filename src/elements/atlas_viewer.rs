@@ -0,0 +1,103 @@
+use crate::{
+    App, AtlasTextureSnapshot, BorderStyle, Bounds, Component, IntoElement, ParentElement as _,
+    Pixels, RenderOnce, Styled as _, Window, canvas, div, fill, hsla, outline, point,
+    prelude::FluentBuilder as _, px, rgba, size, white,
+};
+
+/// Create an element that displays the textures in the window's sprite atlas.
+///
+/// Each texture is drawn with an outline around every allocated tile, along with its size,
+/// tile count and occupancy. This is mainly useful for debugging glyph and image caching.
+/// See also [`Window::toggle_atlas_viewer`], which shows this element as an overlay.
+pub fn atlas_viewer() -> AtlasViewer {
+    AtlasViewer {
+        texture_width: px(256.),
+    }
+}
+
+/// An element that displays the textures in the window's sprite atlas.
+pub struct AtlasViewer {
+    texture_width: Pixels,
+}
+
+impl AtlasViewer {
+    /// Set the width each atlas texture is drawn at. The height follows the texture's aspect ratio.
+    pub fn texture_width(mut self, texture_width: impl Into<Pixels>) -> Self {
+        self.texture_width = texture_width.into();
+        self
+    }
+}
+
+impl RenderOnce for AtlasViewer {
+    fn render(self, window: &mut Window, _cx: &mut App) -> impl IntoElement {
+        let snapshots = window.atlas_texture_snapshots();
+
+        div()
+            .absolute()
+            .top_0()
+            .left_0()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .p_2()
+            .bg(hsla(0., 0., 0., 0.85))
+            .text_xs()
+            .text_color(white())
+            .when(snapshots.is_empty(), |this| {
+                this.child("The sprite atlas is empty")
+            })
+            .children(
+                snapshots
+                    .into_iter()
+                    .map(|snapshot| texture_view(snapshot, self.texture_width)),
+            )
+    }
+}
+
+fn texture_view(snapshot: AtlasTextureSnapshot, width: Pixels) -> impl IntoElement {
+    let texture_size = snapshot.size;
+    let scale = width.0 / texture_size.width.0.max(1) as f32;
+    let label = format!(
+        "{:?} #{}: {}x{}, {} tiles, {:.1}% occupied",
+        snapshot.id.kind,
+        snapshot.id.index,
+        texture_size.width.0,
+        texture_size.height.0,
+        snapshot.tiles.len(),
+        snapshot.occupancy() * 100.
+    );
+
+    div().flex().flex_col().gap_1().child(label).child(
+        canvas(
+            |_, _, _| {},
+            move |bounds, _, window, _| {
+                window.paint_quad(fill(bounds, rgba(0x202020ff)));
+                window.paint_atlas_texture(bounds, &snapshot);
+                for tile in &snapshot.tiles {
+                    let tile_bounds = Bounds {
+                        origin: bounds.origin
+                            + point(
+                                px(tile.origin.x.0 as f32 * scale),
+                                px(tile.origin.y.0 as f32 * scale),
+                            ),
+                        size: size(
+                            px(tile.size.width.0 as f32 * scale),
+                            px(tile.size.height.0 as f32 * scale),
+                        ),
+                    };
+                    window.paint_quad(outline(tile_bounds, rgba(0xff00ff99), BorderStyle::Solid));
+                }
+            },
+        )
+        .w(width)
+        .h(px(texture_size.height.0 as f32 * scale)),
+    )
+}
+
+impl IntoElement for AtlasViewer {
+    type Element = Component<Self>;
+
+    fn into_element(self) -> Self::Element {
+        Component::new(self)
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{
+    AnyElement, App, Bounds, Element, ElementId, GlobalElementId, InspectorElementId, IntoElement,
+    LayoutId, Pixels, Window, hash,
+};
+
+/// Builds a `CachedLayer` element, which paints `child` into a texture once and composites that
+/// texture on later frames until `version` changes.
+pub fn cached_layer(
+    id: impl Into<ElementId>,
+    version: u64,
+    child: impl IntoElement,
+) -> CachedLayer {
+    CachedLayer {
+        id: id.into(),
+        version,
+        child: child.into_any_element(),
+    }
+}
+
+/// An element that caches how its child is painted, for static content that's expensive to
+/// paint, such as a long document or a minimap. The child is laid out on every frame, but only
+/// painted when the version or size of the layer changes, so whatever it registers while painting,
+/// such as mouse listeners, only applies on those frames.
+pub struct CachedLayer {
+    id: ElementId,
+    version: u64,
+    child: AnyElement,
+}
+
+impl Element for CachedLayer {
+    type RequestLayoutState = ();
+    type PrepaintState = ();
+
+    fn id(&self) -> Option<ElementId> {
+        Some(self.id.clone())
+    }
+
+    fn source_location(&self) -> Option<&'static core::panic::Location<'static>> {
+        None
+    }
+
+    fn request_layout(
+        &mut self,
+        _id: Option<&GlobalElementId>,
+        _inspector_id: Option<&InspectorElementId>,
+        window: &mut Window,
+        cx: &mut App,
+    ) -> (LayoutId, ()) {
+        (self.child.request_layout(window, cx), ())
+    }
+
+    fn prepaint(
+        &mut self,
+        _id: Option<&GlobalElementId>,
+        _inspector_id: Option<&InspectorElementId>,
+        _bounds: Bounds<Pixels>,
+        _request_layout: &mut Self::RequestLayoutState,
+        window: &mut Window,
+        cx: &mut App,
+    ) {
+        self.child.prepaint(window, cx);
+    }
+
+    fn paint(
+        &mut self,
+        id: Option<&GlobalElementId>,
+        _inspector_id: Option<&InspectorElementId>,
+        bounds: Bounds<Pixels>,
+        _request_layout: &mut Self::RequestLayoutState,
+        _prepaint: &mut Self::PrepaintState,
+        window: &mut Window,
+        cx: &mut App,
+    ) {
+        let child = &mut self.child;
+        let result = window.paint_cached_layer(hash(&id), self.version, bounds, |window| {
+            child.paint(window, cx);
+        });
+        if let Err(error) = result {
+            log::error!("failed to paint a cached layer: {error:#}");
+        }
+    }
+}
+
+impl IntoElement for CachedLayer {
+    type Element = Self;
+
+    fn into_element(self) -> Self::Element {
+        self
+    }
+}
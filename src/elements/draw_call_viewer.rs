@@ -0,0 +1,115 @@
+use crate::{
+    App, Component, DrawCall, DrawCallKind, FrameDrawCalls, IntoElement, ParentElement as _,
+    RenderOnce, SharedString, Styled as _, Window, div, hsla, white,
+};
+
+/// Create an element that lists the draw calls the renderer issued for the previous frame.
+///
+/// The list shows the number of draw calls of each kind and of the primitives culled, followed by
+/// every draw call with its instance count, atlas texture and instance buffer range. Draw call
+/// recording must be enabled with [`Window::set_draw_call_recording`] for anything to be shown.
+/// See also [`Window::toggle_draw_call_viewer`], which shows this element as an overlay.
+pub fn draw_call_viewer() -> DrawCallViewer {
+    DrawCallViewer { max_listed: 64 }
+}
+
+/// An element that lists the draw calls the renderer issued for the previous frame.
+pub struct DrawCallViewer {
+    max_listed: usize,
+}
+
+impl DrawCallViewer {
+    /// Set the maximum number of individual draw calls listed. Counts always cover the whole frame.
+    pub fn max_listed(mut self, max_listed: usize) -> Self {
+        self.max_listed = max_listed;
+        self
+    }
+}
+
+impl RenderOnce for DrawCallViewer {
+    fn render(self, window: &mut Window, _cx: &mut App) -> impl IntoElement {
+        let container = div()
+            .absolute()
+            .top_0()
+            .right_0()
+            .flex()
+            .flex_col()
+            .p_2()
+            .bg(hsla(0., 0., 0., 0.85))
+            .text_xs()
+            .text_color(white());
+
+        let Some(frame) = window.last_frame_draw_calls() else {
+            return container.child("No draw calls have been recorded");
+        };
+
+        let hidden = frame.draw_calls.len().saturating_sub(self.max_listed);
+        container
+            .child(summary(&frame))
+            .children(
+                frame
+                    .draw_calls
+                    .iter()
+                    .take(self.max_listed)
+                    .enumerate()
+                    .map(|(index, draw_call)| describe(index, draw_call)),
+            )
+            .children((hidden > 0).then(|| SharedString::from(format!("… {hidden} more"))))
+    }
+}
+
+fn summary(frame: &FrameDrawCalls) -> SharedString {
+    let counts = [
+        DrawCallKind::Quads,
+        DrawCallKind::Shadows,
+        DrawCallKind::Underlines,
+        DrawCallKind::MonochromeSprites,
+        DrawCallKind::PolychromeSprites,
+        DrawCallKind::CellGrid,
+        DrawCallKind::Selections,
+        DrawCallKind::Surface,
+        DrawCallKind::Clip,
+        DrawCallKind::Plugin,
+    ]
+    .into_iter()
+    .filter_map(|kind| {
+        let count = frame.count(kind);
+        (count > 0).then(|| format!("{kind:?}: {count}"))
+    })
+    .collect::<Vec<_>>();
+
+    format!(
+        "{} draw calls ({}), {} primitives culled ({} outside, {} occluded)",
+        frame.draw_calls.len(),
+        counts.join(", "),
+        frame.culled.total(),
+        frame.culled.outside_viewport,
+        frame.culled.occluded
+    )
+    .into()
+}
+
+fn describe(index: usize, draw_call: &DrawCall) -> SharedString {
+    let mut description = format!(
+        "#{index} {:?} x{}",
+        draw_call.kind, draw_call.instance_count
+    );
+    if let Some(atlas_texture) = draw_call.atlas_texture {
+        description.push_str(&format!(", atlas texture #{atlas_texture}"));
+    }
+    if let Some(buffer_range) = &draw_call.buffer_range {
+        description.push_str(&format!(
+            ", bytes {}..{}",
+            buffer_range.start, buffer_range.end
+        ));
+    }
+    description.into()
+}
+
+impl IntoElement for DrawCallViewer {
+    type Element = Component<Self>;
+
+    fn into_element(self) -> Self::Element {
+        Component::new(self)
+    }
+}
@@ -1,8 +1,9 @@
 use crate::{
     AnyElement, AnyImageCache, App, Asset, AssetLogger, Bounds, DefiniteLength, Element, ElementId,
-    Entity, GlobalElementId, Hitbox, Image, ImageCache, InspectorElementId, InteractiveElement,
-    Interactivity, IntoElement, LayoutId, Length, ObjectFit, Pixels, RenderImage, Resource,
-    SharedString, SharedUri, StyleRefinement, Styled, Task, Window, px,
+    Entity, GlobalElementId, Hitbox, Hsla, Image, ImageCache, ImageFilter, ImageOptions,
+    InspectorElementId, InteractiveElement, Interactivity, IntoElement, LayoutId, Length,
+    ObjectFit, Pixels, RenderImage, Resource, SharedString, SharedUri, StyleRefinement, Styled,
+    Task, Window, px,
 };
 use anyhow::{Context as _, Result};
 
@@ -125,8 +126,8 @@ where
 
 /// The style of an image element.
 pub struct ImageStyle {
-    grayscale: bool,
     object_fit: ObjectFit,
+    options: ImageOptions,
     loading: Option<Box<dyn Fn() -> AnyElement>>,
     fallback: Option<Box<dyn Fn() -> AnyElement>>,
 }
@@ -134,8 +135,8 @@ pub struct ImageStyle {
 impl Default for ImageStyle {
     fn default() -> Self {
         Self {
-            grayscale: false,
             object_fit: ObjectFit::Contain,
+            options: ImageOptions::default(),
             loading: None,
             fallback: None,
         }
@@ -149,7 +150,14 @@ pub trait StyledImage: Sized {
 
     /// Set the image to be displayed in grayscale.
     fn grayscale(mut self, grayscale: bool) -> Self {
-        self.image_style().grayscale = grayscale;
+        self.image_style().options.grayscale = grayscale;
+        self
+    }
+
+    /// Set a color the image's colors are multiplied by, which tints or dims it without changing
+    /// the image itself.
+    fn tint(mut self, tint: impl Into<Hsla>) -> Self {
+        self.image_style().options.tint = Some(tint.into());
         self
     }
 
@@ -159,6 +167,19 @@ pub trait StyledImage: Sized {
         self
     }
 
+    /// Set how the image's pixels are filtered when it's scaled.
+    fn image_filter(mut self, filter: ImageFilter) -> Self {
+        self.image_style().options.filter = filter;
+        self
+    }
+
+    /// Set whether the image is drawn from a downscaled copy when it's shown at less than half
+    /// its size, which keeps large images from shimmering.
+    fn mipmaps(mut self, mipmaps: bool) -> Self {
+        self.image_style().options.mipmaps = mipmaps;
+        self
+    }
+
     /// Set a fallback function that will be invoked to render an error view should
     /// the image fail to load.
     fn with_fallback(mut self, fallback: impl Fn() -> AnyElement + 'static) -> Self {
@@ -474,12 +495,12 @@ impl Element for Img {
                         .to_pixels(window.rem_size())
                         .clamp_radii_for_quad_size(new_bounds.size);
                     window
-                        .paint_image(
+                        .paint_image_with_options(
                             new_bounds,
                             corner_radii,
                             data,
                             layout_state.frame_index,
-                            self.style.grayscale,
+                            self.style.options,
                         )
                         .log_err();
                 } else if let Some(replacement) = &mut layout_state.replacement {
@@ -1,27 +1,37 @@
 mod anchored;
 mod animation;
+mod atlas_viewer;
+mod cached_layer;
 mod canvas;
 mod deferred;
 mod div;
+mod draw_call_viewer;
 mod image_cache;
 mod img;
 mod list;
 mod surface;
+mod surface_viewer;
 mod svg;
 mod text;
 mod uniform_list;
+mod video;
 mod wgpu_surface;
 
 pub use anchored::*;
 pub use animation::*;
+pub use atlas_viewer::*;
+pub use cached_layer::*;
 pub use canvas::*;
 pub use deferred::*;
 pub use div::*;
+pub use draw_call_viewer::*;
 pub use image_cache::*;
 pub use img::*;
 pub use list::*;
 pub use surface::*;
+pub use surface_viewer::*;
 pub use svg::*;
 pub use text::*;
 pub use uniform_list::*;
+pub use video::*;
 pub use wgpu_surface::*;
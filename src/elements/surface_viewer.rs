@@ -0,0 +1,57 @@
+use crate::{
+    App, Component, IntoElement, ParentElement as _, RenderOnce, SharedString, Styled as _,
+    SurfaceInfo, Window, div, hsla, prelude::FluentBuilder as _, white,
+};
+
+/// Create an element that lists the live WGPU surfaces on the window's device.
+///
+/// Each surface is listed with its name, size, format, frame rate and the window it was created
+/// for, which helps keep track of apps with many embedded viewports.
+/// See also [`Window::toggle_surface_viewer`], which shows this element as an overlay.
+pub fn surface_viewer() -> SurfaceViewer {
+    SurfaceViewer {}
+}
+
+/// An element that lists the live WGPU surfaces on the window's device.
+pub struct SurfaceViewer {}
+
+impl RenderOnce for SurfaceViewer {
+    fn render(self, window: &mut Window, _cx: &mut App) -> impl IntoElement {
+        let surfaces = window.wgpu_surfaces();
+
+        div()
+            .absolute()
+            .bottom_0()
+            .right_0()
+            .flex()
+            .flex_col()
+            .p_2()
+            .bg(hsla(0., 0., 0., 0.85))
+            .text_xs()
+            .text_color(white())
+            .when(surfaces.is_empty(), |this| {
+                this.child("There are no surfaces")
+            })
+            .children(surfaces.iter().map(describe))
+    }
+}
+
+fn describe(surface: &SurfaceInfo) -> SharedString {
+    let name = surface.name.as_deref().unwrap_or("unnamed");
+    let mut description = format!(
+        "{name}: {}x{} {:?}, {:.1} fps",
+        surface.size.0, surface.size.1, surface.format, surface.frames_per_second
+    );
+    if let Some(owner) = surface.owner {
+        description.push_str(&format!(", window #{}", owner.window_id().as_u64()));
+    }
+    description.into()
+}
+
+impl IntoElement for SurfaceViewer {
+    type Element = Component<Self>;
+
+    fn into_element(self) -> Self::Element {
+        Component::new(self)
+    }
+}
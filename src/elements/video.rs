@@ -0,0 +1,293 @@
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use refineable::Refineable as _;
+
+use crate::{
+    App, Bounds, DevicePixels, Element, ElementId, GlobalElementId, InspectorElementId,
+    IntoElement, LayoutId, Pixels, Size, Style, StyleRefinement, Styled, Task, WgpuSurfaceHandle,
+    Window, platform::cross::nv12::Nv12Converter,
+};
+
+/// How many decoded frames are kept ready ahead of the one being shown.
+const DECODED_FRAMES_AHEAD: usize = 4;
+
+/// A decoded video frame in NV12: a plane of luma samples, followed by a plane of interleaved Cb
+/// and Cr samples at half the resolution in each dimension.
+#[derive(Clone)]
+pub struct VideoFrame {
+    /// The size of the frame in pixels.
+    pub size: Size<DevicePixels>,
+    /// The luma samples, one byte per pixel.
+    pub luma: Vec<u8>,
+    /// The number of bytes from the start of one row of luma samples to the next.
+    pub luma_stride: u32,
+    /// The Cb and Cr samples, two bytes for every 2x2 block of pixels.
+    pub chroma: Vec<u8>,
+    /// The number of bytes from the start of one row of chroma samples to the next.
+    pub chroma_stride: u32,
+    /// When the frame is shown, relative to the start of the video.
+    pub timestamp: Duration,
+}
+
+/// Decodes a video's frames in presentation order. Frames are pulled on the background executor,
+/// so decoding may block.
+pub trait VideoFrameProvider: Send + 'static {
+    /// Decodes the next frame, or returns `None` once the video has ended.
+    fn next_frame(&mut self) -> anyhow::Result<Option<VideoFrame>>;
+}
+
+/// A video to play with the [`video()`] element. Clones refer to the same playback, which starts
+/// the first time the video is painted.
+#[derive(Clone)]
+pub struct VideoSource(Rc<RefCell<VideoPlayback>>);
+
+struct VideoPlayback {
+    provider: Option<Box<dyn VideoFrameProvider>>,
+    frames: Option<flume::Receiver<VideoFrame>>,
+    _decode_task: Option<Task<()>>,
+    next_frame: Option<VideoFrame>,
+    started_at: Option<Instant>,
+    surface: Option<WgpuSurfaceHandle>,
+    // the converter and the device it was created on, which the surface leaves when it's lost
+    converter: Option<(wgpu::Device, Nv12Converter)>,
+    finished: bool,
+}
+
+impl VideoSource {
+    /// Create a video that plays the frames of `provider`.
+    pub fn new(provider: impl VideoFrameProvider) -> Self {
+        Self(Rc::new(RefCell::new(VideoPlayback {
+            provider: Some(Box::new(provider)),
+            frames: None,
+            _decode_task: None,
+            next_frame: None,
+            started_at: None,
+            surface: None,
+            converter: None,
+            finished: false,
+        })))
+    }
+
+    /// Whether every frame of the video has been shown.
+    pub fn is_finished(&self) -> bool {
+        self.0.borrow().finished
+    }
+}
+
+impl VideoPlayback {
+    /// Starts decoding if it hasn't started yet, and shows the latest frame whose timestamp has
+    /// been reached. Called on every frame the video is painted in, until the video ends.
+    fn present_due_frame(&mut self, window: &mut Window, cx: &mut App) {
+        if let Some(provider) = self.provider.take() {
+            let (sender, receiver) = flume::bounded(DECODED_FRAMES_AHEAD);
+            self.frames = Some(receiver);
+            self._decode_task = Some(cx.background_executor().spawn(decode(provider, sender)));
+        }
+        let Some(frames) = &self.frames else {
+            return;
+        };
+
+        let now = Instant::now();
+        let mut due_frame = None;
+        loop {
+            if self.next_frame.is_none() {
+                match frames.try_recv() {
+                    Ok(frame) => self.next_frame = Some(frame),
+                    Err(flume::TryRecvError::Empty) => break,
+                    Err(flume::TryRecvError::Disconnected) => {
+                        self.finished = true;
+                        break;
+                    }
+                }
+            }
+            let Some(frame) = &self.next_frame else {
+                break;
+            };
+            // The clock starts when the first frame arrives, so the video doesn't skip ahead while
+            // the decoder warms up.
+            let started_at = *self
+                .started_at
+                .get_or_insert_with(|| now.checked_sub(frame.timestamp).unwrap_or(now));
+            if started_at + frame.timestamp > now {
+                break;
+            }
+            due_frame = self.next_frame.take();
+        }
+
+        if let Some(frame) = due_frame {
+            self.show(&frame, window);
+        }
+        if !self.finished {
+            window.request_animation_frame();
+        }
+    }
+
+    fn show(&mut self, frame: &VideoFrame, window: &mut Window) {
+        let size = (
+            frame.size.width.0.max(0) as u32,
+            frame.size.height.0.max(0) as u32,
+        );
+        let surface = match &self.surface {
+            Some(surface) => {
+                // The surface keeps the video's resolution, and the renderer stretches it over the
+                // element's bounds.
+                if surface.size() != size {
+                    surface.resize(size.0, size.1);
+                }
+                surface.clone()
+            }
+            None => {
+                let Some(surface) =
+                    window.create_wgpu_surface(size.0, size.1, wgpu::TextureFormat::Rgba8Unorm)
+                else {
+                    log::error!("videos can't be shown on this platform");
+                    self.stop();
+                    return;
+                };
+                self.surface = Some(surface.clone());
+                surface
+            }
+        };
+
+        let Some(target) = surface.back_buffer_view() else {
+            return;
+        };
+        let device = surface.device();
+        if self
+            .converter
+            .as_ref()
+            .is_none_or(|(converter_device, _)| *converter_device != device)
+        {
+            self.converter = Some((
+                device.clone(),
+                Nv12Converter::new(&device, surface.format()),
+            ));
+        }
+        let Some((_, converter)) = &mut self.converter else {
+            return;
+        };
+        match converter.convert(&device, &surface.queue(), frame, &target) {
+            Ok(()) => surface.present(),
+            Err(error) => log::error!("failed to show a video frame: {error:#}"),
+        }
+    }
+
+    fn stop(&mut self) {
+        self.frames = None;
+        self._decode_task = None;
+        self.next_frame = None;
+        self.finished = true;
+    }
+}
+
+async fn decode(mut provider: Box<dyn VideoFrameProvider>, frames: flume::Sender<VideoFrame>) {
+    loop {
+        match provider.next_frame() {
+            Ok(Some(frame)) => {
+                if frames.send_async(frame).await.is_err() {
+                    return;
+                }
+            }
+            Ok(None) => return,
+            Err(error) => {
+                log::error!("failed to decode a video frame: {error:#}");
+                return;
+            }
+        }
+    }
+}
+
+/// Create a `Video` element that plays `source`, showing each frame once its timestamp is reached.
+pub fn video(source: VideoSource) -> Video {
+    Video {
+        source,
+        style: StyleRefinement::default(),
+    }
+}
+
+/// An element that plays a video without sound. Frames are decoded on the background executor,
+/// converted from NV12 on the GPU, and composited as a WGPU surface stretched over the element.
+pub struct Video {
+    source: VideoSource,
+    style: StyleRefinement,
+}
+
+impl Element for Video {
+    type RequestLayoutState = Style;
+    type PrepaintState = ();
+
+    fn id(&self) -> Option<ElementId> {
+        None
+    }
+
+    fn source_location(&self) -> Option<&'static core::panic::Location<'static>> {
+        None
+    }
+
+    fn request_layout(
+        &mut self,
+        _id: Option<&GlobalElementId>,
+        _inspector_id: Option<&InspectorElementId>,
+        window: &mut Window,
+        cx: &mut App,
+    ) -> (LayoutId, Self::RequestLayoutState) {
+        let mut style = Style::default();
+        style.refine(&self.style);
+        let layout_id = window.request_layout(style.clone(), [], cx);
+        (layout_id, style)
+    }
+
+    fn prepaint(
+        &mut self,
+        _id: Option<&GlobalElementId>,
+        _inspector_id: Option<&InspectorElementId>,
+        _bounds: Bounds<Pixels>,
+        _request_layout: &mut Self::RequestLayoutState,
+        window: &mut Window,
+        cx: &mut App,
+    ) -> Self::PrepaintState {
+        self.source.0.borrow_mut().present_due_frame(window, cx);
+    }
+
+    fn paint(
+        &mut self,
+        _id: Option<&GlobalElementId>,
+        _inspector_id: Option<&InspectorElementId>,
+        bounds: Bounds<Pixels>,
+        style: &mut Self::RequestLayoutState,
+        _prepaint: &mut Self::PrepaintState,
+        window: &mut Window,
+        cx: &mut App,
+    ) {
+        let surface_id = self
+            .source
+            .0
+            .borrow()
+            .surface
+            .as_ref()
+            .map(|surface| surface.id());
+        style.paint(bounds, window, cx, |window, _cx| {
+            if let Some(surface_id) = surface_id {
+                window.paint_wgpu_surface(bounds, surface_id);
+            }
+        });
+    }
+}
+
+impl IntoElement for Video {
+    type Element = Self;
+
+    fn into_element(self) -> Self::Element {
+        self
+    }
+}
+
+impl Styled for Video {
+    fn style(&mut self) -> &mut StyleRefinement {
+        &mut self.style
+    }
+}
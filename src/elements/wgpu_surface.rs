@@ -1,32 +1,110 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
 
 use refineable::Refineable as _;
 
 use crate::{
-    App, Bounds, Element, ElementId, GlobalElementId, InspectorElementId, IntoElement, LayoutId,
-    Pixels, Style, StyleRefinement, Styled, Window,
-    platform::cross::surface_registry::{SurfaceId, SurfaceRegistry},
+    AnyWindowHandle, App, Bounds, Element, ElementId, GlobalElementId, InspectorElementId,
+    IntoElement, LayoutId, Pixels, SharedString, Style, StyleRefinement, Styled, Window,
+    platform::cross::{
+        submission_scheduler::SubmissionScheduler,
+        surface_registry::{SurfaceDevice, SurfaceId, SurfaceRegistry},
+    },
 };
 
+/// When a frame presented by a [`WgpuSurfaceHandle`] reached the screen, as reported by
+/// [`WgpuSurfaceHandle::latest_present_info()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SurfacePresentInfo {
+    /// How many times the producer had presented when the frame was shown, so the first frame
+    /// passed to [`WgpuSurfaceHandle::present()`] is frame 1.
+    pub frame: u64,
+    /// When the window frame showing it was handed to the compositor.
+    pub presented_at: Instant,
+    /// When the GPU finished drawing that window frame, once the queue has reported it.
+    pub completed_at: Option<Instant>,
+}
+
+/// A live [`WgpuSurfaceHandle`]'s surface, as listed by [`Window::wgpu_surfaces()`].
+#[derive(Clone, Debug)]
+pub struct SurfaceInfo {
+    /// The name the surface was created with, see [`Window::create_named_wgpu_surface()`].
+    pub name: Option<SharedString>,
+    /// The window the surface was created for.
+    pub owner: Option<AnyWindowHandle>,
+    /// The size of the buffer the surface is composited from, in pixels.
+    pub size: (u32, u32),
+    /// The texture format of the surface's buffers.
+    pub format: wgpu::TextureFormat,
+    /// How many frames the producer presents each second, measured over about the last second.
+    pub frames_per_second: f32,
+}
+
+/// How the colors a producer renders into a [`WgpuSurfaceHandle`] relate to their alpha, as
+/// reported by [`WgpuSurfaceHandle::alpha_mode()`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SurfaceAlphaMode {
+    /// The colors aren't multiplied by their alpha, as blended by
+    /// `wgpu::BlendState::ALPHA_BLENDING`.
+    #[default]
+    Straight,
+    /// The colors are already multiplied by their alpha, as blended by
+    /// `wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING` or cleared to a translucent color.
+    Premultiplied,
+}
+
+/// The color space of the colors a producer renders into a [`WgpuSurfaceHandle`], as reported by
+/// [`WgpuSurfaceHandle::color_space()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SurfaceColorSpace {
+    /// The colors are sRGB encoded, as stored in a texture whose format isn't sRGB.
+    Srgb,
+    /// The colors are linear, as written to a texture whose format is sRGB, which encodes them
+    /// on write and decodes them when they're sampled, or to a float texture.
+    Linear,
+}
+
+impl SurfaceColorSpace {
+    /// The color space the renderer samples a texture of `format` in, assuming the producer
+    /// writes sRGB encoded colors to formats that aren't sRGB.
+    pub fn of_format(format: wgpu::TextureFormat) -> Self {
+        if format.is_srgb() {
+            Self::Linear
+        } else {
+            Self::Srgb
+        }
+    }
+}
+
 /// Inner state shared across clones of `WgpuSurfaceHandle`.
 /// When the last clone is dropped, the surface is removed from the registry.
 struct WgpuSurfaceHandleInner {
     surface_id: SurfaceId,
     registry: Arc<SurfaceRegistry>,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
+    // replaced when the surface moves to a new device after the previous one was lost
+    surface_device: Mutex<SurfaceDevice>,
     present_trigger: Arc<dyn Fn() + Send + Sync>,
     /// Optional direct handle to the winit window.  Having an `Arc` lets
     /// us call `request_redraw()` from another thread without touching the
-    /// event bus.
-    winit_window: Option<Arc<winit::window::Window>>,
+    /// event bus. It's set once the window is initialized, which may be
+    /// after the surface was created.
+    winit_window: Arc<OnceLock<Arc<winit::window::Window>>>,
     size: Mutex<(u32, u32)>,
     format: wgpu::TextureFormat,
+    on_resize_request: Mutex<Option<Arc<dyn Fn(u32, u32) + Send + Sync>>>,
+    on_device_lost: Mutex<Option<Arc<dyn Fn(&WgpuSurfaceHandle) + Send + Sync>>>,
 }
 
 impl Drop for WgpuSurfaceHandleInner {
     fn drop(&mut self) {
         self.registry.remove(self.surface_id);
+        self.surface_device
+            .lock()
+            .unwrap()
+            .scheduler
+            .remove(self.surface_id);
     }
 }
 
@@ -54,44 +132,95 @@ impl WgpuSurfaceHandle {
         std::env::var("GPUI_BENCHMARK").is_ok()
     }
     pub(crate) fn new(
-        device: wgpu::Device,
-        queue: wgpu::Queue,
+        surface_device: SurfaceDevice,
         surface_id: SurfaceId,
         registry: Arc<SurfaceRegistry>,
         present_trigger: Arc<dyn Fn() + Send + Sync>,
-        winit_window: Option<Arc<winit::window::Window>>,
+        winit_window: Arc<OnceLock<Arc<winit::window::Window>>>,
         width: u32,
         height: u32,
         format: wgpu::TextureFormat,
     ) -> Self {
-        Self {
+        let handle = Self {
             inner: Arc::new(WgpuSurfaceHandleInner {
                 surface_id,
-                registry,
-                device,
-                queue,
+                registry: registry.clone(),
+                surface_device: Mutex::new(surface_device),
                 present_trigger,
                 winit_window,
                 size: Mutex::new((width, height)),
                 format,
+                on_resize_request: Mutex::new(None),
+                on_device_lost: Mutex::new(None),
             }),
-        }
+        };
+
+        let inner = Arc::downgrade(&handle.inner);
+        registry.on_device_lost(surface_id, move |surface_device| {
+            let Some(inner) = inner.upgrade() else {
+                return;
+            };
+            *inner.surface_device.lock().unwrap() = surface_device.clone();
+            let on_device_lost = inner.on_device_lost.lock().unwrap().clone();
+            if let Some(on_device_lost) = on_device_lost {
+                on_device_lost(&WgpuSurfaceHandle { inner });
+            }
+        });
+        handle
+    }
+
+    /// The wgpu `Device` for creating GPU resources and command encoders. It changes when the
+    /// device is lost, see [`on_device_lost()`](Self::on_device_lost).
+    pub fn device(&self) -> wgpu::Device {
+        self.inner.surface_device.lock().unwrap().device.clone()
     }
 
-    /// The wgpu `Device` for creating GPU resources and command encoders.
-    pub fn device(&self) -> &wgpu::Device {
-        &self.inner.device
+    /// The wgpu `Queue` for submitting command buffers. It changes along with the
+    /// [`device()`](Self::device).
+    pub fn queue(&self) -> wgpu::Queue {
+        self.inner.surface_device.lock().unwrap().queue.clone()
     }
 
-    /// The wgpu `Queue` for submitting command buffers.
-    pub fn queue(&self) -> &wgpu::Queue {
-        &self.inner.queue
+    /// Register a callback invoked after the device was lost, for example because the GPU was
+    /// unplugged or its driver was updated, and the surface was recreated on a new one. Anything
+    /// the producer created from the previous [`device()`](Self::device) is unusable, so it
+    /// recreates its resources and presents again. The callback is invoked on the main thread.
+    pub fn on_device_lost(&self, callback: impl Fn(&WgpuSurfaceHandle) + Send + Sync + 'static) {
+        *self.inner.on_device_lost.lock().unwrap() = Some(Arc::new(callback));
+    }
+
+    fn scheduler(&self) -> Arc<SubmissionScheduler> {
+        self.inner.surface_device.lock().unwrap().scheduler.clone()
+    }
+
+    /// Submit command buffers that render into this surface. Unlike submitting them to
+    /// [`queue()`](Self::queue) directly, large submissions are split into chunks that give way
+    /// to UI frames being drawn, their GPU time is measured, and they're held back once the
+    /// [GPU time budget](Self::set_gpu_time_budget) is used up. This blocks while throttled.
+    pub fn submit(&self, command_buffers: impl IntoIterator<Item = wgpu::CommandBuffer>) {
+        self.scheduler()
+            .submit(self.inner.surface_id, command_buffers);
+    }
+
+    /// Limit how much GPU time the command buffers passed to [`submit()`](Self::submit) may take
+    /// in each second, or lift the limit with `None`.
+    pub fn set_gpu_time_budget(&self, budget: Option<Duration>) {
+        self.scheduler().set_budget(self.inner.surface_id, budget);
+    }
+
+    /// How much GPU time the command buffers passed to [`submit()`](Self::submit) took during
+    /// the last full second. Measured with timestamp queries where the GPU supports them, and
+    /// from submission to completion otherwise.
+    pub fn recent_gpu_time(&self) -> Option<Duration> {
+        self.scheduler().recent_gpu_time(self.inner.surface_id)
     }
 
     /// Get a `TextureView` of the back buffer for use as a render target.
     /// Render into this, then call [`present()`](Self::present).
     pub fn back_buffer_view(&self) -> Option<wgpu::TextureView> {
-        self.inner.registry.back_view(self.inner.surface_id)
+        self.inner
+            .registry
+            .back_view(&self.device(), self.inner.surface_id)
     }
 
     /// Atomically obtain the back buffer view _and_ its pixel dimensions.
@@ -100,7 +229,7 @@ impl WgpuSurfaceHandle {
     pub fn back_view_with_size(&self) -> Option<(wgpu::TextureView, (u32, u32))> {
         self.inner
             .registry
-            .lock_and_get_back_with_size(self.inner.surface_id)
+            .lock_and_get_back_with_size(&self.device(), self.inner.surface_id)
     }
 
     /// Swap front and back buffers (GPU pointer swap, zero copy).
@@ -136,7 +265,11 @@ impl WgpuSurfaceHandle {
             .registry
             .set_present_pending(self.inner.surface_id)
         {
-            if let Some(winit) = &self.inner.winit_window {
+            // low latency presents go through the event loop, which recomposites just this
+            // surface instead of waiting for the next frame
+            if self.inner.registry.is_low_latency(self.inner.surface_id) {
+                self.request_present();
+            } else if let Some(winit) = self.inner.winit_window.get() {
                 winit.request_redraw();
             } else {
                 self.request_present();
@@ -144,7 +277,71 @@ impl WgpuSurfaceHandle {
         }
     }
 
-    /// Current size in device pixels.
+    /// Opt into presenting with lower latency, for latency-sensitive content such as pen input.
+    /// Each [`present()`](Self::present) then redraws just the surface's region of the last
+    /// window frame and presents it right away, instead of waiting for the window's next frame.
+    /// Content drawn over the surface is redrawn with it. Zoomed windows still wait for a full
+    /// frame.
+    pub fn set_low_latency(&self, low_latency: bool) {
+        self.inner
+            .registry
+            .set_low_latency(self.inner.surface_id, low_latency);
+    }
+
+    /// How the compositor interprets the alpha of the surface's frames, straight unless the
+    /// producer [set](Self::set_alpha_mode) it otherwise. Frames rendered in the other mode show
+    /// dark or light fringes where they're translucent.
+    pub fn alpha_mode(&self) -> SurfaceAlphaMode {
+        self.inner
+            .registry
+            .alpha_mode(self.inner.surface_id)
+            .unwrap_or_default()
+    }
+
+    /// Declare how the colors of the frames the producer renders relate to their alpha, so
+    /// translucent frames composite correctly over the content behind the surface.
+    pub fn set_alpha_mode(&self, alpha_mode: SurfaceAlphaMode) {
+        self.inner
+            .registry
+            .set_alpha_mode(self.inner.surface_id, alpha_mode);
+    }
+
+    /// The color space the compositor interprets the surface's frames in. It follows the
+    /// [format](Self::format) unless the producer [set](Self::set_color_space) it otherwise, see
+    /// [`SurfaceColorSpace::of_format()`].
+    pub fn color_space(&self) -> SurfaceColorSpace {
+        self.inner
+            .registry
+            .color_space(self.inner.surface_id)
+            .unwrap_or_else(|| SurfaceColorSpace::of_format(self.inner.format))
+    }
+
+    /// Declare the color space of the frames the producer renders, for producers writing linear
+    /// colors to a texture whose format isn't sRGB, such as HDR content in a float texture, so
+    /// they're converted to the window's colors once and only once.
+    pub fn set_color_space(&self, color_space: SurfaceColorSpace) {
+        self.inner
+            .registry
+            .set_color_space(self.inner.surface_id, color_space);
+    }
+
+    /// The name the surface was created with, see [`Window::create_named_wgpu_surface()`].
+    pub fn name(&self) -> Option<SharedString> {
+        self.inner.registry.name(self.inner.surface_id)
+    }
+
+    pub(crate) fn set_name(&self, name: SharedString) {
+        self.inner
+            .registry
+            .set_name(self.inner.surface_id, Some(name));
+    }
+
+    pub(crate) fn set_owner(&self, owner: AnyWindowHandle) {
+        self.inner.registry.set_owner(self.inner.surface_id, owner);
+    }
+
+    /// Current size in device pixels that the surface is laid out at. While a resize hasn't been
+    /// acknowledged, the back buffer keeps its previous size.
     pub fn size(&self) -> (u32, u32) {
         *self.inner.size.lock().unwrap()
     }
@@ -166,26 +363,71 @@ impl WgpuSurfaceHandle {
         }
     }
 
+    /// When the most recent of this surface's frames to reach the screen was presented. A frame
+    /// shown in several window frames is reported for the first one, so producers can time
+    /// animations and sync audio against the frames users actually saw.
+    pub fn latest_present_info(&self) -> Option<SurfacePresentInfo> {
+        self.inner.registry.latest_present(self.inner.surface_id)
+    }
+
     /// The texture format used by this surface's buffers.
     pub fn format(&self) -> wgpu::TextureFormat {
         self.inner.format
     }
 
+    /// Opt into resizing the buffers only when the producer is ready. When the element's size
+    /// changes, `callback` is invoked with the new size in device pixels instead of the buffers
+    /// being recreated, and the last presented frame keeps being shown letterboxed at its own
+    /// size until the producer calls [`ack_resize()`](Self::ack_resize) and presents a frame at
+    /// the new size. This avoids showing stretched or empty frames while a window is resized.
+    ///
+    /// The callback is invoked on the main thread, so a producer on another thread usually
+    /// forwards the size over a channel.
+    pub fn on_resize_request(&self, callback: impl Fn(u32, u32) + Send + Sync + 'static) {
+        *self.inner.on_resize_request.lock().unwrap() = Some(Arc::new(callback));
+    }
+
+    /// Acknowledge a size passed to the [`on_resize_request()`](Self::on_resize_request)
+    /// callback. From now on the back buffer has that size, and the next presented frame
+    /// replaces the letterboxed one.
+    pub fn ack_resize(&self, width: u32, height: u32) {
+        self.inner
+            .registry
+            .acknowledge_resize(self.inner.surface_id, width, height);
+    }
+
     /// The `SurfaceId` for this handle (used internally by the element).
     pub(crate) fn id(&self) -> SurfaceId {
         self.inner.surface_id
     }
 
-    /// Resize the surface's double buffers. Called by the element when bounds change.
+    /// Resize the surface's double buffers, or ask the producer to if it registered
+    /// [`on_resize_request()`](Self::on_resize_request). Called by the element when bounds change.
     pub(crate) fn resize(&self, width: u32, height: u32) {
         let mut size = self.inner.size.lock().unwrap();
         if size.0 == width && size.1 == height {
             return;
         }
-        self.inner
-            .registry
-            .resize(&self.inner.device, self.inner.surface_id, width, height);
         *size = (width, height);
+        drop(size);
+
+        let on_resize_request = self.inner.on_resize_request.lock().unwrap().clone();
+        match on_resize_request {
+            Some(on_resize_request) => {
+                if self
+                    .inner
+                    .registry
+                    .request_resize(self.inner.surface_id, width, height)
+                {
+                    on_resize_request(width, height);
+                }
+            }
+            None => {
+                self.inner
+                    .registry
+                    .resize(&self.device(), self.inner.surface_id, width, height);
+            }
+        }
     }
 }
 
@@ -211,8 +453,9 @@ pub struct WgpuSurface {
 
 impl WgpuSurface {
     /// Register a callback invoked when the element's layout bounds change.
-    /// The surface textures are automatically resized; use this to recreate
-    /// any external resources that depend on the size.
+    /// The surface textures are automatically resized, unless the handle asks
+    /// the producer to through [`WgpuSurfaceHandle::on_resize_request`]; use
+    /// this to recreate any external resources that depend on the size.
     pub fn on_resize(
         mut self,
         callback: impl Fn(u32, u32, &WgpuSurfaceHandle) + 'static,
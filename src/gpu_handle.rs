@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+type DeviceLostCallback = Arc<dyn Fn(&GpuHandle) + Send + Sync>;
+
+/// A handle to the GPU device GPUI renders with, for allocating buffers and running compute or
+/// render jobs on it without opening a window or creating a [`crate::WgpuSurfaceHandle`]. Get
+/// it with [`crate::App::gpu_handle`].
+///
+/// The jobs share the device with the UI renderer, which follows these rules:
+///
+/// - The renderer submits the frames of every window to the same [`queue()`](Self::queue), from
+///   the main thread or the window's render thread. Jobs are ordered with the frames by
+///   submission alone, so the GPU runs a job after the frames submitted before it and before the
+///   frames submitted after it.
+/// - A long submission holds up the frames submitted after it, so heavy jobs are best split into
+///   several submissions, or passed to [`crate::WgpuSurfaceHandle::submit`] when they render into
+///   a surface, which gives way to UI frames.
+/// - The renderer never touches the resources created with the handle, and surfaces share the
+///   device, so their producers can use the resources directly.
+/// - The renderer doesn't poll the device for mappings it didn't request, so a job waiting on
+///   `wgpu::Buffer::map_async` polls the device itself, on a thread other than the main thread
+///   when it blocks.
+///
+/// The handle is `Clone + Send + Sync`, and every clone follows the device when it's replaced
+/// after being lost, see [`on_device_lost()`](Self::on_device_lost).
+#[derive(Clone)]
+pub struct GpuHandle {
+    inner: Arc<GpuHandleInner>,
+}
+
+struct GpuHandleInner {
+    // replaced when the device is lost
+    device: Mutex<(wgpu::Device, wgpu::Queue)>,
+    on_device_lost: Mutex<Vec<DeviceLostCallback>>,
+}
+
+impl GpuHandle {
+    pub(crate) fn new(device: wgpu::Device, queue: wgpu::Queue) -> Self {
+        Self {
+            inner: Arc::new(GpuHandleInner {
+                device: Mutex::new((device, queue)),
+                on_device_lost: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// The wgpu `Device` the UI is rendered with. It changes when the device is lost, see
+    /// [`on_device_lost()`](Self::on_device_lost).
+    pub fn device(&self) -> wgpu::Device {
+        self.inner.device.lock().0.clone()
+    }
+
+    /// The wgpu `Queue` the UI's frames are submitted to. It changes along with the
+    /// [`device()`](Self::device).
+    pub fn queue(&self) -> wgpu::Queue {
+        self.inner.device.lock().1.clone()
+    }
+
+    /// Register a callback invoked after the device was lost, for example because the GPU was
+    /// unplugged or its driver was updated, and replaced with a new one. Anything created from
+    /// the previous [`device()`](Self::device) is unusable, so jobs recreate their resources. The
+    /// callback is invoked on the main thread.
+    pub fn on_device_lost(&self, callback: impl Fn(&GpuHandle) + Send + Sync + 'static) {
+        self.inner
+            .on_device_lost
+            .lock()
+            .unwrap()
+            .push(Arc::new(callback));
+    }
+
+    /// Moves the handle to the device that replaced a lost one, and tells the callbacks.
+    pub(crate) fn replace_device(&self, device: wgpu::Device, queue: wgpu::Queue) {
+        *self.inner.device.lock() = (device, queue);
+
+        // The callbacks are called without the lock held, since they may register more callbacks.
+        let callbacks = self.inner.on_device_lost.lock().clone();
+        for callback in callbacks {
+            callback(self);
+        }
+    }
+}
@@ -22,6 +22,7 @@ mod elements;
 mod executor;
 mod geometry;
 mod global;
+mod gpu_handle;
 mod input;
 mod inspector;
 mod interactive;
@@ -33,7 +34,10 @@ pub mod prelude;
 mod profiler;
 #[cfg(any(target_os = "windows", target_os = "linux"))]
 mod queue;
+mod render_plugin;
 mod scene;
+/// Magnification of a window's presented frame, for users who need a closer look.
+pub mod screen_zoom;
 mod shared_string;
 mod shared_uri;
 mod style;
@@ -82,6 +86,7 @@ pub use elements::*;
 pub use executor::*;
 pub use geometry::*;
 pub use global::*;
+pub use gpu_handle::*;
 pub use gpui_macros::{AppContext, IntoElement, Render, VisualContext, register_action, test};
 pub use http_client;
 pub use input::*;
@@ -95,7 +100,9 @@ pub use profiler::*;
 #[cfg(any(target_os = "windows", target_os = "linux"))]
 pub(crate) use queue::{PriorityQueueReceiver, PriorityQueueSender};
 pub use refineable::*;
+pub use render_plugin::*;
 pub use scene::*;
+pub use screen_zoom::ScreenZoom;
 pub use shared_string::*;
 pub use shared_uri::*;
 pub use smol::Timer;
@@ -315,3 +322,622 @@ pub struct GpuSpecs {
     /// Further information about the driver, as reported by Vulkan.
     pub driver_info: String,
 }
+
+/// The category of an error reported by the GPU device.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GpuErrorKind {
+    /// An API call was made with invalid arguments or in an invalid state.
+    Validation,
+    /// The device ran out of memory while allocating a resource.
+    OutOfMemory,
+    /// The driver or the graphics backend failed internally.
+    Internal,
+    /// The GPU memory used by the renderer exceeded the budget set with
+    /// [`Application::set_gpu_memory_budget`].
+    BudgetExceeded,
+}
+
+/// An error reported by the GPU device that wasn't handled where it occurred.
+#[derive(Clone, Debug)]
+pub struct GpuError {
+    /// The category of the error.
+    pub kind: GpuErrorKind,
+    /// A description of the error, as reported by the graphics backend.
+    pub message: String,
+    /// The label of the render pass being encoded when the error was raised.
+    /// Only tracked in debug builds.
+    pub pass_label: Option<&'static str>,
+}
+
+impl std::fmt::Display for GpuError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} GPU error", self.kind)?;
+        if let Some(pass_label) = self.pass_label {
+            write!(f, " in pass `{pass_label}`")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Why the renderer failed to draw a frame, reported to [`Window::on_renderer_error`]. The frame
+/// is skipped and the window redrawn, so these are recoverable unless they keep recurring.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, thiserror::Error)]
+pub enum RendererError {
+    /// The window's surface was lost or no longer matched the window, such as after the GPU was
+    /// reset. It's been reconfigured for the next frame.
+    #[error("the window's surface was lost")]
+    SurfaceLost,
+    /// Acquiring the next texture of the window's surface timed out.
+    #[error("timed out acquiring the window's surface texture")]
+    SurfaceTimeout,
+    /// The device ran out of memory acquiring the next texture of the window's surface.
+    #[error("ran out of memory acquiring the window's surface texture")]
+    OutOfMemory,
+    /// The graphics backend failed to acquire the next texture of the window's surface for
+    /// another reason.
+    #[error("failed to acquire the window's surface texture")]
+    SurfaceUnavailable,
+    /// The GPU device was lost, and the window's renderer couldn't be created again on the device
+    /// that replaced it.
+    #[error("the window's renderer couldn't be recreated after the GPU device was lost")]
+    DeviceLost,
+    /// The app is suspended, so the window has no surface to draw to until it's resumed.
+    #[error("the window's surface was released while the app is suspended")]
+    Suspended,
+}
+
+/// The GPU memory allocated by the renderer, in bytes, broken down by what it's used for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GpuMemoryUsage {
+    /// Memory used by sprite atlas textures, which hold glyphs, images and SVGs.
+    pub atlas: u64,
+    /// Memory used by the textures backing [`WgpuSurfaceHandle`]s.
+    pub surfaces: u64,
+    /// Memory used by the buffers scene primitives are uploaded to.
+    pub scene_buffers: u64,
+}
+
+impl GpuMemoryUsage {
+    /// Returns the total memory used.
+    pub fn total(&self) -> u64 {
+        self.atlas + self.surfaces + self.scene_buffers
+    }
+}
+
+/// How the renderer presents frames to the display.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PresentMode {
+    /// Waits for the display's vertical blank, so frames never tear. Supported everywhere.
+    #[default]
+    Fifo,
+    /// Waits for the vertical blank, but replaces a frame waiting to be presented with a newer
+    /// one instead of blocking. Falls back to [`PresentMode::Fifo`] where it isn't supported.
+    Mailbox,
+    /// Presents frames as soon as they're ready, which may tear. Falls back to
+    /// [`PresentMode::Fifo`] where it isn't supported.
+    Immediate,
+}
+
+/// Settings that tune how the renderer draws, changed with [`App::update_renderer_settings`].
+///
+/// Each setting can be overridden with an environment variable, which takes precedence over the
+/// value set by the application: `ZED_PATH_SAMPLE_COUNT`, `ZED_FONTS_GAMMA`,
+/// `ZED_FONTS_GRAYSCALE_ENHANCED_CONTRAST`, `GPUI_PRESENT_MODE` (`fifo`, `mailbox` or
+/// `immediate`), `GPUI_MONOCHROME_ATLAS_STRATEGY` and `GPUI_POLYCHROME_ATLAS_STRATEGY` (`bucketed`
+/// or `shelf`), `GPUI_WHITE_POINT` (`neutral`, `system` or a temperature in kelvin) and
+/// `GPUI_ECO_MODE` (`automatic`, `always` or `never`), `GPUI_DITHERING` (`true` or `false`) and
+/// `GPUI_PIXEL_SNAPPING` (`never`, `requested` or `always`), `GPUI_FRAME_STATS_INTERVAL` (in
+/// seconds) and `GPUI_FRAME_STATS_FORMAT` (`text` or `json`).
+/// Setting `GPUI_DISABLE_VSYNC` is the same as `GPUI_PRESENT_MODE=immediate`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RendererSettings {
+    /// The number of samples used to antialias paths. Only applies to windows opened after it
+    /// changes.
+    pub path_sample_count: u32,
+    /// The gamma used when blending text, between 1.0 and 2.2.
+    pub fonts_gamma: f32,
+    /// How much the contrast of grayscale antialiased text is enhanced. 0.0 disables it.
+    pub grayscale_enhanced_contrast: f32,
+    /// How frames are presented to the display.
+    pub present_mode: PresentMode,
+    /// How glyphs are packed into atlas textures. Only applies to textures created after it
+    /// changes.
+    pub monochrome_atlas_strategy: AtlasAllocationStrategy,
+    /// How images and emoji are packed into atlas textures. Only applies to textures created
+    /// after it changes.
+    pub polychrome_atlas_strategy: AtlasAllocationStrategy,
+    /// The white point frames and embedded surfaces are shifted to, to warm them up at night.
+    pub white_point: WhitePoint,
+    /// When the renderer saves power by drawing less.
+    pub eco_mode: EcoMode,
+    /// Whether gradients and blurred shadows are dithered where they're drawn into 8 or 10-bit
+    /// targets without sRGB encoding, which hides the bands subtle gradients show.
+    pub dithering: bool,
+    /// Which quads, underlines and strikethroughs are aligned to device pixels.
+    pub pixel_snapping: PixelSnappingPolicy,
+    /// How often each window logs statistics about the frames it drew, or `None` to not log
+    /// them.
+    pub frame_stats_logging: Option<FrameStatsLogging>,
+}
+
+impl Default for RendererSettings {
+    fn default() -> Self {
+        Self {
+            path_sample_count: 4,
+            fonts_gamma: 1.8,
+            grayscale_enhanced_contrast: 1.0,
+            present_mode: PresentMode::default(),
+            monochrome_atlas_strategy: AtlasAllocationStrategy::default(),
+            polychrome_atlas_strategy: AtlasAllocationStrategy::default(),
+            white_point: WhitePoint::default(),
+            eco_mode: EcoMode::default(),
+            dithering: true,
+            pixel_snapping: PixelSnappingPolicy::default(),
+            frame_stats_logging: None,
+        }
+    }
+}
+
+impl RendererSettings {
+    /// Sets [`RendererSettings::path_sample_count`].
+    pub fn with_path_sample_count(mut self, path_sample_count: u32) -> Self {
+        self.path_sample_count = path_sample_count;
+        self
+    }
+
+    /// Sets [`RendererSettings::fonts_gamma`].
+    pub fn with_fonts_gamma(mut self, fonts_gamma: f32) -> Self {
+        self.fonts_gamma = fonts_gamma;
+        self
+    }
+
+    /// Sets [`RendererSettings::grayscale_enhanced_contrast`].
+    pub fn with_grayscale_enhanced_contrast(mut self, grayscale_enhanced_contrast: f32) -> Self {
+        self.grayscale_enhanced_contrast = grayscale_enhanced_contrast;
+        self
+    }
+
+    /// Sets [`RendererSettings::present_mode`].
+    pub fn with_present_mode(mut self, present_mode: PresentMode) -> Self {
+        self.present_mode = present_mode;
+        self
+    }
+
+    /// Sets [`RendererSettings::monochrome_atlas_strategy`].
+    pub fn with_monochrome_atlas_strategy(mut self, strategy: AtlasAllocationStrategy) -> Self {
+        self.monochrome_atlas_strategy = strategy;
+        self
+    }
+
+    /// Sets [`RendererSettings::polychrome_atlas_strategy`].
+    pub fn with_polychrome_atlas_strategy(mut self, strategy: AtlasAllocationStrategy) -> Self {
+        self.polychrome_atlas_strategy = strategy;
+        self
+    }
+
+    /// Sets [`RendererSettings::white_point`].
+    pub fn with_white_point(mut self, white_point: WhitePoint) -> Self {
+        self.white_point = white_point;
+        self
+    }
+
+    /// Sets [`RendererSettings::eco_mode`].
+    pub fn with_eco_mode(mut self, eco_mode: EcoMode) -> Self {
+        self.eco_mode = eco_mode;
+        self
+    }
+
+    /// Sets [`RendererSettings::dithering`].
+    pub fn with_dithering(mut self, dithering: bool) -> Self {
+        self.dithering = dithering;
+        self
+    }
+
+    /// Sets [`RendererSettings::pixel_snapping`].
+    pub fn with_pixel_snapping(mut self, pixel_snapping: PixelSnappingPolicy) -> Self {
+        self.pixel_snapping = pixel_snapping;
+        self
+    }
+
+    /// Sets [`RendererSettings::frame_stats_logging`].
+    pub fn with_frame_stats_logging(mut self, logging: Option<FrameStatsLogging>) -> Self {
+        self.frame_stats_logging = logging;
+        self
+    }
+}
+
+/// How a window logs statistics about the frames it drew, see
+/// [`RendererSettings::frame_stats_logging`]. Once per interval, the percentiles of the time the
+/// frames took to draw on the CPU, their draw calls, the bytes they uploaded to the GPU, and how
+/// full the sprite atlas is are logged at the info level, with the `gpui::frame_stats` target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameStatsLogging {
+    /// How long the frames of each summary are collected for.
+    pub interval: std::time::Duration,
+    /// How each summary is written.
+    pub format: FrameStatsFormat,
+}
+
+impl Default for FrameStatsLogging {
+    fn default() -> Self {
+        Self {
+            interval: std::time::Duration::from_secs(5),
+            format: FrameStatsFormat::default(),
+        }
+    }
+}
+
+/// How frame statistics are written to the log, see [`FrameStatsLogging`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FrameStatsFormat {
+    /// A line meant to be read.
+    #[default]
+    Text,
+    /// A JSON object on a single line, for dashboards to parse.
+    Json,
+}
+
+/// The white point the renderer shifts colors to, like a night light does. See
+/// [`RendererSettings::white_point`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum WhitePoint {
+    /// Presents colors unchanged, as daylight at 6500K.
+    #[default]
+    Neutral,
+    /// Shifts colors to the white of a light at this color temperature, in kelvin, between 1000
+    /// and 40000. Lower temperatures are warmer.
+    Temperature(f32),
+    /// Follows the OS night light where it can be detected, which is GNOME and KDE Plasma on
+    /// Linux, and presents colors unchanged elsewhere. It's checked about once a minute.
+    System,
+}
+
+/// Which primitives the renderer aligns to device pixels, see [`RendererSettings::pixel_snapping`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PixelSnappingPolicy {
+    /// Draws every primitive where it's painted, ignoring their [`PixelSnapping`].
+    Never,
+    /// Snaps each primitive as its [`PixelSnapping`] requests.
+    #[default]
+    Requested,
+    /// Snaps the bounds and borders of every quad, underline and strikethrough.
+    Always,
+}
+
+/// When the renderer saves power, see [`RendererSettings::eco_mode`]. While it does, windows draw
+/// at most 30 frames per second, paths aren't multisampled, and the producers of
+/// [`WgpuSurfaceHandle`]s whose surfaces aren't shown submit at most once per second.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EcoMode {
+    /// Saves power while the [`PowerState`] is constrained: on battery, in the OS power saver
+    /// mode, or thermally throttled.
+    #[default]
+    Automatic,
+    /// Always saves power.
+    Always,
+    /// Never saves power.
+    Never,
+}
+
+/// How an atlas packs tiles into its textures. See [`Window::atlas_stats`] to compare them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum AtlasAllocationStrategy {
+    /// Groups tiles of similar sizes into buckets. Allocates quickly, but fragments when tile
+    /// sizes vary a lot, as with mixed glyphs and icons.
+    #[default]
+    Bucketed,
+    /// Packs tiles onto shelves and merges freed space, which fits mixed sizes more tightly at a
+    /// slightly higher allocation cost.
+    Shelf,
+}
+
+/// How the wgpu renderer picks the texture format of a window's surface, set with
+/// [`WindowOptions::surface_format`].
+///
+/// GPUI's shaders produce sRGB encoded colors. On a UNORM surface they're written as they are; on
+/// an sRGB surface, which encodes on write, the renderer decodes them first. Both look the same,
+/// but an sRGB surface blends in linear space.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SurfaceFormatPreference {
+    /// Whether to prefer an sRGB format over a UNORM one.
+    pub prefer_srgb: bool,
+    /// Formats picked first, in order, when the surface supports them.
+    pub preferred_formats: Vec<wgpu::TextureFormat>,
+    /// When not empty, only these formats may be picked, and opening the window fails if the
+    /// surface supports none of them.
+    pub allowed_formats: Vec<wgpu::TextureFormat>,
+    /// Renders through a view of the surface in this format instead of the surface's own, which
+    /// must be its sRGB or UNORM counterpart, for example an sRGB view of a UNORM surface. Ignored
+    /// with a warning when the surface can't be reinterpreted that way.
+    pub view_format: Option<wgpu::TextureFormat>,
+}
+
+impl SurfaceFormatPreference {
+    /// Prefers an sRGB format over a UNORM one.
+    pub fn srgb() -> Self {
+        Self {
+            prefer_srgb: true,
+            ..Default::default()
+        }
+    }
+
+    /// Sets [`SurfaceFormatPreference::preferred_formats`].
+    pub fn with_preferred_formats(mut self, formats: Vec<wgpu::TextureFormat>) -> Self {
+        self.preferred_formats = formats;
+        self
+    }
+
+    /// Sets [`SurfaceFormatPreference::allowed_formats`].
+    pub fn with_allowed_formats(mut self, formats: Vec<wgpu::TextureFormat>) -> Self {
+        self.allowed_formats = formats;
+        self
+    }
+
+    /// Sets [`SurfaceFormatPreference::view_format`].
+    pub fn with_view_format(mut self, format: wgpu::TextureFormat) -> Self {
+        self.view_format = Some(format);
+        self
+    }
+
+    /// Picks a format from those the surface supports, or `None` if none of them are allowed.
+    pub(crate) fn select(&self, supported: &[wgpu::TextureFormat]) -> Option<wgpu::TextureFormat> {
+        let allowed = |format: &&wgpu::TextureFormat| {
+            self.allowed_formats.is_empty() || self.allowed_formats.contains(format)
+        };
+        self.preferred_formats
+            .iter()
+            .find(|format| supported.contains(format) && allowed(format))
+            .or_else(|| {
+                supported
+                    .iter()
+                    .filter(allowed)
+                    .find(|format| format.is_srgb() == self.prefer_srgb)
+            })
+            .or_else(|| supported.iter().find(allowed))
+            .copied()
+    }
+}
+
+/// Where a [`FrameRecording`] writes the frames it captures.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FrameRecordingOutput {
+    /// Writes each frame to the directory as a numbered PNG: `frame_000000.png`,
+    /// `frame_000001.png` and so on. The directory is created if it doesn't exist.
+    ImageSequence(std::path::PathBuf),
+    /// Pipes the raw RGBA pixels of each frame to the standard input of an encoder process, for
+    /// example `ffmpeg -f rawvideo -pixel_format rgba -video_size 1280x720 -i - demo.mp4`. Frames
+    /// with a different size than the first one are skipped, since raw video has a fixed size.
+    Encoder {
+        /// The encoder executable.
+        program: std::path::PathBuf,
+        /// The arguments passed to the encoder.
+        args: Vec<String>,
+    },
+}
+
+/// Settings for recording the frames a window presents, started with
+/// [`Window::start_frame_recording`].
+///
+/// Frames are read back from the GPU and written on a background thread. When writing can't
+/// keep up, frames are dropped rather than stalling rendering.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameRecording {
+    /// Where the frames are written.
+    pub output: FrameRecordingOutput,
+    /// Only every `frame_interval`th presented frame is recorded. 1 records every frame.
+    pub frame_interval: u32,
+    /// The most frames recorded per second, or `None` for no limit.
+    pub max_frames_per_second: Option<f32>,
+    /// How many recorded frames may wait to be written before new ones are dropped.
+    pub max_pending_frames: usize,
+}
+
+impl FrameRecording {
+    /// Records every frame to `output`.
+    pub fn new(output: FrameRecordingOutput) -> Self {
+        Self {
+            output,
+            frame_interval: 1,
+            max_frames_per_second: None,
+            max_pending_frames: 4,
+        }
+    }
+
+    /// Sets [`FrameRecording::frame_interval`].
+    pub fn with_frame_interval(mut self, frame_interval: u32) -> Self {
+        self.frame_interval = frame_interval;
+        self
+    }
+
+    /// Sets [`FrameRecording::max_frames_per_second`].
+    pub fn with_max_frames_per_second(mut self, max_frames_per_second: f32) -> Self {
+        self.max_frames_per_second = Some(max_frames_per_second);
+        self
+    }
+
+    /// Sets [`FrameRecording::max_pending_frames`].
+    pub fn with_max_pending_frames(mut self, max_pending_frames: usize) -> Self {
+        self.max_pending_frames = max_pending_frames;
+        self
+    }
+}
+
+/// A region of a window's scene and where [`Window::draw_region`] draws it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SceneViewport {
+    /// The part of the scene to draw, in the window's device pixels.
+    pub source: Bounds<DevicePixels>,
+    /// The part of the target texture the source is stretched over.
+    pub destination: Bounds<DevicePixels>,
+}
+
+impl SceneViewport {
+    /// Draws `source` into `destination`, scaling it to fit.
+    pub fn new(source: Bounds<DevicePixels>, destination: Bounds<DevicePixels>) -> Self {
+        Self {
+            source,
+            destination,
+        }
+    }
+}
+
+/// One eye's view into an [`XrSwapchainTarget`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct XrEyeView {
+    /// The array layer of the swapchain image the eye is drawn into.
+    pub layer: u32,
+    /// The region of the layer the window's scene is stretched over.
+    pub viewport: Bounds<DevicePixels>,
+}
+
+/// A swapchain image of an OpenXR session that a window's scene is drawn into with
+/// [`Window::draw_to_xr_swapchain`], so the window can be shown as a 2D panel in a headset.
+///
+/// The texture is an array texture with a layer per eye, wrapped from the image the session
+/// acquired for this frame, typically with `wgpu::Device::create_texture_from_hal`. It must
+/// belong to the device the window renders with, be usable as a render attachment, and have the
+/// format returned by [`Window::xr_swapchain_format`].
+#[derive(Clone, Debug)]
+pub struct XrSwapchainTarget {
+    /// The swapchain image.
+    pub texture: wgpu::Texture,
+    /// The views the scene is drawn into, one per eye.
+    pub eyes: Vec<XrEyeView>,
+}
+
+impl XrSwapchainTarget {
+    /// Targets `texture` without any eyes yet.
+    pub fn new(texture: wgpu::Texture) -> Self {
+        Self {
+            texture,
+            eyes: Vec::new(),
+        }
+    }
+
+    /// Adds an eye that draws the scene into `viewport` of the layer `layer`.
+    pub fn with_eye(mut self, layer: u32, viewport: Bounds<DevicePixels>) -> Self {
+        self.eyes.push(XrEyeView { layer, viewport });
+        self
+    }
+}
+
+/// A transform of the colors of the frames a window presents, set with
+/// [`Window::set_color_filter`], to simulate color vision deficiencies, correct for them, or show
+/// a window in grayscale.
+///
+/// Each row computes the red, green or blue channel of a presented pixel from the linear red,
+/// green and blue channels of the drawn pixel and an offset in the fourth column.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorFilter {
+    /// The rows computing red, green and blue.
+    pub matrix: [[f32; 4]; 3],
+}
+
+impl ColorFilter {
+    /// Transforms colors by `matrix`.
+    pub fn new(matrix: [[f32; 4]; 3]) -> Self {
+        Self { matrix }
+    }
+
+    /// Shows the frames in grayscale, keeping the luminance of their colors.
+    pub fn grayscale() -> Self {
+        let luminance = [0.2126, 0.7152, 0.0722, 0.0];
+        Self::new([luminance, luminance, luminance])
+    }
+
+    /// Shows the frames as someone with deuteranopia sees them, following Machado et al. 2009.
+    pub fn deuteranopia() -> Self {
+        Self::new([
+            [0.367322, 0.860646, -0.227968, 0.0],
+            [0.280085, 0.672501, 0.047413, 0.0],
+            [-0.011820, 0.042940, 0.968881, 0.0],
+        ])
+    }
+}
+
+/// The kind of primitive rendered by a [`DrawCall`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DrawCallKind {
+    /// A batch of quads.
+    Quads,
+    /// A batch of shadows.
+    Shadows,
+    /// A batch of underlines.
+    Underlines,
+    /// A batch of monochrome sprites, such as glyphs.
+    MonochromeSprites,
+    /// A batch of polychrome sprites, such as images and emoji.
+    PolychromeSprites,
+    /// A single grid of cells, such as a terminal's screen.
+    CellGrid,
+    /// A batch of selection highlights, drawn a line at a time.
+    Selections,
+    /// A single externally rendered surface.
+    Surface,
+    /// A single clip drawn into or removed from the stencil buffer.
+    Clip,
+    /// A batch of primitives drawn by a render pipeline plugin.
+    Plugin,
+}
+
+/// A draw call issued by the renderer, as recorded when draw call recording is enabled.
+#[derive(Clone, Debug)]
+pub struct DrawCall {
+    /// The kind of primitive drawn.
+    pub kind: DrawCallKind,
+    /// The number of instances drawn.
+    pub instance_count: u32,
+    /// The index of the atlas texture sampled, for sprite batches.
+    pub atlas_texture: Option<u32>,
+    /// The byte range of the instance buffer read by this draw call.
+    pub buffer_range: Option<std::ops::Range<u64>>,
+}
+
+/// The draw calls the renderer issued for a single frame.
+#[derive(Clone, Debug, Default)]
+pub struct FrameDrawCalls {
+    /// The draw calls, in submission order.
+    pub draw_calls: Vec<DrawCall>,
+    /// The primitives that weren't drawn because they couldn't be seen.
+    pub culled: CulledPrimitives,
+}
+
+/// The number of primitives removed from a frame before it was uploaded, because they couldn't
+/// be seen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CulledPrimitives {
+    /// Primitives entirely outside of the window.
+    pub outside_viewport: usize,
+    /// Primitives entirely covered by an opaque quad drawn after them, such as a panel or modal.
+    pub occluded: usize,
+}
+
+impl CulledPrimitives {
+    /// Returns the number of primitives culled for either reason.
+    pub fn total(&self) -> usize {
+        self.outside_viewport + self.occluded
+    }
+}
+
+impl FrameDrawCalls {
+    /// Returns the number of draw calls of the given kind.
+    pub fn count(&self, kind: DrawCallKind) -> usize {
+        self.draw_calls
+            .iter()
+            .filter(|draw_call| draw_call.kind == kind)
+            .count()
+    }
+}
+
+/// How a window's frames are reaching the display, as of the last frame it presented, so
+/// animations can adapt to the rate frames are actually shown at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PresentStats {
+    /// The time between the last two frames presented, or `None` until two have been.
+    pub last_present_duration: Option<std::time::Duration>,
+    /// The number of display refreshes frames missed since the window was created, while the
+    /// window was drawing continuously. Refreshes passed while it was idle aren't counted.
+    pub missed_vblank_count: u64,
+    /// How often the display the window is on refreshes.
+    pub refresh_interval: std::time::Duration,
+}
@@ -480,6 +480,76 @@ impl ScrollDelta {
     }
 }
 
+/// A pinch gesture from a touchpad, usually used to zoom.
+#[derive(Clone, Debug, Default)]
+pub struct PinchEvent {
+    /// The position of the mouse on the window.
+    pub position: Point<Pixels>,
+
+    /// The change in magnification for this event. Positive values zoom in.
+    pub delta: f32,
+
+    /// The magnification since the gesture started, as a scale factor.
+    pub scale: f32,
+
+    /// The modifiers that were held down during the gesture.
+    pub modifiers: Modifiers,
+
+    /// The phase of the gesture.
+    pub touch_phase: TouchPhase,
+}
+
+impl Sealed for PinchEvent {}
+impl InputEvent for PinchEvent {
+    fn to_platform_input(self) -> PlatformInput {
+        PlatformInput::Pinch(self)
+    }
+}
+impl MouseEvent for PinchEvent {}
+
+impl Deref for PinchEvent {
+    type Target = Modifiers;
+
+    fn deref(&self) -> &Self::Target {
+        &self.modifiers
+    }
+}
+
+/// A two-finger rotation gesture from a touchpad.
+#[derive(Clone, Debug, Default)]
+pub struct RotationEvent {
+    /// The position of the mouse on the window.
+    pub position: Point<Pixels>,
+
+    /// The change in rotation for this event, in degrees. Positive values are counterclockwise.
+    pub delta: f32,
+
+    /// The rotation since the gesture started, in degrees.
+    pub rotation: f32,
+
+    /// The modifiers that were held down during the gesture.
+    pub modifiers: Modifiers,
+
+    /// The phase of the gesture.
+    pub touch_phase: TouchPhase,
+}
+
+impl Sealed for RotationEvent {}
+impl InputEvent for RotationEvent {
+    fn to_platform_input(self) -> PlatformInput {
+        PlatformInput::Rotation(self)
+    }
+}
+impl MouseEvent for RotationEvent {}
+
+impl Deref for RotationEvent {
+    type Target = Modifiers;
+
+    fn deref(&self) -> &Self::Target {
+        &self.modifiers
+    }
+}
+
 /// A mouse exit event from the platform, generated when the mouse leaves the window.
 #[derive(Clone, Debug, Default)]
 pub struct MouseExitEvent {
@@ -508,6 +578,34 @@ impl Deref for MouseExitEvent {
     }
 }
 
+/// A paste from the primary selection, generated on Linux when the middle mouse button is pressed,
+/// following the X11 and Wayland convention. Read the pasted content with
+/// [`App::read_from_primary`](crate::App::read_from_primary).
+#[derive(Clone, Debug, Default)]
+pub struct PrimaryPasteEvent {
+    /// The position of the mouse relative to the window.
+    pub position: Point<Pixels>,
+    /// The modifiers that were held down when the mouse button was pressed.
+    pub modifiers: Modifiers,
+}
+
+impl Sealed for PrimaryPasteEvent {}
+impl InputEvent for PrimaryPasteEvent {
+    fn to_platform_input(self) -> PlatformInput {
+        PlatformInput::PrimaryPaste(self)
+    }
+}
+
+impl MouseEvent for PrimaryPasteEvent {}
+
+impl Deref for PrimaryPasteEvent {
+    type Target = Modifiers;
+
+    fn deref(&self) -> &Self::Target {
+        &self.modifiers
+    }
+}
+
 /// A collection of paths from the platform, such as from a file drop.
 #[derive(Debug, Clone, Default, Eq, PartialEq)]
 pub struct ExternalPaths(pub(crate) SmallVec<[PathBuf; 2]>);
@@ -577,8 +675,14 @@ pub enum PlatformInput {
     MouseExited(MouseExitEvent),
     /// The scroll wheel was used.
     ScrollWheel(ScrollWheelEvent),
+    /// A pinch gesture was performed on a touchpad.
+    Pinch(PinchEvent),
+    /// A rotation gesture was performed on a touchpad.
+    Rotation(RotationEvent),
     /// Files were dragged and dropped onto the window.
     FileDrop(FileDropEvent),
+    /// The primary selection was pasted with the middle mouse button.
+    PrimaryPaste(PrimaryPasteEvent),
 }
 
 impl PlatformInput {
@@ -592,7 +696,10 @@ impl PlatformInput {
             PlatformInput::MouseMove(event) => Some(event),
             PlatformInput::MouseExited(event) => Some(event),
             PlatformInput::ScrollWheel(event) => Some(event),
+            PlatformInput::Pinch(event) => Some(event),
+            PlatformInput::Rotation(event) => Some(event),
             PlatformInput::FileDrop(event) => Some(event),
+            PlatformInput::PrimaryPaste(event) => Some(event),
         }
     }
 
@@ -606,7 +713,10 @@ impl PlatformInput {
             PlatformInput::MouseMove(_) => None,
             PlatformInput::MouseExited(_) => None,
             PlatformInput::ScrollWheel(_) => None,
+            PlatformInput::Pinch(_) => None,
+            PlatformInput::Rotation(_) => None,
             PlatformInput::FileDrop(_) => None,
+            PlatformInput::PrimaryPaste(_) => None,
         }
     }
 }
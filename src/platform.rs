@@ -41,11 +41,11 @@ use crate::platform::cross::platform::CrossPlatform;
 use crate::{
     Action, AnyWindowHandle, App, AsyncWindowContext, BackgroundExecutor, Bounds,
     DEFAULT_WINDOW_SIZE, DevicePixels, DispatchEventResult, Font, FontId, FontMetrics, FontRun,
-    ForegroundExecutor, GlyphId, GpuSpecs, ImageSource, Keymap, LineLayout, Pixels, PlatformInput,
-    Point, Priority, RealtimePriority, RenderGlyphParams, RenderImage, RenderImageParams,
-    RenderSvgParams, Scene, ShapedGlyph, ShapedRun, SharedString, Size, SvgRenderer,
-    SystemWindowTab, Task, TaskLabel, TaskTiming, ThreadTaskTimings, Window, WindowControlArea,
-    hash, point, px, size,
+    ForegroundExecutor, GlyphId, GpuSpecs, Hsla, ImageSource, Keymap, LineLayout, Pixels,
+    PlatformInput, Point, Priority, RealtimePriority, RenderGlyphParams, RenderImage,
+    RenderImageParams, RenderLayerParams, RenderSvgParams, Scene, ShapedGlyph, ShapedRun, SharedString, Size,
+    SvgRenderer, SystemWindowTab, Task, TaskLabel, TaskTiming, ThreadTaskTimings, Window,
+    WindowControlArea, hash, point, px, size,
 };
 use anyhow::Result;
 use async_task::Runnable;
@@ -88,6 +88,11 @@ pub(crate) use test::*;
 // #[cfg(all(target_os = "linux", feature = "wayland"))]
 // pub use linux::layer_shell;
 
+pub use cross::embedding::{EmbeddedApp, EmbeddedDevice};
+#[cfg(any(test, feature = "test-support"))]
+pub use cross::snapshot::{
+    SnapshotComparison, assert_snapshot, assert_snapshot_with, render_snapshot,
+};
 #[cfg(any(test, feature = "test-support"))]
 pub use test::TestDispatcher;
 
@@ -96,10 +101,20 @@ pub fn background_executor() -> BackgroundExecutor {
     current_platform(true).background_executor()
 }
 
-pub(crate) fn current_platform(_headless: bool) -> Rc<dyn Platform> {
-    // TODO(mdeand): Support headless
+/// Draws a scene saved with [`Window::save_scene`] offscreen and returns the resulting frame, so
+/// rendering artifacts can be reproduced without the app that produced them.
+pub fn replay_scene(scene_file: &Path) -> Result<image::RgbaImage> {
+    cross::renderer::WgpuRenderer::replay(scene_file)
+}
+
+pub(crate) fn current_platform(headless: bool) -> Rc<dyn Platform> {
     // TODO(mdeand): Monomorphize Platform and its associated types.
-    Rc::new(CrossPlatform::new().expect("Failed to initialize platform"))
+    let platform = if headless {
+        CrossPlatform::headless()
+    } else {
+        CrossPlatform::new()
+    };
+    Rc::new(platform.expect("Failed to initialize platform"))
 }
 
 // #[cfg(target_os = "macos")]
@@ -178,6 +193,16 @@ pub(crate) trait Platform: 'static {
     fn text_system(&self) -> Arc<dyn PlatformTextSystem>;
 
     fn run(&self, on_finish_launching: Box<dyn 'static + FnOnce()>);
+    /// Launches the app like [`Platform::run`], but returns instead of handing over the main
+    /// thread, for apps that drive the event loop with [`Platform::pump_events`].
+    fn start(&self, on_finish_launching: Box<dyn 'static + FnOnce()>) {
+        self.run(on_finish_launching)
+    }
+    /// Processes the pending events of an app launched with [`Platform::start`], waiting at most
+    /// `timeout` for new ones. Returns false once the app has quit.
+    fn pump_events(&self, _timeout: Option<Duration>) -> bool {
+        false
+    }
     fn quit(&self);
     fn restart(&self, binary_path: Option<PathBuf>);
     fn activate(&self, ignoring_other_apps: bool);
@@ -201,6 +226,47 @@ pub(crate) trait Platform: 'static {
     /// Returns the appearance of the application's windows.
     fn window_appearance(&self) -> WindowAppearance;
 
+    /// Returns the accent and title bar colors the OS uses for window chrome.
+    fn system_appearance(&self) -> SystemAppearance {
+        SystemAppearance::default()
+    }
+
+    /// Registers a callback invoked when [`Platform::system_appearance`] changes.
+    fn on_system_appearance_changed(&self, _callback: Box<dyn FnMut()>) {}
+
+    /// Registers a callback invoked when displays are connected or disconnected, or change their
+    /// bounds.
+    fn on_displays_changed(&self, _callback: Box<dyn FnMut()>) {}
+
+    /// Whether the system is on battery, in power saver mode or thermally throttled.
+    fn power_state(&self) -> PowerState {
+        PowerState::default()
+    }
+
+    /// Registers a callback invoked when [`Platform::power_state`] changes.
+    fn on_power_state_changed(&self, _callback: Box<dyn FnMut()>) {}
+
+    /// The user's preferred text size as a multiple of the default, separate from the display's
+    /// scale factor.
+    fn text_scale_factor(&self) -> f32 {
+        1.0
+    }
+
+    /// Registers a callback invoked when [`Platform::text_scale_factor`] changes.
+    fn on_text_scale_factor_changed(&self, _callback: Box<dyn FnMut()>) {}
+
+    /// Whether a blinking caret should currently be shown. It stays shown where the platform
+    /// doesn't blink it.
+    fn cursor_blink_visible(&self) -> bool {
+        true
+    }
+
+    /// Registers a callback invoked when [`Platform::cursor_blink_visible`] changes.
+    fn on_cursor_blink(&self, _callback: Box<dyn FnMut()>) {}
+
+    /// Shows the caret and restarts its blink, such as when focus changes.
+    fn reset_cursor_blink(&self) {}
+
     fn open_url(&self, url: &str);
     fn on_open_urls(&self, callback: Box<dyn FnMut(Vec<String>)>);
     fn register_url_scheme(&self, url: &str) -> Task<Result<()>>;
@@ -221,6 +287,17 @@ pub(crate) trait Platform: 'static {
     fn on_quit(&self, callback: Box<dyn FnMut()>);
     fn on_reopen(&self, callback: Box<dyn FnMut()>);
 
+    /// Makes this the only running instance of the application, so launching it again invokes
+    /// the [`Platform::on_reopen`] callback of this instance. Returns false if another instance is
+    /// already running, after asking it to reopen.
+    fn claim_single_instance(&self) -> bool {
+        true
+    }
+
+    /// Quits the application in an orderly way when the process receives a termination signal,
+    /// instead of leaving the signal's default action in place.
+    fn handle_termination_signals(&self) {}
+
     fn set_menus(&self, menus: Vec<Menu>, keymap: &Keymap);
     fn get_menus(&self) -> Option<Vec<OwnedMenu>> {
         None
@@ -245,6 +322,7 @@ pub(crate) trait Platform: 'static {
     }
     fn app_path(&self) -> Result<PathBuf>;
     fn path_for_auxiliary_executable(&self, name: &str) -> Result<PathBuf>;
+    fn set_auxiliary_executable_dir(&self, _dir: PathBuf) {}
 
     fn set_cursor_style(&self, style: CursorStyle);
     fn should_auto_hide_scrollbars(&self) -> bool;
@@ -255,6 +333,12 @@ pub(crate) trait Platform: 'static {
     #[cfg(any(target_os = "linux", target_os = "freebsd"))]
     fn read_from_primary(&self) -> Option<ClipboardItem>;
     fn read_from_clipboard(&self) -> Option<ClipboardItem>;
+    fn read_from_clipboard_formats(&self, formats: &[ClipboardFormat]) -> Option<ClipboardItem> {
+        let mut item = self.read_from_clipboard()?;
+        item.entries
+            .retain(|entry| formats.contains(&entry.format()));
+        (!item.entries.is_empty()).then_some(item)
+    }
 
     fn write_credentials(&self, url: &str, username: &str, password: &[u8]) -> Task<Result<()>>;
     fn read_credentials(&self, url: &str) -> Task<Result<Option<(String, Vec<u8>)>>>;
@@ -263,6 +347,30 @@ pub(crate) trait Platform: 'static {
     fn keyboard_layout(&self) -> Box<dyn PlatformKeyboardLayout>;
     fn keyboard_mapper(&self) -> Rc<dyn PlatformKeyboardMapper>;
     fn on_keyboard_layout_change(&self, callback: Box<dyn FnMut()>);
+    fn on_gpu_error(&self, _callback: Box<dyn FnMut(crate::GpuError)>) {}
+    fn on_raw_winit_event(
+        &self,
+        _callback: Box<dyn FnMut(AnyWindowHandle, &winit::event::WindowEvent) -> bool>,
+    ) {
+    }
+    fn set_gpu_memory_budget(&self, _budget: Option<u64>) {}
+    fn gpu_memory_usage(&self) -> Option<crate::GpuMemoryUsage> {
+        None
+    }
+    fn renderer_settings(&self) -> crate::RendererSettings {
+        crate::RendererSettings::default()
+    }
+    fn set_renderer_settings(&self, _settings: crate::RendererSettings) {}
+    fn gpu_handle(&self) -> Option<crate::GpuHandle> {
+        None
+    }
+    fn synchronize_presents(&self, _windows: &[AnyWindowHandle]) {}
+    fn register_render_pipeline_plugin(
+        &self,
+        _plugin_type: std::any::TypeId,
+        _plugin: std::sync::Arc<dyn crate::RenderPipelinePlugin>,
+    ) {
+    }
 }
 
 /// A handle to a platform's display, e.g. a monitor or laptop screen.
@@ -441,6 +549,9 @@ pub(crate) trait PlatformWindow: HasWindowHandle + HasDisplayHandle {
     fn appearance(&self) -> WindowAppearance;
     fn display(&self) -> Option<Rc<dyn PlatformDisplay>>;
     fn mouse_position(&self) -> Point<Pixels>;
+    /// Moves the cursor to `position` in the window, or draws a cursor there where the platform
+    /// doesn't let apps move the real one.
+    fn set_mouse_position(&self, _position: Point<Pixels>) {}
     fn modifiers(&self) -> Modifiers;
     fn capslock(&self) -> Capslock;
     fn set_input_handler(&mut self, input_handler: PlatformInputHandler);
@@ -471,6 +582,12 @@ pub(crate) trait PlatformWindow: HasWindowHandle + HasDisplayHandle {
     fn on_hit_test_window_control(&self, callback: Box<dyn FnMut() -> Option<WindowControlArea>>);
     fn on_close(&self, callback: Box<dyn FnOnce()>);
     fn on_appearance_changed(&self, callback: Box<dyn FnMut()>);
+    /// Registers a callback for when the window's first frame has been presented.
+    fn on_first_frame(&self, _callback: Box<dyn FnOnce()>) {}
+    /// Registers a callback for when the renderer fails to draw a frame.
+    fn on_renderer_error(&self, _callback: Box<dyn FnMut(crate::RendererError)>) {}
+    /// Registers a callback for when the renderer starts or stops being overloaded.
+    fn on_frame_overload(&self, _callback: Box<dyn FnMut(bool)>) {}
     fn draw(&self, scene: &Scene);
     fn completed_frame(&self) {}
     fn sprite_atlas(&self) -> Arc<dyn PlatformAtlas>;
@@ -520,10 +637,90 @@ pub(crate) trait PlatformWindow: HasWindowHandle + HasDisplayHandle {
         WindowControls::default()
     }
     fn set_client_inset(&self, _inset: Pixels) {}
+    fn set_input_passthrough(&self, _passthrough: bool) {}
+    fn set_input_passthrough_regions(&self, _regions: Vec<Bounds<Pixels>>) {}
     fn gpu_specs(&self) -> Option<GpuSpecs>;
 
     fn update_ime_position(&self, _bounds: Bounds<Pixels>);
 
+    /// Enables or disables recording of the draw calls issued for each frame.
+    fn set_draw_call_recording(&self, _enabled: bool) {}
+
+    /// Returns the draw calls issued for the last frame, if recording is enabled.
+    fn last_frame_draw_calls(&self) -> Option<crate::FrameDrawCalls> {
+        None
+    }
+
+    /// Returns how the window's frames are reaching the display, once its renderer exists.
+    fn present_stats(&self) -> Option<crate::PresentStats> {
+        None
+    }
+
+    /// Starts recording the frames this window presents.
+    fn start_frame_recording(&self, _recording: crate::FrameRecording) -> Result<()> {
+        anyhow::bail!("frame recording is not supported on this platform")
+    }
+
+    /// Stops recording frames, waiting for the frames already captured to be written.
+    fn stop_frame_recording(&self) {}
+
+    /// Overrides the color the window is cleared to before each frame, or restores the default
+    /// for the window's background appearance when `None`.
+    fn set_clear_color(&self, _color: Option<crate::Hsla>) {}
+
+    /// Magnifies the presented frame, or shows it unmagnified when `None`.
+    fn set_screen_zoom(&self, _zoom: Option<crate::ScreenZoom>) {}
+
+    /// The magnification of the presented frame, if it's zoomed.
+    fn screen_zoom(&self) -> Option<crate::ScreenZoom> {
+        None
+    }
+
+    /// Transforms the colors of the presented frame, or presents it unfiltered when `None`.
+    fn set_color_filter(&self, _color_filter: Option<crate::ColorFilter>) {}
+
+    /// The color filter applied to the presented frame, if any.
+    fn color_filter(&self) -> Option<crate::ColorFilter> {
+        None
+    }
+
+    /// Draws a region of `scene` into part of `target` without presenting it.
+    fn draw_region(
+        &self,
+        _scene: &Scene,
+        _viewport: crate::SceneViewport,
+        _target: &wgpu::Texture,
+    ) -> Result<()> {
+        anyhow::bail!("drawing scene regions is not supported on this platform")
+    }
+
+    /// Draws a region of `scene` into part of the `target_size` texture `target` views without
+    /// presenting it.
+    fn draw_region_to_view(
+        &self,
+        _scene: &Scene,
+        _viewport: crate::SceneViewport,
+        _target: &wgpu::TextureView,
+        _target_size: Size<DevicePixels>,
+    ) -> Result<()> {
+        anyhow::bail!("drawing scene regions is not supported on this platform")
+    }
+
+    /// The format OpenXR swapchain images must have to be drawn into, if the renderer supports
+    /// it.
+    fn xr_swapchain_format(&self) -> Option<wgpu::TextureFormat> {
+        None
+    }
+
+    /// Draws `scene` into each eye of an OpenXR swapchain image instead of the window's surface.
+    fn draw_to_xr_swapchain(
+        &self,
+        _scene: &Scene,
+        _target: &crate::XrSwapchainTarget,
+    ) -> Result<()> {
+        anyhow::bail!("drawing to OpenXR swapchains is not supported on this platform")
+    }
+
     /// Create a double-buffered WGPU surface handle for external rendering.
     /// Returns `None` on platforms that don't use the WGPU renderer.
     fn create_wgpu_surface(
@@ -535,6 +732,16 @@ pub(crate) trait PlatformWindow: HasWindowHandle + HasDisplayHandle {
         None
     }
 
+    /// Describes the live surfaces on the window's device, in the order they were created.
+    fn wgpu_surfaces(&self) -> Vec<crate::SurfaceInfo> {
+        Vec::new()
+    }
+
+    /// Describes the first live surface on the window's device created with `name`.
+    fn find_wgpu_surface(&self, _name: &str) -> Option<crate::SurfaceInfo> {
+        None
+    }
+
     #[cfg(any(test, feature = "test-support"))]
     fn as_test(&mut self) -> Option<&mut TestWindow> {
         None
@@ -572,6 +779,12 @@ pub trait PlatformDispatcher: Send + Sync {
         Instant::now()
     }
 
+    /// How long tasks dispatched to the main thread waited before they ran, if the platform
+    /// measures it.
+    fn main_thread_dispatch_latency(&self) -> Option<crate::DispatchLatency> {
+        None
+    }
+
     #[cfg(any(test, feature = "test-support"))]
     fn as_test(&self) -> Option<&TestDispatcher> {
         None
@@ -755,6 +968,7 @@ pub(crate) enum AtlasKey {
     Glyph(RenderGlyphParams),
     Svg(RenderSvgParams),
     Image(RenderImageParams),
+    Layer(RenderLayerParams),
 }
 
 impl AtlasKey {
@@ -775,7 +989,7 @@ impl AtlasKey {
                 }
             }
             AtlasKey::Svg(_) => AtlasTextureKind::Monochrome,
-            AtlasKey::Image(_) => AtlasTextureKind::Polychrome,
+            AtlasKey::Image(_) | AtlasKey::Layer(_) => AtlasTextureKind::Polychrome,
         }
     }
 }
@@ -798,6 +1012,12 @@ impl From<RenderImageParams> for AtlasKey {
     }
 }
 
+impl From<RenderLayerParams> for AtlasKey {
+    fn from(params: RenderLayerParams) -> Self {
+        Self::Layer(params)
+    }
+}
+
 pub(crate) trait PlatformAtlas: Send + Sync {
     fn get_or_insert_with<'a>(
         &self,
@@ -805,6 +1025,88 @@ pub(crate) trait PlatformAtlas: Send + Sync {
         build: &mut dyn FnMut() -> Result<Option<(Size<DevicePixels>, Cow<'a, [u8]>)>>,
     ) -> Result<Option<AtlasTile>>;
     fn remove(&self, key: &AtlasKey);
+
+    /// Returns the allocation state of every live texture, for debugging.
+    fn texture_snapshots(&self) -> Vec<AtlasTextureSnapshot> {
+        Vec::new()
+    }
+
+    /// Reads back the pixels of a texture, so scenes that sample from it can be saved.
+    fn read_texture(&self, id: AtlasTextureId) -> Result<AtlasTextureContents> {
+        anyhow::bail!("atlas texture {id:?} can't be read back on this platform")
+    }
+
+    /// Returns how full and fragmented the atlas textures are.
+    fn allocation_stats(&self) -> AtlasStats {
+        AtlasStats::default()
+    }
+}
+
+/// How full and fragmented a window's sprite atlas is, returned by [`Window::atlas_stats`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AtlasStats {
+    /// The textures holding glyphs.
+    pub monochrome: AtlasKindStats,
+    /// The textures holding images and emoji.
+    pub polychrome: AtlasKindStats,
+}
+
+/// Allocation statistics for the atlas textures of one kind.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AtlasKindStats {
+    /// The number of live textures.
+    pub textures: usize,
+    /// The number of tiles allocated in them.
+    pub tiles: usize,
+    /// The area covered by allocated tiles, in device pixels.
+    pub allocated_area: u64,
+    /// The area not covered by any tile, in device pixels.
+    pub free_area: u64,
+    /// How often a texture turned down a tile although it had enough free area for it, because
+    /// that area was split into pieces too small to hold the tile.
+    pub fragmented_allocations: u64,
+}
+
+impl AtlasKindStats {
+    /// The fraction of the textures' area that is covered by tiles.
+    pub fn occupancy(&self) -> f32 {
+        let total_area = self.allocated_area + self.free_area;
+        if total_area == 0 {
+            return 0.0;
+        }
+        (self.allocated_area as f64 / total_area as f64) as f32
+    }
+}
+
+/// The allocation state of a single atlas texture at a point in time.
+#[derive(Clone, Debug)]
+pub(crate) struct AtlasTextureSnapshot {
+    pub(crate) id: AtlasTextureId,
+    pub(crate) size: Size<DevicePixels>,
+    pub(crate) tiles: Vec<Bounds<DevicePixels>>,
+}
+
+impl AtlasTextureSnapshot {
+    /// The fraction of the texture's area that is covered by allocated tiles.
+    pub(crate) fn occupancy(&self) -> f32 {
+        let area = |size: Size<DevicePixels>| size.width.0 as f64 * size.height.0 as f64;
+        let total_area = area(self.size);
+        if total_area == 0.0 {
+            return 0.0;
+        }
+
+        let used_area: f64 = self.tiles.iter().map(|tile| area(tile.size)).sum();
+        (used_area / total_area) as f32
+    }
+}
+
+/// The pixels of an atlas texture, one byte per pixel for monochrome textures and RGBA for
+/// polychrome ones.
+#[derive(Clone, Debug)]
+pub(crate) struct AtlasTextureContents {
+    pub(crate) id: AtlasTextureId,
+    pub(crate) size: Size<DevicePixels>,
+    pub(crate) bytes: Vec<u8>,
 }
 
 struct AtlasTextureList<T> {
@@ -842,7 +1144,7 @@ impl<T> AtlasTextureList<T> {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(C)]
 pub(crate) struct AtlasTile {
     pub(crate) texture_id: AtlasTextureId,
@@ -851,7 +1153,7 @@ pub(crate) struct AtlasTile {
     pub(crate) bounds: Bounds<DevicePixels>,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[repr(C)]
 pub(crate) struct AtlasTextureId {
     // We use u32 instead of usize for Metal Shader Language compatibility
@@ -859,7 +1161,7 @@ pub(crate) struct AtlasTextureId {
     pub(crate) kind: AtlasTextureKind,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[repr(C)]
 #[cfg_attr(
     all(
@@ -873,7 +1175,7 @@ pub(crate) enum AtlasTextureKind {
     Polychrome = 1,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[repr(C)]
 pub(crate) struct TileId(pub(crate) u32);
 
@@ -1181,6 +1483,18 @@ pub struct WindowOptions {
 
     /// Tab group name, allows opening the window as a native tab on macOS 10.12+. Windows with the same tabbing identifier will be grouped together.
     pub tabbing_identifier: Option<String>,
+
+    /// Whether to draw and present the window on a dedicated render thread, so a slow GPU pass
+    /// doesn't block input handling. Only supported by the wgpu renderer.
+    pub render_thread: bool,
+
+    /// How the texture format of the window's surface is picked. Only supported by the wgpu
+    /// renderer.
+    pub surface_format: crate::SurfaceFormatPreference,
+
+    /// Whether a shown window stays hidden until its first frame is presented, so it never
+    /// appears without content. Only supported by the wgpu renderer.
+    pub show_after_first_frame: bool,
 }
 
 /// The variables that can be configured when creating a new window
@@ -1230,6 +1544,12 @@ pub(crate) struct WindowParams {
     pub window_min_size: Option<Size<Pixels>>,
     #[cfg(target_os = "macos")]
     pub tabbing_identifier: Option<String>,
+
+    pub render_thread: bool,
+
+    pub surface_format: crate::SurfaceFormatPreference,
+
+    pub show_after_first_frame: bool,
 }
 
 /// Represents the status of how a window should be opened.
@@ -1288,6 +1608,9 @@ impl Default for WindowOptions {
             window_min_size: None,
             window_decorations: None,
             tabbing_identifier: None,
+            render_thread: false,
+            surface_format: crate::SurfaceFormatPreference::default(),
+            show_after_first_frame: false,
         }
     }
 }
@@ -1348,6 +1671,43 @@ pub enum WindowAppearance {
     VibrantDark,
 }
 
+/// The colors the OS draws window chrome with, so applications can match them. Each is `None`
+/// where the OS doesn't have it or it can't be read.
+///
+/// On Windows these come from the personalization settings. On Linux they come from the
+/// appearance settings of the desktop portal, which GNOME and KDE Plasma provide, and from KDE
+/// Plasma's color scheme.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct SystemAppearance {
+    /// The accent color the user picked.
+    pub accent_color: Option<Hsla>,
+    /// Whether title bars are light or dark, which can differ from the windows' contents.
+    pub titlebar_appearance: Option<WindowAppearance>,
+    /// The background color of active title bars, when the OS colors them.
+    pub titlebar_color: Option<Hsla>,
+}
+
+/// Whether the system is short on power or cooling, which [`EcoMode::Automatic`] saves power for.
+///
+/// On Windows this comes from the system power status. On Linux it comes from the power supplies
+/// the kernel reports and from power-profiles-daemon.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PowerState {
+    /// Whether the system runs on its battery.
+    pub on_battery: bool,
+    /// Whether the OS power saver mode is on.
+    pub power_saver: bool,
+    /// Whether the system slows down to keep cool.
+    pub thermally_throttled: bool,
+}
+
+impl PowerState {
+    /// Whether any of the conditions [`EcoMode::Automatic`] saves power for holds.
+    pub fn is_constrained(&self) -> bool {
+        self.on_battery || self.power_saver || self.thermally_throttled
+    }
+}
+
 /// The appearance of the background of the window itself, when there is
 /// no content or the content is transparent.
 #[derive(Copy, Clone, Debug, Default, PartialEq)]
@@ -1557,6 +1917,30 @@ pub enum ClipboardEntry {
     Image(Image),
     /// A file entry
     ExternalPaths(crate::ExternalPaths),
+    /// A rich text entry, as an HTML fragment
+    Html(String),
+}
+
+/// A kind of data that can be read from the clipboard.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum ClipboardFormat {
+    /// Plain text, or the paths of copied files
+    Text,
+    /// Rich text, as an HTML fragment
+    Html,
+    /// An image
+    Image,
+}
+
+impl ClipboardEntry {
+    /// The format this entry is read from and written to the clipboard as.
+    pub fn format(&self) -> ClipboardFormat {
+        match self {
+            ClipboardEntry::String(_) | ClipboardEntry::ExternalPaths(_) => ClipboardFormat::Text,
+            ClipboardEntry::Image(_) => ClipboardFormat::Image,
+            ClipboardEntry::Html(_) => ClipboardFormat::Html,
+        }
+    }
 }
 
 impl ClipboardItem {
@@ -1593,6 +1977,17 @@ impl ClipboardItem {
         }
     }
 
+    /// Create a new ClipboardItem with the given HTML, and the plain text to paste into
+    /// applications that don't accept rich text
+    pub fn new_html(html: String, text: String) -> Self {
+        Self {
+            entries: vec![
+                ClipboardEntry::String(ClipboardString::new(text)),
+                ClipboardEntry::Html(html),
+            ],
+        }
+    }
+
     /// Concatenates together all the ClipboardString entries in the item.
     /// Returns None if there were no ClipboardString entries.
     pub fn text(&self) -> Option<String> {
@@ -1622,6 +2017,14 @@ impl ClipboardItem {
         }
     }
 
+    /// Returns the first ClipboardEntry::Html in the item, if any.
+    pub fn html(&self) -> Option<&str> {
+        self.entries.iter().find_map(|entry| match entry {
+            ClipboardEntry::Html(html) => Some(html.as_str()),
+            _ => None,
+        })
+    }
+
     /// If this item is one ClipboardEntry::String, returns its metadata.
     #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
     pub fn metadata(&self) -> Option<&String> {
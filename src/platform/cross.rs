@@ -1,9 +1,37 @@
 pub mod atlas;
+pub mod clipboard;
+pub mod cursor_blink;
+pub mod display;
 pub mod dispatcher;
+pub mod embedding;
+pub mod frame_pacing;
+pub mod frame_recorder;
+pub mod frame_stats;
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+pub mod gdbus;
+pub mod gpu_memory;
+pub mod headless_window;
+#[cfg(any(test, feature = "test-support"))]
+pub mod input_simulation;
 pub mod keyboard;
+pub mod night_light;
+pub mod nv12;
 pub mod platform;
+pub mod power_state;
+pub mod render_thread;
 pub mod renderer;
+pub mod renderer_settings;
+pub mod resource_pool;
+pub mod shader_preprocessor;
+pub mod single_instance;
+#[cfg(any(test, feature = "test-support"))]
+pub mod snapshot;
+pub mod submission_scheduler;
 pub mod surface_registry;
+pub mod system_appearance;
+pub mod text_scale;
 pub mod text_system;
 pub mod window;
+#[cfg(target_os = "windows")]
+pub mod window_controls;
 pub mod render_context;
\ No newline at end of file
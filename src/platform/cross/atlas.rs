@@ -1,14 +1,19 @@
 use std::sync::Arc;
 
+use anyhow::anyhow;
 use collections::FxHashMap;
-use etagere::BucketedAtlasAllocator;
+use etagere::{AtlasAllocator, BucketedAtlasAllocator};
 use parking_lot::Mutex;
 use wgpu::util::DeviceExt;
 
 use crate::{
-    AtlasKey, AtlasTextureId, AtlasTextureKind, AtlasTile, Bounds, DevicePixels, PlatformAtlas,
-    Point, Size,
-    platform::{AtlasTextureList, cross::render_context::WgpuContext},
+    AtlasAllocationStrategy, AtlasKey, AtlasKindStats, AtlasStats, AtlasTextureContents,
+    AtlasTextureId, AtlasTextureKind, AtlasTextureSnapshot, AtlasTile, Bounds, DevicePixels,
+    PlatformAtlas, Point, Size,
+    platform::{
+        AtlasTextureList,
+        cross::{gpu_memory::GpuMemoryCategory, render_context::WgpuContext},
+    },
 };
 
 pub(crate) struct WgpuAtlas(Mutex<WgpuAtlasState>);
@@ -23,6 +28,10 @@ impl WgpuAtlas {
             tiles_by_key: FxHashMap::default(),
             initializations: Vec::new(),
             uploads: Vec::new(),
+            staging: Vec::new(),
+            allocated_bytes: 0,
+            uploaded_bytes: 0,
+            eviction_requested: false,
         }))
     }
 
@@ -30,17 +39,74 @@ impl WgpuAtlas {
         self.0.lock().flush(encoder);
     }
 
+    /// Returns how many bytes of tile contents were uploaded since the last call.
+    pub fn take_uploaded_bytes(&self) -> u64 {
+        std::mem::take(&mut self.0.lock().uploaded_bytes)
+    }
+
     pub fn after_frame(&self) {
         // TODO(mdeand): Is this even necessary?
     }
 
-    pub(crate) fn get_texture_info(&self, texture_id: AtlasTextureId) -> WgpuTextureInfo {
+    /// Returns true if a texture allocation failed or would have exceeded the memory budget since
+    /// the last call, meaning the atlas should be evicted once the current frame is submitted.
+    pub fn take_eviction_request(&self) -> bool {
+        std::mem::take(&mut self.0.lock().eviction_requested)
+    }
+
+    /// Drops every texture and tile in the atlas. Tiles referenced by previously built scenes
+    /// become invalid, so the caller must make sure the next frame is rebuilt from scratch.
+    pub fn evict(&self) {
+        let mut atlas = self.0.lock();
+        atlas.tiles_by_key.clear();
+        atlas.initializations.clear();
+        atlas.uploads.clear();
+        atlas.staging.clear();
+        atlas.storage = WgpuAtlasStorage::default();
+        let allocated_bytes = std::mem::take(&mut atlas.allocated_bytes);
+        atlas
+            .context
+            .memory
+            .free(GpuMemoryCategory::Atlas, allocated_bytes);
+    }
+
+    /// Moves the atlas onto `context`'s device after the device its textures were created on was
+    /// lost. Every tile is dropped, and rasterized again the next time it's drawn.
+    pub(crate) fn handle_device_lost(&self, context: Arc<WgpuContext>) {
+        self.evict();
+        let mut atlas = self.0.lock();
+        atlas.atlas_target = None;
+        atlas.atlas_target_view = None;
+        atlas.context = context;
+    }
+
+    /// Uploads the contents of a texture read back from another atlas into a new texture,
+    /// returning its id. Used to draw saved scenes, whose sprites refer to the original ids.
+    pub(crate) fn restore_texture(
+        &self,
+        contents: &AtlasTextureContents,
+    ) -> anyhow::Result<AtlasTextureId> {
+        let mut atlas = self.0.lock();
+        let id = atlas.push_texture(contents.size, contents.id.kind)?.id;
+        atlas.upload_texture(
+            id,
+            Bounds {
+                origin: Point::default(),
+                size: contents.size,
+            },
+            &contents.bytes,
+        );
+        Ok(id)
+    }
+
+    pub(crate) fn get_texture_info(&self, texture_id: AtlasTextureId) -> Option<WgpuTextureInfo> {
         let state = self.0.lock();
-        let texture = &state.storage[texture_id];
+        let texture = state.storage.get(texture_id)?;
 
-        WgpuTextureInfo {
+        Some(WgpuTextureInfo {
+            raw: texture.raw.clone(),
             raw_view: texture.raw_view.clone(),
-        }
+        })
     }
 }
 
@@ -61,7 +127,7 @@ impl PlatformAtlas for WgpuAtlas {
 
                 match build()? {
                     Some((size, bytes)) => {
-                        let tile = atlas.allocate(size, key.texture_kind());
+                        let tile = atlas.allocate(size, key.texture_kind())?;
 
                         atlas.upload_texture(tile.texture_id, tile.bounds, &bytes);
                         atlas.tiles_by_key.insert(key.clone(), tile.clone());
@@ -93,6 +159,9 @@ impl PlatformAtlas for WgpuAtlas {
                     .free_list
                     .push(texture.id.index as usize);
 
+                atlas.allocated_bytes = atlas
+                    .allocated_bytes
+                    .saturating_sub(texture.size_in_bytes());
                 // TODO(mdeand): Is this even necessary?
                 texture.destroy(&atlas.context);
             } else {
@@ -100,6 +169,66 @@ impl PlatformAtlas for WgpuAtlas {
             }
         }
     }
+
+    fn texture_snapshots(&self) -> Vec<AtlasTextureSnapshot> {
+        let atlas = self.0.lock();
+
+        let mut snapshots = Vec::new();
+        for kind in [AtlasTextureKind::Monochrome, AtlasTextureKind::Polychrome] {
+            for texture in atlas.storage[kind].textures.iter().flatten() {
+                snapshots.push(AtlasTextureSnapshot {
+                    id: texture.id,
+                    size: texture.allocator.size().into(),
+                    tiles: Vec::new(),
+                });
+            }
+        }
+
+        for tile in atlas.tiles_by_key.values() {
+            if let Some(snapshot) = snapshots
+                .iter_mut()
+                .find(|snapshot| snapshot.id == tile.texture_id)
+            {
+                snapshot.tiles.push(tile.bounds);
+            }
+        }
+
+        snapshots
+    }
+
+    fn read_texture(&self, id: AtlasTextureId) -> anyhow::Result<AtlasTextureContents> {
+        let atlas = self.0.lock();
+        let texture = atlas
+            .storage
+            .get(id)
+            .ok_or_else(|| anyhow!("atlas texture {id:?} doesn't exist"))?;
+
+        Ok(AtlasTextureContents {
+            id,
+            size: texture.allocator.size().into(),
+            bytes: atlas.context.read_texture(&texture.raw)?,
+        })
+    }
+
+    fn allocation_stats(&self) -> AtlasStats {
+        let atlas = self.0.lock();
+        let kind_stats = |kind: AtlasTextureKind| {
+            let mut stats = AtlasKindStats::default();
+            for texture in atlas.storage[kind].textures.iter().flatten() {
+                stats.textures += 1;
+                stats.tiles += texture.live_atlas_keys as usize;
+                stats.allocated_area += texture.allocator.allocated_space() as u64;
+                stats.free_area += texture.allocator.free_space() as u64;
+                stats.fragmented_allocations += texture.fragmented_allocations;
+            }
+            stats
+        };
+
+        AtlasStats {
+            monochrome: kind_stats(AtlasTextureKind::Monochrome),
+            polychrome: kind_stats(AtlasTextureKind::Polychrome),
+        }
+    }
 }
 
 struct WgpuAtlasState {
@@ -110,10 +239,20 @@ struct WgpuAtlasState {
     tiles_by_key: FxHashMap<AtlasKey, AtlasTile>,
     initializations: Vec<AtlasTextureId>,
     uploads: Vec<PendingUpload>,
+    // the bytes of every pending upload, copied into a single buffer when the uploads are flushed
+    staging: Vec<u8>,
+    allocated_bytes: u64,
+    // the bytes uploaded by flushes since frame statistics last read them
+    uploaded_bytes: u64,
+    eviction_requested: bool,
 }
 
 impl WgpuAtlasState {
-    fn allocate(&mut self, size: Size<DevicePixels>, texture_kind: AtlasTextureKind) -> AtlasTile {
+    fn allocate(
+        &mut self,
+        size: Size<DevicePixels>,
+        texture_kind: AtlasTextureKind,
+    ) -> anyhow::Result<AtlasTile> {
         {
             let textures = &mut self.storage[texture_kind];
 
@@ -122,27 +261,41 @@ impl WgpuAtlasState {
                 .rev()
                 .find_map(|texture| texture.allocate(size))
             {
-                return tile;
+                return Ok(tile);
             }
         }
 
-        let texture = self.push_texture(size, texture_kind);
+        let texture = self.push_texture(size, texture_kind)?;
 
-        // TODO(mdeand): Note this unwrap use.
-        texture.allocate(size).unwrap()
+        texture
+            .allocate(size)
+            .ok_or_else(|| anyhow!("atlas tile of size {size:?} doesn't fit in a new texture"))
     }
 
     fn push_texture(
         &mut self,
         min_size: Size<DevicePixels>,
         texture_kind: AtlasTextureKind,
-    ) -> &mut WgpuAtlasTexture {
+    ) -> anyhow::Result<&mut WgpuAtlasTexture> {
         const DEFAULT_ATLAS_SIZE: Size<DevicePixels> = Size {
             width: DevicePixels(1024),
             height: DevicePixels(1024),
         };
 
-        let size = min_size.max(&DEFAULT_ATLAS_SIZE);
+        // No texture can hold a tile larger than the device's textures, so evicting wouldn't make
+        // room for it and isn't requested.
+        let max_dimension =
+            DevicePixels(self.context.device.limits().max_texture_dimension_2d as i32);
+        if min_size.width > max_dimension || min_size.height > max_dimension {
+            return Err(anyhow!(
+                "atlas tile of size {min_size:?} is larger than the largest texture the device supports"
+            ));
+        }
+        let max_size = Size {
+            width: max_dimension,
+            height: max_dimension,
+        };
+        let size = min_size.max(&DEFAULT_ATLAS_SIZE).min(&max_size);
 
         let (format, usage) = match texture_kind {
             AtlasTextureKind::Monochrome => (
@@ -161,6 +314,18 @@ impl WgpuAtlasState {
             ),
         };
 
+        let texture_bytes = size.width.0 as u64
+            * size.height.0 as u64
+            * format.block_copy_size(None).unwrap_or(4) as u64;
+        // Evicting now would invalidate tiles in the scene that's being built, so the renderer
+        // evicts once the frame has been submitted.
+        if self.context.memory.would_exceed_budget(texture_bytes) {
+            self.eviction_requested = true;
+        }
+
+        self.context
+            .device
+            .push_error_scope(wgpu::ErrorFilter::OutOfMemory);
         let texture_raw = self
             .context
             .device
@@ -179,6 +344,14 @@ impl WgpuAtlasState {
                 // TODO(mdeand): Create view formats?
                 view_formats: &[],
             });
+        if let Some(error) = pollster::block_on(self.context.device.pop_error_scope()) {
+            self.eviction_requested = true;
+            return Err(anyhow!("failed to allocate atlas texture: {error}"));
+        }
+        self.allocated_bytes += texture_bytes;
+        self.context
+            .memory
+            .allocate(GpuMemoryCategory::Atlas, texture_bytes);
 
         let texture_raw_view = texture_raw.create_view(&wgpu::TextureViewDescriptor {
             label: Some("Atlas Texture Raw"),
@@ -192,6 +365,12 @@ impl WgpuAtlasState {
             array_layer_count: None,
         });
 
+        let settings = self.context.renderer_settings.get();
+        let strategy = match texture_kind {
+            AtlasTextureKind::Monochrome => settings.monochrome_atlas_strategy,
+            AtlasTextureKind::Polychrome => settings.polychrome_atlas_strategy,
+        };
+
         let texture_list = &mut self.storage[texture_kind];
 
         let index = texture_list.free_list.pop();
@@ -201,17 +380,18 @@ impl WgpuAtlasState {
                 kind: texture_kind,
                 index: index.unwrap_or(texture_list.textures.len()) as u32,
             },
-            allocator: BucketedAtlasAllocator::new(size.into()),
+            allocator: TileAllocator::new(strategy, size),
             raw: texture_raw,
             raw_view: texture_raw_view,
             format,
             live_atlas_keys: 0,
+            fragmented_allocations: 0,
         };
 
         self.initializations.push(atlas_texture.id);
 
         // TODO(mdeand): This is weird
-        match index {
+        Ok(match index {
             Some(index) => {
                 texture_list.textures[index] = Some(atlas_texture);
                 texture_list
@@ -225,7 +405,7 @@ impl WgpuAtlasState {
                 texture_list.textures.push(Some(atlas_texture));
                 texture_list.textures.last_mut().unwrap().as_mut().unwrap()
             }
-        }
+        })
     }
 
     fn upload_texture(
@@ -240,36 +420,28 @@ impl WgpuAtlasState {
         let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as usize;
         let padded_bytes_per_row = (unpadded_bytes_per_row + align - 1) / align * align;
         let height = bounds.size.height.0 as usize;
+        if unpadded_bytes_per_row == 0 || height == 0 {
+            return;
+        }
 
-        let padded_data = if padded_bytes_per_row != unpadded_bytes_per_row {
-            let mut padded = vec![0u8; padded_bytes_per_row * height];
-            for row in 0..height {
-                let src_start = row * unpadded_bytes_per_row;
-                let dst_start = row * padded_bytes_per_row;
-                padded[dst_start..dst_start + unpadded_bytes_per_row]
-                    .copy_from_slice(&bytes[src_start..src_start + unpadded_bytes_per_row]);
-            }
-            Some(padded)
-        } else {
-            None
-        };
-
-        let contents = padded_data.as_deref().unwrap_or(bytes);
-
-        let buffer = self
-            .context
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: None,
-                usage: wgpu::BufferUsages::COPY_SRC,
-                contents,
-            });
+        // Every upload is a whole number of padded rows, so each one starts at an offset that
+        // satisfies the copy alignment.
+        let offset = self.staging.len();
+        self.staging
+            .resize(offset + padded_bytes_per_row * height, 0);
+        for (row, source) in bytes
+            .chunks_exact(unpadded_bytes_per_row)
+            .take(height)
+            .enumerate()
+        {
+            let start = offset + row * padded_bytes_per_row;
+            self.staging[start..start + unpadded_bytes_per_row].copy_from_slice(source);
+        }
 
         self.uploads.push(PendingUpload {
             texture_id,
             bounds,
-            buffer,
-            offset: 0,
+            offset: offset as u64,
             padded_bytes_per_row: padded_bytes_per_row as u32,
         })
     }
@@ -281,12 +453,26 @@ impl WgpuAtlasState {
     fn flush(&mut self, encoder: &mut wgpu::CommandEncoder) {
         self.flush_initializations(encoder);
 
+        if self.uploads.is_empty() {
+            return;
+        }
+        let staging_buffer =
+            self.context
+                .device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("atlas_staging"),
+                    usage: wgpu::BufferUsages::COPY_SRC,
+                    contents: &self.staging,
+                });
+        self.uploaded_bytes += self.staging.len() as u64;
+        self.staging.clear();
+
         for upload in self.uploads.drain(..) {
             let texture = &self.storage[upload.texture_id];
 
             encoder.copy_buffer_to_texture(
                 wgpu::TexelCopyBufferInfo {
-                    buffer: &upload.buffer,
+                    buffer: &staging_buffer,
                     layout: wgpu::TexelCopyBufferLayout {
                         offset: upload.offset,
                         bytes_per_row: Some(upload.padded_bytes_per_row),
@@ -315,16 +501,23 @@ impl WgpuAtlasState {
 
 pub(crate) struct WgpuAtlasTexture {
     id: AtlasTextureId,
-    allocator: BucketedAtlasAllocator,
+    allocator: TileAllocator,
     raw: wgpu::Texture,
     raw_view: wgpu::TextureView,
     format: wgpu::TextureFormat,
     live_atlas_keys: u32,
+    // allocations turned down although the free area was large enough for them
+    fragmented_allocations: u64,
 }
 
 impl WgpuAtlasTexture {
     fn allocate(&mut self, size: Size<DevicePixels>) -> Option<AtlasTile> {
-        let allocation = self.allocator.allocate(size.into())?;
+        let Some(allocation) = self.allocator.allocate(size.into()) else {
+            if self.allocator.free_space() as i64 >= size.width.0 as i64 * size.height.0 as i64 {
+                self.fragmented_allocations += 1;
+            }
+            return None;
+        };
 
         let tile = AtlasTile {
             texture_id: self.id,
@@ -351,6 +544,11 @@ impl WgpuAtlasTexture {
         }
     }
 
+    fn size_in_bytes(&self) -> u64 {
+        let size = self.allocator.size();
+        size.width as u64 * size.height as u64 * self.bytes_per_pixel() as u64
+    }
+
     fn decrement_ref_count(&mut self) {
         self.live_atlas_keys = self.live_atlas_keys.saturating_sub(1);
     }
@@ -359,9 +557,56 @@ impl WgpuAtlasTexture {
         self.live_atlas_keys == 0
     }
 
-    fn destroy(self, _context: &WgpuContext) {
+    fn destroy(self, context: &WgpuContext) {
         // NOTE(mdeand): In wgpu, textures are automatically cleaned up when dropped.
-        // NOTE(mdeand): If there were any additional resources to free, they would be handled here.
+        context
+            .memory
+            .free(GpuMemoryCategory::Atlas, self.size_in_bytes());
+    }
+}
+
+/// Packs the tiles of one texture, with the packing chosen by [`AtlasAllocationStrategy`].
+enum TileAllocator {
+    Bucketed(BucketedAtlasAllocator),
+    Shelf(AtlasAllocator),
+}
+
+impl TileAllocator {
+    fn new(strategy: AtlasAllocationStrategy, size: Size<DevicePixels>) -> Self {
+        match strategy {
+            AtlasAllocationStrategy::Bucketed => {
+                Self::Bucketed(BucketedAtlasAllocator::new(size.into()))
+            }
+            AtlasAllocationStrategy::Shelf => Self::Shelf(AtlasAllocator::new(size.into())),
+        }
+    }
+
+    fn allocate(&mut self, size: etagere::Size) -> Option<etagere::Allocation> {
+        match self {
+            Self::Bucketed(allocator) => allocator.allocate(size),
+            Self::Shelf(allocator) => allocator.allocate(size),
+        }
+    }
+
+    fn size(&self) -> etagere::Size {
+        match self {
+            Self::Bucketed(allocator) => allocator.size(),
+            Self::Shelf(allocator) => allocator.size(),
+        }
+    }
+
+    fn allocated_space(&self) -> i32 {
+        match self {
+            Self::Bucketed(allocator) => allocator.allocated_space(),
+            Self::Shelf(allocator) => allocator.allocated_space(),
+        }
+    }
+
+    fn free_space(&self) -> i32 {
+        match self {
+            Self::Bucketed(allocator) => allocator.free_space(),
+            Self::Shelf(allocator) => allocator.free_space(),
+        }
     }
 }
 
@@ -402,14 +647,29 @@ struct WgpuAtlasStorage {
     polychrome_textures: AtlasTextureList<WgpuAtlasTexture>,
 }
 
+impl WgpuAtlasStorage {
+    fn get(&self, id: AtlasTextureId) -> Option<&WgpuAtlasTexture> {
+        self[id.kind].textures.get(id.index as usize)?.as_ref()
+    }
+}
+
+impl Drop for WgpuAtlasState {
+    fn drop(&mut self) {
+        self.context
+            .memory
+            .free(GpuMemoryCategory::Atlas, self.allocated_bytes);
+    }
+}
+
 pub(crate) struct WgpuTextureInfo {
+    pub raw: wgpu::Texture,
     pub raw_view: wgpu::TextureView,
 }
 
 struct PendingUpload {
     texture_id: AtlasTextureId,
     bounds: Bounds<DevicePixels>,
-    buffer: wgpu::Buffer,
+    // where the upload's rows start in the staging bytes
     offset: u64,
     padded_bytes_per_row: u32,
 }
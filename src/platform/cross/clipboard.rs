@@ -0,0 +1,171 @@
+use std::{borrow::Cow, cell::RefCell, io::Cursor};
+
+use anyhow::Context as _;
+
+use crate::{ClipboardEntry, ClipboardFormat, ClipboardItem, ClipboardString, Image, ImageFormat};
+
+/// Which of the system's selections to read or write.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ClipboardKind {
+    Clipboard,
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    Primary,
+}
+
+/// The system clipboard, accessed through arboard.
+///
+/// The clipboard holds images as raw RGBA pixels, so an image is only decoded when it's written
+/// and only encoded as PNG when it's read with [`ClipboardFormat::Image`] requested.
+#[derive(Default)]
+pub(crate) struct CrossClipboard {
+    // Connected on first use, since connecting spawns a thread on X11 and fails outright on systems
+    // without a clipboard.
+    clipboard: RefCell<Option<arboard::Clipboard>>,
+}
+
+impl CrossClipboard {
+    pub(crate) fn write(&self, kind: ClipboardKind, item: ClipboardItem) {
+        let result = self.with_clipboard(|clipboard| {
+            let text = item.text();
+            if let Some(html) = item.html() {
+                set(clipboard, kind).html(html, text)?;
+            } else if let Some(text) = text {
+                set(clipboard, kind).text(text)?;
+            } else if let Some(image) = item.entries().iter().find_map(|entry| match entry {
+                ClipboardEntry::Image(image) => Some(image),
+                _ => None,
+            }) {
+                set(clipboard, kind).image(decode_image(image)?)?;
+            } else {
+                clear(clipboard, kind)?;
+            }
+            Ok(())
+        });
+
+        if let Err(error) = result {
+            log::error!("failed to write to the clipboard: {error:#}");
+        }
+    }
+
+    /// Reads each of `formats` that's on the clipboard, in the order given.
+    pub(crate) fn read(
+        &self,
+        kind: ClipboardKind,
+        formats: &[ClipboardFormat],
+    ) -> Option<ClipboardItem> {
+        let result = self.with_clipboard(|clipboard| {
+            let mut entries = Vec::new();
+            for format in formats {
+                let entry = match format {
+                    ClipboardFormat::Text => available(get(clipboard, kind).text())?
+                        .map(|text| ClipboardEntry::String(ClipboardString::new(text))),
+                    ClipboardFormat::Html => {
+                        available(get(clipboard, kind).html())?.map(ClipboardEntry::Html)
+                    }
+                    ClipboardFormat::Image => available(get(clipboard, kind).image())?
+                        .map(|image| encode_image(image).map(ClipboardEntry::Image))
+                        .transpose()?,
+                };
+                entries.extend(entry);
+            }
+            Ok(entries)
+        });
+
+        match result {
+            Ok(entries) if entries.is_empty() => None,
+            Ok(entries) => Some(ClipboardItem { entries }),
+            Err(error) => {
+                log::error!("failed to read from the clipboard: {error:#}");
+                None
+            }
+        }
+    }
+
+    fn with_clipboard<R>(
+        &self,
+        f: impl FnOnce(&mut arboard::Clipboard) -> anyhow::Result<R>,
+    ) -> anyhow::Result<R> {
+        let mut clipboard = self.clipboard.borrow_mut();
+        let clipboard = match &mut *clipboard {
+            Some(clipboard) => clipboard,
+            None => clipboard
+                .insert(arboard::Clipboard::new().context("failed to connect to the clipboard")?),
+        };
+        f(clipboard)
+    }
+}
+
+fn set(clipboard: &mut arboard::Clipboard, kind: ClipboardKind) -> arboard::Set<'_> {
+    match kind {
+        ClipboardKind::Clipboard => clipboard.set(),
+        #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+        ClipboardKind::Primary => {
+            use arboard::SetExtLinux as _;
+            clipboard
+                .set()
+                .clipboard(arboard::LinuxClipboardKind::Primary)
+        }
+    }
+}
+
+fn get(clipboard: &mut arboard::Clipboard, kind: ClipboardKind) -> arboard::Get<'_> {
+    match kind {
+        ClipboardKind::Clipboard => clipboard.get(),
+        #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+        ClipboardKind::Primary => {
+            use arboard::GetExtLinux as _;
+            clipboard
+                .get()
+                .clipboard(arboard::LinuxClipboardKind::Primary)
+        }
+    }
+}
+
+fn clear(clipboard: &mut arboard::Clipboard, kind: ClipboardKind) -> Result<(), arboard::Error> {
+    match kind {
+        ClipboardKind::Clipboard => clipboard.clear(),
+        #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+        ClipboardKind::Primary => {
+            use arboard::ClearExtLinux as _;
+            clipboard
+                .clear_with()
+                .clipboard(arboard::LinuxClipboardKind::Primary)
+        }
+    }
+}
+
+/// Treats a format that isn't on the clipboard as absent rather than as an error.
+fn available<T>(result: Result<T, arboard::Error>) -> anyhow::Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(arboard::Error::ContentNotAvailable) => Ok(None),
+        Err(error) => Err(error.into()),
+    }
+}
+
+fn decode_image(image: &Image) -> anyhow::Result<arboard::ImageData<'static>> {
+    let Some(format) = image::ImageFormat::from_mime_type(image.format.mime_type()) else {
+        anyhow::bail!(
+            "{:?} images can't be written to the clipboard",
+            image.format
+        );
+    };
+    let pixels = image::load_from_memory_with_format(&image.bytes, format)?.into_rgba8();
+    Ok(arboard::ImageData {
+        width: pixels.width() as usize,
+        height: pixels.height() as usize,
+        bytes: Cow::Owned(pixels.into_raw()),
+    })
+}
+
+fn encode_image(image: arboard::ImageData) -> anyhow::Result<Image> {
+    let pixels = image::RgbaImage::from_raw(
+        image.width as u32,
+        image.height as u32,
+        image.bytes.into_owned(),
+    )
+    .context("clipboard image has the wrong number of pixels")?;
+    let mut bytes = Vec::new();
+    pixels.write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png)?;
+    Ok(Image::from_bytes(ImageFormat::Png, bytes))
+}
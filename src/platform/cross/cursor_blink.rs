@@ -0,0 +1,90 @@
+use std::time::{Duration, Instant};
+
+/// How long the caret stays shown or hidden where the system's blink rate can't be read.
+const DEFAULT_BLINK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Blinks the caret at the system's rate. It's shown again and the blink restarts whenever a key
+/// is pressed or focus changes, so the caret stays visible while the user types.
+pub(crate) struct CursorBlink {
+    // how long the caret stays shown or hidden, or None when the system doesn't blink it
+    interval: Option<Duration>,
+    visible: bool,
+    // what observers were last told, so resets during a frame are reported afterwards
+    reported_visible: bool,
+    next_toggle_at: Instant,
+}
+
+impl CursorBlink {
+    pub(crate) fn new() -> Self {
+        let interval = read_blink_interval();
+        Self {
+            interval,
+            visible: true,
+            reported_visible: true,
+            next_toggle_at: Instant::now() + interval.unwrap_or(DEFAULT_BLINK_INTERVAL),
+        }
+    }
+
+    pub(crate) fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Shows the caret and restarts the blink.
+    pub(crate) fn reset(&mut self) {
+        self.visible = true;
+        self.next_toggle_at = Instant::now() + self.interval.unwrap_or(DEFAULT_BLINK_INTERVAL);
+    }
+
+    /// Toggles the caret once its time has come. Returns whether it changed since the last call,
+    /// whether it was toggled or reset.
+    pub(crate) fn update(&mut self, now: Instant) -> bool {
+        match self.interval {
+            Some(interval) if now >= self.next_toggle_at => {
+                self.visible = !self.visible;
+                self.next_toggle_at = now + interval;
+            }
+            _ => {}
+        }
+        let changed = self.visible != self.reported_visible;
+        self.reported_visible = self.visible;
+        changed
+    }
+}
+
+/// Reads how long the caret stays shown or hidden from GNOME's settings through the desktop
+/// portal.
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+fn read_blink_interval() -> Option<Duration> {
+    use crate::platform::cross::gdbus;
+
+    let read_setting = |key: &str| gdbus::read_portal_setting("org.gnome.desktop.interface", key);
+    if read_setting("cursor-blink").is_some_and(|reply| reply.contains("false")) {
+        return None;
+    }
+    // GNOME's blink time is the length of a whole cycle of showing and hiding.
+    let cycle = read_setting("cursor-blink-time")
+        .and_then(|reply| gdbus::parse_number(&reply))
+        .filter(|cycle| *cycle > 0.0);
+    Some(cycle.map_or(DEFAULT_BLINK_INTERVAL, |cycle| {
+        Duration::from_secs_f32(cycle / 2000.0)
+    }))
+}
+
+#[cfg(target_os = "windows")]
+fn read_blink_interval() -> Option<Duration> {
+    use ::windows::Win32::UI::WindowsAndMessaging::GetCaretBlinkTime;
+
+    const INFINITE: u32 = u32::MAX;
+
+    // SAFETY: `GetCaretBlinkTime` has no preconditions.
+    match unsafe { GetCaretBlinkTime() } {
+        INFINITE => None,
+        0 => Some(DEFAULT_BLINK_INTERVAL),
+        milliseconds => Some(Duration::from_millis(milliseconds as u64)),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "freebsd", target_os = "windows")))]
+fn read_blink_interval() -> Option<Duration> {
+    Some(DEFAULT_BLINK_INTERVAL)
+}
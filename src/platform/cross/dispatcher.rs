@@ -1,35 +1,128 @@
 use crate::{
-    GLOBAL_THREAD_TIMINGS, PlatformDispatcher, Priority, PriorityQueueSender, RealtimePriority,
-    RunnableVariant, THREAD_TIMINGS, ThreadTaskTimings,
+    DispatchLatency, GLOBAL_THREAD_TIMINGS, PlatformDispatcher, Priority, PriorityQueueSender,
+    RealtimePriority, RunnableVariant, THREAD_TIMINGS, TaskTiming, ThreadTaskTimings,
+    platform::cross::render_context::WgpuContext, profiler,
 };
+use parking_lot::Mutex;
 use priority_threadpool::ThreadPool;
-use std::thread::ThreadId;
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::ThreadId,
+    time::Instant,
+};
 use winit::event_loop::EventLoopProxy;
 
 pub enum CrossEvent {
     WakeUp,
     SurfacePresent(winit::window::WindowId),
+    GpuContextCreated(anyhow::Result<Arc<WgpuContext>>),
+    GpuError(crate::GpuError),
+    DeviceLost(String),
+    RendererSettingsChanged,
+    SystemAppearanceChanged,
+    ReconcileDisplays,
+    PowerStateChanged,
+    TextScaleFactorChanged,
+    Reopen,
+    Terminate,
+}
+
+/// A task queued for the main thread, stamped with when it was dispatched so the time it waited
+/// can be measured.
+pub(crate) struct MainThreadTask {
+    runnable: RunnableVariant,
+    dispatched_at: Instant,
+}
+
+impl MainThreadTask {
+    /// Runs the task, recording how long it waited in `latency` and, for tasks that know where
+    /// they were spawned, how long it ran in the thread's task timings.
+    pub(crate) fn run(self, latency: &Mutex<DispatchLatency>) {
+        let start = Instant::now();
+        latency
+            .lock()
+            .record(start.saturating_duration_since(self.dispatched_at));
+
+        match self.runnable {
+            RunnableVariant::Meta(runnable) => {
+                let mut timing = TaskTiming {
+                    location: runnable.metadata().location,
+                    start,
+                    end: None,
+                };
+                profiler::add_task_timing(timing);
+                runnable.run();
+                timing.end = Some(Instant::now());
+                profiler::add_task_timing(timing);
+            }
+            // Compat runnables carry no location, and any location recorded here would be this
+            // function's, so they aren't timed.
+            RunnableVariant::Compat(runnable) => runnable.run(),
+        }
+    }
 }
 
 pub struct Dispatcher {
     main_thread_id: ThreadId,
-    main_tx: PriorityQueueSender<RunnableVariant>,
+    main_tx: PriorityQueueSender<MainThreadTask>,
     threadpool: ThreadPool<Priority>,
-    proxy: EventLoopProxy<CrossEvent>,
+    // None on a headless platform, where the main queue is pumped manually.
+    proxy: Option<EventLoopProxy<CrossEvent>>,
+    dispatch_latency: Mutex<DispatchLatency>,
+    // Set once the application quits or the event loop exits, after which main thread dispatches
+    // are discarded instead of queued.
+    closed: AtomicBool,
+    // set while the main thread drains the main queue, which then runs the tasks the main thread
+    // dispatches without waking the event loop
+    draining: AtomicBool,
 }
 
 impl Dispatcher {
     pub fn new(
-        main_tx: PriorityQueueSender<RunnableVariant>,
-        proxy: EventLoopProxy<CrossEvent>,
+        main_tx: PriorityQueueSender<MainThreadTask>,
+        proxy: Option<EventLoopProxy<CrossEvent>>,
     ) -> Self {
         Self {
             main_thread_id: std::thread::current().id(),
             main_tx,
             threadpool: ThreadPool::new(num_cpus::get() * 8),
             proxy,
+            dispatch_latency: Mutex::default(),
+            closed: AtomicBool::new(false),
+            draining: AtomicBool::new(false),
         }
     }
+
+    /// The latency of main thread tasks, recorded by whoever drains the main queue.
+    pub(crate) fn dispatch_latency(&self) -> &Mutex<DispatchLatency> {
+        &self.dispatch_latency
+    }
+
+    /// Stops accepting tasks for the main thread. Tasks that are already queued stay there until
+    /// the main thread discards them.
+    pub(crate) fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+    }
+
+    /// Drops a task that will never run, running its destructors if that's safe on this thread.
+    fn discard(&self, task: MainThreadTask) {
+        if self.is_main_thread() {
+            drop(task);
+        } else {
+            // The task may own values that aren't Send, which must not be dropped on this thread.
+            // The application is shutting down, so it's leaked.
+            log::debug!("leaking a main thread task dispatched after shutdown");
+            std::mem::forget(task);
+        }
+    }
+
+    /// Records whether the main thread is draining the main queue.
+    pub(crate) fn set_draining(&self, draining: bool) {
+        self.draining.store(draining, Ordering::Release);
+    }
 }
 
 impl PlatformDispatcher for Dispatcher {
@@ -71,16 +164,39 @@ impl PlatformDispatcher for Dispatcher {
     }
 
     fn dispatch_on_main_thread(&self, runnable: RunnableVariant, priority: Priority) {
-        match self.main_tx.send(priority, runnable) {
+        let task = MainThreadTask {
+            runnable,
+            dispatched_at: Instant::now(),
+        };
+        if self.closed.load(Ordering::Acquire) {
+            self.discard(task);
+            return;
+        }
+        match self.main_tx.send(priority, task) {
             Ok(_) => {
-                let _ = self.proxy.send_event(CrossEvent::WakeUp);
-            }
-            Err(runnable) => {
-                std::mem::forget(runnable);
+                // A task dispatched by a task the main thread is running is popped by the same
+                // drain, so the round trip through the user event queue is skipped. Anywhere
+                // else, such as the callbacks `about_to_wait` runs after its drain, the event
+                // loop is woken so the task doesn't wait for the next unrelated event.
+                if self.is_main_thread() && self.draining.load(Ordering::Acquire) {
+                    return;
+                }
+                if let Some(proxy) = &self.proxy {
+                    // The event loop has exited, so the task will never run. It stays queued until
+                    // the main thread discards the queue.
+                    if proxy.send_event(CrossEvent::WakeUp).is_err() {
+                        self.close();
+                    }
+                }
             }
+            Err(error) => self.discard(error.into_inner()),
         }
     }
 
+    fn main_thread_dispatch_latency(&self) -> Option<DispatchLatency> {
+        Some(*self.dispatch_latency.lock())
+    }
+
     fn dispatch_after(&self, duration: std::time::Duration, runnable: RunnableVariant) {
         match runnable {
             RunnableVariant::Meta(runnable) => {
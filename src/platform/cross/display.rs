@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use uuid::Uuid;
+use winit::{event_loop::EventLoopProxy, monitor::MonitorHandle};
+
+use crate::{
+    BackgroundExecutor, Bounds, DisplayId, Pixels, PlatformDisplay,
+    platform::cross::dispatcher::CrossEvent, point, px, size,
+};
+
+/// How often the displays are compared with the last ones seen. winit doesn't report displays
+/// being connected or disconnected, so changes are found by polling.
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A monitor as it was when it was queried. winit's monitor handles can't be shared across
+/// threads on every backend, so the display keeps a copy of what GPUI needs.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CrossDisplay {
+    id: DisplayId,
+    uuid: Uuid,
+    bounds: Bounds<Pixels>,
+}
+
+impl CrossDisplay {
+    pub(crate) fn new(monitor: &MonitorHandle) -> Self {
+        let uuid = monitor_uuid(monitor);
+        Self {
+            id: display_id(&uuid),
+            uuid,
+            bounds: monitor_bounds(monitor),
+        }
+    }
+}
+
+impl PlatformDisplay for CrossDisplay {
+    fn id(&self) -> DisplayId {
+        self.id
+    }
+
+    fn uuid(&self) -> anyhow::Result<Uuid> {
+        Ok(self.uuid)
+    }
+
+    fn bounds(&self) -> Bounds<Pixels> {
+        self.bounds
+    }
+}
+
+/// Sends [`CrossEvent::ReconcileDisplays`] periodically, until the event loop exits.
+pub(crate) fn watch(executor: &BackgroundExecutor, proxy: EventLoopProxy<CrossEvent>) {
+    let timer_executor = executor.clone();
+    executor
+        .spawn(async move {
+            loop {
+                timer_executor.timer(RECONCILE_INTERVAL).await;
+                if proxy.send_event(CrossEvent::ReconcileDisplays).is_err() {
+                    return;
+                }
+            }
+        })
+        .detach();
+}
+
+/// The id of the display showing `monitor`.
+pub(crate) fn monitor_display_id(monitor: &MonitorHandle) -> DisplayId {
+    display_id(&monitor_uuid(monitor))
+}
+
+/// The bounds of `monitor` in logical pixels, in the coordinates GPUI places windows in.
+pub(crate) fn monitor_bounds(monitor: &MonitorHandle) -> Bounds<Pixels> {
+    let scale_factor = monitor.scale_factor() as f32;
+    let position = monitor.position();
+    let physical_size = monitor.size();
+    Bounds::new(
+        point(
+            px(position.x as f32 / scale_factor),
+            px(position.y as f32 / scale_factor),
+        ),
+        size(
+            px(physical_size.width as f32 / scale_factor),
+            px(physical_size.height as f32 / scale_factor),
+        ),
+    )
+}
+
+/// Monitors are identified by their name, which stays the same while the monitor is connected to
+/// the same port, or by their position where winit can't name them.
+fn monitor_uuid(monitor: &MonitorHandle) -> Uuid {
+    let name = monitor.name().unwrap_or_else(|| {
+        let position = monitor.position();
+        format!("{},{}", position.x, position.y)
+    });
+    Uuid::new_v5(&Uuid::NAMESPACE_DNS, name.as_bytes())
+}
+
+fn display_id(uuid: &Uuid) -> DisplayId {
+    let bytes = uuid.as_bytes();
+    DisplayId(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
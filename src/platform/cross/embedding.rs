@@ -0,0 +1,83 @@
+use std::{rc::Rc, sync::Arc};
+
+use anyhow::Result;
+
+use crate::{
+    AnyWindowHandle, App, AppCell, Bounds, DevicePixels, PlatformInput, Point, SceneViewport, Size,
+    app::NullHttpClient, platform::cross::platform::CrossPlatform,
+};
+
+/// The GPU device of a host GPUI is embedded in, which GPUI draws with instead of creating its
+/// own.
+pub struct EmbeddedDevice {
+    /// The instance the adapter was requested from.
+    pub instance: wgpu::Instance,
+    /// The adapter the device was requested from.
+    pub adapter: wgpu::Adapter,
+    /// The device the host draws with.
+    pub device: wgpu::Device,
+    /// The queue of the device the host submits to.
+    pub queue: wgpu::Queue,
+}
+
+/// A GPUI app embedded in a host that owns the event loop and the GPU device, such as a game
+/// drawing GPUI panels over its own frames. It's the inverse of a [`crate::WgpuSurface`], which
+/// shows a host's rendering inside GPUI.
+///
+/// The app's windows have no window of the operating system behind them. Their bounds place
+/// them in the target the host draws them into with [`EmbeddedApp::draw`], so several of them
+/// can share one target, and they only receive the input the host passes to
+/// [`EmbeddedApp::dispatch_input`]. Their scale factor is always 1. The app only makes progress
+/// when the host calls [`EmbeddedApp::run_until_parked`], typically once per frame.
+pub struct EmbeddedApp {
+    platform: Rc<CrossPlatform>,
+    app: Rc<AppCell>,
+}
+
+impl EmbeddedApp {
+    /// Creates an app that draws with the host's device.
+    pub fn new(device: EmbeddedDevice) -> Result<Self> {
+        let platform = Rc::new(CrossPlatform::embedded(device)?);
+        let app = App::new_app(platform.clone(), Arc::new(()), Arc::new(NullHttpClient));
+        Ok(Self { platform, app })
+    }
+
+    /// Updates the app, such as to open its windows with [`App::open_window`].
+    pub fn update<R>(&self, update: impl FnOnce(&mut App) -> R) -> R {
+        update(&mut self.app.borrow_mut())
+    }
+
+    /// Runs the app's main thread tasks and lets its windows draw their frames until there's
+    /// nothing left to do.
+    pub fn run_until_parked(&self) {
+        self.platform.run_until_parked();
+    }
+
+    /// Dispatches `event` to `window` as if the operating system had delivered it, with positions
+    /// relative to the window. Returns true if the window handled it.
+    pub fn dispatch_input(&self, window: AnyWindowHandle, event: PlatformInput) -> bool {
+        self.platform
+            .headless_window(window)
+            .is_some_and(|window| window.simulate_input(event))
+    }
+
+    /// Draws the last frame of each window at its bounds in the `target_size` texture `target`
+    /// views, in the order the windows were opened. The target isn't cleared first, so the
+    /// windows are drawn over the host's own rendering. The view must have the format returned by
+    /// [`crate::Window::xr_swapchain_format`], and each window must lie within the target.
+    pub fn draw(&self, target: &wgpu::TextureView, target_size: Size<DevicePixels>) -> Result<()> {
+        let mut app = self.app.borrow_mut();
+        for window in self.platform.headless_windows() {
+            window.handle().update(&mut *app, |_, window, _| {
+                let destination = window.bounds().to_device_pixels(window.scale_factor());
+                let source = Bounds::new(Point::default(), destination.size);
+                window.draw_region_to_view(
+                    SceneViewport::new(source, destination),
+                    target,
+                    target_size,
+                )
+            })??;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::PresentStats;
+
+/// How many of the most recent frames the work time is judged by.
+const HISTORY_LENGTH: usize = 30;
+
+/// The refresh interval of displays whose refresh rate can't be read, 60 Hz.
+pub(crate) const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_micros(16_667);
+
+/// The renderer becomes overloaded once the median frame takes longer than the refresh
+/// interval, and stops being overloaded once it fits in this fraction of it again. The gap keeps
+/// it from flipping back and forth around the limit.
+const RECOVERY_RATIO: f32 = 0.75;
+
+/// Detects when the renderer is overloaded: when frames take longer to draw than the display
+/// takes to refresh, so redraw requests pile up and each frame shows an older state than the one
+/// before. While it's overloaded, windows skip the frames in between and draw only the latest
+/// state, at the rate the renderer keeps up with.
+pub(crate) struct FramePacer {
+    refresh_interval: Duration,
+    // the work time of the most recent frames, oldest first
+    history: VecDeque<Duration>,
+    overloaded: bool,
+    // when the most recent frame was presented
+    last_presented_at: Option<Instant>,
+    last_present_duration: Option<Duration>,
+    missed_vblank_count: u64,
+}
+
+impl FramePacer {
+    pub(crate) fn new() -> Self {
+        Self {
+            refresh_interval: DEFAULT_REFRESH_INTERVAL,
+            history: VecDeque::with_capacity(HISTORY_LENGTH),
+            overloaded: false,
+            last_presented_at: None,
+            last_present_duration: None,
+            missed_vblank_count: 0,
+        }
+    }
+
+    pub(crate) fn set_refresh_interval(&mut self, refresh_interval: Duration) {
+        self.refresh_interval = refresh_interval;
+    }
+
+    /// Records how long a frame took to draw, leaving out the time spent waiting for the
+    /// swapchain. That wait is how FIFO presentation paces frames to the display, so counting it
+    /// would make every frame look exactly as long as the refresh interval.
+    pub(crate) fn record(&mut self, work_time: Duration) {
+        if self.history.len() == HISTORY_LENGTH {
+            self.history.pop_front();
+        }
+        self.history.push_back(work_time);
+
+        let median = self.median_work_time();
+        if self.overloaded {
+            self.overloaded =
+                median.as_secs_f32() >= self.refresh_interval.as_secs_f32() * RECOVERY_RATIO;
+        } else {
+            // A full history is required, so a few slow frames such as the first ones of a window,
+            // which rasterize every glyph, aren't mistaken for sustained overload.
+            self.overloaded =
+                self.history.len() == HISTORY_LENGTH && median > self.refresh_interval;
+        }
+    }
+
+    /// Records that a frame started drawing at `started_at` was presented at `presented_at`,
+    /// counting the refreshes it missed.
+    pub(crate) fn record_present(&mut self, started_at: Instant, presented_at: Instant) {
+        let Some(previous_presented_at) = self.last_presented_at.replace(presented_at) else {
+            return;
+        };
+        let present_duration = presented_at.saturating_duration_since(previous_presented_at);
+        self.last_present_duration = Some(present_duration);
+
+        // A frame started after the refresh that followed the previous present came from the window
+        // going idle, so the refreshes in between weren't missed.
+        if started_at.saturating_duration_since(previous_presented_at) < self.refresh_interval {
+            let refreshes = (present_duration.as_secs_f64() / self.refresh_interval.as_secs_f64())
+                .round() as u64;
+            self.missed_vblank_count += refreshes.saturating_sub(1);
+        }
+    }
+
+    pub(crate) fn present_stats(&self) -> PresentStats {
+        PresentStats {
+            last_present_duration: self.last_present_duration,
+            missed_vblank_count: self.missed_vblank_count,
+            refresh_interval: self.refresh_interval,
+        }
+    }
+
+    /// The shortest time between frames while the renderer is overloaded, which is how long it
+    /// takes to draw one, or `None` when it keeps up with the display.
+    pub(crate) fn overload_frame_interval(&self) -> Option<Duration> {
+        self.overloaded.then(|| self.median_work_time())
+    }
+
+    fn median_work_time(&self) -> Duration {
+        let mut work_times = self.history.iter().copied().collect::<Vec<_>>();
+        work_times.sort_unstable();
+        work_times
+            .get(work_times.len() / 2)
+            .copied()
+            .unwrap_or_default()
+    }
+}
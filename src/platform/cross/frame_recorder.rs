@@ -0,0 +1,308 @@
+use std::{
+    io::Write as _,
+    path::PathBuf,
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context as _;
+
+use crate::{FrameRecording, FrameRecordingOutput};
+
+/// Copies presented frames into readback buffers and hands them to a writer thread, which writes
+/// them to an image sequence or an encoder process.
+///
+/// Frames are read back asynchronously: the copy is submitted with the frame, the buffer is mapped
+/// once the GPU finishes it, and the pixels are written off the render thread. Frames are dropped
+/// when too many are waiting to be written, so a slow disk or encoder never stalls rendering.
+pub(crate) struct FrameRecorder {
+    frame_interval: u64,
+    min_frame_time: Option<Duration>,
+    max_pending_frames: usize,
+    presented_frames: u64,
+    last_capture: Option<Instant>,
+    pending_frames: Arc<AtomicUsize>,
+    frames: Option<flume::Sender<CapturedFrame>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+/// A frame whose copy has been encoded but not yet submitted.
+pub(crate) struct PendingCapture(CapturedFrame);
+
+struct CapturedFrame {
+    buffer: Arc<wgpu::Buffer>,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    is_bgra: bool,
+}
+
+impl FrameRecorder {
+    pub(crate) fn new(
+        recording: FrameRecording,
+        format: wgpu::TextureFormat,
+    ) -> anyhow::Result<Self> {
+        if !matches!(
+            format,
+            wgpu::TextureFormat::Bgra8Unorm
+                | wgpu::TextureFormat::Bgra8UnormSrgb
+                | wgpu::TextureFormat::Rgba8Unorm
+                | wgpu::TextureFormat::Rgba8UnormSrgb
+        ) {
+            anyhow::bail!("frames in the {format:?} format can't be recorded");
+        }
+
+        let mut sink = FrameSink::new(recording.output)?;
+        let pending_frames = Arc::new(AtomicUsize::new(0));
+        let (frames, received_frames) = flume::unbounded::<CapturedFrame>();
+        let writer = std::thread::Builder::new()
+            .name("gpui-frame-recorder".into())
+            .spawn({
+                let pending_frames = pending_frames.clone();
+                move || {
+                    for frame in received_frames {
+                        let pixels = frame.read_pixels();
+                        pending_frames.fetch_sub(1, Ordering::AcqRel);
+                        if let Err(error) = sink.write(frame.width, frame.height, pixels) {
+                            log::error!("failed to record frame: {error:#}");
+                        }
+                    }
+                    if let Err(error) = sink.finish() {
+                        log::error!("failed to finish frame recording: {error:#}");
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            frame_interval: recording.frame_interval.max(1) as u64,
+            min_frame_time: recording
+                .max_frames_per_second
+                .filter(|frames_per_second| *frames_per_second > 0.0)
+                .map(|frames_per_second| Duration::from_secs_f32(1.0 / frames_per_second)),
+            max_pending_frames: recording.max_pending_frames.max(1),
+            presented_frames: 0,
+            last_capture: None,
+            pending_frames,
+            frames: Some(frames),
+            writer: Some(writer),
+        })
+    }
+
+    /// Encodes a copy of `texture` if this frame should be recorded. The command buffer has to be
+    /// submitted before [`FrameRecorder::submitted`] is called with the capture.
+    pub(crate) fn capture(
+        &mut self,
+        device: &wgpu::Device,
+        texture: &wgpu::Texture,
+    ) -> Option<(wgpu::CommandBuffer, PendingCapture)> {
+        let frame_index = self.presented_frames;
+        self.presented_frames += 1;
+        if frame_index % self.frame_interval != 0 {
+            return None;
+        }
+
+        let now = Instant::now();
+        let throttled = self.min_frame_time.zip(self.last_capture).is_some_and(
+            |(min_frame_time, last_capture)| now.duration_since(last_capture) < min_frame_time,
+        );
+        if throttled {
+            return None;
+        }
+
+        if self.pending_frames.load(Ordering::Acquire) >= self.max_pending_frames {
+            log::debug!(
+                "dropping recorded frame, {} are still being written",
+                self.max_pending_frames
+            );
+            return None;
+        }
+        self.last_capture = Some(now);
+        self.pending_frames.fetch_add(1, Ordering::AcqRel);
+
+        let bytes_per_row = texture.width() * 4;
+        let padded_bytes_per_row = bytes_per_row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("frame_recorder"),
+            size: padded_bytes_per_row as u64 * texture.height() as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        }));
+        let mut command_encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("frame_recorder"),
+        });
+        command_encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            texture.size(),
+        );
+
+        Some((
+            command_encoder.finish(),
+            PendingCapture(CapturedFrame {
+                buffer,
+                width: texture.width(),
+                height: texture.height(),
+                padded_bytes_per_row,
+                is_bgra: matches!(
+                    texture.format(),
+                    wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+                ),
+            }),
+        ))
+    }
+
+    /// Maps the buffer of a submitted capture, handing it to the writer once the GPU has finished
+    /// the copy. The mapping completes during a later device poll.
+    pub(crate) fn submitted(&self, capture: PendingCapture) {
+        let PendingCapture(frame) = capture;
+        let Some(frames) = self.frames.clone() else {
+            return;
+        };
+        let pending_frames = self.pending_frames.clone();
+        let buffer = frame.buffer.clone();
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                if let Err(error) = result {
+                    log::error!("failed to read back recorded frame: {error}");
+                    pending_frames.fetch_sub(1, Ordering::AcqRel);
+                } else if frames.send(frame).is_err() {
+                    pending_frames.fetch_sub(1, Ordering::AcqRel);
+                }
+            });
+    }
+
+    /// Waits for the frames that were already read back to be written, then closes the output.
+    /// The device must have been polled until its mappings completed.
+    pub(crate) fn finish(mut self) {
+        self.frames.take();
+        let Some(writer) = self.writer.take() else {
+            return;
+        };
+        if writer.join().is_err() {
+            log::error!("frame recorder thread panicked");
+        }
+    }
+}
+
+impl CapturedFrame {
+    fn read_pixels(self) -> Vec<u8> {
+        let bytes_per_row = (self.width * 4) as usize;
+        let mut pixels = Vec::with_capacity(bytes_per_row * self.height as usize);
+        {
+            let data = self.buffer.slice(..).get_mapped_range();
+            for row in data.chunks(self.padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..bytes_per_row]);
+            }
+        }
+        self.buffer.unmap();
+
+        if self.is_bgra {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        pixels
+    }
+}
+
+enum FrameSink {
+    ImageSequence {
+        directory: PathBuf,
+        next_frame: u64,
+    },
+    Encoder {
+        process: Child,
+        stdin: Option<ChildStdin>,
+        size: Option<(u32, u32)>,
+    },
+}
+
+impl FrameSink {
+    fn new(output: FrameRecordingOutput) -> anyhow::Result<Self> {
+        match output {
+            FrameRecordingOutput::ImageSequence(directory) => {
+                std::fs::create_dir_all(&directory)
+                    .with_context(|| format!("failed to create {}", directory.display()))?;
+                Ok(Self::ImageSequence {
+                    directory,
+                    next_frame: 0,
+                })
+            }
+            FrameRecordingOutput::Encoder { program, args } => {
+                let mut process = Command::new(&program)
+                    .args(&args)
+                    .stdin(Stdio::piped())
+                    .spawn()
+                    .with_context(|| format!("failed to start {}", program.display()))?;
+                let stdin = process.stdin.take();
+                Ok(Self::Encoder {
+                    process,
+                    stdin,
+                    size: None,
+                })
+            }
+        }
+    }
+
+    fn write(&mut self, width: u32, height: u32, pixels: Vec<u8>) -> anyhow::Result<()> {
+        match self {
+            Self::ImageSequence {
+                directory,
+                next_frame,
+            } => {
+                let path = directory.join(format!("frame_{next_frame:06}.png"));
+                *next_frame += 1;
+                image::RgbaImage::from_raw(width, height, pixels)
+                    .context("recorded frame has the wrong number of pixels")?
+                    .save(&path)
+                    .with_context(|| format!("failed to write {}", path.display()))
+            }
+            Self::Encoder { stdin, size, .. } => {
+                let expected_size = *size.get_or_insert((width, height));
+                if expected_size != (width, height) {
+                    log::warn!(
+                        "skipping {width}x{height} frame, the encoder was started with {}x{} frames",
+                        expected_size.0,
+                        expected_size.1
+                    );
+                    return Ok(());
+                }
+                let Some(stdin) = stdin else {
+                    return Ok(());
+                };
+                stdin
+                    .write_all(&pixels)
+                    .context("failed to write frame to the encoder")
+            }
+        }
+    }
+
+    fn finish(self) -> anyhow::Result<()> {
+        match self {
+            Self::ImageSequence { .. } => Ok(()),
+            Self::Encoder {
+                mut process, stdin, ..
+            } => {
+                // Closing stdin tells the encoder the stream ended.
+                drop(stdin);
+                let status = process.wait().context("failed to wait for the encoder")?;
+                anyhow::ensure!(status.success(), "encoder exited with {status}");
+                Ok(())
+            }
+        }
+    }
+}
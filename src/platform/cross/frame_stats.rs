@@ -0,0 +1,141 @@
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::{AtlasStats, FrameStatsFormat, FrameStatsLogging};
+
+const LOG_TARGET: &str = "gpui::frame_stats";
+
+/// What drawing a single frame cost.
+pub(crate) struct FrameSample {
+    // the time spent encoding, submitting and presenting the frame on the CPU
+    pub(crate) draw_time: Duration,
+    pub(crate) draw_calls: usize,
+    pub(crate) uploaded_bytes: u64,
+}
+
+/// Collects the samples of the frames a renderer draws, and logs a summary of them once per
+/// interval of [`FrameStatsLogging`].
+pub(crate) struct FrameStatsLogger {
+    logging: FrameStatsLogging,
+    interval_started_at: Instant,
+    samples: Vec<FrameSample>,
+}
+
+impl FrameStatsLogger {
+    pub(crate) fn new(logging: FrameStatsLogging) -> Self {
+        Self {
+            logging,
+            interval_started_at: Instant::now(),
+            samples: Vec::new(),
+        }
+    }
+
+    pub(crate) fn set_logging(&mut self, logging: FrameStatsLogging) {
+        self.logging = logging;
+    }
+
+    /// Records a frame, and logs the summary of the interval once it has passed. The atlas's
+    /// statistics are only read then.
+    pub(crate) fn record(&mut self, sample: FrameSample, atlas_stats: impl FnOnce() -> AtlasStats) {
+        self.samples.push(sample);
+        let now = Instant::now();
+        let elapsed = now - self.interval_started_at;
+        if elapsed < self.logging.interval {
+            return;
+        }
+
+        let summary = FrameStatsSummary::new(&self.samples, elapsed, &atlas_stats());
+        match self.logging.format {
+            FrameStatsFormat::Text => log::info!(target: LOG_TARGET, "{}", summary.to_text()),
+            FrameStatsFormat::Json => match serde_json::to_string(&summary) {
+                Ok(json) => log::info!(target: LOG_TARGET, "{json}"),
+                Err(error) => log::error!("failed to serialize frame statistics: {error}"),
+            },
+        }
+        self.samples.clear();
+        self.interval_started_at = now;
+    }
+}
+
+#[derive(Serialize)]
+struct FrameStatsSummary {
+    interval_seconds: f32,
+    frames: usize,
+    draw_time_milliseconds: Percentiles,
+    draw_calls: Percentiles,
+    uploaded_bytes: u64,
+    monochrome_atlas_occupancy: f32,
+    polychrome_atlas_occupancy: f32,
+    atlas_textures: usize,
+}
+
+impl FrameStatsSummary {
+    fn new(samples: &[FrameSample], interval: Duration, atlas_stats: &AtlasStats) -> Self {
+        Self {
+            interval_seconds: interval.as_secs_f32(),
+            frames: samples.len(),
+            draw_time_milliseconds: Percentiles::new(
+                samples
+                    .iter()
+                    .map(|sample| sample.draw_time.as_secs_f32() * 1000.0),
+            ),
+            draw_calls: Percentiles::new(samples.iter().map(|sample| sample.draw_calls as f32)),
+            uploaded_bytes: samples.iter().map(|sample| sample.uploaded_bytes).sum(),
+            monochrome_atlas_occupancy: atlas_stats.monochrome.occupancy(),
+            polychrome_atlas_occupancy: atlas_stats.polychrome.occupancy(),
+            atlas_textures: atlas_stats.monochrome.textures + atlas_stats.polychrome.textures,
+        }
+    }
+
+    fn to_text(&self) -> String {
+        format!(
+            "{} frames in {:.1}s: draw time {} ms, draw calls {}, {} bytes uploaded, atlas {:.0}% \
+             monochrome and {:.0}% polychrome occupied across {} textures",
+            self.frames,
+            self.interval_seconds,
+            self.draw_time_milliseconds.to_text(2),
+            self.draw_calls.to_text(0),
+            self.uploaded_bytes,
+            self.monochrome_atlas_occupancy * 100.0,
+            self.polychrome_atlas_occupancy * 100.0,
+            self.atlas_textures,
+        )
+    }
+}
+
+#[derive(Default, Serialize)]
+struct Percentiles {
+    p50: f32,
+    p90: f32,
+    p99: f32,
+    max: f32,
+}
+
+impl Percentiles {
+    fn new(values: impl Iterator<Item = f32>) -> Self {
+        let mut values = values.collect::<Vec<_>>();
+        if values.is_empty() {
+            return Self::default();
+        }
+        values.sort_by(f32::total_cmp);
+        // The nearest rank, so a percentile is always one of the values.
+        let percentile = |fraction: f32| {
+            let rank = (fraction * values.len() as f32).ceil() as usize;
+            values[rank.clamp(1, values.len()) - 1]
+        };
+        Self {
+            p50: percentile(0.5),
+            p90: percentile(0.9),
+            p99: percentile(0.99),
+            max: values[values.len() - 1],
+        }
+    }
+
+    fn to_text(&self, precision: usize) -> String {
+        format!(
+            "p50 {:.precision$} p90 {:.precision$} p99 {:.precision$} max {:.precision$}",
+            self.p50, self.p90, self.p99, self.max,
+        )
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    io::{BufRead as _, BufReader},
+    process::{Command, Stdio},
+};
+
+const PORTAL_DESTINATION: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_OBJECT_PATH: &str = "/org/freedesktop/portal/desktop";
+
+/// Calls `method` on an object of the session bus with the `gdbus` tool, returning the printed
+/// reply, such as `(<uint32 4000>,)`, or `None` if the call failed.
+pub(crate) fn call(
+    destination: &str,
+    object_path: &str,
+    method: &str,
+    arguments: &[&str],
+) -> Option<String> {
+    call_on_bus("--session", destination, object_path, method, arguments)
+}
+
+fn call_on_bus(
+    bus: &str,
+    destination: &str,
+    object_path: &str,
+    method: &str,
+    arguments: &[&str],
+) -> Option<String> {
+    let output = Command::new("gdbus")
+        .args(["call", bus, "--dest", destination])
+        .args(["--object-path", object_path])
+        .args(["--method", method])
+        .args(arguments)
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Reads `property` of `interface` on an object of the session bus. See [`call`].
+pub(crate) fn property(
+    destination: &str,
+    object_path: &str,
+    interface: &str,
+    property: &str,
+) -> Option<String> {
+    call(
+        destination,
+        object_path,
+        "org.freedesktop.DBus.Properties.Get",
+        &[interface, property],
+    )
+}
+
+/// Reads `property` of `interface` on an object of the system bus. See [`call`].
+pub(crate) fn system_property(
+    destination: &str,
+    object_path: &str,
+    interface: &str,
+    property: &str,
+) -> Option<String> {
+    call_on_bus(
+        "--system",
+        destination,
+        object_path,
+        "org.freedesktop.DBus.Properties.Get",
+        &[interface, property],
+    )
+}
+
+/// Calls `refresh`, then again whenever the desktop portal reports that a setting changed, until
+/// `refresh` returns false or the portal can't be monitored. Blocks the calling thread.
+pub(crate) fn watch_portal_settings(refresh: impl Fn() -> bool) {
+    if !refresh() {
+        return;
+    }
+
+    let monitor = Command::new("gdbus")
+        .args(["monitor", "--session", "--dest", PORTAL_DESTINATION])
+        .args(["--object-path", PORTAL_OBJECT_PATH])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut monitor = match monitor {
+        Ok(monitor) => monitor,
+        Err(error) => {
+            log::info!("not watching the desktop settings, gdbus is unavailable: {error}");
+            return;
+        }
+    };
+    let Some(stdout) = monitor.stdout.take() else {
+        return;
+    };
+
+    for line in BufReader::new(stdout).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        // Desktops report some settings under their own namespaces, such as KDE Plasma's color
+        // scheme, so any changed setting triggers a refresh.
+        if line.contains("SettingChanged") && !refresh() {
+            break;
+        }
+    }
+
+    if let Err(error) = monitor.kill() {
+        log::debug!("failed to stop monitoring the desktop portal: {error}");
+    }
+    if let Err(error) = monitor.wait() {
+        log::debug!("failed to wait for the desktop portal monitor: {error}");
+    }
+}
+
+/// Reads `key` in `namespace` of the desktop portal's settings. See [`call`].
+pub(crate) fn read_portal_setting(namespace: &str, key: &str) -> Option<String> {
+    call(
+        PORTAL_DESTINATION,
+        PORTAL_OBJECT_PATH,
+        "org.freedesktop.portal.Settings.ReadOne",
+        &[namespace, key],
+    )
+}
+
+/// Parses the number at the end of a printed reply such as `(<uint32 4000>,)` or `(<1200>,)`.
+pub(crate) fn parse_number(reply: &str) -> Option<f32> {
+    reply
+        .split_whitespace()
+        .last()?
+        .trim_matches(|character: char| !character.is_ascii_digit())
+        .parse()
+        .ok()
+}
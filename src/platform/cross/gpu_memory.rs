@@ -0,0 +1,81 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::GpuMemoryUsage;
+
+/// What a tracked GPU allocation is used for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum GpuMemoryCategory {
+    Atlas,
+    Surfaces,
+    SceneBuffers,
+}
+
+/// Accounts for the GPU memory allocated by the atlas, the surface registry and the renderer,
+/// and compares it against an optional budget.
+///
+/// wgpu doesn't expose how much memory the driver actually allocated, so this tracks the
+/// logical size of each texture and buffer as they are created and dropped.
+#[derive(Default)]
+pub(crate) struct GpuMemoryTracker {
+    atlas: AtomicU64,
+    surfaces: AtomicU64,
+    scene_buffers: AtomicU64,
+    // Zero means no budget.
+    budget: AtomicU64,
+}
+
+impl GpuMemoryTracker {
+    fn counter(&self, category: GpuMemoryCategory) -> &AtomicU64 {
+        match category {
+            GpuMemoryCategory::Atlas => &self.atlas,
+            GpuMemoryCategory::Surfaces => &self.surfaces,
+            GpuMemoryCategory::SceneBuffers => &self.scene_buffers,
+        }
+    }
+
+    pub(crate) fn allocate(&self, category: GpuMemoryCategory, bytes: u64) {
+        self.counter(category).fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn free(&self, category: GpuMemoryCategory, bytes: u64) {
+        let counter = self.counter(category);
+        let mut current = counter.load(Ordering::Relaxed);
+        while let Err(actual) = counter.compare_exchange_weak(
+            current,
+            current.saturating_sub(bytes),
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        ) {
+            current = actual;
+        }
+    }
+
+    pub(crate) fn usage(&self) -> GpuMemoryUsage {
+        GpuMemoryUsage {
+            atlas: self.atlas.load(Ordering::Relaxed),
+            surfaces: self.surfaces.load(Ordering::Relaxed),
+            scene_buffers: self.scene_buffers.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn budget(&self) -> Option<u64> {
+        match self.budget.load(Ordering::Relaxed) {
+            0 => None,
+            budget => Some(budget),
+        }
+    }
+
+    pub(crate) fn set_budget(&self, budget: Option<u64>) {
+        self.budget.store(budget.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    /// Returns true if allocating `additional` more bytes would exceed the budget.
+    pub(crate) fn would_exceed_budget(&self, additional: u64) -> bool {
+        self.budget()
+            .is_some_and(|budget| self.usage().total() + additional > budget)
+    }
+
+    pub(crate) fn is_over_budget(&self) -> bool {
+        self.would_exceed_budget(0)
+    }
+}
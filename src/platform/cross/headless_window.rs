@@ -0,0 +1,550 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::{Rc, Weak},
+    sync::Arc,
+};
+
+use crate::{
+    AnyWindowHandle, Bounds, Capslock, DevicePixels, Modifiers, Pixels, PlatformInput,
+    PlatformInputHandler, PlatformWindow, Point, RequestFrameOptions, Size, WindowAppearance,
+    WindowBackgroundAppearance, WindowBounds,
+    platform::cross::{
+        atlas::WgpuAtlas,
+        render_context::WgpuContext,
+        renderer::{SoftwareCursor, WgpuRenderer},
+        window::Callbacks,
+    },
+};
+
+/// A window of a headless [`CrossPlatform`](super::platform::CrossPlatform), which has no winit
+/// window behind it.
+///
+/// Scenes are drawn into an offscreen texture that can be read back with
+/// [`HeadlessWindow::capture_frame`], and input only arrives through
+/// [`HeadlessWindow::simulate_input`]. Its scale factor is always 1.
+#[derive(Clone)]
+pub(crate) struct HeadlessWindow(Rc<HeadlessWindowState>);
+
+pub(crate) struct HeadlessWindowState {
+    handle: AnyWindowHandle,
+    bounds: Cell<Bounds<Pixels>>,
+    title: RefCell<String>,
+    active: Cell<bool>,
+    fullscreen: Cell<bool>,
+    mouse_position: Cell<Point<Pixels>>,
+    modifiers: Cell<Modifiers>,
+    input_handler: RefCell<Option<PlatformInputHandler>>,
+    callbacks: Callbacks,
+    renderer: RefCell<WgpuRenderer>,
+    sprite_atlas: Arc<WgpuAtlas>,
+    // set when the next frame must be rebuilt from scratch rather than reusing cached primitives
+    force_render: Cell<bool>,
+}
+
+#[allow(dead_code)]
+impl HeadlessWindow {
+    pub(crate) fn new(
+        wgpu_context: Arc<WgpuContext>,
+        handle: AnyWindowHandle,
+        bounds: Bounds<Pixels>,
+    ) -> Self {
+        let sprite_atlas = Arc::new(WgpuAtlas::new(wgpu_context.clone()));
+        let size = device_size(bounds.size);
+        let renderer = WgpuRenderer::new_offscreen(
+            wgpu_context,
+            sprite_atlas.clone(),
+            size.width.0 as u32,
+            size.height.0 as u32,
+        );
+
+        Self(Rc::new(HeadlessWindowState {
+            handle,
+            bounds: Cell::new(bounds),
+            title: RefCell::new(String::new()),
+            active: Cell::new(false),
+            fullscreen: Cell::new(false),
+            mouse_position: Cell::new(Point::default()),
+            modifiers: Cell::new(Modifiers::default()),
+            input_handler: RefCell::new(None),
+            callbacks: Callbacks::default(),
+            renderer: RefCell::new(renderer),
+            sprite_atlas,
+            force_render: Cell::new(false),
+        }))
+    }
+
+    pub(crate) fn downgrade(&self) -> Weak<HeadlessWindowState> {
+        Rc::downgrade(&self.0)
+    }
+
+    pub(crate) fn upgrade(state: &Weak<HeadlessWindowState>) -> Option<Self> {
+        state.upgrade().map(Self)
+    }
+
+    /// The handle of the GPUI window this window shows.
+    pub(crate) fn handle(&self) -> AnyWindowHandle {
+        self.0.handle
+    }
+
+    /// Asks the window to draw a frame, which it only does if its contents changed.
+    pub(crate) fn request_frame(&self) {
+        let callbacks = &self.0.callbacks;
+        callbacks.invoke_mut(&callbacks.on_request_frame, |cb| {
+            cb(RequestFrameOptions {
+                force_render: self.0.force_render.take(),
+                require_presentation: false,
+            });
+        });
+    }
+
+    /// Dispatches `event` to the window as if the OS had delivered it. Returns true if the
+    /// window handled it.
+    pub(crate) fn simulate_input(&self, event: PlatformInput) -> bool {
+        match &event {
+            PlatformInput::MouseMove(event) => {
+                self.0.mouse_position.set(event.position);
+                self.0.modifiers.set(event.modifiers);
+            }
+            PlatformInput::ModifiersChanged(event) => self.0.modifiers.set(event.modifiers),
+            _ => {}
+        }
+
+        let mut handled = false;
+        let callbacks = &self.0.callbacks;
+        callbacks.invoke_mut(&callbacks.on_input, |cb| {
+            handled = !cb(event).propagate;
+        });
+        handled
+    }
+
+    /// Resizes the window as if the user had resized it.
+    pub(crate) fn simulate_resize(&self, size: Size<Pixels>) {
+        let mut bounds = self.0.bounds.get();
+        bounds.size = size;
+        self.0.bounds.set(bounds);
+        self.0
+            .renderer
+            .borrow_mut()
+            .update_drawable_size(device_size(size));
+
+        let callbacks = &self.0.callbacks;
+        callbacks.invoke_mut(&callbacks.on_resize, |cb| cb(size, 1.0));
+    }
+
+    /// Reads back the last frame the window drew.
+    pub(crate) fn capture_frame(&self) -> anyhow::Result<image::RgbaImage> {
+        self.0.renderer.borrow().capture_frame()
+    }
+}
+
+fn device_size(size: Size<Pixels>) -> Size<DevicePixels> {
+    Size {
+        width: DevicePixels(size.width.0.ceil() as i32),
+        height: DevicePixels(size.height.0.ceil() as i32),
+    }
+}
+
+impl PlatformWindow for HeadlessWindow {
+    fn bounds(&self) -> Bounds<Pixels> {
+        self.0.bounds.get()
+    }
+
+    fn is_maximized(&self) -> bool {
+        false
+    }
+
+    fn window_bounds(&self) -> WindowBounds {
+        WindowBounds::Windowed(self.0.bounds.get())
+    }
+
+    fn content_size(&self) -> Size<Pixels> {
+        self.0.bounds.get().size
+    }
+
+    fn resize(&mut self, size: Size<Pixels>) {
+        self.simulate_resize(size);
+    }
+
+    fn scale_factor(&self) -> f32 {
+        1.0
+    }
+
+    fn appearance(&self) -> WindowAppearance {
+        WindowAppearance::default()
+    }
+
+    fn display(&self) -> Option<Rc<dyn crate::PlatformDisplay>> {
+        None
+    }
+
+    fn mouse_position(&self) -> Point<Pixels> {
+        self.0.mouse_position.get()
+    }
+
+    /// A headless window has no cursor of its own, so the renderer draws one at `position`.
+    fn set_mouse_position(&self, position: Point<Pixels>) {
+        self.0.mouse_position.set(position);
+        self.0
+            .renderer
+            .borrow_mut()
+            .set_software_cursor(Some(SoftwareCursor {
+                position: position.scale(1.0),
+                scale_factor: 1.0,
+            }));
+        self.0.force_render.set(true);
+    }
+
+    fn modifiers(&self) -> Modifiers {
+        self.0.modifiers.get()
+    }
+
+    fn capslock(&self) -> Capslock {
+        Capslock::default()
+    }
+
+    fn set_input_handler(&mut self, input_handler: PlatformInputHandler) {
+        self.0.input_handler.replace(Some(input_handler));
+    }
+
+    fn take_input_handler(&mut self) -> Option<PlatformInputHandler> {
+        self.0.input_handler.take()
+    }
+
+    fn activate(&self) {
+        if !self.0.active.replace(true) {
+            let callbacks = &self.0.callbacks;
+            callbacks.invoke_mut(&callbacks.on_active_status_change, |cb| cb(true));
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.0.active.get()
+    }
+
+    fn is_hovered(&self) -> bool {
+        false
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.0.title.replace(title.to_string());
+    }
+
+    fn set_background_appearance(&self, background_appearance: WindowBackgroundAppearance) {
+        self.0
+            .renderer
+            .borrow_mut()
+            .update_transparency(background_appearance != WindowBackgroundAppearance::Opaque);
+    }
+
+    fn minimize(&self) {}
+
+    fn zoom(&self) {}
+
+    fn toggle_fullscreen(&self) {
+        self.0.fullscreen.set(!self.0.fullscreen.get());
+    }
+
+    fn is_fullscreen(&self) -> bool {
+        self.0.fullscreen.get()
+    }
+
+    fn on_request_frame(&self, callback: Box<dyn FnMut(RequestFrameOptions)>) {
+        self.0.callbacks.on_request_frame.set(Some(callback));
+    }
+
+    fn on_input(&self, callback: Box<dyn FnMut(PlatformInput) -> crate::DispatchEventResult>) {
+        self.0.callbacks.on_input.set(Some(callback));
+    }
+
+    fn on_active_status_change(&self, callback: Box<dyn FnMut(bool)>) {
+        self.0.callbacks.on_active_status_change.set(Some(callback));
+    }
+
+    fn on_hover_status_change(&self, callback: Box<dyn FnMut(bool)>) {
+        self.0.callbacks.on_hover_status_change.set(Some(callback));
+    }
+
+    fn on_resize(&self, callback: Box<dyn FnMut(Size<Pixels>, f32)>) {
+        self.0.callbacks.on_resize.set(Some(callback));
+    }
+
+    fn on_moved(&self, callback: Box<dyn FnMut()>) {
+        self.0.callbacks.on_moved.set(Some(callback));
+    }
+
+    fn on_should_close(&self, callback: Box<dyn FnMut() -> bool>) {
+        self.0.callbacks.on_should_close.set(Some(callback));
+    }
+
+    fn on_hit_test_window_control(
+        &self,
+        callback: Box<dyn FnMut() -> Option<crate::WindowControlArea>>,
+    ) {
+        self.0
+            .callbacks
+            .on_hit_test_window_control
+            .set(Some(callback));
+    }
+
+    fn on_close(&self, callback: Box<dyn FnOnce()>) {
+        self.0.callbacks.on_close.set(Some(callback));
+    }
+
+    fn on_appearance_changed(&self, callback: Box<dyn FnMut()>) {
+        self.0.callbacks.on_appearance_changed.set(Some(callback));
+    }
+
+    fn draw(&self, scene: &crate::Scene) {
+        let mut renderer = self.0.renderer.borrow_mut();
+        if let Err(error) = renderer.draw(scene) {
+            log::error!("failed to draw a frame: {error}");
+        }
+        if renderer.take_needs_full_redraw() {
+            self.0.force_render.set(true);
+        }
+    }
+
+    fn sprite_atlas(&self) -> Arc<dyn crate::PlatformAtlas> {
+        self.0.sprite_atlas.clone()
+    }
+
+    fn gpu_specs(&self) -> Option<crate::GpuSpecs> {
+        Some(self.0.renderer.borrow().gpu_specs())
+    }
+
+    fn start_frame_recording(&self, recording: crate::FrameRecording) -> anyhow::Result<()> {
+        self.0
+            .renderer
+            .borrow_mut()
+            .start_frame_recording(recording)
+    }
+
+    fn stop_frame_recording(&self) {
+        self.0.renderer.borrow_mut().stop_frame_recording();
+    }
+
+    fn set_screen_zoom(&self, zoom: Option<crate::ScreenZoom>) {
+        self.0.renderer.borrow_mut().set_screen_zoom(zoom);
+        self.0.force_render.set(true);
+    }
+
+    fn screen_zoom(&self) -> Option<crate::ScreenZoom> {
+        self.0.renderer.borrow().screen_zoom()
+    }
+
+    fn set_color_filter(&self, color_filter: Option<crate::ColorFilter>) {
+        self.0.renderer.borrow_mut().set_color_filter(color_filter);
+        self.0.force_render.set(true);
+    }
+
+    fn color_filter(&self) -> Option<crate::ColorFilter> {
+        self.0.renderer.borrow().color_filter()
+    }
+
+    fn draw_region(
+        &self,
+        scene: &crate::Scene,
+        viewport: crate::SceneViewport,
+        target: &wgpu::Texture,
+    ) -> anyhow::Result<()> {
+        self.0
+            .renderer
+            .borrow_mut()
+            .draw_region(scene, viewport, target)
+    }
+
+    fn draw_region_to_view(
+        &self,
+        scene: &crate::Scene,
+        viewport: crate::SceneViewport,
+        target: &wgpu::TextureView,
+        target_size: Size<DevicePixels>,
+    ) -> anyhow::Result<()> {
+        self.0
+            .renderer
+            .borrow_mut()
+            .draw_region_to_view(scene, viewport, target, target_size)
+    }
+
+    fn xr_swapchain_format(&self) -> Option<wgpu::TextureFormat> {
+        Some(self.0.renderer.borrow().xr_swapchain_format())
+    }
+
+    fn draw_to_xr_swapchain(
+        &self,
+        scene: &crate::Scene,
+        target: &crate::XrSwapchainTarget,
+    ) -> anyhow::Result<()> {
+        self.0
+            .renderer
+            .borrow_mut()
+            .draw_to_xr_swapchain(scene, target)
+    }
+
+    fn set_clear_color(&self, color: Option<crate::Hsla>) {
+        self.0.renderer.borrow_mut().set_clear_color(color);
+    }
+
+    fn update_ime_position(&self, _bounds: Bounds<Pixels>) {}
+
+    #[cfg(target_os = "windows")]
+    fn get_raw_handle(&self) -> windows::Win32::Foundation::HWND {
+        windows::Win32::Foundation::HWND(std::ptr::null_mut())
+    }
+}
+
+impl raw_window_handle::HasDisplayHandle for HeadlessWindow {
+    fn display_handle(
+        &self,
+    ) -> Result<raw_window_handle::DisplayHandle<'_>, raw_window_handle::HandleError> {
+        Err(raw_window_handle::HandleError::Unavailable)
+    }
+}
+
+impl raw_window_handle::HasWindowHandle for HeadlessWindow {
+    fn window_handle(
+        &self,
+    ) -> Result<raw_window_handle::WindowHandle<'_>, raw_window_handle::HandleError> {
+        Err(raw_window_handle::HandleError::Unavailable)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{rc::Rc, sync::Arc};
+
+    use crate::{
+        App, AppCell, AppContext as _, Bounds, Context, IntoElement, Render, Styled as _, Window,
+        WindowBounds, WindowOptions, app::NullHttpClient, div,
+        platform::cross::platform::CrossPlatform, point, px, rgb, size,
+    };
+
+    /// A view that fills its window with `color`.
+    pub(crate) struct FilledView {
+        pub(crate) color: u32,
+    }
+
+    impl Render for FilledView {
+        fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+            div().size_full().bg(rgb(self.color))
+        }
+    }
+
+    /// An app on a headless platform. The tests that draw need a GPU, so they're ignored unless
+    /// run with `--ignored` on a machine that has one.
+    pub(crate) fn headless_app() -> (Rc<CrossPlatform>, Rc<AppCell>) {
+        let platform = Rc::new(
+            CrossPlatform::headless().expect("a headless platform needs a GPU to draw with"),
+        );
+        let app = App::new_app(platform.clone(), Arc::new(()), Arc::new(NullHttpClient));
+        (platform, app)
+    }
+
+    /// Options for a window of `width` by `height` pixels.
+    pub(crate) fn window_options(width: f32, height: f32) -> WindowOptions {
+        WindowOptions {
+            window_bounds: Some(WindowBounds::Windowed(Bounds::new(
+                point(px(0.), px(0.)),
+                size(px(width), px(height)),
+            ))),
+            ..Default::default()
+        }
+    }
+
+    #[track_caller]
+    fn assert_filled(frame: &image::RgbaImage, color: [u8; 4]) {
+        for (x, y, pixel) in frame.enumerate_pixels() {
+            let close_enough = pixel
+                .0
+                .iter()
+                .zip(color)
+                .all(|(actual, expected)| actual.abs_diff(expected) <= 1);
+            assert!(
+                close_enough,
+                "pixel ({x}, {y}) is {:?}, expected {color:?}",
+                pixel.0
+            );
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GPU to draw with"]
+    fn test_headless_window_draws_and_resizes() {
+        let (platform, app) = headless_app();
+        app.borrow_mut()
+            .open_window(window_options(32., 24.), |_, cx| {
+                cx.new(|_| FilledView { color: 0xff0000 })
+            })
+            .unwrap();
+        platform.run_until_parked();
+
+        let window = platform.headless_windows().into_iter().next().unwrap();
+        let frame = window.capture_frame().unwrap();
+        assert_eq!(frame.dimensions(), (32, 24));
+        assert_filled(&frame, [255, 0, 0, 255]);
+
+        window.simulate_resize(size(px(48.), px(16.)));
+        platform.run_until_parked();
+        let frame = window.capture_frame().unwrap();
+        assert_eq!(frame.dimensions(), (48, 16));
+        assert_filled(&frame, [255, 0, 0, 255]);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU to draw with"]
+    fn test_headless_window_draws_the_cursor() {
+        let (platform, app) = headless_app();
+        let handle = app
+            .borrow_mut()
+            .open_window(window_options(32., 32.), |_, cx| {
+                cx.new(|_| FilledView { color: 0xff0000 })
+            })
+            .unwrap();
+        platform.run_until_parked();
+        handle
+            .update(&mut *app.borrow_mut(), |_, window, _| {
+                window.set_mouse_position(point(px(16.), px(16.)))
+            })
+            .unwrap();
+        platform.run_until_parked();
+
+        let window = platform.headless_windows().into_iter().next().unwrap();
+        let frame = window.capture_frame().unwrap();
+        let [red, green, _, _] = frame.get_pixel(16, 16).0;
+        assert!(
+            red > 200 && green < 50,
+            "the middle of the cursor shows what it points at"
+        );
+        let [_, green, _, _] = frame.get_pixel(21, 16).0;
+        assert!(green > 200, "the cursor's inner ring is white");
+        let [red, _, _, _] = frame.get_pixel(23, 16).0;
+        assert!(red < 200, "the cursor's outer ring is dark");
+        let [red, green, _, _] = frame.get_pixel(0, 0).0;
+        assert!(
+            red > 200 && green < 50,
+            "the rest of the frame is left as it was"
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a GPU to draw with"]
+    fn test_headless_window_closes() {
+        let (platform, app) = headless_app();
+        let handle = app
+            .borrow_mut()
+            .open_window(window_options(16., 16.), |_, cx| {
+                cx.new(|_| FilledView { color: 0x0000ff })
+            })
+            .unwrap();
+        platform.run_until_parked();
+        assert_eq!(platform.headless_windows().len(), 1);
+
+        handle
+            .update(&mut *app.borrow_mut(), |_, window, _| {
+                window.remove_window()
+            })
+            .unwrap();
+        platform.run_until_parked();
+        assert!(platform.headless_windows().is_empty());
+    }
+}
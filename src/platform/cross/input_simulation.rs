@@ -0,0 +1,213 @@
+use crate::{
+    KeyDownEvent, KeyUpEvent, Modifiers, MouseButton, MouseDownEvent, MouseMoveEvent, MouseUpEvent,
+    Pixels, PlatformInput, PlatformWindow, Point, RequestFrameOptions, ScrollDelta,
+    ScrollWheelEvent, TouchPhase,
+    platform::cross::{
+        headless_window::HeadlessWindow, platform::winit_key_to_keystroke, window::CrossWindow,
+    },
+};
+
+/// Injects synthesized input into a window of the cross platform, for end-to-end tests.
+///
+/// Keystrokes are produced by the same translation the event loop applies to winit's key events,
+/// and click counts are given explicitly so tests don't depend on the double-click timing.
+pub(crate) trait SimulatedInput: PlatformWindow {
+    /// Dispatches `event` to the window, returning true if the window handled it.
+    fn simulate_input(&self, event: PlatformInput) -> bool;
+
+    /// Asks the window to draw a frame, as the event loop does when the window needs redrawing.
+    fn step_frame(&self);
+
+    /// Types `text` one character at a time, pressing and releasing a key for each.
+    fn simulate_text(&self, text: &str) {
+        for character in text.chars() {
+            let Some(keystroke) = character_keystroke(character, self.modifiers()) else {
+                log::warn!("no key types {character:?}");
+                continue;
+            };
+            self.simulate_input(PlatformInput::KeyDown(KeyDownEvent {
+                keystroke: keystroke.clone(),
+                is_held: false,
+                prefer_character_input: false,
+            }));
+            self.simulate_input(PlatformInput::KeyUp(KeyUpEvent { keystroke }));
+        }
+    }
+
+    /// Moves the mouse to `position`, then presses and releases `button` there.
+    fn simulate_click(&self, position: Point<Pixels>, button: MouseButton, click_count: usize) {
+        let modifiers = self.modifiers();
+        self.simulate_input(PlatformInput::MouseMove(MouseMoveEvent {
+            position,
+            pressed_button: None,
+            modifiers,
+        }));
+        self.simulate_input(PlatformInput::MouseDown(MouseDownEvent {
+            button,
+            position,
+            modifiers,
+            click_count,
+            first_mouse: false,
+        }));
+        self.simulate_input(PlatformInput::MouseUp(MouseUpEvent {
+            button,
+            position,
+            modifiers,
+            click_count,
+        }));
+    }
+
+    /// Scrolls by `delta` with the mouse at `position`, as a single discrete wheel event.
+    fn simulate_scroll(&self, position: Point<Pixels>, delta: ScrollDelta) {
+        self.simulate_input(PlatformInput::ScrollWheel(ScrollWheelEvent {
+            position,
+            delta,
+            modifiers: self.modifiers(),
+            touch_phase: TouchPhase::Moved,
+        }));
+    }
+}
+
+fn character_keystroke(character: char, modifiers: Modifiers) -> Option<crate::Keystroke> {
+    use winit::keyboard::{Key, NamedKey, SmolStr};
+
+    let text = SmolStr::new(character.encode_utf8(&mut [0; 4]));
+    let key = match character {
+        ' ' => Key::Named(NamedKey::Space),
+        '\n' => Key::Named(NamedKey::Enter),
+        '\t' => Key::Named(NamedKey::Tab),
+        _ => Key::Character(text.clone()),
+    };
+    let modifiers = Modifiers {
+        shift: modifiers.shift || character.is_uppercase(),
+        ..modifiers
+    };
+    winit_key_to_keystroke(&key, modifiers, &Some(text))
+}
+
+impl SimulatedInput for CrossWindow {
+    fn simulate_input(&self, event: PlatformInput) -> bool {
+        let state = &self.0.state;
+        match &event {
+            PlatformInput::MouseMove(event) => state.mouse_position.set(event.position),
+            PlatformInput::ModifiersChanged(event) => state.modifiers.set(event.modifiers),
+            _ => {}
+        }
+
+        let mut handled = false;
+        state.callbacks.invoke_mut(&state.callbacks.on_input, |cb| {
+            handled = !cb(event).propagate;
+        });
+        handled
+    }
+
+    fn step_frame(&self) {
+        let state = &self.0.state;
+        state
+            .callbacks
+            .invoke_mut(&state.callbacks.on_request_frame, |cb| {
+                cb(RequestFrameOptions {
+                    force_render: self.take_force_render(),
+                    require_presentation: true,
+                });
+            });
+    }
+}
+
+impl SimulatedInput for HeadlessWindow {
+    fn simulate_input(&self, event: PlatformInput) -> bool {
+        HeadlessWindow::simulate_input(self, event)
+    }
+
+    fn step_frame(&self) {
+        self.request_frame();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SimulatedInput as _;
+    use crate::{
+        AppContext as _, Context, FocusHandle, InteractiveElement as _, IntoElement, KeyDownEvent,
+        MouseButton, MouseDownEvent, Pixels, Point, Render, ScrollDelta, ScrollWheelEvent,
+        Styled as _, Window, div,
+        platform::cross::headless_window::tests::{headless_app, window_options},
+        point, px,
+    };
+
+    /// A view that records the input it receives.
+    struct RecordingView {
+        focus_handle: FocusHandle,
+        click_count: usize,
+        typed_keys: Vec<String>,
+        scrolled: Point<Pixels>,
+    }
+
+    impl Render for RecordingView {
+        fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+            div()
+                .size_full()
+                .track_focus(&self.focus_handle)
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|this, event: &MouseDownEvent, _, _| {
+                        this.click_count += event.click_count;
+                    }),
+                )
+                .on_key_down(cx.listener(|this, event: &KeyDownEvent, _, _| {
+                    this.typed_keys.push(event.keystroke.key.clone());
+                }))
+                .on_scroll_wheel(cx.listener(|this, event: &ScrollWheelEvent, _, _| {
+                    this.scrolled += event.delta.pixel_delta(px(1.));
+                }))
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GPU to draw with"]
+    fn test_simulated_input_reaches_the_view() {
+        let (platform, app) = headless_app();
+        let handle = app
+            .borrow_mut()
+            .open_window(window_options(64., 64.), |window, cx| {
+                let view = cx.new(|cx| RecordingView {
+                    focus_handle: cx.focus_handle(),
+                    click_count: 0,
+                    typed_keys: Vec::new(),
+                    scrolled: Point::default(),
+                });
+                let focus_handle = view.read(cx).focus_handle.clone();
+                window.focus(&focus_handle);
+                view
+            })
+            .unwrap();
+        platform.run_until_parked();
+        let window = platform.headless_windows().into_iter().next().unwrap();
+
+        window.simulate_click(point(px(32.), px(32.)), MouseButton::Left, 2);
+        window.simulate_text("hi");
+        window.simulate_scroll(
+            point(px(32.), px(32.)),
+            ScrollDelta::Pixels(point(px(0.), px(-12.))),
+        );
+        window.step_frame();
+        platform.run_until_parked();
+
+        let (click_count, typed_keys, scrolled) = handle
+            .update(&mut *app.borrow_mut(), |view, _, _| {
+                (view.click_count, view.typed_keys.clone(), view.scrolled)
+            })
+            .unwrap();
+        assert_eq!(click_count, 2);
+        assert_eq!(typed_keys, ["h", "i"]);
+        assert_eq!(scrolled, point(px(0.), px(-12.)));
+    }
+
+    #[test]
+    fn test_uppercase_characters_are_typed_with_shift() {
+        let keystroke = super::character_keystroke('A', crate::Modifiers::default()).unwrap();
+        assert!(keystroke.modifiers.shift);
+        assert_eq!(keystroke.key, "a");
+        assert_eq!(keystroke.key_char.as_deref(), Some("A"));
+    }
+}
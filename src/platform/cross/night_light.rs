@@ -0,0 +1,69 @@
+use std::{sync::Weak, time::Duration};
+
+use crate::{
+    BackgroundExecutor, WhitePoint, platform::cross::renderer_settings::RendererSettingsRegistry,
+};
+
+/// How often the OS night light is checked while the renderer settings follow it.
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Checks the OS night light periodically while the renderer settings follow
+/// [`WhitePoint::System`], recording its color temperature so the windows redraw when it changes.
+pub(crate) fn watch(executor: &BackgroundExecutor, settings: Weak<RendererSettingsRegistry>) {
+    let timer_executor = executor.clone();
+    executor
+        .spawn(async move {
+            loop {
+                let Some(settings) = settings.upgrade() else {
+                    return;
+                };
+                if settings.get().white_point == WhitePoint::System {
+                    settings.set_night_light_temperature(system_night_light_temperature());
+                }
+                drop(settings);
+                timer_executor.timer(POLL_INTERVAL).await;
+            }
+        })
+        .detach();
+}
+
+/// The color temperature the OS night light shifts the display to, in kelvin, or `None` while
+/// it's off or where it can't be detected.
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+fn system_night_light_temperature() -> Option<f32> {
+    use crate::platform::cross::gdbus;
+
+    const GNOME_COLOR: [&str; 3] = [
+        "org.gnome.SettingsDaemon.Color",
+        "/org/gnome/SettingsDaemon/Color",
+        "org.gnome.SettingsDaemon.Color",
+    ];
+    const KWIN_NIGHT_LIGHT: [&str; 3] = [
+        "org.kde.KWin",
+        "/org/kde/KWin/NightLight",
+        "org.kde.KWin.NightLight",
+    ];
+    let property = |[destination, object_path, interface]: [&str; 3], property: &str| {
+        gdbus::property(destination, object_path, interface, property)
+    };
+
+    if let Some(active) = property(GNOME_COLOR, "NightLightActive") {
+        if !active.contains("true") {
+            return None;
+        }
+        return property(GNOME_COLOR, "Temperature").and_then(|reply| gdbus::parse_number(&reply));
+    }
+    if let Some(running) = property(KWIN_NIGHT_LIGHT, "running") {
+        if !running.contains("true") {
+            return None;
+        }
+        return property(KWIN_NIGHT_LIGHT, "currentTemperature")
+            .and_then(|reply| gdbus::parse_number(&reply));
+    }
+    None
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "freebsd")))]
+fn system_night_light_temperature() -> Option<f32> {
+    None
+}
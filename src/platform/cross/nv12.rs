@@ -0,0 +1,256 @@
+use anyhow::{Result, bail};
+
+use crate::VideoFrame;
+
+/// Converts NV12 frames to RGB by drawing them into a render target, so decoded video can be
+/// shown through a `WgpuSurfaceHandle`.
+pub(crate) struct Nv12Converter {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    planes: Option<Nv12Planes>,
+}
+
+/// The textures a frame's planes are uploaded to, kept while frames have the same size.
+struct Nv12Planes {
+    size: (u32, u32),
+    luma: wgpu::Texture,
+    chroma: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Nv12Converter {
+    /// Creates a converter that draws into targets of the given format.
+    pub(crate) fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("nv12_shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/nv12.wgsl").into()),
+        });
+
+        let plane_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("nv12_bind_group_layout"),
+            entries: &[
+                plane_entry(0),
+                plane_entry(1),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("nv12_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("nv12"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_nv12"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_nv12"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("nv12_sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            sampler,
+            planes: None,
+        }
+    }
+
+    /// Uploads the planes of `frame` and draws it over all of `target`.
+    pub(crate) fn convert(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        frame: &VideoFrame,
+        target: &wgpu::TextureView,
+    ) -> Result<()> {
+        let width = frame.size.width.0.max(0) as u32;
+        let height = frame.size.height.0.max(0) as u32;
+        if width == 0 || height == 0 {
+            bail!("video frame is empty");
+        }
+        let chroma_size = (width.div_ceil(2), height.div_ceil(2));
+        if !plane_fits(&frame.luma, frame.luma_stride, width, height) {
+            bail!("video frame's luma plane is smaller than its size");
+        }
+        if !plane_fits(
+            &frame.chroma,
+            frame.chroma_stride,
+            chroma_size.0 * 2,
+            chroma_size.1,
+        ) {
+            bail!("video frame's chroma plane is smaller than its size");
+        }
+
+        let planes = match self.planes.take() {
+            Some(planes) if planes.size == (width, height) => planes,
+            _ => self.create_planes(device, (width, height), chroma_size),
+        };
+        let planes = self.planes.insert(planes);
+
+        let write_plane = |texture: &wgpu::Texture, data: &[u8], stride: u32, size: (u32, u32)| {
+            queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                data,
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(stride),
+                    rows_per_image: Some(size.1),
+                },
+                wgpu::Extent3d {
+                    width: size.0,
+                    height: size.1,
+                    depth_or_array_layers: 1,
+                },
+            );
+        };
+        write_plane(
+            &planes.luma,
+            &frame.luma,
+            frame.luma_stride,
+            (width, height),
+        );
+        write_plane(
+            &planes.chroma,
+            &frame.chroma,
+            frame.chroma_stride,
+            chroma_size,
+        );
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("nv12_conversion"),
+        });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("nv12_conversion"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &planes.bind_group, &[]);
+            pass.draw(0..4, 0..1);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+        Ok(())
+    }
+
+    fn create_planes(
+        &self,
+        device: &wgpu::Device,
+        size: (u32, u32),
+        chroma_size: (u32, u32),
+    ) -> Nv12Planes {
+        let create_plane = |label, size: (u32, u32), format| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width: size.0,
+                    height: size.1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            })
+        };
+        let luma = create_plane("nv12_luma", size, wgpu::TextureFormat::R8Unorm);
+        let chroma = create_plane("nv12_chroma", chroma_size, wgpu::TextureFormat::Rg8Unorm);
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("nv12_bind_group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &luma.create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(
+                        &chroma.create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+
+        Nv12Planes {
+            size,
+            luma,
+            chroma,
+            bind_group,
+        }
+    }
+}
+
+/// Whether `data` holds `height` rows of `row_bytes` bytes each, `stride` bytes apart.
+fn plane_fits(data: &[u8], stride: u32, row_bytes: u32, height: u32) -> bool {
+    stride >= row_bytes
+        && data.len() as u64 >= stride as u64 * (height as u64 - 1) + row_bytes as u64
+}
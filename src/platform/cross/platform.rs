@@ -1,21 +1,43 @@
 use crate::{
-    BackgroundExecutor, Capslock, DevicePixels, DummyKeyboardMapper, ForegroundExecutor,
-    KeyDownEvent, KeyUpEvent, Keystroke, Modifiers, ModifiersChangedEvent, MouseButton,
-    MouseDownEvent, MouseExitEvent, MouseMoveEvent, MouseUpEvent, Pixels, Platform, PlatformInput,
-    PlatformWindow as _, PriorityQueueReceiver, RunnableVariant, ScrollWheelEvent, Size,
+    AnyWindowHandle, BackgroundExecutor, Bounds, Capslock, ClipboardFormat, DevicePixels,
+    DisplayId, DummyKeyboardMapper, ForegroundExecutor, KeyDownEvent, KeyUpEvent, Keystroke,
+    Modifiers, ModifiersChangedEvent, MouseButton, MouseDownEvent, MouseExitEvent, MouseMoveEvent,
+    MouseUpEvent, PinchEvent, Pixels, Platform, PlatformDisplay as _, PlatformInput,
+    PlatformWindow as _, PowerState, PriorityQueueReceiver, RenderPluginRegistry, RotationEvent,
+    ScrollWheelEvent, Size, SystemAppearance, WindowControlArea, WindowKind,
     platform::cross::{
-        dispatcher::{CrossEvent, Dispatcher},
+        clipboard::{ClipboardKind, CrossClipboard},
+        cursor_blink::CursorBlink,
+        dispatcher::{CrossEvent, Dispatcher, MainThreadTask},
+        display::{self, CrossDisplay, monitor_bounds, monitor_display_id},
+        embedding::EmbeddedDevice,
+        gpu_memory::GpuMemoryTracker,
+        headless_window::{HeadlessWindow, HeadlessWindowState},
         keyboard::CrossKeyboardLayout,
+        night_light, power_state,
         render_context::WgpuContext,
+        renderer_settings::RendererSettingsRegistry,
+        single_instance::{self, Claim, SingleInstance},
+        surface_registry::SurfaceRegistry,
+        system_appearance, text_scale,
         text_system::CosmicTextSystem,
         window::CrossWindow,
     },
     point,
 };
-use anyhow::Result;
+use anyhow::{Context as _, Result};
 use collections::FxHashMap;
-use std::{cell::Cell, rc::Rc, sync::Arc, time::Instant};
-use winit::event_loop::ActiveEventLoop;
+use std::{
+    cell::{Cell, RefCell},
+    path::{Path, PathBuf},
+    rc::{Rc, Weak},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use winit::{
+    event_loop::ActiveEventLoop,
+    platform::pump_events::{EventLoopExtPumpEvents, PumpStatus},
+};
 
 thread_local! {
     static ACTIVE_CONTEXT: Cell<Option<(*const ActiveEventLoop, *mut AppState)>> = Cell::new(None);
@@ -30,36 +52,180 @@ fn with_active_context<R>(f: impl FnOnce(&ActiveEventLoop, &mut AppState) -> R)
     })
 }
 
+type RawWinitEventCallback = Box<dyn FnMut(AnyWindowHandle, &winit::event::WindowEvent) -> bool>;
+
 pub(crate) struct CrossPlatform {
     background_executor: BackgroundExecutor,
     foreground_executor: ForegroundExecutor,
     text_system: Arc<CosmicTextSystem>,
-    wgpu_context: Arc<WgpuContext>,
-    main_rx: PriorityQueueReceiver<RunnableVariant>,
+    // None until the GPU device is ready, and replaced when it's lost, see
+    // `AppState::recover_from_device_loss`
+    wgpu_context: Rc<RefCell<Option<Arc<WgpuContext>>>>,
+    // the state of the GPU context that can be configured before its device is ready
+    renderer_settings: Arc<RendererSettingsRegistry>,
+    gpu_memory: Arc<GpuMemoryTracker>,
+    render_plugins: Arc<RenderPluginRegistry>,
+    main_rx: PriorityQueueReceiver<MainThreadTask>,
+    dispatcher: Arc<Dispatcher>,
     event_loop: Cell<Option<winit::event_loop::EventLoop<CrossEvent>>>,
-    event_loop_proxy: winit::event_loop::EventLoopProxy<CrossEvent>,
+    // the state the event loop is pumped with between calls to `Platform::pump_events`, set by
+    // `Platform::start`
+    pumped_app_state: RefCell<Option<AppState>>,
+    // None on a headless platform, which has no event loop and opens `HeadlessWindow`s instead.
+    event_loop_proxy: Option<winit::event_loop::EventLoopProxy<CrossEvent>>,
+    headless_windows: RefCell<Vec<Weak<HeadlessWindowState>>>,
     callbacks: PlatformCallbacks,
+    clipboard: CrossClipboard,
+    auxiliary_executable_dir: RefCell<Option<PathBuf>>,
+    system_appearance: Arc<parking_lot::Mutex<SystemAppearance>>,
+    text_scale_factor: Arc<parking_lot::Mutex<f32>>,
+    window_registry: Rc<RefCell<WindowRegistry>>,
+    cursor_blink: Rc<RefCell<CursorBlink>>,
+    next_present_group: Cell<u64>,
+    // the claim made by `Platform::claim_single_instance`, released when the event loop exits
+    single_instance: Rc<Cell<Option<SingleInstance>>>,
 }
 
 #[derive(Default)]
 struct PlatformCallbacks {
     on_open_urls: Cell<Option<Box<dyn FnMut(Vec<String>)>>>,
-    on_quit: Cell<Option<Box<dyn FnMut()>>>,
-    on_reopen: Cell<Option<Box<dyn FnMut()>>>,
+    on_quit: Rc<Cell<Option<Box<dyn FnMut()>>>>,
+    on_reopen: Rc<Cell<Option<Box<dyn FnMut()>>>>,
     on_app_menu_action: Cell<Option<Box<dyn FnMut(&dyn crate::Action)>>>,
     on_will_open_app_menu: Cell<Option<Box<dyn FnMut()>>>,
     on_validate_app_menu_command: Cell<Option<Box<dyn FnMut(&dyn crate::Action) -> bool>>>,
+    on_gpu_error: Rc<Cell<Option<Box<dyn FnMut(crate::GpuError)>>>>,
+    on_raw_winit_event: Rc<Cell<Option<RawWinitEventCallback>>>,
+    on_system_appearance_changed: Rc<Cell<Option<Box<dyn FnMut()>>>>,
+    on_displays_changed: Rc<Cell<Option<Box<dyn FnMut()>>>>,
+    on_power_state_changed: Rc<Cell<Option<Box<dyn FnMut()>>>>,
+    on_text_scale_factor_changed: Rc<Cell<Option<Box<dyn FnMut()>>>>,
+    on_cursor_blink: Rc<Cell<Option<Box<dyn FnMut()>>>>,
 }
 
 struct AppState {
     windows: FxHashMap<winit::window::WindowId, CrossWindow>,
+    wgpu_context: Rc<RefCell<Option<Arc<WgpuContext>>>>,
+    event_loop_proxy: winit::event_loop::EventLoopProxy<CrossEvent>,
     on_finish_launching: Cell<Option<Box<dyn 'static + FnOnce()>>>,
-    main_rx: PriorityQueueReceiver<RunnableVariant>,
+    // set once the event loop first resumed, before which no window can be created
+    resumed: bool,
+    main_rx: PriorityQueueReceiver<MainThreadTask>,
+    dispatcher: Arc<Dispatcher>,
     current_modifiers: Modifiers,
+    pointers: FxHashMap<winit::event::DeviceId, PointerState>,
+    gesture: GestureState,
+    on_gpu_error: Rc<Cell<Option<Box<dyn FnMut(crate::GpuError)>>>>,
+    on_raw_winit_event: Rc<Cell<Option<RawWinitEventCallback>>>,
+    on_system_appearance_changed: Rc<Cell<Option<Box<dyn FnMut()>>>>,
+    on_displays_changed: Rc<Cell<Option<Box<dyn FnMut()>>>>,
+    on_power_state_changed: Rc<Cell<Option<Box<dyn FnMut()>>>>,
+    on_text_scale_factor_changed: Rc<Cell<Option<Box<dyn FnMut()>>>>,
+    on_cursor_blink: Rc<Cell<Option<Box<dyn FnMut()>>>>,
+    on_quit: Rc<Cell<Option<Box<dyn FnMut()>>>>,
+    on_reopen: Rc<Cell<Option<Box<dyn FnMut()>>>>,
+    window_registry: Rc<RefCell<WindowRegistry>>,
+    cursor_blink: Rc<RefCell<CursorBlink>>,
+    // the windows hidden by `Platform::hide`, shown again when the app is activated
+    hidden_windows: Vec<winit::window::WindowId>,
+    // the displays as they were when they were last reconciled
+    displays: Vec<CrossDisplay>,
+    // set when a window event hints that the displays changed, so they're reconciled early
+    displays_may_have_changed: bool,
+    single_instance: Rc<Cell<Option<SingleInstance>>>,
+}
+
+/// Maps GPUI's window handles to the winit windows showing them and back, so platform operations
+/// that target a handle can find its window. It's kept in sync as windows open and close.
+#[derive(Default)]
+struct WindowRegistry {
+    handles: FxHashMap<winit::window::WindowId, AnyWindowHandle>,
+    window_ids: FxHashMap<AnyWindowHandle, winit::window::WindowId>,
+    // front to back, with the most recently activated window first
+    stack: Vec<winit::window::WindowId>,
+    active: Option<winit::window::WindowId>,
+}
+
+impl WindowRegistry {
+    fn insert(&mut self, handle: AnyWindowHandle, window_id: winit::window::WindowId) {
+        self.handles.insert(window_id, handle);
+        self.window_ids.insert(handle, window_id);
+        self.stack.insert(0, window_id);
+    }
+
+    fn remove(&mut self, window_id: winit::window::WindowId) {
+        if let Some(handle) = self.handles.remove(&window_id) {
+            self.window_ids.remove(&handle);
+        }
+        self.stack.retain(|id| *id != window_id);
+        if self.active == Some(window_id) {
+            self.active = None;
+        }
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn window_id(&self, handle: AnyWindowHandle) -> Option<winit::window::WindowId> {
+        self.window_ids.get(&handle).copied()
+    }
+
+    fn handle(&self, window_id: winit::window::WindowId) -> Option<AnyWindowHandle> {
+        self.handles.get(&window_id).copied()
+    }
+
+    fn set_active(&mut self, window_id: winit::window::WindowId, active: bool) {
+        if active {
+            self.active = Some(window_id);
+            self.stack.retain(|id| *id != window_id);
+            self.stack.insert(0, window_id);
+        } else if self.active == Some(window_id) {
+            self.active = None;
+        }
+    }
+
+    fn active_handle(&self) -> Option<AnyWindowHandle> {
+        self.handles.get(&self.active?).copied()
+    }
+
+    fn handle_stack(&self) -> Vec<AnyWindowHandle> {
+        self.stack
+            .iter()
+            .filter_map(|window_id| self.handles.get(window_id).copied())
+            .collect()
+    }
+}
+
+/// The state of one pointing device. Each device is tracked separately so that multiple seats,
+/// drawing tablets and secondary pointers don't corrupt each other's buttons and click counts.
+struct PointerState {
+    position: crate::Point<Pixels>,
     pressed_button: Option<MouseButton>,
     click_state: ClickState,
 }
 
+impl PointerState {
+    fn new(position: crate::Point<Pixels>) -> Self {
+        Self {
+            position,
+            pressed_button: None,
+            click_state: ClickState {
+                last_button: MouseButton::Left,
+                last_position: point(Pixels(0.0), Pixels(0.0)),
+                last_time: None,
+                current_count: 0,
+            },
+        }
+    }
+}
+
+/// The totals of the touchpad gesture in progress, accumulated from winit's per-event deltas.
+struct GestureState {
+    scale: f32,
+    rotation: f32,
+}
+
 struct ClickState {
     last_button: MouseButton,
     last_position: crate::Point<Pixels>,
@@ -69,27 +235,253 @@ struct ClickState {
 
 impl CrossPlatform {
     pub fn new() -> Result<Self> {
-        let (main_tx, main_rx) = PriorityQueueReceiver::new();
         let mut event_loop =
             winit::event_loop::EventLoop::<CrossEvent>::with_user_event().build()?;
         event_loop.set_control_flow(winit::event_loop::ControlFlow::Poll);
-        let event_loop_proxy = event_loop.create_proxy();
+        Self::with_event_loop(Some(event_loop), None)
+    }
+
+    /// Creates a platform that never touches the windowing system, for tests and CI machines
+    /// without a display. Its windows render offscreen, and its main thread only makes progress
+    /// when [`CrossPlatform::run_until_parked`] is called.
+    pub(crate) fn headless() -> Result<Self> {
+        Self::with_event_loop(None, None)
+    }
+
+    /// Creates a headless platform that draws with the device of the host it's embedded in, see
+    /// [`crate::EmbeddedApp`].
+    pub(crate) fn embedded(embedded_device: EmbeddedDevice) -> Result<Self> {
+        Self::with_event_loop(None, Some(embedded_device))
+    }
+
+    fn with_event_loop(
+        event_loop: Option<winit::event_loop::EventLoop<CrossEvent>>,
+        embedded_device: Option<EmbeddedDevice>,
+    ) -> Result<Self> {
+        let (main_tx, main_rx) = PriorityQueueReceiver::new();
+        let event_loop_proxy = event_loop
+            .as_ref()
+            .map(|event_loop| event_loop.create_proxy());
 
         let dispatcher = Arc::new(Dispatcher::new(main_tx, event_loop_proxy.clone()));
         let background_executor = BackgroundExecutor::new(dispatcher.clone());
         let foreground_executor = ForegroundExecutor::new(dispatcher.clone());
 
+        let gpu_memory = Arc::new(GpuMemoryTracker::default());
+        let renderer_settings = Arc::new(RendererSettingsRegistry::from_env());
+        let render_plugins = Arc::new(RenderPluginRegistry::default());
+        let surface_registry = Arc::new(SurfaceRegistry::new(gpu_memory.clone()));
+        let wgpu_context = match (embedded_device, &event_loop_proxy) {
+            // The host owns its device and the handling of its errors, so the device of an embedded
+            // platform isn't watched.
+            (Some(embedded_device), _) => {
+                let mut context = WgpuContext::with_device(
+                    embedded_device,
+                    surface_registry,
+                    gpu_memory.clone(),
+                    renderer_settings.clone(),
+                    render_plugins.clone(),
+                );
+                context.background_executor = Some(background_executor.clone());
+                Some(Arc::new(context))
+            }
+            // The device is requested in the background so slow drivers don't stall startup. The
+            // app finishes launching, and opens its windows, once it's ready, see
+            // `AppState::handle_gpu_context_created`.
+            (None, Some(event_loop_proxy)) => {
+                let context = WgpuContext::with_shared_state(
+                    surface_registry,
+                    gpu_memory.clone(),
+                    renderer_settings.clone(),
+                    render_plugins.clone(),
+                );
+                let event_loop_proxy = event_loop_proxy.clone();
+                let executor = background_executor.clone();
+                background_executor
+                    .spawn(async move {
+                        let context = context.await.map(|mut context| {
+                            context.background_executor = Some(executor);
+                            Arc::new(context)
+                        });
+                        if event_loop_proxy
+                            .send_event(CrossEvent::GpuContextCreated(context))
+                            .is_err()
+                        {
+                            log::error!("the GPU device was ready after the event loop exited");
+                        }
+                    })
+                    .detach();
+                None
+            }
+            // Headless platforms open their windows as soon as they're asked to, so the device has
+            // to be ready before anything runs.
+            (None, None) => {
+                let mut context = pollster::block_on(WgpuContext::with_shared_state(
+                    surface_registry,
+                    gpu_memory.clone(),
+                    renderer_settings.clone(),
+                    render_plugins.clone(),
+                ))?;
+                context.background_executor = Some(background_executor.clone());
+                watch_device(&context, None);
+                Some(Arc::new(context))
+            }
+        };
+
+        if let Some(event_loop_proxy) = &event_loop_proxy {
+            // Settings may change on any thread, but the renderers are owned by the windows on the
+            // main thread, so they're updated from the event loop.
+            let settings_proxy = event_loop_proxy.clone();
+            renderer_settings.observe(move |_| {
+                if settings_proxy
+                    .send_event(CrossEvent::RendererSettingsChanged)
+                    .is_err()
+                {
+                    log::error!("renderer settings changed after the event loop exited");
+                }
+            });
+        }
+
+        // Headless platforms render with fixed colors, so snapshots don't depend on the time of
+        // day.
+        if let Some(event_loop_proxy) = &event_loop_proxy {
+            night_light::watch(&background_executor, Arc::downgrade(&renderer_settings));
+            power_state::watch(
+                &background_executor,
+                event_loop_proxy.clone(),
+                Arc::downgrade(&renderer_settings),
+            );
+        }
+
+        let system_appearance = Arc::new(parking_lot::Mutex::new(SystemAppearance::default()));
+        if let Some(event_loop_proxy) = &event_loop_proxy {
+            system_appearance::watch(
+                &background_executor,
+                event_loop_proxy.clone(),
+                system_appearance.clone(),
+            );
+            display::watch(&background_executor, event_loop_proxy.clone());
+        }
+
+        let text_scale_factor = Arc::new(parking_lot::Mutex::new(1.0));
+        if let Some(event_loop_proxy) = &event_loop_proxy {
+            text_scale::watch(
+                &background_executor,
+                event_loop_proxy.clone(),
+                text_scale_factor.clone(),
+            );
+        }
+
+        #[cfg(target_os = "macos")]
+        if let Some(event_loop_proxy) = &event_loop_proxy {
+            single_instance::watch_dock_activation(event_loop_proxy.clone());
+        }
+
         Ok(Self {
             background_executor,
             foreground_executor,
             text_system: Arc::new(CosmicTextSystem::new()),
-            wgpu_context: Arc::new(WgpuContext::new()?),
+            wgpu_context: Rc::new(RefCell::new(wgpu_context)),
+            renderer_settings,
+            gpu_memory,
+            render_plugins,
             main_rx,
-            event_loop: Cell::new(Some(event_loop)),
+            dispatcher,
+            event_loop: Cell::new(event_loop),
+            pumped_app_state: RefCell::new(None),
             event_loop_proxy,
+            headless_windows: RefCell::new(Vec::new()),
             callbacks: PlatformCallbacks::default(),
+            clipboard: CrossClipboard::default(),
+            auxiliary_executable_dir: RefCell::new(None),
+            system_appearance,
+            text_scale_factor,
+            window_registry: Rc::new(RefCell::new(WindowRegistry::default())),
+            cursor_blink: Rc::new(RefCell::new(CursorBlink::new())),
+            next_present_group: Cell::new(0),
+            single_instance: Rc::new(Cell::new(None)),
         })
     }
+
+    /// Runs main thread tasks and draws the headless windows until there's nothing left to do.
+    pub(crate) fn run_until_parked(&self) {
+        let mut main_rx = self.main_rx.clone();
+        loop {
+            let ran_tasks = drain_main_queue(&mut main_rx, &self.dispatcher);
+            // Drawing a frame may queue more work for the main thread, which the next iteration
+            // picks up.
+            for window in self.headless_windows() {
+                window.request_frame();
+            }
+            if !ran_tasks {
+                break;
+            }
+        }
+    }
+
+    /// The windows of a headless platform that are still open.
+    pub(crate) fn headless_windows(&self) -> Vec<HeadlessWindow> {
+        let mut windows = Vec::new();
+        self.headless_windows.borrow_mut().retain(|window| {
+            HeadlessWindow::upgrade(window)
+                .map(|window| windows.push(window))
+                .is_some()
+        });
+        windows
+    }
+
+    /// The state the event loop runs the app with, which finishes launching it with
+    /// `on_finish_launching`.
+    fn app_state(
+        &self,
+        event_loop_proxy: winit::event_loop::EventLoopProxy<CrossEvent>,
+        on_finish_launching: Box<dyn 'static + FnOnce()>,
+    ) -> AppState {
+        AppState {
+            windows: Default::default(),
+            wgpu_context: self.wgpu_context.clone(),
+            event_loop_proxy,
+            on_finish_launching: Cell::new(Some(on_finish_launching)),
+            resumed: false,
+            main_rx: self.main_rx.clone(),
+            dispatcher: self.dispatcher.clone(),
+            current_modifiers: Modifiers::default(),
+            pointers: FxHashMap::default(),
+            gesture: GestureState {
+                scale: 1.0,
+                rotation: 0.0,
+            },
+            on_gpu_error: self.callbacks.on_gpu_error.clone(),
+            on_raw_winit_event: self.callbacks.on_raw_winit_event.clone(),
+            on_system_appearance_changed: self.callbacks.on_system_appearance_changed.clone(),
+            on_displays_changed: self.callbacks.on_displays_changed.clone(),
+            on_power_state_changed: self.callbacks.on_power_state_changed.clone(),
+            on_text_scale_factor_changed: self.callbacks.on_text_scale_factor_changed.clone(),
+            on_cursor_blink: self.callbacks.on_cursor_blink.clone(),
+            on_quit: self.callbacks.on_quit.clone(),
+            on_reopen: self.callbacks.on_reopen.clone(),
+            window_registry: self.window_registry.clone(),
+            cursor_blink: self.cursor_blink.clone(),
+            hidden_windows: Vec::new(),
+            displays: Vec::new(),
+            displays_may_have_changed: false,
+            single_instance: self.single_instance.clone(),
+        }
+    }
+
+    /// The open window of a headless platform showing `handle`.
+    pub(crate) fn headless_window(&self, handle: AnyWindowHandle) -> Option<HeadlessWindow> {
+        self.headless_windows()
+            .into_iter()
+            .find(|window| window.handle() == handle)
+    }
+
+    /// The open window showing `handle`. Must be called on the main thread while the event loop
+    /// is running.
+    pub(crate) fn window(&self, handle: AnyWindowHandle) -> Option<CrossWindow> {
+        let window_id = self.window_registry.borrow().window_id(handle)?;
+        with_active_context(|_, app_state| app_state.windows.get(&window_id).cloned()).flatten()
+    }
 }
 
 impl Platform for CrossPlatform {
@@ -106,28 +498,63 @@ impl Platform for CrossPlatform {
     }
 
     fn run(&self, on_finish_launching: Box<dyn 'static + FnOnce()>) {
-        let mut event_loop = self.event_loop.take().expect("App is already running");
-
-        let mut app_state = AppState {
-            windows: Default::default(),
-            on_finish_launching: Cell::new(Some(on_finish_launching)),
-            main_rx: self.main_rx.clone(),
-            current_modifiers: Modifiers::default(),
-            pressed_button: None,
-            click_state: ClickState {
-                last_button: MouseButton::Left,
-                last_position: point(Pixels(0.0), Pixels(0.0)),
-                last_time: None,
-                current_count: 0,
-            },
+        let Some(event_loop_proxy) = self.event_loop_proxy.clone() else {
+            on_finish_launching();
+            self.run_until_parked();
+            return;
         };
 
+        let mut event_loop = self.event_loop.take().expect("App is already running");
+        let mut app_state = self.app_state(event_loop_proxy, on_finish_launching);
+
         event_loop
             .run_app(&mut app_state)
             .expect("Failed to run App");
+
+        // Background threads may have queued tasks between the event loop exiting and the
+        // dispatcher closing.
+        self.dispatcher.close();
+        discard_main_queue(&mut self.main_rx.clone());
+    }
+
+    fn start(&self, on_finish_launching: Box<dyn 'static + FnOnce()>) {
+        let Some(event_loop_proxy) = self.event_loop_proxy.clone() else {
+            on_finish_launching();
+            self.run_until_parked();
+            return;
+        };
+        let app_state = self.app_state(event_loop_proxy, on_finish_launching);
+        self.pumped_app_state.replace(Some(app_state));
+    }
+
+    fn pump_events(&self, timeout: Option<Duration>) -> bool {
+        if self.event_loop_proxy.is_none() {
+            self.run_until_parked();
+            return true;
+        }
+        let Some(mut event_loop) = self.event_loop.take() else {
+            return false;
+        };
+        let Some(mut app_state) = self.pumped_app_state.take() else {
+            log::error!("the app's events were pumped before it was started");
+            self.event_loop.set(Some(event_loop));
+            return false;
+        };
+
+        if let PumpStatus::Exit(_) = event_loop.pump_app_events(timeout, &mut app_state) {
+            // Background threads may have queued tasks between the event loop exiting and the
+            // dispatcher closing.
+            self.dispatcher.close();
+            discard_main_queue(&mut self.main_rx.clone());
+            return false;
+        }
+        self.event_loop.set(Some(event_loop));
+        self.pumped_app_state.replace(Some(app_state));
+        true
     }
 
     fn quit(&self) {
+        self.dispatcher.close();
         // NOTE(mdeand): The event loop will exit when all windows are closed and there are no
         // NOTE(mdeand): more events to process. For an explicit quit, we rely on winit's exit
         // NOTE(mdeand): mechanism via the ActiveEventLoop.
@@ -140,43 +567,106 @@ impl Platform for CrossPlatform {
         log::warn!("restart is not yet implemented on this platform");
     }
 
-    fn activate(&self, _ignoring_other_apps: bool) {}
+    fn activate(&self, _ignoring_other_apps: bool) {
+        with_active_context(|_, app_state| app_state.unhide_windows());
+
+        // On Wayland, winit raises the window through xdg-activation, which only succeeds if the
+        // compositor considers the request recent enough.
+        let handle = {
+            let window_registry = self.window_registry.borrow();
+            window_registry
+                .active_handle()
+                .or_else(|| window_registry.handle_stack().first().copied())
+        };
+        if let Some(window) = handle.and_then(|handle| self.window(handle)) {
+            window.window().focus_window();
+        }
+    }
 
     fn hide(&self) {
-        log::warn!("hide is not yet implemented on this platform");
+        #[cfg(target_os = "macos")]
+        with_active_context(|event_loop, _| {
+            use winit::platform::macos::ActiveEventLoopExtMacOS as _;
+
+            event_loop.hide_application();
+        });
+        #[cfg(not(target_os = "macos"))]
+        with_active_context(|_, app_state| app_state.hide_windows());
     }
 
     fn hide_other_apps(&self) {
-        log::warn!("hide_other_apps is not yet implemented on this platform");
+        // Only macOS lets an application hide the others, elsewhere each application's windows are
+        // managed by the user.
+        #[cfg(target_os = "macos")]
+        with_active_context(|event_loop, _| {
+            use winit::platform::macos::ActiveEventLoopExtMacOS as _;
+
+            event_loop.hide_other_applications();
+        });
+        #[cfg(not(target_os = "macos"))]
+        log::warn!("hide_other_apps is not supported on this platform");
     }
 
     fn unhide_other_apps(&self) {
-        log::warn!("unhide_other_apps is not yet implemented on this platform");
+        // winit can't show the applications hidden by `hide_other_apps`.
+        log::warn!("unhide_other_apps is not supported on this platform");
     }
 
     fn displays(&self) -> Vec<Rc<dyn crate::PlatformDisplay>> {
-        // TODO(mdeand): Add support for multiple displays.
-        vec![]
+        with_active_context(|event_loop, _| {
+            event_loop
+                .available_monitors()
+                .map(|monitor| {
+                    Rc::new(CrossDisplay::new(&monitor)) as Rc<dyn crate::PlatformDisplay>
+                })
+                .collect()
+        })
+        .unwrap_or_default()
     }
 
     fn primary_display(&self) -> Option<Rc<dyn crate::PlatformDisplay>> {
-        // TODO(mdeand): Add support for multiple displays and primary display.
-        None
+        // Wayland has no primary monitor, so the first one stands in for it.
+        with_active_context(|event_loop, _| {
+            event_loop
+                .primary_monitor()
+                .or_else(|| event_loop.available_monitors().next())
+                .map(|monitor| {
+                    Rc::new(CrossDisplay::new(&monitor)) as Rc<dyn crate::PlatformDisplay>
+                })
+        })
+        .flatten()
     }
 
     fn active_window(&self) -> Option<crate::AnyWindowHandle> {
-        // TODO(mdeand): Add support for tracking active window.
-        None
+        self.window_registry.borrow().active_handle()
+    }
+
+    fn window_stack(&self) -> Option<Vec<AnyWindowHandle>> {
+        if self.event_loop_proxy.is_none() {
+            return None;
+        }
+        Some(self.window_registry.borrow().handle_stack())
     }
 
     fn open_window(
         &self,
-        _handle: crate::AnyWindowHandle,
+        handle: crate::AnyWindowHandle,
         options: crate::WindowParams,
     ) -> anyhow::Result<Box<dyn crate::PlatformWindow>> {
-        let window = CrossWindow::new(self.wgpu_context.clone(), self.event_loop_proxy.clone());
+        let wgpu_context = self
+            .wgpu_context
+            .borrow()
+            .clone()
+            .context("the GPU device isn't ready yet")?;
+        let Some(event_loop_proxy) = self.event_loop_proxy.clone() else {
+            let window = HeadlessWindow::new(wgpu_context, handle, options.bounds);
+            self.headless_windows.borrow_mut().push(window.downgrade());
+            return Ok(Box::new(window));
+        };
+
+        let window = CrossWindow::new(wgpu_context, event_loop_proxy);
 
-        let success = with_active_context(|event_loop, app_state| {
+        let opened = with_active_context(|event_loop, app_state| {
             let bounds = options.bounds;
             let attributes = winit::window::Window::default_attributes()
                 .with_title(
@@ -189,22 +679,73 @@ impl Platform for CrossPlatform {
                 .with_inner_size(winit::dpi::LogicalSize::new(
                     bounds.size.width.0 as f64,
                     bounds.size.height.0 as f64,
-                ));
+                ))
+                .with_position(window_position(event_loop, bounds, options.display_id))
+                .with_resizable(options.is_resizable)
+                .with_enabled_buttons(window_buttons(&options))
+                .with_active(options.focus)
+                // Windows are shown once their renderer exists, so they never appear without
+                // content.
+                .with_visible(false);
+            let attributes = match options.window_min_size {
+                Some(min_size) => attributes.with_min_inner_size(winit::dpi::LogicalSize::new(
+                    min_size.width.0 as f64,
+                    min_size.height.0 as f64,
+                )),
+                None => attributes,
+            };
+            let attributes = match options.kind {
+                WindowKind::Normal => attributes,
+                WindowKind::PopUp => attributes
+                    .with_window_level(winit::window::WindowLevel::AlwaysOnTop)
+                    .with_decorations(false),
+                WindowKind::Floating => {
+                    attributes.with_window_level(winit::window::WindowLevel::AlwaysOnTop)
+                }
+            };
+
+            // The process that launched us passes an activation token through the environment. It's
+            // only valid once, so it's given to the first window and removed so child processes
+            // don't inherit it.
+            #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+            let attributes = {
+                use winit::platform::startup_notify::{
+                    EventLoopExtStartupNotify as _, WindowAttributesExtStartupNotify as _,
+                };
+
+                match event_loop.read_token_from_env() {
+                    Some(token) => {
+                        winit::platform::startup_notify::reset_activation_token_env();
+                        attributes.with_activation_token(token)
+                    }
+                    None => attributes,
+                }
+            };
 
             let winit_window = event_loop
                 .create_window(attributes)
                 .expect("Failed to create window");
             let window_id = winit_window.id();
 
-            window.initialize(winit_window);
+            window.initialize(winit_window, options.render_thread, &options.surface_format)?;
             app_state.windows.insert(window_id, window.clone());
+            app_state
+                .window_registry
+                .borrow_mut()
+                .insert(handle, window_id);
+            if options.show_after_first_frame {
+                window.0.state.show_after_first_frame.set(options.show);
+            } else if options.show {
+                window.window().set_visible(true);
+            }
             window.window().request_redraw();
-        })
-        .is_some();
+            anyhow::Ok(())
+        });
 
-        if !success {
+        let Some(opened) = opened else {
             anyhow::bail!("open_window called outside of main thread event loop");
-        }
+        };
+        opened?;
 
         Ok(Box::new(window))
     }
@@ -213,6 +754,50 @@ impl Platform for CrossPlatform {
         crate::WindowAppearance::default()
     }
 
+    fn system_appearance(&self) -> SystemAppearance {
+        *self.system_appearance.lock()
+    }
+
+    fn on_system_appearance_changed(&self, callback: Box<dyn FnMut()>) {
+        self.callbacks
+            .on_system_appearance_changed
+            .set(Some(callback));
+    }
+
+    fn on_displays_changed(&self, callback: Box<dyn FnMut()>) {
+        self.callbacks.on_displays_changed.set(Some(callback));
+    }
+
+    fn power_state(&self) -> PowerState {
+        self.renderer_settings.power_state()
+    }
+
+    fn on_power_state_changed(&self, callback: Box<dyn FnMut()>) {
+        self.callbacks.on_power_state_changed.set(Some(callback));
+    }
+
+    fn text_scale_factor(&self) -> f32 {
+        *self.text_scale_factor.lock()
+    }
+
+    fn on_text_scale_factor_changed(&self, callback: Box<dyn FnMut()>) {
+        self.callbacks
+            .on_text_scale_factor_changed
+            .set(Some(callback));
+    }
+
+    fn cursor_blink_visible(&self) -> bool {
+        self.cursor_blink.borrow().is_visible()
+    }
+
+    fn on_cursor_blink(&self, callback: Box<dyn FnMut()>) {
+        self.callbacks.on_cursor_blink.set(Some(callback));
+    }
+
+    fn reset_cursor_blink(&self) {
+        self.cursor_blink.borrow_mut().reset();
+    }
+
     fn open_url(&self, _url: &str) {
         log::warn!("open_url is not yet implemented on this platform");
     }
@@ -266,6 +851,35 @@ impl Platform for CrossPlatform {
         self.callbacks.on_reopen.set(Some(callback));
     }
 
+    fn claim_single_instance(&self) -> bool {
+        let Some(event_loop_proxy) = &self.event_loop_proxy else {
+            return true;
+        };
+        match single_instance::claim(event_loop_proxy.clone()) {
+            Claim::Claimed(single_instance) => {
+                self.single_instance.set(single_instance);
+                true
+            }
+            Claim::AlreadyRunning => false,
+        }
+    }
+
+    fn handle_termination_signals(&self) {
+        let Some(event_loop_proxy) = self.event_loop_proxy.clone() else {
+            return;
+        };
+
+        // Termination signals are turned into an orderly quit on the main thread, so
+        // applications can flush unsaved state when the session manager stops them.
+        if let Err(error) = ctrlc::set_handler(move || {
+            if event_loop_proxy.send_event(CrossEvent::Terminate).is_err() {
+                log::warn!("received a termination signal after the event loop exited");
+            }
+        }) {
+            log::warn!("failed to install the termination signal handler: {error}");
+        }
+    }
+
     fn set_menus(&self, _menus: Vec<crate::Menu>, _keymap: &crate::Keymap) {}
 
     fn set_dock_menu(&self, _menu: Vec<crate::MenuItem>, _keymap: &crate::Keymap) {}
@@ -288,10 +902,40 @@ impl Platform for CrossPlatform {
         Ok(std::env::current_exe()?)
     }
 
-    fn path_for_auxiliary_executable(&self, _name: &str) -> anyhow::Result<std::path::PathBuf> {
-        Err(anyhow::anyhow!(
-            "path_for_auxiliary_executable is not yet implemented on this platform"
-        ))
+    fn path_for_auxiliary_executable(&self, name: &str) -> anyhow::Result<PathBuf> {
+        let executable = std::env::current_exe()?;
+        let executable_dir = executable
+            .parent()
+            .context("executable has no parent directory")?;
+        let file_name = if Path::new(name).extension().is_some() {
+            name.to_string()
+        } else {
+            format!("{name}{}", std::env::consts::EXE_SUFFIX)
+        };
+
+        let libexec_dir = self
+            .auxiliary_executable_dir
+            .borrow()
+            .clone()
+            .unwrap_or_else(|| executable_dir.join("../libexec"));
+        // The last two cover a macOS bundle, where the executable lives in Contents/MacOS and
+        // helpers are usually placed in Contents/Helpers.
+        let candidates = [
+            executable_dir.to_path_buf(),
+            libexec_dir,
+            executable_dir.join("../Helpers"),
+            executable_dir.join("../Resources"),
+        ];
+
+        candidates
+            .iter()
+            .map(|dir| dir.join(&file_name))
+            .find(|path| path.is_file())
+            .with_context(|| format!("auxiliary executable {file_name:?} not found"))
+    }
+
+    fn set_auxiliary_executable_dir(&self, dir: PathBuf) {
+        self.auxiliary_executable_dir.replace(Some(dir));
     }
 
     fn set_cursor_style(&self, _style: crate::CursorStyle) {}
@@ -302,21 +946,43 @@ impl Platform for CrossPlatform {
     }
 
     #[cfg(any(target_os = "linux", target_os = "freebsd"))]
-    fn write_to_primary(&self, _item: crate::ClipboardItem) {
-        log::warn!("write_to_primary is not yet implemented on this platform");
+    fn write_to_primary(&self, item: crate::ClipboardItem) {
+        self.clipboard.write(ClipboardKind::Primary, item);
     }
 
-    fn write_to_clipboard(&self, _item: crate::ClipboardItem) {
-        log::warn!("write_to_clipboard is not yet implemented on this platform");
+    fn write_to_clipboard(&self, item: crate::ClipboardItem) {
+        self.clipboard.write(ClipboardKind::Clipboard, item);
     }
 
     #[cfg(any(target_os = "linux", target_os = "freebsd"))]
     fn read_from_primary(&self) -> Option<crate::ClipboardItem> {
-        None
+        // The primary selection is text selected in another application, so there's no point asking
+        // it for an image.
+        self.clipboard.read(
+            ClipboardKind::Primary,
+            &[ClipboardFormat::Text, ClipboardFormat::Html],
+        )
     }
 
     fn read_from_clipboard(&self) -> Option<crate::ClipboardItem> {
-        None
+        // Images are only read when there's no text, so copying text from an application that also
+        // offers a rendered image of it stays cheap.
+        self.clipboard
+            .read(
+                ClipboardKind::Clipboard,
+                &[ClipboardFormat::Text, ClipboardFormat::Html],
+            )
+            .or_else(|| {
+                self.clipboard
+                    .read(ClipboardKind::Clipboard, &[ClipboardFormat::Image])
+            })
+    }
+
+    fn read_from_clipboard_formats(
+        &self,
+        formats: &[ClipboardFormat],
+    ) -> Option<crate::ClipboardItem> {
+        self.clipboard.read(ClipboardKind::Clipboard, formats)
     }
 
     fn write_credentials(
@@ -356,6 +1022,59 @@ impl Platform for CrossPlatform {
     fn on_keyboard_layout_change(&self, _callback: Box<dyn FnMut()>) {
         // TODO(mdeand): Is this possible to implement in a cross-platform way?
     }
+
+    fn on_gpu_error(&self, callback: Box<dyn FnMut(crate::GpuError)>) {
+        self.callbacks.on_gpu_error.set(Some(callback));
+    }
+
+    fn on_raw_winit_event(&self, callback: RawWinitEventCallback) {
+        self.callbacks.on_raw_winit_event.set(Some(callback));
+    }
+
+    fn set_gpu_memory_budget(&self, budget: Option<u64>) {
+        self.gpu_memory.set_budget(budget);
+    }
+
+    fn gpu_memory_usage(&self) -> Option<crate::GpuMemoryUsage> {
+        Some(self.gpu_memory.usage())
+    }
+
+    fn renderer_settings(&self) -> crate::RendererSettings {
+        self.renderer_settings.get()
+    }
+
+    fn set_renderer_settings(&self, settings: crate::RendererSettings) {
+        self.renderer_settings.set(settings);
+    }
+
+    fn gpu_handle(&self) -> Option<crate::GpuHandle> {
+        let context = self.wgpu_context.borrow();
+        context.as_ref().map(|context| context.gpu_handle.clone())
+    }
+
+    fn synchronize_presents(&self, windows: &[AnyWindowHandle]) {
+        let present_group = (windows.len() > 1).then(|| {
+            let present_group = self.next_present_group.get();
+            self.next_present_group.set(present_group + 1);
+            present_group
+        });
+        for handle in windows {
+            if let Some(window) = self.window(*handle) {
+                window.0.state.present_group.set(present_group);
+                if present_group.is_none() {
+                    window.present_prepared_frame();
+                }
+            }
+        }
+    }
+
+    fn register_render_pipeline_plugin(
+        &self,
+        plugin_type: std::any::TypeId,
+        plugin: Arc<dyn crate::RenderPipelinePlugin>,
+    ) {
+        self.render_plugins.register(plugin_type, plugin);
+    }
 }
 
 impl AppState {
@@ -368,16 +1087,257 @@ impl AppState {
     }
 
     fn drain_main_queue(&mut self) {
-        while let Ok(Some(runnable)) = self.main_rx.try_pop() {
-            match runnable {
-                RunnableVariant::Compat(runnable) => {
-                    runnable.run();
-                }
-                RunnableVariant::Meta(runnable) => {
-                    runnable.run();
+        drain_main_queue(&mut self.main_rx, &self.dispatcher);
+    }
+
+    /// Offers `event` to the app's raw winit event handler before it's translated, returning
+    /// true if the handler consumed it.
+    fn raw_winit_event_consumed(
+        &self,
+        window_id: winit::window::WindowId,
+        event: &winit::event::WindowEvent,
+    ) -> bool {
+        let Some(handle) = self.window_registry.borrow().handle(window_id) else {
+            return false;
+        };
+        let Some(mut callback) = self.on_raw_winit_event.take() else {
+            return false;
+        };
+        let consumed = callback(handle, event);
+        self.on_raw_winit_event.set(Some(callback));
+        consumed
+    }
+
+    /// Finishes launching the app once the event loop has resumed and the GPU device is ready,
+    /// whichever happens last.
+    fn finish_launching(&mut self, event_loop: &ActiveEventLoop) {
+        if !self.resumed || self.wgpu_context.borrow().is_none() {
+            return;
+        }
+        if let Some(on_finish_launching) = self.on_finish_launching.take() {
+            self.displays = available_displays(event_loop);
+            on_finish_launching();
+        }
+    }
+
+    /// Takes the GPU context requested when the platform was created and finishes launching the
+    /// app with it. Without a device there's nothing to draw windows with, so the app exits.
+    fn handle_gpu_context_created(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        context: anyhow::Result<Arc<WgpuContext>>,
+    ) {
+        let context = match context {
+            Ok(context) => context,
+            Err(error) => {
+                log::error!("failed to create the GPU context: {error:#}");
+                event_loop.exit();
+                return;
+            }
+        };
+        watch_device(&context, Some(self.event_loop_proxy.clone()));
+        self.wgpu_context.replace(Some(context));
+        self.finish_launching(event_loop);
+    }
+
+    /// Recreates the GPU context after its device was lost, such as when an external GPU was
+    /// unplugged or the driver was updated, and moves every window onto the new one.
+    fn recover_from_device_loss(&mut self, message: &str) {
+        log::warn!("the GPU device was lost, recreating the renderer: {message}");
+        let Some(context) = self.wgpu_context.borrow().clone() else {
+            return;
+        };
+        let context = match context.recreate() {
+            Ok(context) => context,
+            Err(error) => {
+                log::error!("failed to recreate the GPU context: {error:#}");
+                return;
+            }
+        };
+        watch_device(&context, Some(self.event_loop_proxy.clone()));
+        let context = Arc::new(context);
+        self.wgpu_context.replace(Some(context.clone()));
+
+        for window in self.windows.values() {
+            if let Err(error) = window.recover_from_device_loss(context.clone()) {
+                log::error!("failed to recreate a window's renderer: {error:#}");
+            }
+        }
+    }
+
+    /// Compares the displays with the last ones seen, moving windows left without a display onto
+    /// the primary one and telling the app if anything changed.
+    fn reconcile_displays(&mut self, event_loop: &ActiveEventLoop) {
+        let displays = available_displays(event_loop);
+        if displays == self.displays {
+            return;
+        }
+        self.displays = displays;
+
+        let target_monitor = event_loop
+            .primary_monitor()
+            .or_else(|| event_loop.available_monitors().next());
+        if let Some(target_monitor) = target_monitor {
+            for window in self.windows.values() {
+                let bounds = window.bounds();
+                let on_screen = self
+                    .displays
+                    .iter()
+                    .any(|display| display.bounds().intersects(&bounds));
+                if !on_screen {
+                    center_on_monitor(window.window(), &target_monitor);
                 }
             }
         }
+
+        if let Some(mut callback) = self.on_displays_changed.take() {
+            callback();
+            self.on_displays_changed.set(Some(callback));
+        }
+    }
+
+    /// Hides every shown window, remembering them so [`AppState::unhide_windows`] can show them
+    /// again. Windows that are already minimized stay that way.
+    #[cfg_attr(target_os = "macos", allow(dead_code))]
+    fn hide_windows(&mut self) {
+        for (window_id, window) in &self.windows {
+            let winit_window = window.window();
+            if winit_window.is_minimized() == Some(true) || winit_window.is_visible() == Some(false)
+            {
+                continue;
+            }
+            // Wayland can't hide windows, only minimize them.
+            match winit_window.is_visible() {
+                Some(_) => winit_window.set_visible(false),
+                None => winit_window.set_minimized(true),
+            }
+            self.hidden_windows.push(*window_id);
+        }
+    }
+
+    fn unhide_windows(&mut self) {
+        for window_id in self.hidden_windows.drain(..) {
+            if let Some(window) = self.windows.get(&window_id) {
+                window.window().set_visible(true);
+                window.window().set_minimized(false);
+            }
+        }
+    }
+}
+
+/// Where to place a window with `bounds`, on the display `display_id` if one was requested or
+/// otherwise on the display containing its origin. The position is converted with that display's
+/// scale factor, since displays with different scale factors don't share logical coordinates.
+fn window_position(
+    event_loop: &ActiveEventLoop,
+    bounds: Bounds<Pixels>,
+    display_id: Option<DisplayId>,
+) -> winit::dpi::Position {
+    let monitor = event_loop
+        .available_monitors()
+        .find(|monitor| match display_id {
+            Some(display_id) => monitor_display_id(monitor) == display_id,
+            None => monitor_bounds(monitor).contains(&bounds.origin),
+        });
+    let Some(monitor) = monitor else {
+        return winit::dpi::LogicalPosition::new(
+            bounds.origin.x.0 as f64,
+            bounds.origin.y.0 as f64,
+        )
+        .into();
+    };
+
+    let display_origin = monitor_bounds(&monitor).origin;
+    let monitor_position = monitor.position();
+    let scale_factor = monitor.scale_factor();
+    winit::dpi::PhysicalPosition::new(
+        monitor_position.x + ((bounds.origin.x - display_origin.x).0 as f64 * scale_factor) as i32,
+        monitor_position.y + ((bounds.origin.y - display_origin.y).0 as f64 * scale_factor) as i32,
+    )
+    .into()
+}
+
+fn available_displays(event_loop: &ActiveEventLoop) -> Vec<CrossDisplay> {
+    event_loop
+        .available_monitors()
+        .map(|monitor| CrossDisplay::new(&monitor))
+        .collect()
+}
+
+fn center_on_monitor(window: &winit::window::Window, monitor: &winit::monitor::MonitorHandle) {
+    let monitor_position = monitor.position();
+    let monitor_size = monitor.size();
+    let window_size = window.outer_size();
+    window.set_outer_position(winit::dpi::PhysicalPosition::new(
+        monitor_position.x + (monitor_size.width.saturating_sub(window_size.width) / 2) as i32,
+        monitor_position.y + (monitor_size.height.saturating_sub(window_size.height) / 2) as i32,
+    ));
+}
+
+/// The title bar buttons of a window opened with `options`.
+fn window_buttons(options: &crate::WindowParams) -> winit::window::WindowButtons {
+    let mut buttons = winit::window::WindowButtons::all();
+    if !options.is_minimizable {
+        buttons.remove(winit::window::WindowButtons::MINIMIZE);
+    }
+    if !options.is_resizable {
+        buttons.remove(winit::window::WindowButtons::MAXIMIZE);
+    }
+    buttons
+}
+
+/// Runs every task queued for the main thread, returning whether there were any.
+fn drain_main_queue(
+    main_rx: &mut PriorityQueueReceiver<MainThreadTask>,
+    dispatcher: &Dispatcher,
+) -> bool {
+    let mut ran_any = false;
+    dispatcher.set_draining(true);
+    while let Ok(Some(task)) = main_rx.try_pop() {
+        ran_any = true;
+        task.run(dispatcher.dispatch_latency());
+    }
+    dispatcher.set_draining(false);
+    ran_any
+}
+
+/// Routes the errors of `context`'s device to the event loop, and tells it when the device is
+/// lost.
+fn watch_device(
+    context: &WgpuContext,
+    event_loop_proxy: Option<winit::event_loop::EventLoopProxy<CrossEvent>>,
+) {
+    // wgpu may report errors from any thread, so they're sent to the event loop and handed to the
+    // platform callback on the main thread.
+    let error_proxy = event_loop_proxy.clone();
+    context.on_uncaptured_error(move |error| match &error_proxy {
+        Some(error_proxy) => {
+            if let Err(winit::event_loop::EventLoopClosed(CrossEvent::GpuError(error))) =
+                error_proxy.send_event(CrossEvent::GpuError(error))
+            {
+                log::error!("{error}");
+            }
+        }
+        None => log::error!("{error}"),
+    });
+
+    if let Some(event_loop_proxy) = event_loop_proxy {
+        context.on_device_lost(move |message| {
+            if event_loop_proxy
+                .send_event(CrossEvent::DeviceLost(message))
+                .is_err()
+            {
+                log::error!("the GPU device was lost after the event loop exited");
+            }
+        });
+    }
+}
+
+/// Drops every task queued for the main thread without running it. Must be called on the main
+/// thread, where the tasks' destructors are allowed to run.
+fn discard_main_queue(main_rx: &mut PriorityQueueReceiver<MainThreadTask>) {
+    while let Ok(Some(task)) = main_rx.try_pop() {
+        drop(task);
     }
 }
 
@@ -393,9 +1353,71 @@ impl winit::application::ApplicationHandler<CrossEvent> for AppState {
             }
             CrossEvent::SurfacePresent(window_id) => {
                 if let Some(window) = self.windows.get(&window_id) {
+                    if !window.recomposite_low_latency_surfaces() {
+                        window.window().request_redraw();
+                    }
+                }
+            }
+            CrossEvent::GpuContextCreated(context) => {
+                self.handle_gpu_context_created(event_loop, context)
+            }
+            CrossEvent::DeviceLost(message) => self.recover_from_device_loss(&message),
+            CrossEvent::GpuError(error) => match self.on_gpu_error.take() {
+                Some(mut callback) => {
+                    callback(error);
+                    self.on_gpu_error.set(Some(callback));
+                }
+                None => log::error!("{error}"),
+            },
+            CrossEvent::SystemAppearanceChanged => {
+                if let Some(mut callback) = self.on_system_appearance_changed.take() {
+                    callback();
+                    self.on_system_appearance_changed.set(Some(callback));
+                }
+                for window in self.windows.values() {
+                    window
+                        .0
+                        .state
+                        .callbacks
+                        .invoke_mut(&window.0.state.callbacks.on_appearance_changed, |cb| cb());
+                }
+            }
+            CrossEvent::ReconcileDisplays => self.reconcile_displays(event_loop),
+            CrossEvent::PowerStateChanged => {
+                if let Some(mut callback) = self.on_power_state_changed.take() {
+                    callback();
+                    self.on_power_state_changed.set(Some(callback));
+                }
+            }
+            CrossEvent::TextScaleFactorChanged => {
+                if let Some(mut callback) = self.on_text_scale_factor_changed.take() {
+                    callback();
+                    self.on_text_scale_factor_changed.set(Some(callback));
+                }
+            }
+            CrossEvent::Reopen => {
+                if let Some(mut callback) = self.on_reopen.take() {
+                    callback();
+                    self.on_reopen.set(Some(callback));
+                }
+            }
+            CrossEvent::RendererSettingsChanged => {
+                for window in self.windows.values() {
+                    let settings = window.0.wgpu_context.borrow().renderer_settings.get();
+                    window.with_renderer(|renderer| renderer.apply_settings(&settings));
+                    window.0.state.force_render.set(true);
                     window.window().request_redraw();
                 }
             }
+            CrossEvent::Terminate => {
+                self.window_registry.borrow_mut().clear();
+                for (_, window) in self.windows.drain() {
+                    if let Some(callback) = window.0.state.callbacks.on_close.take() {
+                        callback();
+                    }
+                }
+                event_loop.exit();
+            }
         }
 
         self.clear_active_context();
@@ -405,33 +1427,98 @@ impl winit::application::ApplicationHandler<CrossEvent> for AppState {
         &mut self,
         _event_loop: &ActiveEventLoop,
         _device_id: winit::event::DeviceId,
-        _event: winit::event::DeviceEvent,
+        event: winit::event::DeviceEvent,
     ) {
+        // A window gets no cursor events while the cursor is over one of its passthrough regions,
+        // so raw motion is used to notice the cursor leaving it. Raw motion isn't accelerated, so
+        // this is only an estimate until the next real cursor event.
+        if let winit::event::DeviceEvent::MouseMotion { delta: (x, y) } = event {
+            for window in self.windows.values() {
+                if window.is_passing_through_region() {
+                    let scale_factor = window.scale_factor();
+                    let position = window.0.state.mouse_position.get()
+                        + point(
+                            Pixels(x as f32 / scale_factor),
+                            Pixels(y as f32 / scale_factor),
+                        );
+                    window.0.state.mouse_position.set(position);
+                    window.update_cursor_hittest(position);
+                }
+            }
+        }
     }
 
     fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
         self.set_active_context(event_loop);
 
         self.drain_main_queue();
+        if std::mem::take(&mut self.displays_may_have_changed) {
+            self.reconcile_displays(event_loop);
+        }
+        if self.cursor_blink.borrow_mut().update(Instant::now()) {
+            if let Some(mut callback) = self.on_cursor_blink.take() {
+                callback();
+                self.on_cursor_blink.set(Some(callback));
+            }
+        }
+
+        // Every window that needed a redraw has drawn by now, so the frames of synchronized windows
+        // are presented back to back, a group at a time.
+        let mut synchronized_windows = self
+            .windows
+            .values()
+            .filter_map(|window| Some((window.0.state.present_group.get()?, window)))
+            .collect::<Vec<_>>();
+        synchronized_windows.sort_by_key(|(present_group, _)| *present_group);
+        for (_, window) in synchronized_windows {
+            window.present_prepared_frame();
+        }
 
         for window in self.windows.values() {
-            window.window().request_redraw();
+            // Some platforms don't send redraws to hidden windows, so windows waiting for their
+            // first frame draw it directly.
+            if window.0.state.show_after_first_frame.get() {
+                window.request_frame();
+            } else {
+                window.window().request_redraw();
+            }
         }
 
         self.clear_active_context();
     }
 
-    fn suspended(&mut self, _event_loop: &ActiveEventLoop) {}
+    fn suspended(&mut self, event_loop: &ActiveEventLoop) {
+        self.set_active_context(event_loop);
 
-    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {}
+        for window in self.windows.values() {
+            window.suspend();
+        }
+
+        self.clear_active_context();
+    }
+
+    fn exiting(&mut self, event_loop: &ActiveEventLoop) {
+        self.set_active_context(event_loop);
+
+        if let Some(mut callback) = self.on_quit.take() {
+            callback();
+        }
+        self.dispatcher.close();
+        discard_main_queue(&mut self.main_rx);
+        drop(self.single_instance.take());
+
+        self.clear_active_context();
+    }
 
     fn memory_warning(&mut self, _event_loop: &ActiveEventLoop) {}
 
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         self.set_active_context(event_loop);
 
-        if let Some(on_finish_launching) = self.on_finish_launching.take() {
-            on_finish_launching();
+        self.resumed = true;
+        self.finish_launching(event_loop);
+        for window in self.windows.values() {
+            window.resume();
         }
 
         self.clear_active_context();
@@ -448,6 +1535,10 @@ impl winit::application::ApplicationHandler<CrossEvent> for AppState {
         let Some(window) = self.windows.get(&window_id) else {
             return;
         };
+        if self.raw_winit_event_consumed(window_id, &event) {
+            self.clear_active_context();
+            return;
+        }
 
         match event {
             winit::event::WindowEvent::Resized(physical_size) => {
@@ -457,12 +1548,33 @@ impl winit::application::ApplicationHandler<CrossEvent> for AppState {
 
                 let scale_factor = window.scale_factor();
 
-                if let Some(renderer) = window.0.renderer.get() {
-                    renderer.borrow_mut().update_drawable_size(Size {
-                        width: DevicePixels(physical_size.width as i32),
-                        height: DevicePixels(physical_size.height as i32),
+                window.resize_renderer(Size {
+                    width: DevicePixels(physical_size.width as i32),
+                    height: DevicePixels(physical_size.height as i32),
+                });
+                let size = crate::Size {
+                    width: crate::Pixels(physical_size.width as f32 / scale_factor),
+                    height: crate::Pixels(physical_size.height as f32 / scale_factor),
+                };
+
+                window
+                    .0
+                    .state
+                    .callbacks
+                    .invoke_mut(&window.0.state.callbacks.on_resize, |cb| {
+                        cb(size, scale_factor);
                     });
-                }
+            }
+
+            winit::event::WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                // The scale factor changes when the window's display is reconfigured or
+                // disconnected.
+                self.displays_may_have_changed = true;
+                // Glyphs are rasterized for a particular scale factor, so the window is told about
+                // the new one even when its physical size is unchanged and no resize follows.
+                // Redrawing then rasterizes them afresh.
+                let scale_factor = scale_factor as f32;
+                let physical_size = window.window().inner_size();
                 let size = crate::Size {
                     width: crate::Pixels(physical_size.width as f32 / scale_factor),
                     height: crate::Pixels(physical_size.height as f32 / scale_factor),
@@ -478,6 +1590,7 @@ impl winit::application::ApplicationHandler<CrossEvent> for AppState {
             }
 
             winit::event::WindowEvent::Moved(_) => {
+                window.update_refresh_interval();
                 window
                     .0
                     .state
@@ -488,6 +1601,10 @@ impl winit::application::ApplicationHandler<CrossEvent> for AppState {
             }
 
             winit::event::WindowEvent::Focused(active) => {
+                self.window_registry
+                    .borrow_mut()
+                    .set_active(window_id, active);
+                self.cursor_blink.borrow_mut().reset();
                 window
                     .0
                     .state
@@ -523,6 +1640,7 @@ impl winit::application::ApplicationHandler<CrossEvent> for AppState {
                     if let Some(cb) = window.0.state.callbacks.on_close.take() {
                         cb();
                     }
+                    self.window_registry.borrow_mut().remove(window_id);
                     self.windows.remove(&window_id);
                 }
             }
@@ -530,18 +1648,11 @@ impl winit::application::ApplicationHandler<CrossEvent> for AppState {
             winit::event::WindowEvent::RedrawRequested => {
                 let physical_size = window.window().inner_size();
                 if physical_size.width == 0 || physical_size.height == 0 {
+                    window.0.state.pending_frame_request.set(true);
                     return;
                 }
 
-                window.0.state.callbacks.invoke_mut(
-                    &window.0.state.callbacks.on_request_frame,
-                    |cb| {
-                        cb(crate::RequestFrameOptions {
-                            force_render: false,
-                            require_presentation: true,
-                        });
-                    },
-                );
+                window.request_frame();
             }
 
             winit::event::WindowEvent::KeyboardInput {
@@ -556,6 +1667,20 @@ impl winit::application::ApplicationHandler<CrossEvent> for AppState {
                 ..
             } => {
                 let modifiers = self.current_modifiers;
+                if state == winit::event::ElementState::Pressed {
+                    self.cursor_blink.borrow_mut().reset();
+                }
+
+                // Platforms only open the window menu with alt-space for windows they decorate, so
+                // it's opened for undecorated ones here.
+                if state == winit::event::ElementState::Pressed
+                    && logical_key == winit::keyboard::Key::Named(winit::keyboard::NamedKey::Space)
+                    && modifiers == Modifiers::alt()
+                    && !window.window().is_decorated()
+                {
+                    window.show_window_menu(point(Pixels(0.0), Pixels(0.0)));
+                    return;
+                }
 
                 if let Some(keystroke) = winit_key_to_keystroke(&logical_key, modifiers, &text) {
                     let platform_event = match state {
@@ -601,18 +1726,36 @@ impl winit::application::ApplicationHandler<CrossEvent> for AppState {
                     });
             }
 
-            winit::event::WindowEvent::CursorMoved { position, .. } => {
+            winit::event::WindowEvent::CursorMoved {
+                device_id,
+                position,
+            } => {
                 let scale_factor = window.scale_factor();
-                let position = point(
+                let window_position = point(
                     Pixels(position.x as f32 / scale_factor),
                     Pixels(position.y as f32 / scale_factor),
                 );
+                // Resize edges belong to the window itself, everything else to the possibly
+                // magnified frame shown under the cursor.
+                let position = window.unzoomed_position(window_position);
+                let position = match window.0.state.software_cursor_offset.get() {
+                    Some(offset) => position + offset,
+                    None => position,
+                };
 
                 window.0.state.mouse_position.set(position);
+                window.move_software_cursor();
+                window.update_cursor_hittest(position);
+                window.update_resize_cursor(window_position);
+                let pointer = self
+                    .pointers
+                    .entry(device_id)
+                    .or_insert_with(|| PointerState::new(position));
+                pointer.position = position;
 
                 let platform_event = PlatformInput::MouseMove(MouseMoveEvent {
                     position,
-                    pressed_button: self.pressed_button,
+                    pressed_button: pointer.pressed_button,
                     modifiers: self.current_modifiers,
                 });
 
@@ -625,11 +1768,16 @@ impl winit::application::ApplicationHandler<CrossEvent> for AppState {
                     });
             }
 
-            winit::event::WindowEvent::CursorLeft { .. } => {
-                let position = window.0.state.mouse_position.get();
+            winit::event::WindowEvent::CursorLeft { device_id } => {
+                window.set_software_cursor_offset(None);
+                let window_position = window.0.state.mouse_position.get();
+                let pointer = self
+                    .pointers
+                    .entry(device_id)
+                    .or_insert_with(|| PointerState::new(window_position));
                 let platform_event = PlatformInput::MouseExited(MouseExitEvent {
-                    position,
-                    pressed_button: self.pressed_button,
+                    position: pointer.position,
+                    pressed_button: pointer.pressed_button,
                     modifiers: self.current_modifiers,
                 });
 
@@ -642,17 +1790,36 @@ impl winit::application::ApplicationHandler<CrossEvent> for AppState {
                     });
             }
 
-            winit::event::WindowEvent::MouseInput { state, button, .. } => {
-                let position = window.0.state.mouse_position.get();
+            winit::event::WindowEvent::MouseInput {
+                device_id,
+                state,
+                button,
+            } => {
+                let window_position = window.0.state.mouse_position.get();
+                let pointer = self
+                    .pointers
+                    .entry(device_id)
+                    .or_insert_with(|| PointerState::new(window_position));
+                let position = pointer.position;
                 let mouse_button = winit_mouse_button_to_gpui(button);
                 let modifiers = self.current_modifiers;
+                // The resize border of an undecorated window belongs to the window manager, so a
+                // left press there never reaches the app.
+                let resize_edge = match button {
+                    winit::event::MouseButton::Left => window.resize_edge(position),
+                    _ => None,
+                };
 
-                match state {
-                    winit::event::ElementState::Pressed => {
-                        self.pressed_button = Some(mouse_button);
+                match (state, resize_edge) {
+                    (winit::event::ElementState::Pressed, Some(edge)) => {
+                        window.start_window_resize(edge);
+                    }
+                    (winit::event::ElementState::Pressed, None) => {
+                        pointer.pressed_button = Some(mouse_button);
 
                         let click_count =
-                            self.click_state
+                            pointer
+                                .click_state
                                 .update(mouse_button, position, Instant::now());
 
                         let platform_event = PlatformInput::MouseDown(MouseDownEvent {
@@ -663,21 +1830,49 @@ impl winit::application::ApplicationHandler<CrossEvent> for AppState {
                             first_mouse: false,
                         });
 
+                        let mut default_prevented = false;
                         window.0.state.callbacks.invoke_mut(
                             &window.0.state.callbacks.on_input,
                             |cb| {
-                                cb(platform_event.clone());
+                                default_prevented = cb(platform_event.clone()).default_prevented;
                             },
                         );
+
+                        if cfg!(any(target_os = "linux", target_os = "freebsd"))
+                            && mouse_button == MouseButton::Middle
+                            && !default_prevented
+                        {
+                            let paste_event =
+                                PlatformInput::PrimaryPaste(crate::PrimaryPasteEvent {
+                                    position,
+                                    modifiers,
+                                });
+                            window.0.state.callbacks.invoke_mut(
+                                &window.0.state.callbacks.on_input,
+                                |cb| {
+                                    cb(paste_event);
+                                },
+                            );
+                        }
+
+                        // Right clicking the titlebar of an undecorated window opens the window
+                        // menu, like it does on a decorated one.
+                        if mouse_button == MouseButton::Right
+                            && !default_prevented
+                            && !window.window().is_decorated()
+                            && window.hit_test_window_control() == Some(WindowControlArea::Drag)
+                        {
+                            window.show_window_menu(position);
+                        }
                     }
-                    winit::event::ElementState::Released => {
-                        self.pressed_button = None;
+                    (winit::event::ElementState::Released, _) => {
+                        pointer.pressed_button = None;
 
                         let platform_event = PlatformInput::MouseUp(MouseUpEvent {
                             button: mouse_button,
                             position,
                             modifiers,
-                            click_count: self.click_state.current_count,
+                            click_count: pointer.click_state.current_count,
                         });
 
                         window.0.state.callbacks.invoke_mut(
@@ -690,8 +1885,15 @@ impl winit::application::ApplicationHandler<CrossEvent> for AppState {
                 }
             }
 
-            winit::event::WindowEvent::MouseWheel { delta, phase, .. } => {
-                let position = window.0.state.mouse_position.get();
+            winit::event::WindowEvent::MouseWheel {
+                device_id,
+                delta,
+                phase,
+            } => {
+                let position = self.pointers.get(&device_id).map_or_else(
+                    || window.0.state.mouse_position.get(),
+                    |pointer| pointer.position,
+                );
                 let modifiers = self.current_modifiers;
 
                 let scroll_delta = match delta {
@@ -707,19 +1909,74 @@ impl winit::application::ApplicationHandler<CrossEvent> for AppState {
                     }
                 };
 
-                let touch_phase = match phase {
-                    winit::event::TouchPhase::Started => crate::TouchPhase::Started,
-                    winit::event::TouchPhase::Moved => crate::TouchPhase::Moved,
-                    winit::event::TouchPhase::Ended | winit::event::TouchPhase::Cancelled => {
-                        crate::TouchPhase::Ended
-                    }
-                };
-
                 let platform_event = PlatformInput::ScrollWheel(ScrollWheelEvent {
                     position,
                     delta: scroll_delta,
                     modifiers,
-                    touch_phase,
+                    touch_phase: winit_touch_phase_to_gpui(phase),
+                });
+
+                window
+                    .0
+                    .state
+                    .callbacks
+                    .invoke_mut(&window.0.state.callbacks.on_input, |cb| {
+                        cb(platform_event.clone());
+                    });
+            }
+
+            winit::event::WindowEvent::PinchGesture {
+                device_id,
+                delta,
+                phase,
+            } => {
+                let position = self.pointers.get(&device_id).map_or_else(
+                    || window.0.state.mouse_position.get(),
+                    |pointer| pointer.position,
+                );
+                if phase == winit::event::TouchPhase::Started {
+                    self.gesture.scale = 1.0;
+                }
+                let delta = delta as f32;
+                self.gesture.scale *= 1.0 + delta;
+
+                let platform_event = PlatformInput::Pinch(PinchEvent {
+                    position,
+                    delta,
+                    scale: self.gesture.scale,
+                    modifiers: self.current_modifiers,
+                    touch_phase: winit_touch_phase_to_gpui(phase),
+                });
+
+                window
+                    .0
+                    .state
+                    .callbacks
+                    .invoke_mut(&window.0.state.callbacks.on_input, |cb| {
+                        cb(platform_event.clone());
+                    });
+            }
+
+            winit::event::WindowEvent::RotationGesture {
+                device_id,
+                delta,
+                phase,
+            } => {
+                let position = self.pointers.get(&device_id).map_or_else(
+                    || window.0.state.mouse_position.get(),
+                    |pointer| pointer.position,
+                );
+                if phase == winit::event::TouchPhase::Started {
+                    self.gesture.rotation = 0.0;
+                }
+                self.gesture.rotation += delta;
+
+                let platform_event = PlatformInput::Rotation(RotationEvent {
+                    position,
+                    delta,
+                    rotation: self.gesture.rotation,
+                    modifiers: self.current_modifiers,
+                    touch_phase: winit_touch_phase_to_gpui(phase),
                 });
 
                 window
@@ -782,6 +2039,16 @@ fn winit_modifiers_to_gpui(modifiers: winit::keyboard::ModifiersState) -> Modifi
     }
 }
 
+fn winit_touch_phase_to_gpui(phase: winit::event::TouchPhase) -> crate::TouchPhase {
+    match phase {
+        winit::event::TouchPhase::Started => crate::TouchPhase::Started,
+        winit::event::TouchPhase::Moved => crate::TouchPhase::Moved,
+        winit::event::TouchPhase::Ended | winit::event::TouchPhase::Cancelled => {
+            crate::TouchPhase::Ended
+        }
+    }
+}
+
 fn winit_mouse_button_to_gpui(button: winit::event::MouseButton) -> MouseButton {
     match button {
         winit::event::MouseButton::Left => MouseButton::Left,
@@ -795,7 +2062,7 @@ fn winit_mouse_button_to_gpui(button: winit::event::MouseButton) -> MouseButton
     }
 }
 
-fn winit_key_to_keystroke(
+pub(crate) fn winit_key_to_keystroke(
     logical_key: &winit::keyboard::Key,
     modifiers: Modifiers,
     text: &Option<winit::keyboard::SmolStr>,
@@ -0,0 +1,111 @@
+use std::{sync::Weak, time::Duration};
+
+use winit::event_loop::EventLoopProxy;
+
+use crate::{
+    BackgroundExecutor, PowerState,
+    platform::cross::{dispatcher::CrossEvent, renderer_settings::RendererSettingsRegistry},
+};
+
+/// How often the power state is checked.
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Checks the system's power state periodically, recording it in the renderer settings and
+/// sending [`CrossEvent::PowerStateChanged`] whenever it changes.
+pub(crate) fn watch(
+    executor: &BackgroundExecutor,
+    proxy: EventLoopProxy<CrossEvent>,
+    settings: Weak<RendererSettingsRegistry>,
+) {
+    let timer_executor = executor.clone();
+    executor
+        .spawn(async move {
+            loop {
+                let Some(settings) = settings.upgrade() else {
+                    return;
+                };
+                if settings.set_power_state(read_power_state())
+                    && proxy.send_event(CrossEvent::PowerStateChanged).is_err()
+                {
+                    return;
+                }
+                drop(settings);
+                timer_executor.timer(POLL_INTERVAL).await;
+            }
+        })
+        .detach();
+}
+
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+fn read_power_state() -> PowerState {
+    use crate::platform::cross::gdbus;
+
+    const POWER_PROFILES: [&str; 3] = [
+        "net.hadess.PowerProfiles",
+        "/net/hadess/PowerProfiles",
+        "net.hadess.PowerProfiles",
+    ];
+    let property = |[destination, object_path, interface]: [&str; 3], property: &str| {
+        gdbus::system_property(destination, object_path, interface, property)
+    };
+
+    PowerState {
+        on_battery: on_battery(),
+        power_saver: property(POWER_PROFILES, "ActiveProfile")
+            .is_some_and(|reply| reply.contains("power-saver")),
+        // power-profiles-daemon explains why performance is degraded, and an empty string means it
+        // isn't.
+        thermally_throttled: property(POWER_PROFILES, "PerformanceDegraded")
+            .is_some_and(|reply| reply.contains("high-operating-temperature")),
+    }
+}
+
+/// Whether the system has a battery and no power supply is plugged in, from the power supplies
+/// the kernel reports. Batteries of peripherals such as mice are skipped.
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+fn on_battery() -> bool {
+    let Ok(supplies) = std::fs::read_dir("/sys/class/power_supply") else {
+        return false;
+    };
+
+    let mut has_battery = false;
+    for supply in supplies.flatten() {
+        let path = supply.path();
+        let read = |name: &str| {
+            std::fs::read_to_string(path.join(name))
+                .map(|value| value.trim().to_owned())
+                .unwrap_or_default()
+        };
+        match read("type").as_str() {
+            "Battery" if read("scope") != "Device" => has_battery = true,
+            "Mains" | "USB" if read("online") == "1" => return false,
+            _ => {}
+        }
+    }
+    has_battery
+}
+
+#[cfg(target_os = "windows")]
+fn read_power_state() -> PowerState {
+    use ::windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    const AC_LINE_OFFLINE: u8 = 0;
+    const BATTERY_SAVER_ON: u8 = 1;
+
+    let mut status = SYSTEM_POWER_STATUS::default();
+    // SAFETY: `status` is a valid, writable `SYSTEM_POWER_STATUS`.
+    if let Err(error) = unsafe { GetSystemPowerStatus(&mut status) } {
+        log::debug!("failed to read the system power status: {error}");
+        return PowerState::default();
+    }
+    PowerState {
+        on_battery: status.ACLineStatus == AC_LINE_OFFLINE,
+        power_saver: status.SystemStatusFlag == BATTERY_SAVER_ON,
+        thermally_throttled: false,
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "freebsd", target_os = "windows")))]
+fn read_power_state() -> PowerState {
+    PowerState::default()
+}
@@ -1,6 +1,24 @@
 use std::sync::Arc;
 
-use super::surface_registry::SurfaceRegistry;
+use crate::{BackgroundExecutor, GpuError, GpuErrorKind, GpuHandle, RenderPluginRegistry};
+
+use super::{
+    embedding::EmbeddedDevice,
+    gpu_memory::GpuMemoryTracker,
+    renderer_settings::RendererSettingsRegistry,
+    submission_scheduler::{self, SubmissionScheduler},
+    surface_registry::{SurfaceDevice, SurfaceRegistry},
+};
+
+type GpuErrorHandler = Arc<dyn Fn(GpuError) + Send + Sync>;
+
+// Thread local because passes are encoded on several threads at once, and wgpu reports uncaptured
+// errors on the thread that made the failing call.
+#[cfg(debug_assertions)]
+thread_local! {
+    static ACTIVE_PASS_LABEL: std::cell::Cell<Option<&'static str>> =
+        const { std::cell::Cell::new(None) };
+}
 
 pub struct WgpuContext {
     pub(super) adapter: wgpu::Adapter,
@@ -8,92 +26,124 @@ pub struct WgpuContext {
     pub(super) queue: wgpu::Queue,
     pub(super) instance: wgpu::Instance,
 
-    pub(super) globals_buffer: wgpu::Buffer,
-    pub(super) quads_buffer: wgpu::Buffer,
-    pub(super) shadows_buffer: wgpu::Buffer,
-    pub(super) underlines_buffer: wgpu::Buffer,
-    pub(super) mono_sprites_buffer: wgpu::Buffer,
-    pub(super) poly_sprites_buffer: wgpu::Buffer,
     pub(super) color_adjustments_buffer: wgpu::Buffer,
 
     pub(crate) surface_registry: Arc<SurfaceRegistry>,
+    pub(crate) submission_scheduler: Arc<SubmissionScheduler>,
+    pub(crate) memory: Arc<GpuMemoryTracker>,
+    pub(crate) renderer_settings: Arc<RendererSettingsRegistry>,
+    pub(crate) render_plugins: Arc<RenderPluginRegistry>,
+    // handed to the app, and moved to the device of the context that replaces this one
+    pub(crate) gpu_handle: GpuHandle,
+    // encodes the command buffers of large frames in parallel when set
+    pub(crate) background_executor: Option<BackgroundExecutor>,
+
+    error_handler: parking_lot::Mutex<Option<GpuErrorHandler>>,
 }
 
 impl WgpuContext {
-    pub fn new() -> anyhow::Result<Self> {
+    /// Creates a context on the default adapter. Requesting the adapter and device can take a
+    /// while on slow drivers, so this is best awaited off the main thread.
+    pub async fn new() -> anyhow::Result<Self> {
+        let memory = Arc::new(GpuMemoryTracker::default());
+        Self::with_shared_state(
+            Arc::new(SurfaceRegistry::new(memory.clone())),
+            memory,
+            Arc::new(RendererSettingsRegistry::from_env()),
+            Arc::new(RenderPluginRegistry::default()),
+        )
+        .await
+    }
+
+    /// Creates a context on a new device, for when the device of this one was lost. The renderer
+    /// settings, the render pipeline plugins, the memory accounting and the registered surfaces
+    /// carry over, and the surfaces' buffers are recreated on the new device.
+    pub(super) fn recreate(&self) -> anyhow::Result<Self> {
+        // No window can draw until the device is recreated, so the main thread waits for it.
+        let mut context = pollster::block_on(Self::with_shared_state(
+            self.surface_registry.clone(),
+            self.memory.clone(),
+            self.renderer_settings.clone(),
+            self.render_plugins.clone(),
+        ))?;
+        context
+            .submission_scheduler
+            .set_eco_mode(context.renderer_settings.eco_mode_active());
+        for (client, budget) in self.submission_scheduler.budgets() {
+            context.submission_scheduler.set_budget(client, budget);
+        }
+        context.surface_registry.replace_device(SurfaceDevice {
+            device: context.device.clone(),
+            queue: context.queue.clone(),
+            scheduler: context.submission_scheduler.clone(),
+        });
+        context.gpu_handle = self.gpu_handle.clone();
+        context
+            .gpu_handle
+            .replace_device(context.device.clone(), context.queue.clone());
+        context.background_executor = self.background_executor.clone();
+        Ok(context)
+    }
+
+    /// Creates a context on the default adapter around state created before the device, so it can
+    /// be configured while the device is requested.
+    pub(super) async fn with_shared_state(
+        surface_registry: Arc<SurfaceRegistry>,
+        memory: Arc<GpuMemoryTracker>,
+        renderer_settings: Arc<RendererSettingsRegistry>,
+        render_plugins: Arc<RenderPluginRegistry>,
+    ) -> anyhow::Result<Self> {
         let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
             backends: wgpu::Backends::all(),
             ..Default::default()
         });
 
-        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
-            power_preference: wgpu::PowerPreference::HighPerformance,
-            compatible_surface: None,
-            force_fallback_adapter: false,
-        }))?;
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            })
+            .await?;
 
-        let (device, queue) =
-            pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
                 label: None,
-                required_features: wgpu::Features::empty(),
+                required_features: adapter.features() & submission_scheduler::TIMESTAMP_FEATURES,
                 required_limits: wgpu::Limits::default(),
                 ..Default::default()
-            }))?;
+            })
+            .await?;
 
-        let globals_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Globals Buffer"),
-            // FIXME(mdeand): Hack
-            size: 16 as u64,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        let quads_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Quads Buffer"),
-            // TODO(mdeand): Determine appropriate size
-            size: 1024 * 1024, // 1 MB buffer for quads, for now. (:
-            usage: wgpu::BufferUsages::VERTEX
-                | wgpu::BufferUsages::COPY_DST
-                | wgpu::BufferUsages::STORAGE,
-            mapped_at_creation: false,
-        });
-
-        let mono_sprites_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Monosprites Buffer"),
-            // TODO(mdeand): Determine appropriate size, or make resizable.
-            size: 1024 * 1024,
-            usage: wgpu::BufferUsages::VERTEX
-                | wgpu::BufferUsages::COPY_DST
-                | wgpu::BufferUsages::STORAGE,
-            mapped_at_creation: false,
-        });
-
-        let shadows_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Shadows Buffer"),
-            size: 1024 * 1024,
-            usage: wgpu::BufferUsages::VERTEX
-                | wgpu::BufferUsages::COPY_DST
-                | wgpu::BufferUsages::STORAGE,
-            mapped_at_creation: false,
-        });
-
-        let underlines_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Underlines Buffer"),
-            size: 1024 * 1024,
-            usage: wgpu::BufferUsages::VERTEX
-                | wgpu::BufferUsages::COPY_DST
-                | wgpu::BufferUsages::STORAGE,
-            mapped_at_creation: false,
-        });
+        Ok(Self::with_device(
+            EmbeddedDevice {
+                instance,
+                adapter,
+                device,
+                queue,
+            },
+            surface_registry,
+            memory,
+            renderer_settings,
+            render_plugins,
+        ))
+    }
 
-        let poly_sprites_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Poly Sprites Buffer"),
-            size: 1024 * 1024,
-            usage: wgpu::BufferUsages::VERTEX
-                | wgpu::BufferUsages::COPY_DST
-                | wgpu::BufferUsages::STORAGE,
-            mapped_at_creation: false,
-        });
+    /// Creates a context on a device that was already created, such as the one of a host GPUI is
+    /// embedded in.
+    pub(super) fn with_device(
+        embedded_device: EmbeddedDevice,
+        surface_registry: Arc<SurfaceRegistry>,
+        memory: Arc<GpuMemoryTracker>,
+        renderer_settings: Arc<RendererSettingsRegistry>,
+        render_plugins: Arc<RenderPluginRegistry>,
+    ) -> Self {
+        let EmbeddedDevice {
+            instance,
+            adapter,
+            device,
+            queue,
+        } = embedded_device;
 
         let color_adjustments_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Color Adjustments Buffer"),
@@ -104,21 +154,154 @@ impl WgpuContext {
             mapped_at_creation: false,
         });
 
-        Ok(Self {
+        let submission_scheduler =
+            Arc::new(SubmissionScheduler::new(device.clone(), queue.clone()));
+        let gpu_handle = GpuHandle::new(device.clone(), queue.clone());
+
+        Self {
             adapter,
             device,
             queue,
             instance,
 
-            globals_buffer,
-            quads_buffer,
-            shadows_buffer,
-            underlines_buffer,
-            mono_sprites_buffer,
-            poly_sprites_buffer,
             color_adjustments_buffer,
 
-            surface_registry: Arc::new(SurfaceRegistry::new()),
-        })
+            surface_registry,
+            submission_scheduler,
+            memory,
+            renderer_settings,
+            render_plugins,
+            gpu_handle,
+            background_executor: None,
+
+            error_handler: parking_lot::Mutex::new(None),
+        }
+    }
+
+    /// Routes errors the device doesn't report through an error scope to `handler`, instead of
+    /// wgpu's default handler, which panics.
+    pub fn on_uncaptured_error(&self, handler: impl Fn(GpuError) + Send + Sync + 'static) {
+        let handler: GpuErrorHandler = Arc::new(handler);
+        *self.error_handler.lock() = Some(handler.clone());
+
+        self.device.on_uncaptured_error(Box::new(move |error| {
+            let (kind, message) = match error {
+                wgpu::Error::Validation { description, .. } => {
+                    (GpuErrorKind::Validation, description)
+                }
+                wgpu::Error::OutOfMemory { source } => {
+                    (GpuErrorKind::OutOfMemory, source.to_string())
+                }
+                wgpu::Error::Internal { description, .. } => (GpuErrorKind::Internal, description),
+            };
+
+            #[cfg(debug_assertions)]
+            let pass_label = ACTIVE_PASS_LABEL.get();
+            #[cfg(not(debug_assertions))]
+            let pass_label = None;
+
+            handler(GpuError {
+                kind,
+                message,
+                pass_label,
+            });
+        }));
+    }
+
+    /// Calls `handler` with the reason when the device is lost, such as when the GPU is unplugged
+    /// or its driver is updated. The device being destroyed along with the context isn't reported.
+    pub fn on_device_lost(&self, handler: impl Fn(String) + Send + 'static) {
+        self.device
+            .set_device_lost_callback(move |reason, message| match reason {
+                wgpu::DeviceLostReason::Destroyed => {}
+                _ => handler(message),
+            });
+    }
+
+    /// Reports an error detected by the renderer itself, such as a failed allocation, through the
+    /// same handler as uncaptured device errors.
+    pub(super) fn report_error(&self, error: GpuError) {
+        let handler = self.error_handler.lock().clone();
+        match handler {
+            Some(handler) => handler(error),
+            None => log::error!("{error}"),
+        }
+    }
+
+    /// Copies the contents of `texture` back from the GPU, blocking until the copy is done. The
+    /// rows of the returned pixels are tightly packed.
+    pub(super) fn read_texture(&self, texture: &wgpu::Texture) -> anyhow::Result<Vec<u8>> {
+        let bytes_per_pixel = texture.format().block_copy_size(None).unwrap_or(4);
+        let unpadded_bytes_per_row = texture.width() * bytes_per_pixel;
+        let padded_bytes_per_row = unpadded_bytes_per_row
+            .div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("read_texture"),
+            size: padded_bytes_per_row as u64 * texture.height() as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut command_encoder =
+            self.device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("read_texture"),
+                });
+        command_encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            texture.size(),
+        );
+        self.queue.submit([command_encoder.finish()]);
+
+        let (sender, receiver) = flume::bounded(1);
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                if sender.send(result).is_err() {
+                    log::error!("texture read finished after it was abandoned");
+                }
+            });
+        self.device.poll(wgpu::PollType::Wait)?;
+        receiver.recv()??;
+
+        let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * texture.height()) as usize);
+        {
+            let data = buffer.slice(..).get_mapped_range();
+            for row in data.chunks(padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+            }
+        }
+        buffer.unmap();
+        Ok(pixels)
+    }
+
+    /// Records the pass currently being encoded on this thread, so uncaptured errors can name it.
+    /// Only tracked in debug builds.
+    pub(super) fn set_active_pass(&self, _label: Option<&'static str>) {
+        #[cfg(debug_assertions)]
+        {
+            ACTIVE_PASS_LABEL.set(_label);
+        }
+    }
+
+    /// Submits command buffers to the queue, naming the submission in the uncaptured errors it
+    /// raises.
+    pub(super) fn submit(
+        &self,
+        command_buffers: impl IntoIterator<Item = wgpu::CommandBuffer>,
+    ) -> wgpu::SubmissionIndex {
+        self.set_active_pass(Some("submit"));
+        let submission = self.queue.submit(command_buffers);
+        self.set_active_pass(None);
+        submission
     }
 }
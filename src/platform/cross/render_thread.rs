@@ -0,0 +1,165 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use parking_lot::Mutex;
+
+use crate::{
+    DevicePixels, PresentStats, RendererError, Scene, Size, platform::cross::renderer::WgpuRenderer,
+};
+
+enum RenderCommand {
+    Draw(Scene),
+    Resize(Size<DevicePixels>),
+    Shutdown,
+}
+
+/// Draws and presents a window's scenes on a dedicated thread, so a slow GPU pass doesn't block
+/// the event loop.
+///
+/// Commands are processed in the order they were sent, so a resize always applies to the scenes
+/// drawn after it. When the thread falls behind, every scene but the most recent one is dropped.
+pub(crate) struct RenderThread {
+    renderer: Arc<Mutex<WgpuRenderer>>,
+    commands: flume::Sender<RenderCommand>,
+    needs_full_redraw: Arc<AtomicBool>,
+    // the errors of frames that failed to draw, until the window reports them
+    errors: Arc<Mutex<Vec<RendererError>>>,
+    // the renderer's overload frame interval in nanoseconds as of its last frame, 0 when it isn't
+    // overloaded, so it can be read without waiting for the frame in flight
+    overload_frame_interval: Arc<AtomicU64>,
+    // the renderer's present stats as of its last frame
+    present_stats: Arc<Mutex<PresentStats>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RenderThread {
+    pub(crate) fn spawn(
+        renderer: WgpuRenderer,
+        winit_window: Arc<winit::window::Window>,
+    ) -> anyhow::Result<Self> {
+        let renderer = Arc::new(Mutex::new(renderer));
+        let needs_full_redraw = Arc::new(AtomicBool::new(false));
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let overload_frame_interval = Arc::new(AtomicU64::new(0));
+        let present_stats = Arc::new(Mutex::new(renderer.present_stats()));
+        let (commands, receiver) = flume::unbounded();
+
+        let thread = std::thread::Builder::new()
+            .name("gpui-render".into())
+            .spawn({
+                let renderer = renderer.clone();
+                let needs_full_redraw = needs_full_redraw.clone();
+                let errors = errors.clone();
+                let overload_frame_interval = overload_frame_interval.clone();
+                let present_stats = present_stats.clone();
+                move || {
+                    while let Ok(command) = receiver.recv() {
+                        let mut pending = vec![command];
+                        pending.extend(receiver.try_iter());
+                        let latest_draw = pending
+                            .iter()
+                            .rposition(|command| matches!(command, RenderCommand::Draw(_)));
+
+                        let mut renderer = renderer.lock();
+                        // the cached layers of skipped scenes, which later scenes still show
+                        let mut skipped_layers = Vec::new();
+                        for (index, command) in pending.into_iter().enumerate() {
+                            match command {
+                                RenderCommand::Draw(mut scene) => {
+                                    if Some(index) != latest_draw {
+                                        skipped_layers.append(&mut scene.layer_renders);
+                                        continue;
+                                    }
+                                    scene.layer_renders.splice(0..0, skipped_layers.drain(..));
+                                    if let Err(error) = renderer.draw(&scene) {
+                                        errors.lock().push(error);
+                                    }
+                                    overload_frame_interval.store(
+                                        renderer
+                                            .overload_frame_interval()
+                                            .map_or(0, |interval| interval.as_nanos() as u64),
+                                        Ordering::Release,
+                                    );
+                                    *present_stats.lock() = renderer.present_stats();
+                                    if renderer.take_needs_full_redraw() {
+                                        needs_full_redraw.store(true, Ordering::Release);
+                                        winit_window.request_redraw();
+                                    }
+                                }
+                                RenderCommand::Resize(size) => renderer.update_drawable_size(size),
+                                RenderCommand::Shutdown => return,
+                            }
+                        }
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            renderer,
+            commands,
+            needs_full_redraw,
+            errors,
+            overload_frame_interval,
+            present_stats,
+            thread: Some(thread),
+        })
+    }
+
+    pub(crate) fn draw(&self, scene: &Scene) {
+        self.send(RenderCommand::Draw(scene.clone_primitives()));
+    }
+
+    pub(crate) fn resize(&self, size: Size<DevicePixels>) {
+        self.send(RenderCommand::Resize(size));
+    }
+
+    /// Runs `f` with exclusive access to the renderer, waiting for any frame in flight.
+    pub(crate) fn with_renderer<R>(&self, f: impl FnOnce(&mut WgpuRenderer) -> R) -> R {
+        f(&mut self.renderer.lock())
+    }
+
+    pub(crate) fn take_needs_full_redraw(&self) -> bool {
+        self.needs_full_redraw.swap(false, Ordering::Acquire)
+    }
+
+    /// See [`WgpuRenderer::overload_frame_interval`].
+    pub(crate) fn overload_frame_interval(&self) -> Option<Duration> {
+        match self.overload_frame_interval.load(Ordering::Acquire) {
+            0 => None,
+            nanoseconds => Some(Duration::from_nanos(nanoseconds)),
+        }
+    }
+
+    /// See [`WgpuRenderer::present_stats`].
+    pub(crate) fn present_stats(&self) -> PresentStats {
+        *self.present_stats.lock()
+    }
+
+    /// Returns the errors of the frames that failed to draw since the last call.
+    pub(crate) fn take_errors(&self) -> Vec<RendererError> {
+        std::mem::take(&mut *self.errors.lock())
+    }
+
+    fn send(&self, command: RenderCommand) {
+        if self.commands.send(command).is_err() {
+            log::error!("render thread exited unexpectedly");
+        }
+    }
+}
+
+impl Drop for RenderThread {
+    fn drop(&mut self) {
+        self.send(RenderCommand::Shutdown);
+        if let Some(thread) = self.thread.take()
+            && thread.join().is_err()
+        {
+            log::error!("render thread panicked");
+        }
+    }
+}
@@ -1,10 +1,36 @@
-use std::sync::Arc;
+use std::{
+    any::TypeId,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use anyhow::Context as _;
+use collections::FxHashMap;
+use smallvec::SmallVec;
 
 use crate::{
-    AtlasTextureId, AtlasTile, DevicePixels, GpuSpecs, Hsla, LinearColorStop, MonochromeSprite,
-    PlatformAtlas, PrimitiveBatch, Quad, ScaledPixels, Scene, TransformationMatrix, color,
+    AtlasTextureId, AtlasTile, BorderStyle, CellGlyph, CellGrid, Clip, ClipId, ColorFilter,
+    ContentMask, DevicePixels, DrawCall, DrawCallKind, DrawOrder, FrameDrawCalls, FrameRecording,
+    GpuError, GpuErrorKind, GpuSpecs, GridCell, Half, Hsla, ImageFilter, LinearColorStop,
+    MonochromeSprite, PixelSnapping, PixelSnappingPolicy, PlatformAtlas, PluginBatch,
+    PluginPipelines, PolychromeSprite, PresentMode, PresentStats, PrimitiveBatch, Priority, Quad,
+    RenderPipelineContext, RendererError, RendererSettings, SavedScene, ScaledPixels, Scene,
+    SceneViewport, ScreenZoom, Shadow, SurfaceAlphaMode, SurfaceColorSpace,
+    SurfaceFormatPreference, TransformationMatrix, Underline, WhitePoint, XrSwapchainTarget, color,
     geometry,
-    platform::cross::{atlas::WgpuAtlas, render_context::WgpuContext},
+    platform::cross::{
+        atlas::WgpuAtlas,
+        frame_pacing::FramePacer,
+        frame_recorder::FrameRecorder,
+        frame_stats::{FrameSample, FrameStatsLogger},
+        gpu_memory::{GpuMemoryCategory, GpuMemoryTracker},
+        render_context::WgpuContext,
+        resource_pool::{ResourcePool, SamplerKey},
+        shader_preprocessor::{self, ShaderFeatures},
+        surface_registry::SurfaceId,
+    },
 };
 
 const fn map_attributes<const N: usize>(
@@ -118,11 +144,16 @@ impl color::Background {
 struct GlobalParams {
     viewport_size: [f32; 2],
     premultimated_alpha: u32,
-    pad: u32,
+    srgb_output: u32,
+    // the scene position drawn at the top left corner of the viewport
+    viewport_origin: [f32; 2],
+    // the scene position of the target's top left corner, where fragments are at their scene
+    // position unless the scene is drawn into a cached layer
+    target_origin: [f32; 2],
 }
 
 impl GlobalParams {
-    const VERTEX_ATTRIBUTES: &'static [wgpu::VertexAttribute; 3] = &[
+    const VERTEX_ATTRIBUTES: &'static [wgpu::VertexAttribute; 5] = &[
         wgpu::VertexAttribute {
             offset: std::mem::offset_of!(GlobalParams, viewport_size) as wgpu::BufferAddress,
             shader_location: 0,
@@ -134,10 +165,20 @@ impl GlobalParams {
             format: wgpu::VertexFormat::Uint32,
         },
         wgpu::VertexAttribute {
-            offset: std::mem::offset_of!(GlobalParams, pad) as wgpu::BufferAddress,
+            offset: std::mem::offset_of!(GlobalParams, srgb_output) as wgpu::BufferAddress,
             shader_location: 2,
             format: wgpu::VertexFormat::Uint32,
         },
+        wgpu::VertexAttribute {
+            offset: std::mem::offset_of!(GlobalParams, viewport_origin) as wgpu::BufferAddress,
+            shader_location: 3,
+            format: wgpu::VertexFormat::Float32x2,
+        },
+        wgpu::VertexAttribute {
+            offset: std::mem::offset_of!(GlobalParams, target_origin) as wgpu::BufferAddress,
+            shader_location: 4,
+            format: wgpu::VertexFormat::Float32x2,
+        },
     ];
 }
 
@@ -225,8 +266,25 @@ impl Bounds {
 struct SurfaceParams {
     bounds: Bounds,
     content_mask: Bounds,
+    // the surface's colors are already multiplied by their alpha
+    premultiplied: u32,
+    // the surface's colors are linear rather than sRGB encoded like the renderer's own
+    linear: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct ColorFilterParams {
+    matrix: [[f32; 4]; 3],
 }
 
+/// A color filter matrix that leaves colors unchanged, for copying a kept frame to the surface.
+const IDENTITY_COLOR_MATRIX: [[f32; 4]; 3] = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+];
+
 impl Quad {
     const VERTEX_ATTRIBUTES: &'static [wgpu::VertexAttribute; 22] = &{
         let bounds_vertex_attributes = map_attributes(
@@ -565,9 +623,528 @@ impl MonochromeSprite {
 struct ColorAdjustments {
     gamma_ratios: [f32; 4],
     grayscale_enhanced_contrast: f32,
-    _padding: [f32; 3],
+    // the step between the target's representable values, which gradients are dithered by, or
+    // zero when they aren't dithered
+    dither_amplitude: f32,
+    _padding: [f32; 2],
+    // multiplies the output colors, in the encoding they're written in
+    white_point: [f32; 4],
+}
+
+// The scene primitives contain enums and `bool`s, so they can't be cast to bytes directly. These
+// mirror the WGSL storage structs field for field and are what actually gets uploaded.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuHsla {
+    h: f32,
+    s: f32,
+    l: f32,
+    a: f32,
+}
+
+impl From<Hsla> for GpuHsla {
+    fn from(color: Hsla) -> Self {
+        Self {
+            h: color.h,
+            s: color.s,
+            l: color.l,
+            a: color.a,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuCorners {
+    top_left: f32,
+    top_right: f32,
+    bottom_right: f32,
+    bottom_left: f32,
+}
+
+impl From<&geometry::Corners<ScaledPixels>> for GpuCorners {
+    fn from(corners: &geometry::Corners<ScaledPixels>) -> Self {
+        Self {
+            top_left: corners.top_left.0,
+            top_right: corners.top_right.0,
+            bottom_right: corners.bottom_right.0,
+            bottom_left: corners.bottom_left.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuEdges {
+    top: f32,
+    right: f32,
+    bottom: f32,
+    left: f32,
+}
+
+impl From<&geometry::Edges<ScaledPixels>> for GpuEdges {
+    fn from(edges: &geometry::Edges<ScaledPixels>) -> Self {
+        Self {
+            top: edges.top.0,
+            right: edges.right.0,
+            bottom: edges.bottom.0,
+            left: edges.left.0,
+        }
+    }
+}
+
+impl From<&geometry::Bounds<ScaledPixels>> for Bounds {
+    fn from(bounds: &geometry::Bounds<ScaledPixels>) -> Self {
+        Self {
+            origin: [bounds.origin.x.0, bounds.origin.y.0],
+            size: [bounds.size.width.0, bounds.size.height.0],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuLinearColorStop {
+    color: GpuHsla,
+    percentage: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuBackground {
+    tag: u32,
+    color_space: u32,
+    solid: GpuHsla,
+    gradient_angle_or_pattern_height: f32,
+    colors: [GpuLinearColorStop; 2],
+    pad: u32,
+}
+
+impl From<&color::Background> for GpuBackground {
+    fn from(background: &color::Background) -> Self {
+        let stop = |stop: &LinearColorStop| GpuLinearColorStop {
+            color: stop.color.into(),
+            percentage: stop.percentage,
+        };
+
+        Self {
+            tag: background.tag as u32,
+            color_space: background.color_space as u32,
+            solid: background.solid.into(),
+            gradient_angle_or_pattern_height: background.gradient_angle_or_pattern_height,
+            colors: [stop(&background.colors[0]), stop(&background.colors[1])],
+            pad: 0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuAtlasTile {
+    texture_index: u32,
+    texture_kind: u32,
+    tile_id: u32,
+    padding: u32,
+    origin: [i32; 2],
+    size: [i32; 2],
+}
+
+impl From<&AtlasTile> for GpuAtlasTile {
+    fn from(tile: &AtlasTile) -> Self {
+        Self {
+            texture_index: tile.texture_id.index,
+            texture_kind: tile.texture_id.kind as u32,
+            tile_id: tile.tile_id.0,
+            padding: 0,
+            origin: [tile.bounds.origin.x.0, tile.bounds.origin.y.0],
+            size: [tile.bounds.size.width.0, tile.bounds.size.height.0],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuQuad {
+    order: u32,
+    border_style: u32,
+    bounds: Bounds,
+    content_mask: Bounds,
+    background: GpuBackground,
+    border_color: GpuHsla,
+    corner_radii: GpuCorners,
+    border_widths: GpuEdges,
+}
+
+impl From<&Quad> for GpuQuad {
+    fn from(quad: &Quad) -> Self {
+        Self {
+            order: quad.order,
+            border_style: quad.border_style as u32,
+            bounds: (&quad.bounds).into(),
+            content_mask: (&quad.content_mask.bounds).into(),
+            background: (&quad.background).into(),
+            border_color: quad.border_color.into(),
+            corner_radii: (&quad.corner_radii).into(),
+            border_widths: (&quad.border_widths).into(),
+        }
+    }
+}
+
+impl GpuQuad {
+    fn snapped(quad: &Quad, policy: PixelSnappingPolicy) -> Self {
+        let mut gpu_quad = Self::from(quad);
+        let snapping = resolve_snapping(quad.snapping, policy);
+        if snapping.bounds {
+            gpu_quad.bounds = snap_bounds(gpu_quad.bounds);
+        }
+        if snapping.borders {
+            let edges = &mut gpu_quad.border_widths;
+            for width in [
+                &mut edges.top,
+                &mut edges.right,
+                &mut edges.bottom,
+                &mut edges.left,
+            ] {
+                *width = snap_length(*width);
+            }
+        }
+        gpu_quad
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuShadow {
+    order: u32,
+    blur_radius: f32,
+    bounds: Bounds,
+    corner_radii: GpuCorners,
+    content_mask: Bounds,
+    color: GpuHsla,
+}
+
+impl From<&Shadow> for GpuShadow {
+    fn from(shadow: &Shadow) -> Self {
+        Self {
+            order: shadow.order,
+            blur_radius: shadow.blur_radius.0,
+            bounds: (&shadow.bounds).into(),
+            corner_radii: (&shadow.corner_radii).into(),
+            content_mask: (&shadow.content_mask.bounds).into(),
+            color: shadow.color.into(),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuUnderline {
+    order: u32,
+    pad: u32,
+    bounds: Bounds,
+    content_mask: Bounds,
+    color: GpuHsla,
+    thickness: f32,
+    pattern: u32,
+}
+
+impl From<&Underline> for GpuUnderline {
+    fn from(underline: &Underline) -> Self {
+        Self {
+            order: underline.order,
+            pad: 0,
+            bounds: (&underline.bounds).into(),
+            content_mask: (&underline.content_mask.bounds).into(),
+            color: underline.color.into(),
+            thickness: underline.thickness.0,
+            pattern: underline.pattern as u32,
+        }
+    }
+}
+
+impl GpuUnderline {
+    fn snapped(underline: &Underline, policy: PixelSnappingPolicy) -> Self {
+        let mut gpu_underline = Self::from(underline);
+        // An underline's line is its border, so it's snapped with its bounds.
+        if resolve_snapping(underline.snapping, policy).bounds {
+            gpu_underline.bounds = snap_bounds(gpu_underline.bounds);
+            gpu_underline.thickness = snap_length(gpu_underline.thickness);
+        }
+        gpu_underline
+    }
+}
+
+/// The snapping a primitive gets under the renderer's policy.
+fn resolve_snapping(requested: PixelSnapping, policy: PixelSnappingPolicy) -> PixelSnapping {
+    match policy {
+        PixelSnappingPolicy::Never => PixelSnapping::NONE,
+        PixelSnappingPolicy::Requested => requested,
+        PixelSnappingPolicy::Always => PixelSnapping::ALL,
+    }
+}
+
+/// Rounds the edges of `bounds` to device pixels, keeping bounds that aren't empty at least a
+/// pixel wide and tall so hairlines don't vanish.
+fn snap_bounds(bounds: Bounds) -> Bounds {
+    let mut snapped = bounds;
+    for axis in 0..2 {
+        let start = bounds.origin[axis].round();
+        let end = (bounds.origin[axis] + bounds.size[axis]).round();
+        snapped.origin[axis] = start;
+        snapped.size[axis] = if bounds.size[axis] > 0.0 {
+            (end - start).max(1.0)
+        } else {
+            0.0
+        };
+    }
+    snapped
 }
 
+/// Rounds a length to whole device pixels, keeping lengths that aren't zero at least a pixel.
+fn snap_length(length: f32) -> f32 {
+    if length > 0.0 {
+        length.round().max(1.0)
+    } else {
+        length
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuMonochromeSprite {
+    order: u32,
+    pad: u32,
+    bounds: Bounds,
+    content_mask: Bounds,
+    color: GpuHsla,
+    tile: GpuAtlasTile,
+    rotation_scale: [[f32; 2]; 2],
+    translation: [f32; 2],
+}
+
+impl From<&MonochromeSprite> for GpuMonochromeSprite {
+    fn from(sprite: &MonochromeSprite) -> Self {
+        Self {
+            order: sprite.order,
+            pad: 0,
+            bounds: (&sprite.bounds).into(),
+            content_mask: (&sprite.content_mask.bounds).into(),
+            color: sprite.color.into(),
+            tile: (&sprite.tile).into(),
+            rotation_scale: sprite.transformation.rotation_scale,
+            translation: sprite.transformation.translation,
+        }
+    }
+}
+
+/// The parameters of a cell grid, which its glyph table and cells are uploaded next to.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuCellGrid {
+    origin: [f32; 2],
+    cell_size: [f32; 2],
+    content_mask: Bounds,
+    columns: u32,
+    cell_count: u32,
+    glyph_count: u32,
+    opacity: f32,
+}
+
+impl From<&CellGrid> for GpuCellGrid {
+    fn from(grid: &CellGrid) -> Self {
+        Self {
+            origin: [grid.bounds.origin.x.0, grid.bounds.origin.y.0],
+            cell_size: [grid.cell_size.width.0, grid.cell_size.height.0],
+            content_mask: (&grid.content_mask.bounds).into(),
+            columns: grid.columns,
+            cell_count: grid.cells.len() as u32,
+            glyph_count: grid.glyphs.len() as u32,
+            opacity: grid.opacity,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuCellGlyph {
+    bounds: Bounds,
+    tile_origin: [i32; 2],
+    tile_size: [i32; 2],
+}
+
+impl From<&CellGlyph> for GpuCellGlyph {
+    fn from(glyph: &CellGlyph) -> Self {
+        Self {
+            bounds: (&glyph.bounds).into(),
+            tile_origin: [glyph.tile_bounds.origin.x.0, glyph.tile_bounds.origin.y.0],
+            tile_size: [
+                glyph.tile_bounds.size.width.0,
+                glyph.tile_bounds.size.height.0,
+            ],
+        }
+    }
+}
+
+/// Set in a grid cell's glyph when the cell is blank.
+const BLANK_CELL_GLYPH: u32 = u32::MAX;
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuGridCell {
+    foreground: GpuHsla,
+    background: GpuHsla,
+    glyph: u32,
+}
+
+impl From<&GridCell> for GpuGridCell {
+    fn from(cell: &GridCell) -> Self {
+        Self {
+            foreground: cell.foreground.into(),
+            background: cell.background.into(),
+            glyph: cell.glyph.unwrap_or(BLANK_CELL_GLYPH),
+        }
+    }
+}
+
+/// A selection, whose lines are uploaded as [`GpuSelectionLine`]s.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuSelection {
+    color: GpuHsla,
+    content_mask: Bounds,
+    top: f32,
+    line_height: f32,
+    corner_radius: f32,
+    // the index of the selection's first line among the lines of every selection
+    first_line: u32,
+    line_count: u32,
+    pad: [u32; 3],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuSelectionLine {
+    start_x: f32,
+    end_x: f32,
+    // the index of the selection the line belongs to
+    selection: u32,
+}
+
+/// Set in a sprite's grayscale field when its tile's colors are premultiplied by their alpha.
+const PREMULTIPLIED_SPRITE_FLAG: u32 = 0x100;
+/// Set in a sprite's grayscale field when it's sampled from the nearest texel.
+const NEAREST_SPRITE_FLAG: u32 = 0x200;
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuPolychromeSprite {
+    order: u32,
+    pad: u32,
+    grayscale: u32,
+    opacity: f32,
+    bounds: Bounds,
+    content_mask: Bounds,
+    corner_radii: GpuCorners,
+    tile: GpuAtlasTile,
+    tint: GpuHsla,
+}
+
+impl From<&PolychromeSprite> for GpuPolychromeSprite {
+    fn from(sprite: &PolychromeSprite) -> Self {
+        Self {
+            order: sprite.order,
+            pad: 0,
+            grayscale: sprite.grayscale as u32
+                | if sprite.premultiplied {
+                    PREMULTIPLIED_SPRITE_FLAG
+                } else {
+                    0
+                }
+                | match sprite.filter {
+                    ImageFilter::Linear => 0,
+                    ImageFilter::Nearest => NEAREST_SPRITE_FLAG,
+                },
+            opacity: sprite.opacity,
+            bounds: (&sprite.bounds).into(),
+            content_mask: (&sprite.content_mask.bounds).into(),
+            corner_radii: (&sprite.corner_radii).into(),
+            tile: (&sprite.tile).into(),
+            tint: sprite.tint.unwrap_or(Hsla::white()).into(),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuClip {
+    bounds: Bounds,
+    corner_radii: GpuCorners,
+    rotation_scale: [[f32; 2]; 2],
+    translation: [f32; 2],
+}
+
+impl From<&Clip> for GpuClip {
+    fn from(clip: &Clip) -> Self {
+        Self {
+            bounds: (&clip.bounds).into(),
+            corner_radii: (&clip.corner_radii).into(),
+            rotation_scale: clip.transformation.rotation_scale,
+            translation: clip.transformation.translation,
+        }
+    }
+}
+
+// Sizes and offsets as computed by WGSL's storage buffer layout rules for the structs in
+// `shaders/*.wgsl`. Keep these in sync when either side changes.
+const _: () = {
+    use std::mem::{offset_of, size_of};
+
+    assert!(size_of::<GlobalParams>() == 32);
+    assert!(size_of::<Bounds>() == 16);
+    assert!(size_of::<GpuHsla>() == 16);
+    assert!(size_of::<GpuBackground>() == 72);
+    assert!(offset_of!(GpuBackground, colors) == 28);
+    assert!(size_of::<GpuAtlasTile>() == 32);
+    assert!(offset_of!(GpuAtlasTile, origin) == 16);
+
+    assert!(size_of::<GpuQuad>() == 160);
+    assert!(offset_of!(GpuQuad, bounds) == 8);
+    assert!(offset_of!(GpuQuad, background) == 40);
+    assert!(offset_of!(GpuQuad, border_color) == 112);
+    assert!(offset_of!(GpuQuad, border_widths) == 144);
+
+    assert!(size_of::<GpuShadow>() == 72);
+    assert!(offset_of!(GpuShadow, content_mask) == 40);
+
+    assert!(size_of::<GpuUnderline>() == 64);
+    assert!(offset_of!(GpuUnderline, thickness) == 56);
+
+    assert!(size_of::<GpuMonochromeSprite>() == 112);
+    assert!(offset_of!(GpuMonochromeSprite, tile) == 56);
+    assert!(offset_of!(GpuMonochromeSprite, rotation_scale) == 88);
+
+    assert!(size_of::<GpuPolychromeSprite>() == 112);
+    assert!(offset_of!(GpuPolychromeSprite, corner_radii) == 48);
+    assert!(offset_of!(GpuPolychromeSprite, tile) == 64);
+    assert!(offset_of!(GpuPolychromeSprite, tint) == 96);
+
+    assert!(size_of::<GpuCellGrid>() == 48);
+    assert!(offset_of!(GpuCellGrid, columns) == 32);
+    assert!(size_of::<GpuCellGlyph>() == 32);
+    assert!(size_of::<GpuGridCell>() == 36);
+
+    assert!(size_of::<GpuSelection>() == 64);
+    assert!(offset_of!(GpuSelection, top) == 32);
+    assert!(size_of::<GpuSelectionLine>() == 12);
+
+    assert!(size_of::<GpuClip>() == 56);
+    assert!(offset_of!(GpuClip, rotation_scale) == 32);
+
+    assert!(size_of::<SurfaceParams>() == 40);
+    assert!(size_of::<ColorFilterParams>() == 48);
+    assert!(offset_of!(ColorAdjustments, white_point) == 32);
+};
+
 struct WgpuPipelines {
     color_targets: Vec<Option<wgpu::ColorTargetState>>,
 
@@ -577,7 +1154,12 @@ struct WgpuPipelines {
     sprites_bind_group_layout: wgpu::BindGroupLayout,
     mono_sprites_bind_group_layout: wgpu::BindGroupLayout,
     poly_sprites_bind_group_layout: wgpu::BindGroupLayout,
+    cell_grids_bind_group_layout: wgpu::BindGroupLayout,
+    selections_bind_group_layout: wgpu::BindGroupLayout,
     surfaces_bind_group_layout: wgpu::BindGroupLayout,
+    color_filter_bind_group_layout: wgpu::BindGroupLayout,
+    clips_bind_group_layout: wgpu::BindGroupLayout,
+    globals_bind_group_layout: wgpu::BindGroupLayout,
 
     globals_bind_group: wgpu::BindGroup,
     color_adjustments_bind_group: wgpu::BindGroup,
@@ -587,34 +1169,52 @@ struct WgpuPipelines {
     underlines_pipeline: wgpu::RenderPipeline,
     mono_sprites_pipeline: wgpu::RenderPipeline,
     poly_sprites_pipeline: wgpu::RenderPipeline,
+    cell_grids_pipeline: wgpu::RenderPipeline,
+    selections_pipeline: wgpu::RenderPipeline,
     surfaces_pipeline: wgpu::RenderPipeline,
+    color_filter_pipeline: wgpu::RenderPipeline,
+    // draw a clip into the stencil buffer, and remove it again
+    clip_push_pipeline: wgpu::RenderPipeline,
+    clip_pop_pipeline: wgpu::RenderPipeline,
 }
 
 impl WgpuPipelines {
     pub fn new(
         context: &WgpuContext,
         surface_configuration: &wgpu::SurfaceConfiguration,
+        view_format: wgpu::TextureFormat,
         _path_sample_count: u32,
+        globals: &UniformSlab<GlobalParams>,
     ) -> Self {
+        // The globals tell render pipeline plugins the same, for their own shaders.
+        let features = ShaderFeatures {
+            premultiplied_alpha: surface_configuration.alpha_mode
+                == wgpu::CompositeAlphaMode::PreMultiplied,
+            srgb_output: view_format.is_srgb(),
+        };
+        let shader_source = |source: &str| {
+            wgpu::ShaderSource::Wgsl(shader_preprocessor::preprocess(source, features).into())
+        };
+
         let quads_shader = context
             .device
             .create_shader_module(wgpu::ShaderModuleDescriptor {
                 label: Some("quads_shader"),
-                source: wgpu::ShaderSource::Wgsl(include_str!("shaders/quads.wgsl").into()),
+                source: shader_source(include_str!("shaders/quads.wgsl")),
             });
 
         let shadows_shader = context
             .device
             .create_shader_module(wgpu::ShaderModuleDescriptor {
                 label: Some("shadows_shader"),
-                source: wgpu::ShaderSource::Wgsl(include_str!("shaders/shadows.wgsl").into()),
+                source: shader_source(include_str!("shaders/shadows.wgsl")),
             });
 
         let underlines_shader = context
             .device
             .create_shader_module(wgpu::ShaderModuleDescriptor {
                 label: Some("underlines_shader"),
-                source: wgpu::ShaderSource::Wgsl(include_str!("shaders/underlines.wgsl").into()),
+                source: shader_source(include_str!("shaders/underlines.wgsl")),
             });
 
         let mono_sprite_shader =
@@ -622,9 +1222,7 @@ impl WgpuPipelines {
                 .device
                 .create_shader_module(wgpu::ShaderModuleDescriptor {
                     label: Some("mono_sprites shader"),
-                    source: wgpu::ShaderSource::Wgsl(
-                        include_str!("shaders/mono_sprites.wgsl").into(),
-                    ),
+                    source: shader_source(include_str!("shaders/mono_sprites.wgsl")),
                 });
 
         let poly_sprite_shader =
@@ -632,11 +1230,23 @@ impl WgpuPipelines {
                 .device
                 .create_shader_module(wgpu::ShaderModuleDescriptor {
                     label: Some("poly_sprites shader"),
-                    source: wgpu::ShaderSource::Wgsl(
-                        include_str!("shaders/poly_sprites.wgsl").into(),
-                    ),
+                    source: shader_source(include_str!("shaders/poly_sprites.wgsl")),
                 });
 
+        let selections_shader = context
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("selections shader"),
+                source: shader_source(include_str!("shaders/selections.wgsl")),
+            });
+
+        let cell_grids_shader = context
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("cell_grids shader"),
+                source: shader_source(include_str!("shaders/cell_grids.wgsl")),
+            });
+
         let blend_mode = match surface_configuration.alpha_mode {
             wgpu::CompositeAlphaMode::PreMultiplied => {
                 wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING
@@ -645,7 +1255,7 @@ impl WgpuPipelines {
         };
 
         let color_targets = &[Some(wgpu::ColorTargetState {
-            format: surface_configuration.format,
+            format: view_format,
             blend: Some(blend_mode),
             write_mask: wgpu::ColorWrites::ALL,
         })];
@@ -655,16 +1265,30 @@ impl WgpuPipelines {
                 .device
                 .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                     label: Some("globals"),
-                    entries: &[wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: true,
+                                min_binding_size: None,
+                            },
+                            count: None,
                         },
-                        count: None,
-                    }],
+                        // Every shader applies the white point of the color adjustments to its
+                        // output.
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                    ],
                 });
 
         let color_adjustments_bind_group_layout =
@@ -719,7 +1343,7 @@ impl WgpuPipelines {
                         visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
                         ty: wgpu::BindingType::Buffer {
                             ty: wgpu::BufferBindingType::Storage { read_only: true },
-                            has_dynamic_offset: false,
+                            has_dynamic_offset: true,
                             min_binding_size: None,
                         },
                         count: None,
@@ -745,7 +1369,7 @@ impl WgpuPipelines {
                         visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
                         ty: wgpu::BindingType::Buffer {
                             ty: wgpu::BufferBindingType::Storage { read_only: true },
-                            has_dynamic_offset: false,
+                            has_dynamic_offset: true,
                             min_binding_size: None,
                         },
                         count: None,
@@ -771,7 +1395,7 @@ impl WgpuPipelines {
                         visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
                         ty: wgpu::BindingType::Buffer {
                             ty: wgpu::BufferBindingType::Storage { read_only: true },
-                            has_dynamic_offset: false,
+                            has_dynamic_offset: true,
                             min_binding_size: None,
                         },
                         count: None,
@@ -800,7 +1424,7 @@ impl WgpuPipelines {
                         visibility: wgpu::ShaderStages::VERTEX,
                         ty: wgpu::BindingType::Buffer {
                             ty: wgpu::BufferBindingType::Storage { read_only: true },
-                            has_dynamic_offset: false,
+                            has_dynamic_offset: true,
                             min_binding_size: None,
                         },
                         count: None,
@@ -831,7 +1455,7 @@ impl WgpuPipelines {
                         visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
                         ty: wgpu::BindingType::Buffer {
                             ty: wgpu::BufferBindingType::Storage { read_only: true },
-                            has_dynamic_offset: false,
+                            has_dynamic_offset: true,
                             min_binding_size: None,
                         },
                         count: None,
@@ -851,16 +1475,71 @@ impl WgpuPipelines {
                     push_constant_ranges: &[],
                 });
 
-        let surfaces_shader =
+        // A grid's parameters, glyph table and cells are separate ranges of the instance buffer,
+        // each bound at its own dynamic offset, and so are the selections and their lines.
+        let dynamic_storage_entry = |binding: u32| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: true,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let cell_grids_bind_group_layout =
             context
                 .device
-                .create_shader_module(wgpu::ShaderModuleDescriptor {
-                    label: Some("surfaces_shader"),
-                    source: wgpu::ShaderSource::Wgsl(
-                        include_str!("shaders/surfaces.wgsl").into(),
-                    ),
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("Cell grids bind group layout"),
+                    entries: &[
+                        dynamic_storage_entry(0),
+                        dynamic_storage_entry(1),
+                        dynamic_storage_entry(2),
+                    ],
+                });
+
+        let cell_grids_pipeline_layout =
+            context
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("Cell grids pipeline layout"),
+                    bind_group_layouts: &[
+                        &globals_bind_group_layout,
+                        &color_adjustments_bind_group_layout,
+                        &sprites_bind_group_layout,
+                        &cell_grids_bind_group_layout,
+                    ],
+                    push_constant_ranges: &[],
+                });
+
+        let selections_bind_group_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("selections_bind_group_layout"),
+                    entries: &[dynamic_storage_entry(0), dynamic_storage_entry(1)],
+                });
+
+        let selections_pipeline_layout =
+            context
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("selections_pipeline_layout"),
+                    bind_group_layouts: &[
+                        &globals_bind_group_layout,
+                        &selections_bind_group_layout,
+                    ],
+                    push_constant_ranges: &[],
                 });
 
+        let surfaces_shader = context
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("surfaces_shader"),
+                source: shader_source(include_str!("shaders/surfaces.wgsl")),
+            });
+
         let surfaces_bind_group_layout =
             context
                 .device
@@ -872,7 +1551,7 @@ impl WgpuPipelines {
                             visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
                             ty: wgpu::BindingType::Buffer {
                                 ty: wgpu::BufferBindingType::Uniform,
-                                has_dynamic_offset: false,
+                                has_dynamic_offset: true,
                                 min_binding_size: None,
                             },
                             count: None,
@@ -908,19 +1587,142 @@ impl WgpuPipelines {
                     push_constant_ranges: &[],
                 });
 
+        let color_filter_shader =
+            context
+                .device
+                .create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some("color_filter_shader"),
+                    source: shader_source(include_str!("shaders/color_filter.wgsl")),
+                });
+
+        let color_filter_bind_group_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("color_filter_bind_group_layout"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                    ],
+                });
+
+        let color_filter_pipeline_layout =
+            context
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("color_filter_pipeline_layout"),
+                    bind_group_layouts: &[
+                        &globals_bind_group_layout,
+                        &color_filter_bind_group_layout,
+                    ],
+                    push_constant_ranges: &[],
+                });
+
+        let clips_shader = context
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("clips_shader"),
+                source: shader_source(include_str!("shaders/clips.wgsl")),
+            });
+
+        let clips_bind_group_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("clips_bind_group_layout"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: true,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    }],
+                });
+
+        let clips_pipeline_layout =
+            context
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("clips_pipeline_layout"),
+                    bind_group_layouts: &[&globals_bind_group_layout, &clips_bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        // Clips only touch the stencil buffer, and each one is only drawn where its parent passes,
+        // so the stencil counts how many clips a pixel is inside of.
+        let create_clip_pipeline = |label: &str, pass_op: wgpu::StencilOperation| {
+            context
+                .device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some(label),
+                    layout: Some(&clips_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &clips_shader,
+                        entry_point: Some("vs_clip"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        buffers: &[],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleStrip,
+                        ..Default::default()
+                    },
+                    depth_stencil: Some(clip_stencil_state(pass_op)),
+                    fragment: Some(wgpu::FragmentState {
+                        module: &clips_shader,
+                        entry_point: Some("fs_clip"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format: view_format,
+                            blend: None,
+                            write_mask: wgpu::ColorWrites::empty(),
+                        })],
+                    }),
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview: None,
+                    cache: None,
+                })
+        };
+
         let globals_bind_group = context
             .device
             .create_bind_group(&wgpu::BindGroupDescriptor {
                 label: Some("globals_bind_group"),
                 layout: &globals_bind_group_layout,
-                entries: &[wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                        buffer: &context.globals_buffer,
-                        offset: 0,
-                        size: None,
-                    }),
-                }],
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: globals.binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                            buffer: &context.color_adjustments_buffer,
+                            offset: 0,
+                            size: None,
+                        }),
+                    },
+                ],
             });
 
         let color_adjustments_bind_group =
@@ -948,6 +1750,8 @@ impl WgpuPipelines {
             mono_sprites_bind_group_layout,
             sprites_bind_group_layout,
             poly_sprites_bind_group_layout,
+            cell_grids_bind_group_layout,
+            selections_bind_group_layout,
 
             globals_bind_group,
             color_adjustments_bind_group,
@@ -966,7 +1770,7 @@ impl WgpuPipelines {
                         topology: wgpu::PrimitiveTopology::TriangleStrip,
                         ..Default::default()
                     },
-                    depth_stencil: None,
+                    depth_stencil: Some(clip_stencil_state(wgpu::StencilOperation::Keep)),
                     multisample: wgpu::MultisampleState::default(),
                     fragment: Some(wgpu::FragmentState {
                         module: &quads_shader,
@@ -993,7 +1797,7 @@ impl WgpuPipelines {
                         topology: wgpu::PrimitiveTopology::TriangleStrip,
                         ..Default::default()
                     },
-                    depth_stencil: None,
+                    depth_stencil: Some(clip_stencil_state(wgpu::StencilOperation::Keep)),
                     multisample: wgpu::MultisampleState::default(),
                     fragment: Some(wgpu::FragmentState {
                         module: &shadows_shader,
@@ -1020,7 +1824,7 @@ impl WgpuPipelines {
                         topology: wgpu::PrimitiveTopology::TriangleStrip,
                         ..Default::default()
                     },
-                    depth_stencil: None,
+                    depth_stencil: Some(clip_stencil_state(wgpu::StencilOperation::Keep)),
                     multisample: wgpu::MultisampleState::default(),
                     fragment: Some(wgpu::FragmentState {
                         module: &underlines_shader,
@@ -1047,7 +1851,7 @@ impl WgpuPipelines {
                         topology: wgpu::PrimitiveTopology::TriangleStrip,
                         ..Default::default()
                     },
-                    depth_stencil: None,
+                    depth_stencil: Some(clip_stencil_state(wgpu::StencilOperation::Keep)),
                     fragment: Some(wgpu::FragmentState {
                         module: &mono_sprite_shader,
                         entry_point: Some("fs_mono_sprite"),
@@ -1074,7 +1878,7 @@ impl WgpuPipelines {
                         topology: wgpu::PrimitiveTopology::TriangleStrip,
                         ..Default::default()
                     },
-                    depth_stencil: None,
+                    depth_stencil: Some(clip_stencil_state(wgpu::StencilOperation::Keep)),
                     fragment: Some(wgpu::FragmentState {
                         module: &poly_sprite_shader,
                         entry_point: Some("fs_poly_sprite"),
@@ -1087,6 +1891,60 @@ impl WgpuPipelines {
                 },
             ),
 
+            cell_grids_pipeline: context.device.create_render_pipeline(
+                &wgpu::RenderPipelineDescriptor {
+                    label: Some("cell_grids"),
+                    layout: Some(&cell_grids_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &cell_grids_shader,
+                        entry_point: Some("vs_cell_grid"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        buffers: &[],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleStrip,
+                        ..Default::default()
+                    },
+                    depth_stencil: Some(clip_stencil_state(wgpu::StencilOperation::Keep)),
+                    fragment: Some(wgpu::FragmentState {
+                        module: &cell_grids_shader,
+                        entry_point: Some("fs_cell_grid"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        targets: color_targets,
+                    }),
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview: None,
+                    cache: None,
+                },
+            ),
+
+            selections_pipeline: context.device.create_render_pipeline(
+                &wgpu::RenderPipelineDescriptor {
+                    label: Some("selections"),
+                    layout: Some(&selections_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &selections_shader,
+                        entry_point: Some("vs_selection"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        buffers: &[],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleStrip,
+                        ..Default::default()
+                    },
+                    depth_stencil: Some(clip_stencil_state(wgpu::StencilOperation::Keep)),
+                    fragment: Some(wgpu::FragmentState {
+                        module: &selections_shader,
+                        entry_point: Some("fs_selection"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        targets: color_targets,
+                    }),
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview: None,
+                    cache: None,
+                },
+            ),
+
             surfaces_bind_group_layout,
 
             surfaces_pipeline: context.device.create_render_pipeline(
@@ -1103,7 +1961,7 @@ impl WgpuPipelines {
                         topology: wgpu::PrimitiveTopology::TriangleStrip,
                         ..Default::default()
                     },
-                    depth_stencil: None,
+                    depth_stencil: Some(clip_stencil_state(wgpu::StencilOperation::Keep)),
                     fragment: Some(wgpu::FragmentState {
                         module: &surfaces_shader,
                         entry_point: Some("fs_surface"),
@@ -1115,193 +1973,1655 @@ impl WgpuPipelines {
                     cache: None,
                 },
             ),
+
+            color_filter_bind_group_layout,
+
+            // The filter replaces every pixel of the frame, so it doesn't blend.
+            color_filter_pipeline: context.device.create_render_pipeline(
+                &wgpu::RenderPipelineDescriptor {
+                    label: Some("color_filter"),
+                    layout: Some(&color_filter_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &color_filter_shader,
+                        entry_point: Some("vs_color_filter"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        buffers: &[],
+                    },
+                    primitive: wgpu::PrimitiveState::default(),
+                    depth_stencil: None,
+                    fragment: Some(wgpu::FragmentState {
+                        module: &color_filter_shader,
+                        entry_point: Some("fs_color_filter"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format: view_format,
+                            blend: None,
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview: None,
+                    cache: None,
+                },
+            ),
+
+            clips_bind_group_layout,
+            clip_push_pipeline: create_clip_pipeline(
+                "clip_push",
+                wgpu::StencilOperation::IncrementClamp,
+            ),
+            clip_pop_pipeline: create_clip_pipeline(
+                "clip_pop",
+                wgpu::StencilOperation::DecrementClamp,
+            ),
+            globals_bind_group_layout,
         }
     }
 }
 
+/// The format of the stencil buffer the main pass draws clips into.
+const CLIP_STENCIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Stencil8;
+
+/// The format cached layers are drawn in, which is the format of the atlas textures they're
+/// copied into.
+const LAYER_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// Tests fragments against the stencil buffer clips are drawn into, passing them where it equals
+/// the stencil reference, and applies `pass_op` to the stencil where they pass.
+fn clip_stencil_state(pass_op: wgpu::StencilOperation) -> wgpu::DepthStencilState {
+    let face = wgpu::StencilFaceState {
+        compare: wgpu::CompareFunction::Equal,
+        fail_op: wgpu::StencilOperation::Keep,
+        depth_fail_op: wgpu::StencilOperation::Keep,
+        pass_op,
+    };
+    wgpu::DepthStencilState {
+        format: CLIP_STENCIL_FORMAT,
+        depth_write_enabled: false,
+        depth_compare: wgpu::CompareFunction::Always,
+        stencil: wgpu::StencilState {
+            front: face,
+            back: face,
+            read_mask: !0,
+            write_mask: !0,
+        },
+        bias: wgpu::DepthBiasState::default(),
+    }
+}
+
 struct RenderingParameters {
-    path_sample_count: u32,
     gamma_ratios: [f32; 4],
     grayscale_enhanced_contrast: f32,
+    // linear multipliers of the red, green and blue channels
+    white_point: [f32; 3],
+    dithering: bool,
+    pixel_snapping: PixelSnappingPolicy,
 }
 
 impl RenderingParameters {
-    fn from_env() -> Self {
-        use std::env;
-
-        let path_sample_count = env::var("ZED_PATH_SAMPLE_COUNT")
-            .ok()
-            .and_then(|v| v.parse().ok())
-            .unwrap_or(4);
-        let gamma = env::var("ZED_FONTS_GAMMA")
-            .ok()
-            .and_then(|v| v.parse().ok())
-            .unwrap_or(1.8_f32)
-            .clamp(1.0, 2.2);
-        let gamma_ratios = crate::platform::get_gamma_correction_ratios(gamma);
-        let grayscale_enhanced_contrast = env::var("ZED_FONTS_GRAYSCALE_ENHANCED_CONTRAST")
-            .ok()
-            .and_then(|v| v.parse().ok())
-            .unwrap_or(1.0_f32)
-            .max(0.0);
-
+    fn new(settings: &RendererSettings, night_light_temperature: Option<f32>) -> Self {
+        let temperature = match settings.white_point {
+            WhitePoint::Neutral => None,
+            WhitePoint::Temperature(temperature) => Some(temperature),
+            WhitePoint::System => night_light_temperature,
+        };
         Self {
-            path_sample_count,
-            gamma_ratios,
-            grayscale_enhanced_contrast,
+            gamma_ratios: crate::platform::get_gamma_correction_ratios(settings.fonts_gamma),
+            grayscale_enhanced_contrast: settings.grayscale_enhanced_contrast,
+            white_point: temperature.map_or([1.0; 3], white_point_multipliers),
+            dithering: settings.dithering,
+            pixel_snapping: settings.pixel_snapping,
         }
     }
 }
 
-use std::collections::HashMap;
-use std::sync::Mutex;
+/// The step between adjacent values of the color channels of a normalized integer format, or
+/// `None` for formats precise enough not to band.
+fn quantization_step(format: wgpu::TextureFormat) -> Option<f32> {
+    match format {
+        wgpu::TextureFormat::Rgb10a2Unorm => Some(1.0 / 1023.0),
+        wgpu::TextureFormat::Rgba8Unorm
+        | wgpu::TextureFormat::Rgba8UnormSrgb
+        | wgpu::TextureFormat::Bgra8Unorm
+        | wgpu::TextureFormat::Bgra8UnormSrgb => Some(1.0 / 255.0),
+        _ => None,
+    }
+}
 
-pub struct WgpuRenderer {
-    context: Arc<WgpuContext>,
-    surface: wgpu::Surface<'static>,
-    surface_configuration: wgpu::SurfaceConfiguration,
-    atlas_sampler: wgpu::Sampler,
-    surface_sampler: wgpu::Sampler,
-    surface_params_buffer: wgpu::Buffer,
-    atlas: Arc<WgpuAtlas>,
-    pipelines: WgpuPipelines,
-    rendering_parameters: RenderingParameters,
+fn create_target_texture(
+    device: &wgpu::Device,
+    surface_configuration: &wgpu::SurfaceConfiguration,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("offscreen_target"),
+        size: wgpu::Extent3d {
+            width: surface_configuration.width.max(1),
+            height: surface_configuration.height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: surface_configuration.format,
+        usage: surface_configuration.usage,
+        view_formats: &[],
+    })
+}
 
-    // cache bind groups for each double-buffered surface (index 0/1)
-    surface_bind_groups: Mutex<HashMap<crate::platform::cross::surface_registry::SurfaceId, [wgpu::BindGroup; 2]>>,
+/// The quads a [`SoftwareCursor`] is drawn with.
+const SOFTWARE_CURSOR_QUAD_COUNT: usize = 2;
+
+/// Returns the quads that draw `cursor`, which are drawn over everything else.
+fn software_cursor_quads(cursor: SoftwareCursor) -> [Quad; SOFTWARE_CURSOR_QUAD_COUNT] {
+    // The cursor is a white ring inside a black one, so it shows on any background.
+    [(16.0, color::black().opacity(0.6)), (12.0, color::white())].map(|(diameter, border_color)| {
+        let diameter = ScaledPixels(diameter * cursor.scale_factor);
+        let bounds =
+            geometry::Bounds::centered_at(cursor.position, geometry::size(diameter, diameter));
+        Quad {
+            order: DrawOrder::MAX,
+            border_style: BorderStyle::default(),
+            bounds,
+            content_mask: ContentMask { bounds },
+            background: color::transparent_black().into(),
+            border_color,
+            corner_radii: geometry::Corners::all(diameter.half()),
+            border_widths: geometry::Edges::all(ScaledPixels(2.0 * cursor.scale_factor)),
+            clip: 0,
+            snapping: PixelSnapping::default(),
+        }
+    })
 }
 
-impl WgpuRenderer {
-    pub fn new<WindowHandle>(
-        context: Arc<WgpuContext>,
-        window: WindowHandle,
-        atlas: Arc<WgpuAtlas>,
-        width: u32,
-        height: u32,
-        path_sample_count: u32,
-    ) -> anyhow::Result<Self>
-    where
-        WindowHandle: raw_window_handle::HasWindowHandle + raw_window_handle::HasDisplayHandle,
-    {
-        let surface = unsafe {
-            context
-                .instance
-                .create_surface_unsafe(wgpu::SurfaceTargetUnsafe::RawHandle {
-                    raw_display_handle: window.display_handle()?.as_raw(),
-                    raw_window_handle: window.window_handle()?.as_raw(),
-                })?
-        };
+/// Fails unless `viewport` is non-empty and lies within `texture`, as render pass viewports must.
+fn ensure_viewport_fits(
+    viewport: geometry::Bounds<DevicePixels>,
+    target_size: wgpu::Extent3d,
+) -> anyhow::Result<()> {
+    let fits = viewport.origin.x.0 >= 0
+        && viewport.origin.y.0 >= 0
+        && viewport.size.width.0 > 0
+        && viewport.size.height.0 > 0
+        && (viewport.origin.x.0 + viewport.size.width.0) as u32 <= target_size.width
+        && (viewport.origin.y.0 + viewport.size.height.0) as u32 <= target_size.height;
+    anyhow::ensure!(
+        fits,
+        "the viewport {viewport:?} doesn't fit in the {}x{} target",
+        target_size.width,
+        target_size.height
+    );
+    Ok(())
+}
 
-        let surface_capabilities = surface.get_capabilities(&context.adapter);
+/// The formats a texture of `format` can also be viewed as: its sRGB and UNORM counterparts.
+fn compatible_view_formats(format: wgpu::TextureFormat) -> Vec<wgpu::TextureFormat> {
+    let mut view_formats = Vec::new();
+    for view_format in [format.add_srgb_suffix(), format.remove_srgb_suffix()] {
+        if view_format != format && !view_formats.contains(&view_format) {
+            view_formats.push(view_format);
+        }
+    }
+    view_formats
+}
 
-        // NOTE(mdeand): The shaders (hsla_to_rgba) output sRGB values directly, so we need a
-        // NOTE(mdeand): non-sRGB surface format to avoid a double linear-to-sRGB conversion.
-        // NOTE(mdeand): Prefer a non-sRGB format; fall back to whatever is available.
-        let format = surface_capabilities
-            .formats
-            .iter()
-            .find(|f| !f.is_srgb())
-            .copied()
-            .unwrap_or(surface_capabilities.formats[0]);
+fn srgb_to_linear(component: f32) -> f32 {
+    if component <= 0.04045 {
+        component / 12.92
+    } else {
+        ((component + 0.055) / 1.055).powf(2.4)
+    }
+}
 
-        let alpha_mode = if surface_capabilities
-            .alpha_modes
-            .contains(&wgpu::CompositeAlphaMode::PreMultiplied)
-        {
-            wgpu::CompositeAlphaMode::PreMultiplied
+fn linear_to_srgb(component: f32) -> f32 {
+    if component <= 0.0031308 {
+        component * 12.92
+    } else {
+        1.055 * component.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// The linear multipliers that shift white to the color of a black body at `temperature` kelvin,
+/// relative to daylight at 6500K. Follows Tanner Helland's fit of the black body colors.
+fn white_point_multipliers(temperature: f32) -> [f32; 3] {
+    fn black_body_color(temperature: f32) -> [f32; 3] {
+        let temperature = temperature.clamp(1000.0, 40000.0) / 100.0;
+        let red = if temperature <= 66.0 {
+            255.0
         } else {
-            surface_capabilities.alpha_modes[0]
+            329.69873 * (temperature - 60.0).powf(-0.13320476)
+        };
+        let green = if temperature <= 66.0 {
+            99.4708 * temperature.ln() - 161.11957
+        } else {
+            288.12216 * (temperature - 60.0).powf(-0.075514846)
+        };
+        let blue = if temperature >= 66.0 {
+            255.0
+        } else if temperature <= 19.0 {
+            0.0
+        } else {
+            138.51773 * (temperature - 10.0).ln() - 305.0448
         };
+        [red, green, blue].map(|channel| srgb_to_linear((channel / 255.0).clamp(0.0, 1.0)))
+    }
 
-        // allow overriding vsync behaviour.  The default is `Fifo` (vsync
-        // enabled) which is what `wgpu` considers the safest presentation mode.
-        // Setting `GPUI_DISABLE_VSYNC=1` in the environment will switch to
-        // `Immediate`, which drops frames at the display's full rate.  A more
-        // fine‑grained control (`GPUI_PRESENT_MODE=mailbox|fifo|immediate`) is
-        // also supported for experimentation.
-        let present_mode = std::env::var("GPUI_PRESENT_MODE")
-            .ok()
-            .and_then(|s| match s.to_lowercase().as_str() {
-                "mailbox" => Some(wgpu::PresentMode::Mailbox),
-                "immediate" => Some(wgpu::PresentMode::Immediate),
-                "fifo" => Some(wgpu::PresentMode::Fifo),
-                _ => None,
-            })
-            .unwrap_or_else(|| {
-                if std::env::var("GPUI_DISABLE_VSYNC").is_ok() {
-                    wgpu::PresentMode::Immediate
-                } else {
-                    wgpu::PresentMode::Fifo
-                }
-            });
+    let color = black_body_color(temperature);
+    let daylight = black_body_color(6500.0);
+    [0, 1, 2].map(|channel| (color[channel] / daylight[channel]).min(1.0))
+}
 
-        let surface_configuration = wgpu::SurfaceConfiguration {
+/// The sample count paths are drawn with. Paths aren't multisampled in eco mode.
+fn path_sample_count(context: &WgpuContext) -> u32 {
+    if context.renderer_settings.eco_mode_active() {
+        1
+    } else {
+        context.renderer_settings.get().path_sample_count
+    }
+}
+
+/// Returns the wgpu present mode for `present_mode`, falling back to `Fifo`, which every surface
+/// supports.
+fn supported_present_mode(
+    present_mode: PresentMode,
+    capabilities: &wgpu::SurfaceCapabilities,
+) -> wgpu::PresentMode {
+    let present_mode = match present_mode {
+        PresentMode::Fifo => wgpu::PresentMode::Fifo,
+        PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+        PresentMode::Immediate => wgpu::PresentMode::Immediate,
+    };
+    if capabilities.present_modes.contains(&present_mode) {
+        present_mode
+    } else {
+        wgpu::PresentMode::Fifo
+    }
+}
+
+const INSTANCE_BELT_CHUNK_SIZE: u64 = 1024 * 1024;
+const INITIAL_INSTANCE_CAPACITY: u64 = 1024 * 1024;
+/// The most buffers the [`InstanceBelt`] grows to while the GPU is still reading all of them, and
+/// the number of regions of each [`UniformSlab`].
+const MAX_INSTANCE_BUFFERS: u32 = 8;
+
+/// Per-frame storage for the primitive instances of a scene.
+///
+/// Every primitive type gets its own sub-range of a single storage buffer, which is bound
+/// through a dynamic offset. The data is written through a `StagingBelt`, whose mapped
+/// chunks are recalled and reused once the GPU is done with them rather than allocating a
+/// fresh staging copy for every `write_buffer` call.
+///
+/// There's a storage buffer for each frame that can be in flight. They're used in turn, and a
+/// buffer is only written again once the GPU has finished the submission that last read it. A
+/// frame can submit several times, for cached layers, other viewports or recomposited surfaces,
+/// so instead of waiting for the next buffer, the belt picks one the GPU is done with, adding
+/// buffers up to [`MAX_INSTANCE_BUFFERS`] while they're all in use.
+struct InstanceBelt {
+    // Locked so instances can be written on another thread while the main pass is encoded against
+    // the same belt.
+    staging_belt: parking_lot::Mutex<wgpu::util::StagingBelt>,
+    frames: Vec<InstanceBuffer>,
+    // the index of the buffer the current frame writes to
+    current_frame: usize,
+    alignment: u64,
+    cursor: u64,
+    memory: Arc<GpuMemoryTracker>,
+}
+
+/// The storage buffer of one frame in flight.
+struct InstanceBuffer {
+    buffer: wgpu::Buffer,
+    bind_groups: InstanceBindGroups,
+    capacity: u64,
+    // the last submission that read the buffer, which has to finish before it's written again,
+    // and a flag set once it has
+    submission: Option<(wgpu::SubmissionIndex, Arc<AtomicBool>)>,
+}
+
+impl InstanceBuffer {
+    fn new(device: &wgpu::Device, pipelines: &WgpuPipelines, capacity: u64) -> Self {
+        let (buffer, bind_groups) = InstanceBelt::create_buffer(device, pipelines, capacity);
+        Self {
+            buffer,
+            bind_groups,
+            capacity,
+            submission: None,
+        }
+    }
+
+    /// Whether the GPU may still be reading the buffer.
+    fn in_use(&self) -> bool {
+        self.submission
+            .as_ref()
+            .is_some_and(|(_, finished)| !finished.load(Ordering::Acquire))
+    }
+}
+
+struct InstanceBindGroups {
+    quads: wgpu::BindGroup,
+    shadows: wgpu::BindGroup,
+    underlines: wgpu::BindGroup,
+    mono_sprites: wgpu::BindGroup,
+    poly_sprites: wgpu::BindGroup,
+    cell_grids: wgpu::BindGroup,
+    selections: wgpu::BindGroup,
+    clips: wgpu::BindGroup,
+}
+
+impl InstanceBelt {
+    /// Creates a belt with a buffer for each of `frames_in_flight` frames.
+    fn new(
+        device: &wgpu::Device,
+        pipelines: &WgpuPipelines,
+        memory: Arc<GpuMemoryTracker>,
+        frames_in_flight: u32,
+    ) -> Self {
+        let capacity = INITIAL_INSTANCE_CAPACITY.min(Self::max_capacity(device));
+        let frames = (0..frames_in_flight.clamp(1, MAX_INSTANCE_BUFFERS))
+            .map(|_| {
+                memory.allocate(GpuMemoryCategory::SceneBuffers, capacity * 2);
+                InstanceBuffer::new(device, pipelines, capacity)
+            })
+            .collect();
+
+        Self {
+            staging_belt: parking_lot::Mutex::new(wgpu::util::StagingBelt::new(
+                INSTANCE_BELT_CHUNK_SIZE,
+            )),
+            frames,
+            current_frame: 0,
+            alignment: device.limits().min_storage_buffer_offset_alignment as u64,
+            cursor: 0,
+            memory,
+        }
+    }
+
+    fn frame(&self) -> &InstanceBuffer {
+        &self.frames[self.current_frame]
+    }
+
+    /// The bind groups of the buffer the current frame writes to.
+    fn bind_groups(&self) -> &InstanceBindGroups {
+        &self.frame().bind_groups
+    }
+
+    // Bindings are a fixed `capacity` bytes wide, so the buffer is twice that to keep the window in
+    // bounds behind any offset handed out during a frame.
+    fn create_buffer(
+        device: &wgpu::Device,
+        pipelines: &WgpuPipelines,
+        capacity: u64,
+    ) -> (wgpu::Buffer, InstanceBindGroups) {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Instances Buffer"),
+            size: capacity * 2,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let buffer_entry = |binding: u32| wgpu::BindGroupEntry {
+            binding,
+            resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                buffer: &buffer,
+                offset: 0,
+                size: wgpu::BufferSize::new(capacity),
+            }),
+        };
+        let create_bind_group = |label: &str, layout: &wgpu::BindGroupLayout| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(label),
+                layout,
+                entries: &[buffer_entry(0)],
+            })
+        };
+
+        let bind_groups = InstanceBindGroups {
+            quads: create_bind_group("quads_bind_group", &pipelines.quads_bind_group_layout),
+            shadows: create_bind_group("shadows_bind_group", &pipelines.shadows_bind_group_layout),
+            underlines: create_bind_group(
+                "underlines_bind_group",
+                &pipelines.underlines_bind_group_layout,
+            ),
+            mono_sprites: create_bind_group(
+                "mono_sprites_bind_group",
+                &pipelines.mono_sprites_bind_group_layout,
+            ),
+            poly_sprites: create_bind_group(
+                "poly_sprites_bind_group",
+                &pipelines.poly_sprites_bind_group_layout,
+            ),
+            cell_grids: device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("cell_grids_bind_group"),
+                layout: &pipelines.cell_grids_bind_group_layout,
+                entries: &[buffer_entry(0), buffer_entry(1), buffer_entry(2)],
+            }),
+            selections: device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("selections_bind_group"),
+                layout: &pipelines.selections_bind_group_layout,
+                entries: &[buffer_entry(0), buffer_entry(1)],
+            }),
+            clips: create_bind_group("clips_bind_group", &pipelines.clips_bind_group_layout),
+        };
+
+        (buffer, bind_groups)
+    }
+
+    fn aligned_size<G>(&self, count: usize) -> u64 {
+        ((count * std::mem::size_of::<G>()) as u64).next_multiple_of(self.alignment)
+    }
+
+    /// The largest capacity a buffer can have, which both its bindings and the buffer itself,
+    /// twice as large, have to stay within the device's limits.
+    fn max_capacity(device: &wgpu::Device) -> u64 {
+        let limits = device.limits();
+        let max_capacity =
+            (limits.max_storage_buffer_binding_size as u64).min(limits.max_buffer_size / 2);
+        max_capacity - max_capacity % wgpu::COPY_BUFFER_ALIGNMENT
+    }
+
+    /// Resets the belt for a new frame, moving to a buffer the GPU is done with, and growing the
+    /// buffer if `required` bytes don't fit. Returns false if they don't fit in the largest buffer
+    /// the device allows either.
+    fn begin_frame(
+        &mut self,
+        device: &wgpu::Device,
+        pipelines: &WgpuPipelines,
+        required: u64,
+    ) -> bool {
+        self.cursor = 0;
+
+        if self.frames[self.current_frame].in_use() {
+            if let Err(error) = device.poll(wgpu::PollType::Poll) {
+                log::error!("failed to poll the device: {error}");
+            }
+            let buffer_count = self.frames.len();
+            let idle_frame = (0..buffer_count)
+                .map(|index| (self.current_frame + index) % buffer_count)
+                .find(|&index| !self.frames[index].in_use());
+            if let Some(index) = idle_frame {
+                self.current_frame = index;
+            } else if buffer_count < MAX_INSTANCE_BUFFERS as usize {
+                let capacity = self.frames[self.current_frame].capacity;
+                self.memory
+                    .allocate(GpuMemoryCategory::SceneBuffers, capacity * 2);
+                self.frames
+                    .push(InstanceBuffer::new(device, pipelines, capacity));
+                self.current_frame = buffer_count;
+            }
+        }
+
+        let frame = &mut self.frames[self.current_frame];
+        if let Some((submission, finished)) = frame.submission.take() {
+            if !finished.load(Ordering::Acquire) {
+                profiling::scope!("wait for frame in flight");
+                if let Err(error) = device.poll(wgpu::PollType::WaitForSubmissionIndex(submission))
+                {
+                    log::error!("failed to wait for a frame in flight: {error}");
+                }
+            }
+        }
+
+        if required <= frame.capacity {
+            return true;
+        }
+
+        let max_capacity = Self::max_capacity(device);
+        let capacity = required.next_power_of_two().min(max_capacity);
+        if capacity <= frame.capacity {
+            return false;
+        }
+
+        let (buffer, bind_groups) = Self::create_buffer(device, pipelines, capacity);
+        self.memory
+            .free(GpuMemoryCategory::SceneBuffers, frame.capacity * 2);
+        self.memory
+            .allocate(GpuMemoryCategory::SceneBuffers, capacity * 2);
+        frame.buffer = buffer;
+        frame.bind_groups = bind_groups;
+        frame.capacity = capacity;
+        required <= capacity
+    }
+
+    /// Reserves the next free range of the buffer for `count` instances and returns its dynamic
+    /// offset, or `None` if the frame has run out of space.
+    fn reserve<G>(&mut self, count: usize) -> Option<u32> {
+        let offset = self.cursor;
+        if offset + (count * std::mem::size_of::<G>()) as u64 > self.frame().capacity {
+            log::error!("dropping {count} primitives that don't fit in the instance buffer");
+            return None;
+        }
+
+        self.cursor = offset + self.aligned_size::<G>(count);
+        Some(offset as u32)
+    }
+
+    /// Copies `primitives` into the range reserved for them at `offset`.
+    fn write<P, G>(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        device: &wgpu::Device,
+        offset: u32,
+        primitives: &[P],
+    ) where
+        G: bytemuck::Pod + for<'a> From<&'a P>,
+    {
+        self.write_with(encoder, device, offset, primitives, G::from);
+    }
+
+    /// Like [`InstanceBelt::write`], converting each primitive with `convert`.
+    fn write_with<P, G: bytemuck::Pod>(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        device: &wgpu::Device,
+        offset: u32,
+        primitives: &[P],
+        convert: impl Fn(&P) -> G,
+    ) {
+        let Some(size) =
+            wgpu::BufferSize::new((primitives.len() * std::mem::size_of::<G>()) as u64)
+        else {
+            return;
+        };
+
+        let mut staging_belt = self.staging_belt.lock();
+        let mut view =
+            staging_belt.write_buffer(encoder, &self.frame().buffer, offset as u64, size, device);
+        for (bytes, primitive) in view
+            .chunks_exact_mut(std::mem::size_of::<G>())
+            .zip(primitives)
+        {
+            bytes.copy_from_slice(bytemuck::bytes_of(&convert(primitive)));
+        }
+    }
+
+    fn finish(&self) {
+        self.staging_belt.lock().finish();
+    }
+
+    /// Records that `submission` reads the current frame's buffer, recalls the staging chunks,
+    /// and moves on to the next buffer.
+    fn recall(&mut self, queue: &wgpu::Queue, submission: wgpu::SubmissionIndex) {
+        let finished = Arc::new(AtomicBool::new(false));
+        queue.on_submitted_work_done({
+            let finished = finished.clone();
+            move || finished.store(true, Ordering::Release)
+        });
+        self.frames[self.current_frame].submission = Some((submission, finished));
+        self.current_frame = (self.current_frame + 1) % self.frames.len();
+        self.staging_belt.lock().recall();
+    }
+}
+
+/// The uniforms of a frame's draw calls, with a region of slots for each of the [`InstanceBelt`]'s
+/// buffers. A frame uses the region of the buffer it writes its instances to, so the belt's check
+/// that the GPU is done with a buffer covers its region too, and each draw call binds its slot
+/// through a dynamic offset.
+struct UniformSlab<T> {
+    buffer: wgpu::Buffer,
+    label: &'static str,
+    regions: u32,
+    // the distance between slots, the size of `T` rounded up to the uniform offset alignment
+    stride: u64,
+    slots_per_frame: u64,
+    // the next free slot of the current frame's region, and the end of the region, in bytes
+    cursor: u64,
+    end: u64,
+    uniform: std::marker::PhantomData<T>,
+}
+
+impl<T: bytemuck::Pod> UniformSlab<T> {
+    /// Creates a slab with `regions` regions of `slots_per_frame` slots.
+    fn new(device: &wgpu::Device, label: &'static str, regions: u32, slots_per_frame: u64) -> Self {
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as u64;
+        let stride = (size_of::<T>() as u64).next_multiple_of(alignment);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: stride * slots_per_frame * regions.max(1) as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            buffer,
+            label,
+            regions,
+            stride,
+            slots_per_frame,
+            cursor: 0,
+            end: stride * slots_per_frame,
+            uniform: std::marker::PhantomData,
+        }
+    }
+
+    /// The binding of the first slot, which draw calls move to theirs with a dynamic offset.
+    fn binding(&self) -> wgpu::BindingResource<'_> {
+        wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+            buffer: &self.buffer,
+            offset: 0,
+            size: wgpu::BufferSize::new(size_of::<T>() as u64),
+        })
+    }
+
+    /// Replaces the buffer with a bigger one when a frame needs more than `slots_per_frame` slots,
+    /// returning whether it did, in which case bind groups created from [`Self::binding`] are
+    /// stale. Frames in flight keep reading the old buffer, so it's replaced without waiting for
+    /// them.
+    fn grow(&mut self, device: &wgpu::Device, slots_per_frame: u64) -> bool {
+        if slots_per_frame <= self.slots_per_frame {
+            return false;
+        }
+        *self = Self::new(
+            device,
+            self.label,
+            self.regions,
+            slots_per_frame.next_power_of_two(),
+        );
+        true
+    }
+
+    /// Starts handing out the slots of `frame`, the index of the [`InstanceBelt`]'s buffer the
+    /// frame writes to.
+    fn begin_frame(&mut self, frame: usize) {
+        let region_size = self.stride * self.slots_per_frame;
+        self.cursor = frame as u64 * region_size;
+        self.end = self.cursor + region_size;
+    }
+
+    /// Reserves `count` consecutive slots and returns the dynamic offset of the first, or `None`
+    /// if the frame has run out of slots.
+    fn reserve(&mut self, count: usize) -> Option<u32> {
+        let size = count as u64 * self.stride;
+        if self.cursor + size > self.end {
+            log::error!("dropping {count} draw calls whose uniforms don't fit in their frame");
+            return None;
+        }
+
+        let offset = self.cursor;
+        self.cursor += size;
+        Some(offset as u32)
+    }
+
+    /// The dynamic offset of the slot `index` slots after the one at `offset`.
+    fn slot(&self, offset: u32, index: usize) -> u32 {
+        offset + (index as u64 * self.stride) as u32
+    }
+
+    /// Writes `uniform` into the slot at `offset`.
+    fn write(&self, queue: &wgpu::Queue, offset: u32, uniform: &T) {
+        queue.write_buffer(&self.buffer, offset as u64, bytemuck::bytes_of(uniform));
+    }
+}
+
+/// The surfaces a frame has room for at first. The parameters of each are written into a slot of
+/// their own, and the slots are grown for frames that draw more surfaces.
+const MAX_SURFACES_PER_FRAME: u64 = 64;
+
+/// The dynamic offsets reserved for each primitive type in the [`InstanceBelt`] this frame.
+struct InstanceOffsets {
+    // the slot of the frame's globals
+    globals: u32,
+    // the first of the slots of the frame's surfaces' parameters
+    surface_params: Option<u32>,
+    quads: Option<u32>,
+    shadows: Option<u32>,
+    underlines: Option<u32>,
+    mono_sprites: Option<u32>,
+    poly_sprites: Option<u32>,
+    // the offsets of each cell grid's parameters, glyph table and cells, in draw order
+    cell_grids: Vec<Option<[u32; 3]>>,
+    // the offsets of the selections and of their lines
+    selections: Option<[u32; 2]>,
+    clips: Option<u32>,
+    // the quads of the software cursor, when the frame draws it
+    software_cursor: Option<u32>,
+}
+
+/// The view the main pass draws into.
+struct MainPassTarget<'a> {
+    view: &'a wgpu::TextureView,
+    // the region the scene is stretched over, the whole view if None
+    viewport: Option<geometry::Bounds<DevicePixels>>,
+    // the color the view is cleared to first, None when an earlier pass already drew into it
+    clear_color: Option<wgpu::Color>,
+    // the region of the view drawn into, without moving the scene, the whole view if None
+    scissor: Option<geometry::Bounds<DevicePixels>>,
+    // the stencil buffer clips are drawn into, the same size as the view
+    clip_stencil: &'a wgpu::TextureView,
+    // the pipelines for the view's format
+    pipelines: &'a WgpuPipelines,
+}
+
+/// A frame drawn and submitted by [`WgpuRenderer::prepare`], waiting to be presented.
+pub struct PreparedFrame {
+    // None when the frame was drawn into an offscreen texture
+    surface_texture: Option<wgpu::SurfaceTexture>,
+    seen_surfaces: CompositedSurfaces,
+    // when the frame started drawing
+    started_at: std::time::Instant,
+    // the time spent drawing the frame so far, including any wait for the swapchain
+    draw_time: std::time::Duration,
+    draw_call_count: usize,
+    instance_bytes: u64,
+}
+
+/// The main pass of a frame, encoded by [`WgpuRenderer::encode_main_pass`].
+struct EncodedMainPass {
+    commands: wgpu::CommandBuffer,
+    draw_calls: Option<Vec<DrawCall>>,
+    seen_surfaces: CompositedSurfaces,
+}
+
+/// Scenes with fewer primitives than this are encoded on the calling thread, where handing the
+/// uploads to the background executor would cost more than it saves.
+const PARALLEL_ENCODING_THRESHOLD: usize = 4096;
+
+impl Drop for InstanceBelt {
+    fn drop(&mut self) {
+        for frame in &self.frames {
+            self.memory
+                .free(GpuMemoryCategory::SceneBuffers, frame.capacity * 2);
+        }
+    }
+}
+
+/// The byte range of the instance buffer read by a draw of `count` instances starting at
+/// `first_instance`, for a binding placed at `offset`.
+fn instance_range<G>(offset: u32, first_instance: u32, count: u32) -> std::ops::Range<u64> {
+    let instance_size = std::mem::size_of::<G>() as u64;
+    let start = offset as u64 + first_instance as u64 * instance_size;
+    start..start + count as u64 * instance_size
+}
+
+/// Where a surface frame of `frame_size` device pixels is drawn within `bounds`: at its own size,
+/// scaled down to fit if it's larger, and centered.
+fn letterboxed_bounds(bounds: &crate::Bounds<ScaledPixels>, frame_size: (u32, u32)) -> Bounds {
+    let (frame_width, frame_height) = (frame_size.0.max(1) as f32, frame_size.1.max(1) as f32);
+    let scale = (bounds.size.width.0 / frame_width)
+        .min(bounds.size.height.0 / frame_height)
+        .min(1.0);
+    let size = [frame_width * scale, frame_height * scale];
+    Bounds {
+        origin: [
+            bounds.origin.x.0 + (bounds.size.width.0 - size[0]) / 2.0,
+            bounds.origin.y.0 + (bounds.size.height.0 - size[1]) / 2.0,
+        ],
+        size,
+    }
+}
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// The surfaces composited into a frame, each once. Few frames show more than a handful, so they
+/// usually fit inline.
+type CompositedSurfaces = SmallVec<[SurfaceId; 8]>;
+
+/// The clips a batch is nested in, outermost first.
+type ClipStack = SmallVec<[ClipId; 8]>;
+
+/// The bind groups of the two buffers of a surface, cached across frames.
+struct SurfaceBindGroups {
+    // the generation of the surface's textures the bind groups were created for
+    generation: u64,
+    bind_groups: [wgpu::BindGroup; 2],
+    // the last frame the surface was composited into, see `WgpuRenderer::frame_serial`
+    composited_frame: u64,
+}
+
+/// A cursor drawn by the renderer in place of the platform's, see
+/// [`WgpuRenderer::set_software_cursor`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SoftwareCursor {
+    /// Where the cursor points, in the frame's device pixels.
+    pub position: geometry::Point<ScaledPixels>,
+    /// The scale factor of the window, which the cursor is sized by.
+    pub scale_factor: f32,
+}
+
+/// Where a renderer draws its frames.
+enum RenderTarget {
+    /// A window's surface, presented after every frame.
+    Surface(wgpu::Surface<'static>),
+    /// A texture that's drawn into without ever being presented, for headless windows.
+    Texture(wgpu::Texture),
+    /// A window's surface, released when the device was lost and a renderer on the new device
+    /// couldn't be created. See [`WgpuRenderer::recreate`].
+    Lost,
+    /// A window's surface, released while the app is suspended. See [`WgpuRenderer::suspend`].
+    Suspended,
+}
+
+pub struct WgpuRenderer {
+    context: Arc<WgpuContext>,
+    target: RenderTarget,
+    surface_configuration: wgpu::SurfaceConfiguration,
+    // the format frames are rendered in, either the surface's or one of its view formats
+    view_format: wgpu::TextureFormat,
+    // the samplers and sprite bind groups reused across frames
+    resource_pool: ResourcePool,
+    globals: UniformSlab<GlobalParams>,
+    surface_params: UniformSlab<SurfaceParams>,
+    // bound in place of the atlas for cell grids whose glyphs don't draw anything
+    blank_glyph_texture: wgpu::TextureView,
+    atlas: Arc<WgpuAtlas>,
+    pipelines: WgpuPipelines,
+    instance_belt: InstanceBelt,
+    rendering_parameters: RenderingParameters,
+    draw_call_recording: bool,
+    last_frame_draw_calls: Option<FrameDrawCalls>,
+    frame_recorder: Option<FrameRecorder>,
+    // collects the cost of each frame while frame statistics are logged
+    frame_stats: Option<FrameStatsLogger>,
+    frame_pacer: FramePacer,
+    transparent: bool,
+    clear_color: Option<Hsla>,
+    screen_zoom: Option<ScreenZoom>,
+    software_cursor: Option<SoftwareCursor>,
+    color_filter: Option<ColorFilter>,
+    color_filter_params_buffer: wgpu::Buffer,
+    // the texture frames are drawn into while a color filter is set, and the bind group the filter
+    // reads it through
+    unfiltered_frame: Option<(wgpu::Texture, wgpu::BindGroup)>,
+    clip_stencil: Option<wgpu::Texture>,
+    // the pipelines cached layers are drawn with, created when the first one is drawn
+    layer_pipelines: Option<WgpuPipelines>,
+    // the texture cached layers are drawn into before they're copied into the atlas, and its
+    // stencil buffer, grown to fit the largest layer
+    layer_target: Option<(wgpu::Texture, wgpu::Texture)>,
+    // called right before each frame is presented, letting the windowing system schedule it
+    pre_present_notify: Option<Box<dyn Fn() + Send + Sync>>,
+    // the last frame's scene while it showed a low latency surface, which is redrawn in the
+    // surface's region when the surface presents
+    low_latency_scene: Option<Scene>,
+    // set once memory pressure has been relieved, so a budget that the renderer can't get under
+    // doesn't evict the atlas on every frame
+    memory_pressure_handled: bool,
+    needs_full_redraw: bool,
+    // the alpha mode the surface switches to right before the next frame is drawn
+    pending_alpha_mode: Option<wgpu::CompositeAlphaMode>,
+
+    // cache bind groups for each double-buffered surface (index 0/1)
+    // keyed by the surface's generation, which changes whenever its textures are recreated
+    surface_bind_groups: Mutex<HashMap<SurfaceId, SurfaceBindGroups>>,
+    // counts the frames drawn by `prepare`, so the bind groups of surfaces that weren't
+    // composited into the last one can be dropped without searching its surfaces
+    frame_serial: u64,
+    // the pipelines of the render pipeline plugins, created for each color target the first time
+    // one of a plugin's primitives is drawn into it
+    plugin_pipelines:
+        parking_lot::Mutex<FxHashMap<(TypeId, wgpu::ColorTargetState), Box<dyn PluginPipelines>>>,
+}
+
+impl WgpuRenderer {
+    /// Creates a renderer that presents to `window`. The surface holds on to the window, so the
+    /// window isn't destroyed before the surface, even when it's closed while a render thread is
+    /// still drawing into it.
+    pub fn new(
+        context: Arc<WgpuContext>,
+        window: Arc<winit::window::Window>,
+        atlas: Arc<WgpuAtlas>,
+        width: u32,
+        height: u32,
+        surface_format: &SurfaceFormatPreference,
+    ) -> anyhow::Result<Self> {
+        let surface = context.instance.create_surface(window)?;
+
+        let surface_capabilities = surface.get_capabilities(&context.adapter);
+
+        // The shaders (hsla_to_rgba) output sRGB values directly. On an sRGB surface they're
+        // decoded first to avoid a double linear-to-sRGB conversion.
+        let format = surface_format
+            .select(&surface_capabilities.formats)
+            .with_context(|| {
+                format!(
+                    "the window's surface supports none of the allowed formats {:?}, only {:?}",
+                    surface_format.allowed_formats, surface_capabilities.formats
+                )
+            })?;
+
+        let alpha_mode = if surface_capabilities
+            .alpha_modes
+            .contains(&wgpu::CompositeAlphaMode::PreMultiplied)
+        {
+            wgpu::CompositeAlphaMode::PreMultiplied
+        } else {
+            surface_capabilities.alpha_modes[0]
+        };
+
+        let present_mode = supported_present_mode(
+            context.renderer_settings.get().present_mode,
+            &surface_capabilities,
+        );
+
+        // Surfaces can be viewed in their sRGB or UNORM counterpart when the backend supports it,
+        // so frames can be rendered in either encoding.
+        let view_formats = if context
+            .adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::SURFACE_VIEW_FORMATS)
+        {
+            compatible_view_formats(format)
+        } else {
+            Vec::new()
+        };
+        let view_format = match surface_format.view_format {
+            Some(view_format) if view_format == format || view_formats.contains(&view_format) => {
+                view_format
+            }
+            Some(view_format) => {
+                log::warn!("can't view a {format:?} surface as {view_format:?}, using {format:?}");
+                format
+            }
+            None => format,
+        };
+
+        let surface_configuration = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format,
             width,
             height,
             present_mode,
             alpha_mode,
-            view_formats: vec![],
+            view_formats,
             // TODO(mdeand): Make this configurable?
             desired_maximum_frame_latency: 2,
         };
 
-        let atlas_sampler = context.device.create_sampler(&wgpu::SamplerDescriptor {
-            label: Some("atlas_sampler"),
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            ..Default::default()
-        });
+        Ok(Self::with_target(
+            context,
+            atlas,
+            RenderTarget::Surface(surface),
+            surface_configuration,
+            view_format,
+        ))
+    }
 
-        let surface_sampler = context.device.create_sampler(&wgpu::SamplerDescriptor {
-            label: Some("surface_sampler"),
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            ..Default::default()
-        });
+    /// Moves the renderer onto `context`'s device after the device it was created on was lost.
+    /// Everything on the GPU is created again, while the options set on the renderer, such as its
+    /// clear color and color filter, carry over. If that fails, frames fail with
+    /// [`RendererError::DeviceLost`] until the renderer is recreated again.
+    pub fn recreate(
+        &mut self,
+        context: Arc<WgpuContext>,
+        window: Arc<winit::window::Window>,
+        surface_format: &SurfaceFormatPreference,
+    ) -> anyhow::Result<()> {
+        // The window's old surface is released first, since some backends refuse to create a second
+        // one for the same window.
+        let old_target = std::mem::replace(&mut self.target, RenderTarget::Lost);
+        drop(old_target);
+        let mut renderer = Self::new(
+            context,
+            window,
+            self.atlas.clone(),
+            self.surface_configuration.width,
+            self.surface_configuration.height,
+            surface_format,
+        )?;
+        renderer.set_draw_call_recording(self.draw_call_recording);
+        renderer.update_transparency(self.transparent);
+        renderer.set_clear_color(self.clear_color);
+        renderer.set_screen_zoom(self.screen_zoom);
+        renderer.set_software_cursor(self.software_cursor);
+        renderer.set_color_filter(self.color_filter);
+        renderer.pre_present_notify = self.pre_present_notify.take();
+        renderer.frame_pacer = std::mem::replace(&mut self.frame_pacer, FramePacer::new());
+        renderer.needs_full_redraw = true;
+        *self = renderer;
+        Ok(())
+    }
 
-        let surface_params_buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Surface Params Buffer"),
-            size: std::mem::size_of::<SurfaceParams>() as u64,
+    /// Releases the window's surface, which platforms such as Android destroy while the app is
+    /// suspended. Frames fail with [`RendererError::Suspended`] until [`WgpuRenderer::resume`]
+    /// creates it again, while the atlas and everything else on the GPU are kept.
+    pub fn suspend(&mut self) {
+        if let RenderTarget::Surface(_) = &self.target {
+            self.target = RenderTarget::Suspended;
+        }
+    }
+
+    /// Creates the window's surface again after [`WgpuRenderer::suspend`], configured like the
+    /// one it replaces.
+    pub fn resume(&mut self, window: Arc<winit::window::Window>) -> anyhow::Result<()> {
+        if !matches!(self.target, RenderTarget::Suspended) {
+            return Ok(());
+        }
+        let surface = self.context.instance.create_surface(window)?;
+        // The pipelines were created for the old surface's format, so the new one has to support it
+        // too.
+        let format = self.surface_configuration.format;
+        if !surface
+            .get_capabilities(&self.context.adapter)
+            .formats
+            .contains(&format)
+        {
+            anyhow::bail!("the window's new surface doesn't support {format:?}");
+        }
+        self.target = RenderTarget::Surface(surface);
+        self.reconfigure_surface();
+        Ok(())
+    }
+
+    /// Creates a renderer that draws into a texture instead of a window's surface, so frames can
+    /// be rendered and read back with [`WgpuRenderer::capture_frame`] without a display server.
+    pub fn new_offscreen(
+        context: Arc<WgpuContext>,
+        atlas: Arc<WgpuAtlas>,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let surface_configuration = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: wgpu::TextureFormat::Bgra8Unorm,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::PreMultiplied,
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+        let texture = create_target_texture(&context.device, &surface_configuration);
+        let view_format = surface_configuration.format;
+
+        Self::with_target(
+            context,
+            atlas,
+            RenderTarget::Texture(texture),
+            surface_configuration,
+            view_format,
+        )
+    }
+
+    fn with_target(
+        context: Arc<WgpuContext>,
+        atlas: Arc<WgpuAtlas>,
+        target: RenderTarget,
+        surface_configuration: wgpu::SurfaceConfiguration,
+        view_format: wgpu::TextureFormat,
+    ) -> Self {
+        let settings = context.renderer_settings.get();
+
+        let frames_in_flight = surface_configuration.desired_maximum_frame_latency;
+        let globals = UniformSlab::new(&context.device, "Globals Buffer", MAX_INSTANCE_BUFFERS, 1);
+        let surface_params = UniformSlab::new(
+            &context.device,
+            "Surface Params Buffer",
+            MAX_INSTANCE_BUFFERS,
+            MAX_SURFACES_PER_FRAME,
+        );
+
+        let color_filter_params_buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("color_filter_params_buffer"),
+            size: std::mem::size_of::<ColorFilterParams>() as u64,
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
-        let pipelines =
-            WgpuPipelines::new(context.as_ref(), &surface_configuration, path_sample_count);
+        let blank_glyph_texture = context
+            .device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("blank_glyph_texture"),
+                size: wgpu::Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::R8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let pipelines = WgpuPipelines::new(
+            context.as_ref(),
+            &surface_configuration,
+            view_format,
+            path_sample_count(&context),
+            &globals,
+        );
+        let instance_belt = InstanceBelt::new(
+            &context.device,
+            &pipelines,
+            context.memory.clone(),
+            frames_in_flight,
+        );
 
-        Ok(Self {
+        Self {
             context: context.clone(),
-            surface,
+            target,
             surface_configuration,
+            view_format,
             atlas,
-            atlas_sampler,
-            surface_sampler,
-            surface_params_buffer,
+            resource_pool: ResourcePool::default(),
+            globals,
+            surface_params,
+            blank_glyph_texture,
             pipelines,
-            rendering_parameters: RenderingParameters::from_env(),
+            instance_belt,
+            rendering_parameters: RenderingParameters::new(
+                &settings,
+                context.renderer_settings.night_light_temperature(),
+            ),
+            draw_call_recording: false,
+            last_frame_draw_calls: None,
+            frame_recorder: None,
+            frame_stats: settings.frame_stats_logging.map(FrameStatsLogger::new),
+            frame_pacer: FramePacer::new(),
+            transparent: false,
+            clear_color: None,
+            screen_zoom: None,
+            software_cursor: None,
+            color_filter: None,
+            color_filter_params_buffer,
+            unfiltered_frame: None,
+            clip_stencil: None,
+            layer_pipelines: None,
+            layer_target: None,
+            pre_present_notify: None,
+            low_latency_scene: None,
+            memory_pressure_handled: false,
+            needs_full_redraw: false,
+            pending_alpha_mode: None,
             surface_bind_groups: Mutex::new(HashMap::new()),
+            frame_serial: 0,
+            plugin_pipelines: parking_lot::Mutex::default(),
+        }
+    }
+
+    /// Draws `scene` and presents it. When the frame can't be drawn, it's skipped and the next
+    /// frame is rebuilt from scratch, since the cached layers it would have drawn are missing.
+    pub fn draw(&mut self, scene: &Scene) -> Result<(), RendererError> {
+        let frame = self.prepare(scene)?;
+        self.present(frame);
+        Ok(())
+    }
+
+    /// Draws `scene` and submits it like [`WgpuRenderer::draw`], but leaves presenting it to
+    /// [`WgpuRenderer::present`], so the frames of several windows can be presented back to back.
+    pub fn prepare(&mut self, scene: &Scene) -> Result<PreparedFrame, RendererError> {
+        let started_at = std::time::Instant::now();
+        self.frame_serial += 1;
+        if let Some(alpha_mode) = self.pending_alpha_mode.take() {
+            self.apply_alpha_mode(alpha_mode);
+        }
+        let acquire_started_at = std::time::Instant::now();
+        let (surface_texture, surface_view) = match self.acquire_target() {
+            Ok(target) => target,
+            Err(error) => {
+                self.needs_full_redraw = true;
+                return Err(error);
+            }
+        };
+        // the time spent blocked on the swapchain, which paces frames rather than drawing them
+        let swapchain_wait = acquire_started_at.elapsed();
+        let ui_frame = self.context.submission_scheduler.ui_frame();
+        let frame_size = geometry::size(
+            self.surface_configuration.width as f32,
+            self.surface_configuration.height as f32,
+        );
+        let zoomed_region = self.screen_zoom.map(|zoom| zoom.visible_region(frame_size));
+        self.draw_layers(scene);
+        let offsets = self.begin_frame(scene, zoomed_region, false, self.software_cursor.is_some());
+        let instance_bytes = self.instance_belt.cursor;
+        let low_latency = self.shows_low_latency_surface(scene);
+        let unfiltered_view = match self.color_filter {
+            Some(color_filter) => self.prepare_color_filter(color_filter),
+            // Low latency surfaces are redrawn over the last frame between frames, so the frame is
+            // kept in a texture and copied to the surface.
+            None if low_latency => {
+                self.prepare_color_filter(ColorFilter::new(IDENTITY_COLOR_MATRIX))
+            }
+            None => None,
+        };
+        let clip_stencil = self.prepare_clip_stencil(wgpu::Extent3d {
+            width: self.surface_configuration.width.max(1),
+            height: self.surface_configuration.height.max(1),
+            depth_or_array_layers: 1,
+        });
+
+        // The atlas uploads, instance uploads and main pass only share read-only state, so they're
+        // encoded concurrently and submitted in dependency order.
+        let primitive_count = scene.quads.len()
+            + scene.shadows.len()
+            + scene.underlines.len()
+            + scene.monochrome_sprites.len()
+            + scene.polychrome_sprites.len()
+            + scene
+                .cell_grids
+                .iter()
+                .map(|grid| grid.cells.len())
+                .sum::<usize>()
+            + scene
+                .selections
+                .iter()
+                .map(|selection| selection.lines.len())
+                .sum::<usize>();
+        let parallel_executor = self
+            .context
+            .background_executor
+            .as_ref()
+            .filter(|_| primitive_count >= PARALLEL_ENCODING_THRESHOLD);
+        let this = &*self;
+        let encode_main_pass = || {
+            this.encode_main_pass(
+                scene,
+                &offsets,
+                &MainPassTarget {
+                    view: unfiltered_view.as_ref().unwrap_or(&surface_view),
+                    viewport: None,
+                    clear_color: Some(this.main_pass_clear_color()),
+                    scissor: None,
+                    clip_stencil: &clip_stencil,
+                    pipelines: &this.pipelines,
+                },
+            )
+        };
+        let mut atlas_commands = None;
+        let mut instance_commands = None;
+        let main_pass = match parallel_executor {
+            // the uploads are encoded on the background threads while this one encodes the main
+            // pass
+            Some(executor) => pollster::block_on(async {
+                let uploads = executor.scoped_priority(Priority::High, |scope| {
+                    scope.spawn(async {
+                        atlas_commands = Some(this.encode_atlas_uploads());
+                    });
+                    scope.spawn(async {
+                        instance_commands = Some(this.encode_instance_uploads(scene, &offsets));
+                    });
+                });
+                let ((), main_pass) = futures::join!(uploads, async { encode_main_pass() });
+                main_pass
+            }),
+            None => {
+                atlas_commands = Some(this.encode_atlas_uploads());
+                instance_commands = Some(this.encode_instance_uploads(scene, &offsets));
+                encode_main_pass()
+            }
+        };
+        let color_filter_commands = unfiltered_view
+            .as_ref()
+            .and_then(|_| self.encode_color_filter_pass(&surface_view, offsets.globals));
+
+        let draw_call_count = main_pass.draw_calls.as_ref().map_or(0, Vec::len);
+        if let Some(draw_calls) = main_pass.draw_calls.filter(|_| self.draw_call_recording) {
+            self.last_frame_draw_calls = Some(FrameDrawCalls {
+                draw_calls,
+                culled: scene.culled,
+            });
+        }
+
+        // remove cached bind groups for surfaces that disappeared this frame
+        {
+            let mut cache = self.surface_bind_groups.lock().unwrap();
+            cache.retain(|_, entry| entry.composited_frame == self.frame_serial);
+        }
+        self.context
+            .submission_scheduler
+            .record_composited(&main_pass.seen_surfaces);
+        let target_texture = match (&surface_texture, &self.target) {
+            (Some(surface_texture), _) => Some(&surface_texture.texture),
+            (None, RenderTarget::Texture(texture)) => Some(texture),
+            (None, RenderTarget::Surface(_) | RenderTarget::Lost | RenderTarget::Suspended) => None,
+        };
+        let frame_capture = self
+            .frame_recorder
+            .as_mut()
+            .zip(target_texture)
+            .and_then(|(recorder, texture)| recorder.capture(&self.context.device, texture));
+        let (capture_commands, pending_capture) = frame_capture.unzip();
+
+        let submission = self.context.submit(
+            atlas_commands
+                .into_iter()
+                .chain(instance_commands)
+                .chain([main_pass.commands])
+                .chain(color_filter_commands)
+                .chain(capture_commands),
+        );
+        drop(ui_frame);
+        self.instance_belt.recall(&self.context.queue, submission);
+        self.resource_pool.end_frame();
+
+        if let (Some(recorder), Some(pending_capture)) = (&self.frame_recorder, pending_capture) {
+            recorder.submitted(pending_capture);
+        }
+        if self.frame_recorder.is_some() {
+            // Polling completes the readbacks of earlier recorded frames without waiting on the one
+            // just submitted.
+            if let Err(error) = self.context.device.poll(wgpu::PollType::Poll) {
+                log::error!("failed to poll for recorded frames: {error}");
+            }
+        }
+
+        if low_latency {
+            // The scene is copied into the one kept from the last frame, reusing its memory.
+            self.low_latency_scene
+                .get_or_insert_with(Scene::default)
+                .clone_primitives_from(scene);
+        } else {
+            self.low_latency_scene = None;
+        }
+        let draw_time = started_at.elapsed();
+        self.frame_pacer
+            .record(draw_time.saturating_sub(swapchain_wait));
+
+        Ok(PreparedFrame {
+            surface_texture,
+            seen_surfaces: main_pass.seen_surfaces,
+            started_at,
+            draw_time,
+            draw_call_count,
+            instance_bytes,
         })
     }
 
-    pub fn draw(&self, scene: &Scene) {
-        let mut command_encoder =
-            self.context
-                .device
-                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                    label: Some("main"),
+    /// Presents a frame drawn with [`WgpuRenderer::prepare`].
+    pub fn present(&mut self, frame: PreparedFrame) {
+        let mut draw_time = frame.draw_time;
+        if let Some(surface_texture) = frame.surface_texture {
+            if let Some(pre_present_notify) = &self.pre_present_notify {
+                pre_present_notify();
+            }
+            let present_started_at = std::time::Instant::now();
+            surface_texture.present();
+            draw_time += present_started_at.elapsed();
+            self.frame_pacer
+                .record_present(frame.started_at, std::time::Instant::now());
+        }
+        self.record_surface_presents(&frame.seen_surfaces);
+
+        if let Some(frame_stats) = &mut self.frame_stats {
+            let atlas = &self.atlas;
+            frame_stats.record(
+                FrameSample {
+                    draw_time,
+                    draw_calls: frame.draw_call_count,
+                    uploaded_bytes: atlas.take_uploaded_bytes() + frame.instance_bytes,
+                },
+                || atlas.allocation_stats(),
+            );
+        }
+        self.relieve_memory_pressure();
+    }
+
+    /// Returns the texture of the window's surface the frame is drawn to, if it's drawn to one,
+    /// and a view of where it's drawn. A surface that was lost or no longer matches the window is
+    /// reconfigured, so the next frame can be drawn to it.
+    fn acquire_target(
+        &mut self,
+    ) -> Result<(Option<wgpu::SurfaceTexture>, wgpu::TextureView), RendererError> {
+        let mut reconfigured = false;
+        loop {
+            let surface = match &self.target {
+                RenderTarget::Surface(surface) => surface,
+                RenderTarget::Texture(texture) => {
+                    return Ok((
+                        None,
+                        texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                    ));
+                }
+                RenderTarget::Lost => return Err(RendererError::DeviceLost),
+                RenderTarget::Suspended => return Err(RendererError::Suspended),
+            };
+            let surface_texture = match surface.get_current_texture() {
+                Ok(surface_texture) => surface_texture,
+                Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                    self.configure_target();
+                    return Err(RendererError::SurfaceLost);
+                }
+                Err(wgpu::SurfaceError::Timeout) => return Err(RendererError::SurfaceTimeout),
+                Err(wgpu::SurfaceError::OutOfMemory) => return Err(RendererError::OutOfMemory),
+                Err(wgpu::SurfaceError::Other) => return Err(RendererError::SurfaceUnavailable),
+            };
+            // A suboptimal texture is still presentable, but it's shown stretched, such as after a
+            // Wayland compositor changed the window's scale or transform. It's dropped without
+            // being presented, and the frame is drawn to a texture of the reconfigured surface
+            // instead. If that one is suboptimal too, it's drawn to anyway rather than skipping
+            // frames.
+            if surface_texture.suboptimal && !reconfigured {
+                drop(surface_texture);
+                self.reconfigure_surface();
+                reconfigured = true;
+                continue;
+            }
+            let surface_view = surface_texture
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor {
+                    format: Some(self.view_format),
+                    ..Default::default()
                 });
+            return Ok((Some(surface_texture), surface_view));
+        }
+    }
 
-        self.atlas.before_frame(&mut command_encoder);
+    /// Configures the window's surface again with its current capabilities, which may have
+    /// changed along with whatever made it suboptimal. The present and alpha modes fall back to
+    /// supported ones if they aren't anymore.
+    fn reconfigure_surface(&mut self) {
+        let RenderTarget::Surface(surface) = &self.target else {
+            return;
+        };
+        let capabilities = surface.get_capabilities(&self.context.adapter);
+        self.surface_configuration.present_mode = supported_present_mode(
+            self.context.renderer_settings.get().present_mode,
+            &capabilities,
+        );
+        match capabilities.alpha_modes.first() {
+            Some(&alpha_mode)
+                if !capabilities
+                    .alpha_modes
+                    .contains(&self.surface_configuration.alpha_mode) =>
+            {
+                // Changing the alpha mode configures the surface too.
+                self.apply_alpha_mode(alpha_mode);
+            }
+            _ => self.configure_target(),
+        }
+    }
 
-        // keep track of which surface ids we rendered this frame
-        let mut seen_surfaces: Vec<crate::platform::cross::surface_registry::SurfaceId> = Vec::new();
+    fn shows_low_latency_surface(&self, scene: &Scene) -> bool {
+        let registry = &self.context.surface_registry;
+        scene.surfaces.iter().any(|surface| {
+            matches!(
+                &surface.content,
+                crate::SurfaceContent::Wgpu(surface_id) if registry.is_low_latency(*surface_id)
+            )
+        })
+    }
 
+    /// Redraws the regions of the low latency surfaces with a pending present over the last
+    /// frame, and presents the result without waiting for a new scene. Returns false when there
+    /// was nothing to recomposite, the last frame wasn't kept or the alpha mode is changing, in
+    /// which case a full frame has to be drawn instead.
+    pub fn recomposite_low_latency_surfaces(&mut self) -> bool {
+        if self.screen_zoom.is_some() || self.pending_alpha_mode.is_some() {
+            return false;
+        }
+        let Some(scene) = self.low_latency_scene.take() else {
+            return false;
+        };
+        let recomposited = self.recomposite_surfaces(&scene);
+        self.low_latency_scene = Some(scene);
+        recomposited
+    }
+
+    fn recomposite_surfaces(&mut self, scene: &Scene) -> bool {
+        let registry = self.context.surface_registry.clone();
+        let mut region: Option<geometry::Bounds<ScaledPixels>> = None;
+        for surface in &scene.surfaces {
+            let presented = matches!(
+                &surface.content,
+                crate::SurfaceContent::Wgpu(surface_id)
+                    if registry.is_low_latency(*surface_id)
+                        && registry.is_present_pending(*surface_id)
+            );
+            if !presented {
+                continue;
+            }
+            let bounds = surface.bounds.intersect(&surface.content_mask.bounds);
+            region = Some(match region {
+                Some(region) => region.union(&bounds),
+                None => bounds,
+            });
+        }
+        let Some(region) = region else {
+            return false;
+        };
+
+        let width = self.surface_configuration.width;
+        let height = self.surface_configuration.height;
+        let left = (region.origin.x.0.floor().max(0.0) as u32).min(width);
+        let top = (region.origin.y.0.floor().max(0.0) as u32).min(height);
+        let right = (region.bottom_right().x.0.ceil().max(0.0) as u32).min(width);
+        let bottom = (region.bottom_right().y.0.ceil().max(0.0) as u32).min(height);
+        if right <= left || bottom <= top {
+            return true;
+        }
+
+        let retained_size = wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        };
+        let retained_view = match &self.unfiltered_frame {
+            Some((texture, _)) if texture.size() == retained_size => {
+                texture.create_view(&wgpu::TextureViewDescriptor::default())
+            }
+            _ => return false,
+        };
+        let RenderTarget::Surface(surface) = &self.target else {
+            return false;
+        };
+        let surface_texture = match surface.get_current_texture() {
+            // A full frame reconfigures the surface first.
+            Ok(surface_texture) if surface_texture.suboptimal => return false,
+            Ok(surface_texture) => surface_texture,
+            Err(error) => {
+                log::debug!("failed to acquire a texture to recomposite surfaces: {error}");
+                return false;
+            }
+        };
+        let surface_view = surface_texture
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor {
+                format: Some(self.view_format),
+                ..Default::default()
+            });
+
+        let ui_frame = self.context.submission_scheduler.ui_frame();
+        let clip_stencil = self.prepare_clip_stencil(retained_size);
+        let offsets = self.begin_frame(scene, None, false, self.software_cursor.is_some());
+        let atlas_commands = self.encode_atlas_uploads();
+        let instance_commands = self.encode_instance_uploads(scene, &offsets);
+        let main_pass = self.encode_main_pass(
+            scene,
+            &offsets,
+            &MainPassTarget {
+                view: &retained_view,
+                viewport: None,
+                clear_color: None,
+                scissor: Some(geometry::Bounds::new(
+                    geometry::point(DevicePixels(left as i32), DevicePixels(top as i32)),
+                    geometry::size(
+                        DevicePixels((right - left) as i32),
+                        DevicePixels((bottom - top) as i32),
+                    ),
+                )),
+                clip_stencil: &clip_stencil,
+                pipelines: &self.pipelines,
+            },
+        );
+        let Some(copy_commands) = self.encode_color_filter_pass(&surface_view, offsets.globals)
+        else {
+            return false;
+        };
+        let submission = self.context.submit([
+            atlas_commands,
+            instance_commands,
+            main_pass.commands,
+            copy_commands,
+        ]);
+        drop(ui_frame);
+        self.instance_belt.recall(&self.context.queue, submission);
+
+        if let Some(pre_present_notify) = &self.pre_present_notify {
+            pre_present_notify();
+        }
+        surface_texture.present();
+        self.record_surface_presents(&main_pass.seen_surfaces);
+        true
+    }
+
+    /// Records that the surfaces composited into the frame just presented reached the screen,
+    /// and when the GPU finished the frame once the queue reports it.
+    fn record_surface_presents(&self, surface_ids: &[SurfaceId]) {
+        let presented_at = std::time::Instant::now();
+        let registry = &self.context.surface_registry;
+        let mut presented_surfaces = CompositedSurfaces::new();
+        for surface_id in surface_ids {
+            if !presented_surfaces.contains(surface_id)
+                && registry.record_present(*surface_id, presented_at)
+            {
+                presented_surfaces.push(*surface_id);
+            }
+        }
+        if presented_surfaces.is_empty() {
+            return;
+        }
+
+        let registry = registry.clone();
+        self.context.queue.on_submitted_work_done(move || {
+            let completed_at = std::time::Instant::now();
+            for surface_id in presented_surfaces {
+                registry.record_completion(surface_id, presented_at, completed_at);
+            }
+        });
+    }
+
+    /// Uploads the frame's globals and reserves room for the scene's instances, returning where
+    /// each kind of instance goes. Only the `source` region of the scene is drawn when one is
+    /// given, and `layer` draws it into the top left corner of a cached layer's target.
+    /// `software_cursor` reserves room for the [`SoftwareCursor`], which is drawn over the scene.
+    fn begin_frame(
+        &mut self,
+        scene: &Scene,
+        source: Option<geometry::Bounds<f32>>,
+        layer: bool,
+        software_cursor: bool,
+    ) -> InstanceOffsets {
+        // Cached layers keep sRGB encoded, premultiplied colors without the white point, which is
+        // applied when they're composited like any other sprite.
+        let srgb_output = self.view_format.is_srgb() && !layer;
+        let mut white_point = if layer {
+            [1.0; 3]
+        } else {
+            self.rendering_parameters.white_point
+        };
+        if !srgb_output {
+            white_point = white_point.map(linear_to_srgb);
+        }
+        let [red, green, blue] = white_point;
+        let target_format = if layer {
+            LAYER_FORMAT
+        } else {
+            self.view_format
+        };
+        // sRGB targets are written linear colors, where the noise would be scaled unevenly by the
+        // encoding, so only non-sRGB targets are dithered.
+        let dither_amplitude = match quantization_step(target_format) {
+            Some(step) if self.rendering_parameters.dithering && !srgb_output => step,
+            _ => 0.0,
+        };
         let color_adjustments = ColorAdjustments {
             gamma_ratios: self.rendering_parameters.gamma_ratios,
             grayscale_enhanced_contrast: self.rendering_parameters.grayscale_enhanced_contrast,
-            _padding: [0.0; 3],
+            dither_amplitude,
+            _padding: [0.0; 2],
+            white_point: [red, green, blue, 1.0],
         };
         self.context.queue.write_buffer(
             &self.context.color_adjustments_buffer,
@@ -1309,186 +3629,839 @@ impl WgpuRenderer {
             bytemuck::bytes_of(&color_adjustments),
         );
 
+        let (viewport_origin, viewport_size) = match source {
+            Some(source) => (
+                [source.origin.x, source.origin.y],
+                [source.size.width, source.size.height],
+            ),
+            None => (
+                [0.0, 0.0],
+                [
+                    self.surface_configuration.width as f32,
+                    self.surface_configuration.height as f32,
+                ],
+            ),
+        };
         let globals = GlobalParams {
-            viewport_size: [
-                self.surface_configuration.width as f32,
-                self.surface_configuration.height as f32,
-            ],
+            viewport_size,
             premultimated_alpha: match self.surface_configuration.alpha_mode {
                 wgpu::CompositeAlphaMode::PreMultiplied => 1,
-                _ => 0,
+                _ => layer as u32,
             },
-            pad: 0,
+            srgb_output: srgb_output as u32,
+            viewport_origin,
+            target_origin: if layer { viewport_origin } else { [0.0, 0.0] },
         };
 
-        self.context.queue.write_buffer(
-            &self.context.globals_buffer,
-            0,
-            bytemuck::bytes_of(&globals),
+        let selection_line_count = scene
+            .selections
+            .iter()
+            .map(|selection| selection.lines.len())
+            .sum();
+        let instance_belt = &mut self.instance_belt;
+        let required_instance_bytes = instance_belt.aligned_size::<GpuQuad>(scene.quads.len())
+            + instance_belt.aligned_size::<GpuShadow>(scene.shadows.len())
+            + instance_belt.aligned_size::<GpuUnderline>(scene.underlines.len())
+            + instance_belt.aligned_size::<GpuMonochromeSprite>(scene.monochrome_sprites.len())
+            + instance_belt.aligned_size::<GpuPolychromeSprite>(scene.polychrome_sprites.len())
+            + scene
+                .cell_grids
+                .iter()
+                .map(|grid| {
+                    instance_belt.aligned_size::<GpuCellGrid>(1)
+                        + instance_belt.aligned_size::<GpuCellGlyph>(grid.glyphs.len())
+                        + instance_belt.aligned_size::<GpuGridCell>(grid.cells.len())
+                })
+                .sum::<u64>()
+            + instance_belt.aligned_size::<GpuSelection>(scene.selections.len())
+            + instance_belt.aligned_size::<GpuSelectionLine>(selection_line_count)
+            + instance_belt.aligned_size::<GpuClip>(scene.clips.len())
+            + instance_belt.aligned_size::<GpuQuad>(if software_cursor {
+                SOFTWARE_CURSOR_QUAD_COUNT
+            } else {
+                0
+            });
+        if !instance_belt.begin_frame(
+            &self.context.device,
+            &self.pipelines,
+            required_instance_bytes,
+        ) {
+            log::error!(
+                "the scene's instances need {required_instance_bytes} bytes, more than the \
+                 largest instance buffer the device allows; the primitives that don't fit are \
+                 dropped"
+            );
+        }
+        self.globals.begin_frame(instance_belt.current_frame);
+        if self
+            .surface_params
+            .grow(&self.context.device, scene.surfaces.len() as u64)
+        {
+            self.surface_bind_groups.lock().unwrap().clear();
+        }
+        self.surface_params.begin_frame(instance_belt.current_frame);
+        // Every frame has a slot of its own for its globals.
+        let globals_offset = self.globals.reserve(1).unwrap_or_default();
+        self.globals
+            .write(&self.context.queue, globals_offset, &globals);
+
+        InstanceOffsets {
+            globals: globals_offset,
+            surface_params: self.surface_params.reserve(scene.surfaces.len()),
+            quads: instance_belt.reserve::<GpuQuad>(scene.quads.len()),
+            shadows: instance_belt.reserve::<GpuShadow>(scene.shadows.len()),
+            underlines: instance_belt.reserve::<GpuUnderline>(scene.underlines.len()),
+            mono_sprites: instance_belt
+                .reserve::<GpuMonochromeSprite>(scene.monochrome_sprites.len()),
+            poly_sprites: instance_belt
+                .reserve::<GpuPolychromeSprite>(scene.polychrome_sprites.len()),
+            cell_grids: scene
+                .cell_grids
+                .iter()
+                .map(|grid| {
+                    Some([
+                        instance_belt.reserve::<GpuCellGrid>(1)?,
+                        instance_belt.reserve::<GpuCellGlyph>(grid.glyphs.len())?,
+                        instance_belt.reserve::<GpuGridCell>(grid.cells.len())?,
+                    ])
+                })
+                .collect(),
+            selections: instance_belt
+                .reserve::<GpuSelection>(scene.selections.len())
+                .zip(instance_belt.reserve::<GpuSelectionLine>(selection_line_count))
+                .map(|(selections, lines)| [selections, lines]),
+            clips: instance_belt.reserve::<GpuClip>(scene.clips.len()),
+            software_cursor: software_cursor
+                .then(|| instance_belt.reserve::<GpuQuad>(SOFTWARE_CURSOR_QUAD_COUNT))
+                .flatten(),
+        }
+    }
+
+    /// Draws `scene` into each eye of an OpenXR swapchain image instead of the surface. The
+    /// scene is drawn once per eye, stretched over the eye's viewport, and nothing is presented;
+    /// the caller releases the image to the XR runtime afterwards.
+    pub fn draw_to_xr_swapchain(
+        &mut self,
+        scene: &Scene,
+        target: &XrSwapchainTarget,
+    ) -> anyhow::Result<()> {
+        let texture = &target.texture;
+        anyhow::ensure!(
+            texture.format() == self.view_format,
+            "the swapchain image is {:?}, but this window renders {:?}",
+            texture.format(),
+            self.view_format
+        );
+        anyhow::ensure!(
+            texture
+                .usage()
+                .contains(wgpu::TextureUsages::RENDER_ATTACHMENT),
+            "the swapchain image can't be rendered to"
+        );
+        let layer_count = texture.depth_or_array_layers();
+        for eye in &target.eyes {
+            anyhow::ensure!(
+                eye.layer < layer_count,
+                "the swapchain image has {layer_count} layers, but an eye draws into layer {}",
+                eye.layer
+            );
+            ensure_viewport_fits(eye.viewport, texture.size())?;
+        }
+
+        let clip_stencil = self.prepare_clip_stencil(wgpu::Extent3d {
+            width: texture.width(),
+            height: texture.height(),
+            depth_or_array_layers: 1,
+        });
+        let offsets = self.begin_frame(scene, None, false, false);
+        let atlas_commands = self.encode_atlas_uploads();
+        let instance_commands = self.encode_instance_uploads(scene, &offsets);
+        let mut commands = vec![atlas_commands, instance_commands];
+        let mut cleared_layers = Vec::new();
+        for eye in &target.eyes {
+            let view = texture.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("xr_eye"),
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                base_array_layer: eye.layer,
+                array_layer_count: Some(1),
+                ..Default::default()
+            });
+            // Eyes can share a layer side by side, so only the first one drawn into a layer clears
+            // it.
+            let clear = !cleared_layers.contains(&eye.layer);
+            cleared_layers.push(eye.layer);
+            let main_pass = self.encode_main_pass(
+                scene,
+                &offsets,
+                &MainPassTarget {
+                    view: &view,
+                    viewport: Some(eye.viewport),
+                    clear_color: clear.then(|| self.main_pass_clear_color()),
+                    scissor: None,
+                    clip_stencil: &clip_stencil,
+                    pipelines: &self.pipelines,
+                },
+            );
+            commands.push(main_pass.commands);
+        }
+
+        let submission = self.context.submit(commands);
+        self.instance_belt.recall(&self.context.queue, submission);
+        self.relieve_memory_pressure();
+        Ok(())
+    }
+
+    /// Draws the `viewport.source` region of `scene` into `viewport.destination` of `target`, so
+    /// one scene can be shown in several places, such as a magnifier lens, a minimap or a
+    /// picture-in-picture preview, without being rebuilt. The destination isn't cleared first, and
+    /// nothing is presented.
+    pub fn draw_region(
+        &mut self,
+        scene: &Scene,
+        viewport: SceneViewport,
+        target: &wgpu::Texture,
+    ) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            target.format() == self.view_format,
+            "the target is {:?}, but this window renders {:?}",
+            target.format(),
+            self.view_format
+        );
+        anyhow::ensure!(
+            target
+                .usage()
+                .contains(wgpu::TextureUsages::RENDER_ATTACHMENT),
+            "the target can't be rendered to"
         );
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+        self.draw_region_to_view(
+            scene,
+            viewport,
+            &view,
+            geometry::size(
+                DevicePixels(target.width() as i32),
+                DevicePixels(target.height() as i32),
+            ),
+        )
+    }
 
-        unsafe fn as_bytes<T>(slice: &[T]) -> &[u8] {
-            unsafe {
-                std::slice::from_raw_parts(
-                    slice.as_ptr() as *const u8,
-                    slice.len() * std::mem::size_of::<T>(),
+    /// Draws the `viewport.source` region of `scene` into `viewport.destination` of the
+    /// `target_size` texture `target` views, like [`WgpuRenderer::draw_region`], for hosts that
+    /// only hand out views of their textures, see [`crate::EmbeddedApp`]. The view must have the
+    /// format returned by [`WgpuRenderer::xr_swapchain_format`] and its texture must be usable as
+    /// a render attachment.
+    pub fn draw_region_to_view(
+        &mut self,
+        scene: &Scene,
+        viewport: SceneViewport,
+        target: &wgpu::TextureView,
+        target_size: geometry::Size<DevicePixels>,
+    ) -> anyhow::Result<()> {
+        let target_size = wgpu::Extent3d {
+            width: target_size.width.0.max(0) as u32,
+            height: target_size.height.0.max(0) as u32,
+            depth_or_array_layers: 1,
+        };
+        anyhow::ensure!(
+            viewport.source.size.width.0 > 0 && viewport.source.size.height.0 > 0,
+            "the source region {:?} is empty",
+            viewport.source
+        );
+        ensure_viewport_fits(viewport.destination, target_size)?;
+
+        let source = viewport.source;
+        let offsets = self.begin_frame(
+            scene,
+            Some(geometry::Bounds::new(
+                geometry::point(source.origin.x.0 as f32, source.origin.y.0 as f32),
+                geometry::size(source.size.width.0 as f32, source.size.height.0 as f32),
+            )),
+            false,
+            false,
+        );
+        let clip_stencil = self.prepare_clip_stencil(target_size);
+        let atlas_commands = self.encode_atlas_uploads();
+        let instance_commands = self.encode_instance_uploads(scene, &offsets);
+        let main_pass = self.encode_main_pass(
+            scene,
+            &offsets,
+            &MainPassTarget {
+                view: target,
+                viewport: Some(viewport.destination),
+                clear_color: None,
+                scissor: None,
+                clip_stencil: &clip_stencil,
+                pipelines: &self.pipelines,
+            },
+        );
+
+        let submission =
+            self.context
+                .submit([atlas_commands, instance_commands, main_pass.commands]);
+        self.instance_belt.recall(&self.context.queue, submission);
+        self.relieve_memory_pressure();
+        Ok(())
+    }
+
+    /// Draws the cached layers painted into `scene` into their atlas tiles. Each layer is drawn
+    /// into a separate target and copied into its tile, since its own sprites can be in the same
+    /// atlas texture, and it's cleared first, so a scene that's drawn again leaves them unchanged.
+    fn draw_layers(&mut self, scene: &Scene) {
+        if scene.layer_renders.is_empty() {
+            return;
+        }
+        let ui_frame = self.context.submission_scheduler.ui_frame();
+        let (width, height) = scene
+            .layer_renders
+            .iter()
+            .fold((1, 1), |(width, height), layer| {
+                (
+                    width.max(layer.tile.bounds.size.width.0.max(1) as u32),
+                    height.max(layer.tile.bounds.size.height.0.max(1) as u32),
                 )
+            });
+        let (target_view, stencil_view) = self.prepare_layer_target(width, height);
+        if self.layer_pipelines.is_none() {
+            let layer_configuration = wgpu::SurfaceConfiguration {
+                alpha_mode: wgpu::CompositeAlphaMode::PreMultiplied,
+                ..self.surface_configuration.clone()
+            };
+            self.layer_pipelines = Some(WgpuPipelines::new(
+                &self.context,
+                &layer_configuration,
+                LAYER_FORMAT,
+                path_sample_count(&self.context),
+                &self.globals,
+            ));
+        }
+
+        for layer in &scene.layer_renders {
+            let tile_size = layer.tile.bounds.size;
+            if tile_size.width.0 <= 0 || tile_size.height.0 <= 0 {
+                continue;
+            }
+            // the texture is gone if the atlas was evicted after this scene was built
+            let Some(atlas_texture) = self.atlas.get_texture_info(layer.tile.texture_id) else {
+                continue;
+            };
+            let offsets = self.begin_frame(
+                &layer.scene,
+                Some(geometry::Bounds::new(
+                    geometry::point(layer.source.origin.x.0, layer.source.origin.y.0),
+                    geometry::size(tile_size.width.0 as f32, tile_size.height.0 as f32),
+                )),
+                true,
+                false,
+            );
+            let Some(layer_pipelines) = &self.layer_pipelines else {
+                return;
+            };
+            let atlas_commands = self.encode_atlas_uploads();
+            let instance_commands = self.encode_instance_uploads(&layer.scene, &offsets);
+            let main_pass = self.encode_main_pass(
+                &layer.scene,
+                &offsets,
+                &MainPassTarget {
+                    view: &target_view,
+                    viewport: Some(geometry::Bounds::new(geometry::Point::default(), tile_size)),
+                    clear_color: Some(wgpu::Color::TRANSPARENT),
+                    scissor: None,
+                    clip_stencil: &stencil_view,
+                    pipelines: layer_pipelines,
+                },
+            );
+
+            let mut copy_encoder =
+                self.context
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                        label: Some("layer_copy"),
+                    });
+            if let Some((target, _)) = &self.layer_target {
+                copy_encoder.copy_texture_to_texture(
+                    target.as_image_copy(),
+                    wgpu::TexelCopyTextureInfo {
+                        texture: &atlas_texture.raw,
+                        mip_level: 0,
+                        origin: wgpu::Origin3d {
+                            x: layer.tile.bounds.origin.x.0 as u32,
+                            y: layer.tile.bounds.origin.y.0 as u32,
+                            z: 0,
+                        },
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    wgpu::Extent3d {
+                        width: tile_size.width.0 as u32,
+                        height: tile_size.height.0 as u32,
+                        depth_or_array_layers: 1,
+                    },
+                );
             }
+
+            let submission = self.context.submit([
+                atlas_commands,
+                instance_commands,
+                main_pass.commands,
+                copy_encoder.finish(),
+            ]);
+            self.instance_belt.recall(&self.context.queue, submission);
         }
+        drop(ui_frame);
+    }
 
-        if !scene.quads.is_empty() {
+    /// Returns views of the texture cached layers are drawn into and its stencil buffer,
+    /// recreating them when they're smaller than `width` by `height`.
+    fn prepare_layer_target(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> (wgpu::TextureView, wgpu::TextureView) {
+        let size = match &self.layer_target {
+            Some((texture, _)) if texture.width() >= width && texture.height() >= height => {
+                texture.size()
+            }
+            Some((texture, _)) => wgpu::Extent3d {
+                width: texture.width().max(width),
+                height: texture.height().max(height),
+                depth_or_array_layers: 1,
+            },
+            None => wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        };
+        let device = &self.context.device;
+        let (texture, stencil) = match self.layer_target.take() {
+            Some(layer_target) if layer_target.0.size() == size => layer_target,
+            _ => {
+                let create_texture = |label, format, usage| {
+                    device.create_texture(&wgpu::TextureDescriptor {
+                        label: Some(label),
+                        size,
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format,
+                        usage,
+                        view_formats: &[],
+                    })
+                };
+                (
+                    create_texture(
+                        "layer_target",
+                        LAYER_FORMAT,
+                        wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+                    ),
+                    create_texture(
+                        "layer_clip_stencil",
+                        CLIP_STENCIL_FORMAT,
+                        wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    ),
+                )
+            }
+        };
+        let views = (
+            texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            stencil.create_view(&wgpu::TextureViewDescriptor::default()),
+        );
+        self.layer_target = Some((texture, stencil));
+        views
+    }
+
+    /// The format OpenXR swapchain images must have for [`WgpuRenderer::draw_to_xr_swapchain`].
+    pub fn xr_swapchain_format(&self) -> wgpu::TextureFormat {
+        self.view_format
+    }
+
+    fn encode_atlas_uploads(&self) -> wgpu::CommandBuffer {
+        self.context.set_active_pass(Some("atlas_uploads"));
+        let mut command_encoder =
             self.context
-                .queue
-                .write_buffer(&self.context.quads_buffer, 0, unsafe {
-                    as_bytes(&scene.quads)
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("atlas_uploads"),
                 });
-        }
-        if !scene.shadows.is_empty() {
+        self.atlas.before_frame(&mut command_encoder);
+        let commands = command_encoder.finish();
+        self.context.set_active_pass(None);
+        commands
+    }
+
+    fn encode_instance_uploads(
+        &self,
+        scene: &Scene,
+        offsets: &InstanceOffsets,
+    ) -> wgpu::CommandBuffer {
+        self.context.set_active_pass(Some("instance_uploads"));
+        let mut command_encoder =
             self.context
-                .queue
-                .write_buffer(&self.context.shadows_buffer, 0, unsafe {
-                    as_bytes(&scene.shadows)
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("instance_uploads"),
                 });
+
+        let instance_belt = &self.instance_belt;
+        let device = &self.context.device;
+        let pixel_snapping = self.rendering_parameters.pixel_snapping;
+        if let Some(offset) = offsets.quads {
+            instance_belt.write_with(&mut command_encoder, device, offset, &scene.quads, |quad| {
+                GpuQuad::snapped(quad, pixel_snapping)
+            });
         }
-        if !scene.underlines.is_empty() {
-            self.context
-                .queue
-                .write_buffer(&self.context.underlines_buffer, 0, unsafe {
-                    as_bytes(&scene.underlines)
-                });
+        if let Some((offset, cursor)) = offsets.software_cursor.zip(self.software_cursor) {
+            instance_belt.write_with(
+                &mut command_encoder,
+                device,
+                offset,
+                &software_cursor_quads(cursor),
+                |quad| GpuQuad::snapped(quad, pixel_snapping),
+            );
         }
-        if !scene.monochrome_sprites.is_empty() {
-            self.context
-                .queue
-                .write_buffer(&self.context.mono_sprites_buffer, 0, unsafe {
-                    as_bytes(&scene.monochrome_sprites)
-                });
+        if let Some(offset) = offsets.shadows {
+            instance_belt.write::<_, GpuShadow>(
+                &mut command_encoder,
+                device,
+                offset,
+                &scene.shadows,
+            );
         }
-        if !scene.polychrome_sprites.is_empty() {
-            self.context
-                .queue
-                .write_buffer(&self.context.poly_sprites_buffer, 0, unsafe {
-                    as_bytes(&scene.polychrome_sprites)
+        if let Some(offset) = offsets.underlines {
+            instance_belt.write_with(
+                &mut command_encoder,
+                device,
+                offset,
+                &scene.underlines,
+                |underline| GpuUnderline::snapped(underline, pixel_snapping),
+            );
+        }
+        if let Some(offset) = offsets.mono_sprites {
+            instance_belt.write::<_, GpuMonochromeSprite>(
+                &mut command_encoder,
+                device,
+                offset,
+                &scene.monochrome_sprites,
+            );
+        }
+        if let Some(offset) = offsets.poly_sprites {
+            instance_belt.write::<_, GpuPolychromeSprite>(
+                &mut command_encoder,
+                device,
+                offset,
+                &scene.polychrome_sprites,
+            );
+        }
+        for (grid, grid_offsets) in scene.cell_grids.iter().zip(&offsets.cell_grids) {
+            let Some([grid_offset, glyphs_offset, cells_offset]) = *grid_offsets else {
+                continue;
+            };
+            instance_belt.write::<_, GpuCellGrid>(
+                &mut command_encoder,
+                device,
+                grid_offset,
+                std::slice::from_ref(grid),
+            );
+            instance_belt.write::<_, GpuCellGlyph>(
+                &mut command_encoder,
+                device,
+                glyphs_offset,
+                &grid.glyphs,
+            );
+            instance_belt.write::<_, GpuGridCell>(
+                &mut command_encoder,
+                device,
+                cells_offset,
+                &grid.cells,
+            );
+        }
+        if let Some([selections_offset, lines_offset]) = offsets.selections {
+            let mut selections = Vec::with_capacity(scene.selections.len());
+            let mut lines = Vec::new();
+            for (index, selection) in scene.selections.iter().enumerate() {
+                selections.push(GpuSelection {
+                    color: selection.color.into(),
+                    content_mask: (&selection.content_mask.bounds).into(),
+                    top: selection.bounds.origin.y.0,
+                    line_height: selection.line_height.0,
+                    corner_radius: selection.corner_radius.0,
+                    first_line: lines.len() as u32,
+                    line_count: selection.lines.len() as u32,
+                    pad: [0; 3],
                 });
+                lines.extend(selection.lines.iter().map(|line| GpuSelectionLine {
+                    start_x: line.start.0,
+                    end_x: line.end.0,
+                    selection: index as u32,
+                }));
+            }
+            instance_belt.write_with(
+                &mut command_encoder,
+                device,
+                selections_offset,
+                &selections,
+                |selection| *selection,
+            );
+            instance_belt.write_with(&mut command_encoder, device, lines_offset, &lines, |line| {
+                *line
+            });
         }
+        if let Some(offset) = offsets.clips {
+            instance_belt.write::<_, GpuClip>(&mut command_encoder, device, offset, &scene.clips);
+        }
+        instance_belt.finish();
 
-        let surface_texture = self
-            .surface
-            .get_current_texture()
-            .expect("Failed to acquire next swap chain texture");
+        let commands = command_encoder.finish();
+        self.context.set_active_pass(None);
+        commands
+    }
 
-        let quads_bind_group = self
-            .context
-            .device
-            .create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("quads_bind_group"),
-                layout: &self.pipelines.quads_bind_group_layout,
-                entries: &[wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                        buffer: &self.context.quads_buffer,
-                        offset: 0,
-                        size: None,
-                    }),
-                }],
-            });
+    /// Uploads `color_filter` and returns the view of the texture the frame is drawn into before
+    /// it's filtered, recreating the texture when the target was resized.
+    fn prepare_color_filter(&mut self, color_filter: ColorFilter) -> Option<wgpu::TextureView> {
+        self.context.queue.write_buffer(
+            &self.color_filter_params_buffer,
+            0,
+            bytemuck::bytes_of(&ColorFilterParams {
+                matrix: color_filter.matrix,
+            }),
+        );
 
-        let shadows_bind_group =
-            self.context
+        let size = wgpu::Extent3d {
+            width: self.surface_configuration.width.max(1),
+            height: self.surface_configuration.height.max(1),
+            depth_or_array_layers: 1,
+        };
+        let is_stale = !matches!(
+            &self.unfiltered_frame,
+            Some((texture, _)) if texture.size() == size
+        );
+        if is_stale {
+            let texture = self
+                .context
                 .device
-                .create_bind_group(&wgpu::BindGroupDescriptor {
-                    label: Some("shadows_bind_group"),
-                    layout: &self.pipelines.shadows_bind_group_layout,
-                    entries: &[wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                            buffer: &self.context.shadows_buffer,
-                            offset: 0,
-                            size: None,
-                        }),
-                    }],
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some("unfiltered_frame"),
+                    size,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: self.view_format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
                 });
-
-        let underlines_bind_group =
-            self.context
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let bind_group = self
+                .context
                 .device
                 .create_bind_group(&wgpu::BindGroupDescriptor {
-                    label: Some("underlines_bind_group"),
-                    layout: &self.pipelines.underlines_bind_group_layout,
-                    entries: &[wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                            buffer: &self.context.underlines_buffer,
-                            offset: 0,
-                            size: None,
-                        }),
-                    }],
+                    label: Some("color_filter_bind_group"),
+                    layout: &self.pipelines.color_filter_bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: self.color_filter_params_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::TextureView(&view),
+                        },
+                    ],
                 });
+            self.unfiltered_frame = Some((texture, bind_group));
+        }
+
+        self.unfiltered_frame
+            .as_ref()
+            .map(|(texture, _)| texture.create_view(&wgpu::TextureViewDescriptor::default()))
+    }
+
+    /// Returns a view of the stencil buffer clips are drawn into, recreating it when it's drawn
+    /// with a target of another size.
+    fn prepare_clip_stencil(&mut self, size: wgpu::Extent3d) -> wgpu::TextureView {
+        if self
+            .clip_stencil
+            .as_ref()
+            .is_some_and(|texture| texture.size() != size)
+        {
+            self.clip_stencil = None;
+        }
+        let device = &self.context.device;
+        self.clip_stencil
+            .get_or_insert_with(|| {
+                device.create_texture(&wgpu::TextureDescriptor {
+                    label: Some("clip_stencil"),
+                    size,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: CLIP_STENCIL_FORMAT,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    view_formats: &[],
+                })
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default())
+    }
 
-        let mono_sprites_bind_group =
+    fn encode_color_filter_pass(
+        &self,
+        target: &wgpu::TextureView,
+        globals_offset: u32,
+    ) -> Option<wgpu::CommandBuffer> {
+        let (_, bind_group) = self.unfiltered_frame.as_ref()?;
+        self.context.set_active_pass(Some("color_filter"));
+        let mut command_encoder =
             self.context
                 .device
-                .create_bind_group(&wgpu::BindGroupDescriptor {
-                    label: Some("mono_sprites_bind_group"),
-                    layout: &self.pipelines.mono_sprites_bind_group_layout,
-                    entries: &[wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                            buffer: &self.context.mono_sprites_buffer,
-                            offset: 0,
-                            size: None,
-                        }),
-                    }],
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("color_filter"),
                 });
+        {
+            let mut pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("color_filter"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    resolve_target: None,
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&self.pipelines.color_filter_pipeline);
+            pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[globals_offset]);
+            pass.set_bind_group(1, bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+        let commands = command_encoder.finish();
+        self.context.set_active_pass(None);
+        Some(commands)
+    }
 
-        let poly_sprites_bind_group =
+    fn encode_main_pass(
+        &self,
+        scene: &Scene,
+        offsets: &InstanceOffsets,
+        target: &MainPassTarget,
+    ) -> EncodedMainPass {
+        self.context.set_active_pass(Some("main"));
+        let mut command_encoder =
             self.context
                 .device
-                .create_bind_group(&wgpu::BindGroupDescriptor {
-                    label: Some("poly_sprites_bind_group"),
-                    layout: &self.pipelines.poly_sprites_bind_group_layout,
-                    entries: &[wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                            buffer: &self.context.poly_sprites_buffer,
-                            offset: 0,
-                            size: None,
-                        }),
-                    }],
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("main"),
                 });
 
+        // keep track of which surface ids we rendered this frame
+        let mut seen_surfaces = CompositedSurfaces::new();
+        // Draw calls are also collected while frame statistics are logged, which count them.
+        let mut draw_calls =
+            (self.draw_call_recording || self.frame_stats.is_some()).then(Vec::new);
         {
             let mut pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("main"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &surface_texture
-                        .texture
-                        .create_view(&wgpu::TextureViewDescriptor::default()),
+                    view: target.view,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        load: match target.clear_color {
+                            Some(clear_color) => wgpu::LoadOp::Clear(clear_color),
+                            None => wgpu::LoadOp::Load,
+                        },
                         store: wgpu::StoreOp::Store,
                     },
                     resolve_target: None,
                 })],
-                depth_stencil_attachment: None,
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: target.clip_stencil,
+                    depth_ops: None,
+                    stencil_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                }),
                 timestamp_writes: None,
                 occlusion_query_set: None,
             });
+            if let Some(viewport) = target.viewport {
+                pass.set_viewport(
+                    viewport.origin.x.0 as f32,
+                    viewport.origin.y.0 as f32,
+                    viewport.size.width.0 as f32,
+                    viewport.size.height.0 as f32,
+                    0.0,
+                    1.0,
+                );
+                // Primitives are clipped to the viewport already, but wide guard bands on some GPUs
+                // let antialiased edges bleed past it.
+                pass.set_scissor_rect(
+                    viewport.origin.x.0 as u32,
+                    viewport.origin.y.0 as u32,
+                    viewport.size.width.0 as u32,
+                    viewport.size.height.0 as u32,
+                );
+            }
+            if let Some(scissor) = target.scissor {
+                pass.set_scissor_rect(
+                    scissor.origin.x.0 as u32,
+                    scissor.origin.y.0 as u32,
+                    scissor.size.width.0 as u32,
+                    scissor.size.height.0 as u32,
+                );
+            }
 
             let mut quads_first_instance: u32 = 0;
             let mut shadows_first_instance: u32 = 0;
             let mut underlines_first_instance: u32 = 0;
             let mut mono_sprites_first_instance: u32 = 0;
             let mut poly_sprites_first_instance: u32 = 0;
+            let mut cell_grids_drawn: usize = 0;
+            let mut surfaces_drawn: usize = 0;
+            let mut selection_lines_first_instance: u32 = 0;
+            // the clips drawn into the stencil buffer, outermost first
+            let mut active_clips = ClipStack::new();
 
             for batch in scene.batches() {
+                if let Some(offset) = offsets.clips {
+                    self.switch_clip(
+                        &mut pass,
+                        target.pipelines,
+                        &scene.clips,
+                        offsets.globals,
+                        offset,
+                        &mut active_clips,
+                        batch.clip(),
+                        &mut draw_calls,
+                    );
+                }
                 match batch {
                     PrimitiveBatch::Quads(quads) => {
                         let count = quads.len() as u32;
-                        pass.set_pipeline(&self.pipelines.quads_pipeline);
-                        pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[]);
-                        pass.set_bind_group(1, &quads_bind_group, &[]);
+                        let Some(offset) = offsets.quads else {
+                            continue;
+                        };
+                        pass.set_pipeline(&target.pipelines.quads_pipeline);
+                        pass.set_bind_group(
+                            0,
+                            &target.pipelines.globals_bind_group,
+                            &[offsets.globals],
+                        );
+                        pass.set_bind_group(1, &self.instance_belt.bind_groups().quads, &[offset]);
                         pass.draw(0..4, quads_first_instance..quads_first_instance + count);
+                        if let Some(draw_calls) = &mut draw_calls {
+                            draw_calls.push(DrawCall {
+                                kind: DrawCallKind::Quads,
+                                instance_count: count,
+                                atlas_texture: None,
+                                buffer_range: Some(instance_range::<GpuQuad>(
+                                    offset,
+                                    quads_first_instance,
+                                    count,
+                                )),
+                            });
+                        }
                         quads_first_instance += count;
                     }
 
@@ -1497,39 +4470,52 @@ impl WgpuRenderer {
                         sprites,
                     } => {
                         let count = sprites.len() as u32;
-                        let tex_info = self.atlas.get_texture_info(texture_id);
-
-                        let sprites_texture_bind_group =
-                            self.context
-                                .device
-                                .create_bind_group(&wgpu::BindGroupDescriptor {
-                                    label: Some("sprites_bind_group"),
-                                    layout: &self.pipelines.sprites_bind_group_layout,
-                                    entries: &[
-                                        wgpu::BindGroupEntry {
-                                            binding: 0,
-                                            resource: wgpu::BindingResource::TextureView(
-                                                &tex_info.raw_view,
-                                            ),
-                                        },
-                                        wgpu::BindGroupEntry {
-                                            binding: 1,
-                                            resource: wgpu::BindingResource::Sampler(
-                                                &self.atlas_sampler,
-                                            ),
-                                        },
-                                    ],
-                                });
+                        let Some(offset) = offsets.mono_sprites else {
+                            continue;
+                        };
+                        // the texture is gone if the atlas was evicted after this scene was built
+                        let Some(tex_info) = self.atlas.get_texture_info(texture_id) else {
+                            mono_sprites_first_instance += count;
+                            continue;
+                        };
+
+                        let sprites_texture_bind_group = self.resource_pool.texture_bind_group(
+                            &self.context.device,
+                            "sprites_bind_group",
+                            &target.pipelines.sprites_bind_group_layout,
+                            &tex_info.raw_view,
+                            self.sprite_sampler(),
+                        );
 
-                        pass.set_pipeline(&self.pipelines.mono_sprites_pipeline);
-                        pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[]);
-                        pass.set_bind_group(1, &self.pipelines.color_adjustments_bind_group, &[]);
+                        pass.set_pipeline(&target.pipelines.mono_sprites_pipeline);
+                        pass.set_bind_group(
+                            0,
+                            &target.pipelines.globals_bind_group,
+                            &[offsets.globals],
+                        );
+                        pass.set_bind_group(1, &target.pipelines.color_adjustments_bind_group, &[]);
                         pass.set_bind_group(2, &sprites_texture_bind_group, &[]);
-                        pass.set_bind_group(3, &mono_sprites_bind_group, &[]);
+                        pass.set_bind_group(
+                            3,
+                            &self.instance_belt.bind_groups().mono_sprites,
+                            &[offset],
+                        );
                         pass.draw(
                             0..4,
                             mono_sprites_first_instance..mono_sprites_first_instance + count,
                         );
+                        if let Some(draw_calls) = &mut draw_calls {
+                            draw_calls.push(DrawCall {
+                                kind: DrawCallKind::MonochromeSprites,
+                                instance_count: count,
+                                atlas_texture: Some(texture_id.index),
+                                buffer_range: Some(instance_range::<GpuMonochromeSprite>(
+                                    offset,
+                                    mono_sprites_first_instance,
+                                    count,
+                                )),
+                            });
+                        }
                         mono_sprites_first_instance += count;
                     }
                     PrimitiveBatch::PolychromeSprites {
@@ -1537,60 +4523,221 @@ impl WgpuRenderer {
                         sprites,
                     } => {
                         let count = sprites.len() as u32;
-                        let tex_info = self.atlas.get_texture_info(texture_id);
-
-                        let sprites_texture_bind_group =
-                            self.context
-                                .device
-                                .create_bind_group(&wgpu::BindGroupDescriptor {
-                                    label: Some("poly_sprites_texture_bind_group"),
-                                    layout: &self.pipelines.sprites_bind_group_layout,
-                                    entries: &[
-                                        wgpu::BindGroupEntry {
-                                            binding: 0,
-                                            resource: wgpu::BindingResource::TextureView(
-                                                &tex_info.raw_view,
-                                            ),
-                                        },
-                                        wgpu::BindGroupEntry {
-                                            binding: 1,
-                                            resource: wgpu::BindingResource::Sampler(
-                                                &self.atlas_sampler,
-                                            ),
-                                        },
-                                    ],
-                                });
+                        let Some(offset) = offsets.poly_sprites else {
+                            continue;
+                        };
+                        // the texture is gone if the atlas was evicted after this scene was built
+                        let Some(tex_info) = self.atlas.get_texture_info(texture_id) else {
+                            poly_sprites_first_instance += count;
+                            continue;
+                        };
+
+                        let sprites_texture_bind_group = self.resource_pool.texture_bind_group(
+                            &self.context.device,
+                            "poly_sprites_texture_bind_group",
+                            &target.pipelines.sprites_bind_group_layout,
+                            &tex_info.raw_view,
+                            self.sprite_sampler(),
+                        );
 
-                        pass.set_pipeline(&self.pipelines.poly_sprites_pipeline);
-                        pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[]);
+                        pass.set_pipeline(&target.pipelines.poly_sprites_pipeline);
+                        pass.set_bind_group(
+                            0,
+                            &target.pipelines.globals_bind_group,
+                            &[offsets.globals],
+                        );
                         pass.set_bind_group(1, &sprites_texture_bind_group, &[]);
-                        pass.set_bind_group(2, &poly_sprites_bind_group, &[]);
+                        pass.set_bind_group(
+                            2,
+                            &self.instance_belt.bind_groups().poly_sprites,
+                            &[offset],
+                        );
                         pass.draw(
                             0..4,
                             poly_sprites_first_instance..poly_sprites_first_instance + count,
                         );
+                        if let Some(draw_calls) = &mut draw_calls {
+                            draw_calls.push(DrawCall {
+                                kind: DrawCallKind::PolychromeSprites,
+                                instance_count: count,
+                                atlas_texture: Some(texture_id.index),
+                                buffer_range: Some(instance_range::<GpuPolychromeSprite>(
+                                    offset,
+                                    poly_sprites_first_instance,
+                                    count,
+                                )),
+                            });
+                        }
                         poly_sprites_first_instance += count;
                     }
                     PrimitiveBatch::Shadows(shadows) => {
                         let count = shadows.len() as u32;
-                        pass.set_pipeline(&self.pipelines.shadows_pipeline);
-                        pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[]);
-                        pass.set_bind_group(1, &shadows_bind_group, &[]);
+                        let Some(offset) = offsets.shadows else {
+                            continue;
+                        };
+                        pass.set_pipeline(&target.pipelines.shadows_pipeline);
+                        pass.set_bind_group(
+                            0,
+                            &target.pipelines.globals_bind_group,
+                            &[offsets.globals],
+                        );
+                        pass.set_bind_group(
+                            1,
+                            &self.instance_belt.bind_groups().shadows,
+                            &[offset],
+                        );
                         pass.draw(0..4, shadows_first_instance..shadows_first_instance + count);
+                        if let Some(draw_calls) = &mut draw_calls {
+                            draw_calls.push(DrawCall {
+                                kind: DrawCallKind::Shadows,
+                                instance_count: count,
+                                atlas_texture: None,
+                                buffer_range: Some(instance_range::<GpuShadow>(
+                                    offset,
+                                    shadows_first_instance,
+                                    count,
+                                )),
+                            });
+                        }
                         shadows_first_instance += count;
                     }
                     PrimitiveBatch::Underlines(underlines) => {
                         let count = underlines.len() as u32;
-                        pass.set_pipeline(&self.pipelines.underlines_pipeline);
-                        pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[]);
-                        pass.set_bind_group(1, &underlines_bind_group, &[]);
+                        let Some(offset) = offsets.underlines else {
+                            continue;
+                        };
+                        pass.set_pipeline(&target.pipelines.underlines_pipeline);
+                        pass.set_bind_group(
+                            0,
+                            &target.pipelines.globals_bind_group,
+                            &[offsets.globals],
+                        );
+                        pass.set_bind_group(
+                            1,
+                            &self.instance_belt.bind_groups().underlines,
+                            &[offset],
+                        );
                         pass.draw(
                             0..4,
                             underlines_first_instance..underlines_first_instance + count,
                         );
+                        if let Some(draw_calls) = &mut draw_calls {
+                            draw_calls.push(DrawCall {
+                                kind: DrawCallKind::Underlines,
+                                instance_count: count,
+                                atlas_texture: None,
+                                buffer_range: Some(instance_range::<GpuUnderline>(
+                                    offset,
+                                    underlines_first_instance,
+                                    count,
+                                )),
+                            });
+                        }
                         underlines_first_instance += count;
                     }
+                    PrimitiveBatch::CellGrids(grids) => {
+                        for grid in grids {
+                            let grid_offsets = offsets.cell_grids[cell_grids_drawn];
+                            cell_grids_drawn += 1;
+                            let Some([grid_offset, glyphs_offset, cells_offset]) = grid_offsets
+                            else {
+                                continue;
+                            };
+                            // Only the backgrounds are drawn when the glyphs' texture was evicted
+                            // from the atlas after the scene was built.
+                            let glyph_texture = grid
+                                .texture_id
+                                .and_then(|texture_id| self.atlas.get_texture_info(texture_id));
+                            let cell_count = grid.cells.len() as u32;
+                            let instance_count = match glyph_texture {
+                                Some(_) => cell_count * 2,
+                                None => cell_count,
+                            };
+                            let texture_bind_group = self.resource_pool.texture_bind_group(
+                                &self.context.device,
+                                "cell_grids_texture_bind_group",
+                                &target.pipelines.sprites_bind_group_layout,
+                                glyph_texture
+                                    .as_ref()
+                                    .map_or(&self.blank_glyph_texture, |info| &info.raw_view),
+                                self.sprite_sampler(),
+                            );
+
+                            pass.set_pipeline(&target.pipelines.cell_grids_pipeline);
+                            pass.set_bind_group(
+                                0,
+                                &target.pipelines.globals_bind_group,
+                                &[offsets.globals],
+                            );
+                            pass.set_bind_group(
+                                1,
+                                &target.pipelines.color_adjustments_bind_group,
+                                &[],
+                            );
+                            pass.set_bind_group(2, &texture_bind_group, &[]);
+                            pass.set_bind_group(
+                                3,
+                                &self.instance_belt.bind_groups().cell_grids,
+                                &[grid_offset, glyphs_offset, cells_offset],
+                            );
+                            pass.draw(0..4, 0..instance_count);
+                            if let Some(draw_calls) = &mut draw_calls {
+                                draw_calls.push(DrawCall {
+                                    kind: DrawCallKind::CellGrid,
+                                    instance_count,
+                                    atlas_texture: grid
+                                        .texture_id
+                                        .map(|texture_id| texture_id.index),
+                                    buffer_range: Some(instance_range::<GpuGridCell>(
+                                        cells_offset,
+                                        0,
+                                        cell_count,
+                                    )),
+                                });
+                            }
+                        }
+                    }
+                    PrimitiveBatch::Selections(selections) => {
+                        let count = selections
+                            .iter()
+                            .map(|selection| selection.lines.len() as u32)
+                            .sum::<u32>();
+                        let Some([selections_offset, lines_offset]) = offsets.selections else {
+                            continue;
+                        };
+                        pass.set_pipeline(&target.pipelines.selections_pipeline);
+                        pass.set_bind_group(
+                            0,
+                            &target.pipelines.globals_bind_group,
+                            &[offsets.globals],
+                        );
+                        pass.set_bind_group(
+                            1,
+                            &self.instance_belt.bind_groups().selections,
+                            &[selections_offset, lines_offset],
+                        );
+                        pass.draw(
+                            0..4,
+                            selection_lines_first_instance..selection_lines_first_instance + count,
+                        );
+                        if let Some(draw_calls) = &mut draw_calls {
+                            draw_calls.push(DrawCall {
+                                kind: DrawCallKind::Selections,
+                                instance_count: count,
+                                atlas_texture: None,
+                                buffer_range: Some(instance_range::<GpuSelectionLine>(
+                                    lines_offset,
+                                    selection_lines_first_instance,
+                                    count,
+                                )),
+                            });
+                        }
+                        selection_lines_first_instance += count;
+                    }
                     PrimitiveBatch::Surfaces(surfaces) => {
+                        let Some(surface_params_offset) = offsets.surface_params else {
+                            continue;
+                        };
                         for surface in surfaces {
                             if let crate::SurfaceContent::Wgpu(surface_id) = &surface.content {
                                 if let Some(idx) =
@@ -1608,8 +4755,17 @@ impl WgpuRenderer {
                                             .surface_registry
                                             .clear_present_pending(*surface_id);
 
-                                        let params = SurfaceParams {
-                                            bounds: Bounds {
+                                        // Until the producer presents a frame at a requested size,
+                                        // its last frame is shown letterboxed instead of stretched.
+                                        let bounds = match self
+                                            .context
+                                            .surface_registry
+                                            .stale_front_size(*surface_id)
+                                        {
+                                            Some(frame_size) => {
+                                                letterboxed_bounds(&surface.bounds, frame_size)
+                                            }
+                                            None => Bounds {
                                                 origin: [
                                                     surface.bounds.origin.x.0,
                                                     surface.bounds.origin.y.0,
@@ -1619,6 +4775,9 @@ impl WgpuRenderer {
                                                     surface.bounds.size.height.0,
                                                 ],
                                             },
+                                        };
+                                        let params = SurfaceParams {
+                                            bounds,
                                             content_mask: Bounds {
                                                 origin: [
                                                     surface.content_mask.bounds.origin.x.0,
@@ -1629,18 +4788,45 @@ impl WgpuRenderer {
                                                     surface.content_mask.bounds.size.height.0,
                                                 ],
                                             },
+                                            premultiplied: (self
+                                                .context
+                                                .surface_registry
+                                                .alpha_mode(*surface_id)
+                                                == Some(SurfaceAlphaMode::Premultiplied))
+                                                as u32,
+                                            linear: (self
+                                                .context
+                                                .surface_registry
+                                                .color_space(*surface_id)
+                                                == Some(SurfaceColorSpace::Linear))
+                                                as u32,
                                         };
 
-                                        self.context.queue.write_buffer(
-                                            &self.surface_params_buffer,
-                                            0,
-                                            bytemuck::bytes_of(&params),
+                                        let params_offset = self
+                                            .surface_params
+                                            .slot(surface_params_offset, surfaces_drawn);
+                                        surfaces_drawn += 1;
+                                        self.surface_params.write(
+                                            &self.context.queue,
+                                            params_offset,
+                                            &params,
                                         );
 
                                         // fetch or create cached bind groups for this surface
                                         let surface_bind_group = {
+                                            let generation = self
+                                                .context
+                                                .surface_registry
+                                                .generation(*surface_id)
+                                                .unwrap_or(0);
                                             let mut cache =
                                                 self.surface_bind_groups.lock().unwrap();
+                                            if cache
+                                                .get(surface_id)
+                                                .is_some_and(|entry| entry.generation != generation)
+                                            {
+                                                cache.remove(surface_id);
+                                            }
                                             let entry = cache
                                                 .entry(*surface_id)
                                                 .or_insert_with(|| {
@@ -1660,19 +4846,13 @@ impl WgpuRenderer {
                                                             .device
                                                             .create_bind_group(&wgpu::BindGroupDescriptor {
                                                                 label: Some("surface_bind_group"),
-                                                                layout: &self.pipelines.surfaces_bind_group_layout,
+                                                                layout: &target.pipelines.surfaces_bind_group_layout,
                                                                 entries: &[
                                                                     wgpu::BindGroupEntry {
                                                                         binding: 0,
-                                                                        resource:
-                                                                            wgpu::BindingResource::Buffer(
-                                                                                wgpu::BufferBinding {
-                                                                                    buffer: &self
-                                                                                        .surface_params_buffer,
-                                                                                    offset: 0,
-                                                                                    size: None,
-                                                                                },
-                                                                            ),
+                                                                        resource: self
+                                                                            .surface_params
+                                                                            .binding(),
                                                                     },
                                                                     wgpu::BindGroupEntry {
                                                                         binding: 1,
@@ -1685,57 +4865,440 @@ impl WgpuRenderer {
                                                                         binding: 2,
                                                                         resource:
                                                                             wgpu::BindingResource::Sampler(
-                                                                                &self.surface_sampler,
+                                                                                &self.resource_pool.sampler(
+                                                                                    &self.context.device,
+                                                                                    SamplerKey::LINEAR,
+                                                                                ),
                                                                             ),
                                                                     },
                                                                 ],
                                                             })
                                                     };
-                                                    [create_bg(&v0), create_bg(&v1)]
+                                                    SurfaceBindGroups {
+                                                        generation,
+                                                        bind_groups: [
+                                                            create_bg(&v0),
+                                                            create_bg(&v1),
+                                                        ],
+                                                        composited_frame: self.frame_serial,
+                                                    }
                                                 });
-                                            entry[idx].clone()
+                                            entry.composited_frame = self.frame_serial;
+                                            entry.bind_groups[idx].clone()
                                         };
 
-                                        pass.set_pipeline(&self.pipelines.surfaces_pipeline);
+                                        pass.set_pipeline(&target.pipelines.surfaces_pipeline);
                                         pass.set_bind_group(
                                             0,
-                                            &self.pipelines.globals_bind_group,
-                                            &[],
+                                            &target.pipelines.globals_bind_group,
+                                            &[offsets.globals],
+                                        );
+                                        pass.set_bind_group(
+                                            1,
+                                            &surface_bind_group,
+                                            &[params_offset],
                                         );
-                                        pass.set_bind_group(1, &surface_bind_group, &[]);
                                         pass.draw(0..4, 0..1);
-
-                                        seen_surfaces.push(*surface_id);
+                                        if let Some(draw_calls) = &mut draw_calls {
+                                            draw_calls.push(DrawCall {
+                                                kind: DrawCallKind::Surface,
+                                                instance_count: 1,
+                                                atlas_texture: None,
+                                                buffer_range: None,
+                                            });
+                                        }
+
+                                        if !seen_surfaces.contains(surface_id) {
+                                            seen_surfaces.push(*surface_id);
+                                        }
                                     }
                                 }
                             }
                         }
                     }
+                    PrimitiveBatch::Plugin { plugin, primitives } => {
+                        let Some(render_plugin) = self.context.render_plugins.get(plugin) else {
+                            continue;
+                        };
+                        let Some(color_target) = target.pipelines.color_targets[0].clone() else {
+                            continue;
+                        };
+                        let mut plugin_pipelines = self.plugin_pipelines.lock();
+                        let pipelines = plugin_pipelines
+                            .entry((plugin, color_target.clone()))
+                            .or_insert_with(|| {
+                                render_plugin.create_pipelines(&RenderPipelineContext {
+                                    device: &self.context.device,
+                                    queue: &self.context.queue,
+                                    color_target,
+                                    depth_stencil: clip_stencil_state(wgpu::StencilOperation::Keep),
+                                    globals_bind_group_layout: &target
+                                        .pipelines
+                                        .globals_bind_group_layout,
+                                })
+                            });
+                        pipelines.encode_batch(
+                            &PluginBatch {
+                                primitives,
+                                globals_bind_group: &target.pipelines.globals_bind_group,
+                                globals_offset: offsets.globals,
+                                device: &self.context.device,
+                                queue: &self.context.queue,
+                            },
+                            &mut pass,
+                        );
+                        if let Some(draw_calls) = &mut draw_calls {
+                            draw_calls.push(DrawCall {
+                                kind: DrawCallKind::Plugin,
+                                instance_count: primitives.len() as u32,
+                                atlas_texture: None,
+                                buffer_range: None,
+                            });
+                        }
+                    }
                     // TODO(mdeand): Implement paths rendering.
                     PrimitiveBatch::Paths(_) => {}
                 }
             }
+
+            if let Some(offset) = offsets.software_cursor {
+                if let Some(clips_offset) = offsets.clips {
+                    self.switch_clip(
+                        &mut pass,
+                        target.pipelines,
+                        &scene.clips,
+                        clips_offset,
+                        &mut active_clips,
+                        0,
+                        &mut draw_calls,
+                    );
+                }
+                let count = SOFTWARE_CURSOR_QUAD_COUNT as u32;
+                pass.set_pipeline(&target.pipelines.quads_pipeline);
+                pass.set_bind_group(0, &target.pipelines.globals_bind_group, &[offsets.globals]);
+                pass.set_bind_group(1, &self.instance_belt.bind_groups().quads, &[offset]);
+                pass.draw(0..4, 0..count);
+                if let Some(draw_calls) = &mut draw_calls {
+                    draw_calls.push(DrawCall {
+                        kind: DrawCallKind::Quads,
+                        instance_count: count,
+                        atlas_texture: None,
+                        buffer_range: Some(instance_range::<GpuQuad>(offset, 0, count)),
+                    });
+                }
+            }
         }
 
-        // remove cached bind groups for surfaces that disappeared this frame
-        {
-            let mut cache = self.surface_bind_groups.lock().unwrap();
-            cache.retain(|id, _| seen_surfaces.contains(id));
+        let commands = command_encoder.finish();
+        self.context.set_active_pass(None);
+        EncodedMainPass {
+            commands,
+            draw_calls,
+            seen_surfaces,
+        }
+    }
+
+    /// Updates the stencil buffer from the `active_clips` to the clips `clip` is nested in. The
+    /// clips that aren't shared are removed innermost first, then the new ones are drawn
+    /// outermost first, and batches are drawn where the stencil counts all of them.
+    #[allow(clippy::too_many_arguments)]
+    fn switch_clip(
+        &self,
+        pass: &mut wgpu::RenderPass,
+        pipelines: &WgpuPipelines,
+        clips: &[Clip],
+        globals_offset: u32,
+        offset: u32,
+        active_clips: &mut ClipStack,
+        clip: ClipId,
+        draw_calls: &mut Option<Vec<DrawCall>>,
+    ) {
+        if active_clips.last().copied().unwrap_or(0) == clip {
+            return;
         }
-        self.context.queue.submit(Some(command_encoder.finish()));
 
-        surface_texture.present();
+        let mut nested_clips = ClipStack::new();
+        let mut clip_id = clip;
+        while clip_id != 0 {
+            nested_clips.push(clip_id);
+            // Parents are pushed before their children, so a parent with a higher id only comes
+            // from a corrupt scene file and ends the chain.
+            clip_id = clips
+                .get(clip_id as usize - 1)
+                .filter(|clip| clip.parent < clip_id)
+                .map_or(0, |clip| clip.parent);
+        }
+        nested_clips.reverse();
+        let shared = active_clips
+            .iter()
+            .zip(&nested_clips)
+            .take_while(|(active, nested)| active == nested)
+            .count();
+
+        pass.set_bind_group(0, &pipelines.globals_bind_group, &[globals_offset]);
+        pass.set_bind_group(1, &self.instance_belt.bind_groups().clips, &[offset]);
+        let mut draw_clip = |pass: &mut wgpu::RenderPass, depth: usize, clip_id: ClipId| {
+            pass.set_stencil_reference(depth as u32);
+            pass.draw(0..4, clip_id - 1..clip_id);
+            if let Some(draw_calls) = draw_calls.as_mut() {
+                draw_calls.push(DrawCall {
+                    kind: DrawCallKind::Clip,
+                    instance_count: 1,
+                    atlas_texture: None,
+                    buffer_range: Some(instance_range::<GpuClip>(offset, clip_id - 1, 1)),
+                });
+            }
+        };
+        pass.set_pipeline(&pipelines.clip_pop_pipeline);
+        for (depth, clip_id) in active_clips.iter().enumerate().skip(shared).rev() {
+            draw_clip(pass, depth + 1, *clip_id);
+        }
+        pass.set_pipeline(&pipelines.clip_push_pipeline);
+        for (depth, clip_id) in nested_clips.iter().enumerate().skip(shared) {
+            draw_clip(pass, depth, *clip_id);
+        }
+        pass.set_stencil_reference(nested_clips.len() as u32);
+        *active_clips = nested_clips;
+    }
+
+    /// Evicts the atlas, and downscales surfaces if that isn't enough, when an atlas allocation
+    /// failed or the memory budget was exceeded, then reports it through the error handler.
+    fn relieve_memory_pressure(&mut self) {
+        let allocation_failed = self.atlas.take_eviction_request();
+        let memory = &self.context.memory;
+        if !memory.is_over_budget() {
+            self.memory_pressure_handled = false;
+        }
+        if !allocation_failed && (self.memory_pressure_handled || !memory.is_over_budget()) {
+            return;
+        }
+
+        let usage = memory.usage();
+        self.atlas.evict();
+        self.needs_full_redraw = true;
+        let surfaces_downscaled = memory.is_over_budget()
+            && self
+                .context
+                .surface_registry
+                .downscale(&self.context.device);
+        self.memory_pressure_handled = memory.is_over_budget();
+
+        let (kind, cause) = if allocation_failed {
+            (
+                GpuErrorKind::OutOfMemory,
+                "an atlas texture allocation failed",
+            )
+        } else {
+            (
+                GpuErrorKind::BudgetExceeded,
+                "the GPU memory budget was exceeded",
+            )
+        };
+        self.context.report_error(GpuError {
+            kind,
+            message: format!(
+                "{cause} with {usage:?} in use; evicted the sprite atlas{}",
+                if surfaces_downscaled {
+                    " and downscaled surfaces"
+                } else {
+                    ""
+                }
+            ),
+            pass_label: None,
+        });
+    }
+
+    /// Returns true if the atlas was evicted since the last call, in which case scenes built
+    /// before that reference tiles that no longer exist and the next frame must be rebuilt.
+    pub fn take_needs_full_redraw(&mut self) -> bool {
+        std::mem::take(&mut self.needs_full_redraw)
     }
 
     pub fn update_drawable_size(&mut self, size: geometry::Size<DevicePixels>) {
         self.surface_configuration.width = size.width.0 as u32;
         self.surface_configuration.height = size.height.0 as u32;
-        self.surface
-            .configure(&self.context.device, &self.surface_configuration);
+        self.configure_target();
 
         // todo!()
     }
 
+    fn configure_target(&mut self) {
+        match &mut self.target {
+            RenderTarget::Surface(surface) => {
+                surface.configure(&self.context.device, &self.surface_configuration)
+            }
+            RenderTarget::Texture(texture) => {
+                if texture.width() != self.surface_configuration.width
+                    || texture.height() != self.surface_configuration.height
+                {
+                    *texture =
+                        create_target_texture(&self.context.device, &self.surface_configuration);
+                }
+            }
+            RenderTarget::Lost | RenderTarget::Suspended => {}
+        }
+    }
+
+    /// Reads back the last frame drawn by an offscreen renderer. Frames drawn to a window's
+    /// surface can't be read back once they're presented.
+    pub fn capture_frame(&self) -> anyhow::Result<image::RgbaImage> {
+        let RenderTarget::Texture(texture) = &self.target else {
+            anyhow::bail!("only offscreen renderers can capture frames");
+        };
+
+        // The target is BGRA, so the channels are swapped into RGBA.
+        let mut pixels = self.context.read_texture(texture)?;
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+
+        image::RgbaImage::from_raw(texture.width(), texture.height(), pixels)
+            .context("captured frame has the wrong number of pixels")
+    }
+
+    /// Draws a scene file written by [`crate::Window::save_scene`] offscreen and reads back the
+    /// frame, so rendering artifacts can be reproduced without the app that produced them.
+    pub fn replay(scene_file: &std::path::Path) -> anyhow::Result<image::RgbaImage> {
+        let bytes = std::fs::read(scene_file)
+            .with_context(|| format!("failed to read {}", scene_file.display()))?;
+        let SavedScene {
+            mut scene,
+            viewport_size,
+            atlas_textures,
+        } = Scene::deserialize(&bytes)?;
+
+        let context = Arc::new(pollster::block_on(WgpuContext::new())?);
+        let atlas = Arc::new(WgpuAtlas::new(context.clone()));
+
+        // The textures get new ids in this atlas, so the sprites are pointed at them.
+        let mut texture_ids = FxHashMap::default();
+        for contents in &atlas_textures {
+            texture_ids.insert(contents.id, atlas.restore_texture(contents)?);
+        }
+        let restore_texture_id = |tile: &mut AtlasTile| -> anyhow::Result<()> {
+            tile.texture_id = *texture_ids.get(&tile.texture_id).with_context(|| {
+                format!("scene file is missing atlas texture {:?}", tile.texture_id)
+            })?;
+            Ok(())
+        };
+        for sprite in &mut scene.monochrome_sprites {
+            restore_texture_id(&mut sprite.tile)?;
+        }
+        for sprite in &mut scene.polychrome_sprites {
+            restore_texture_id(&mut sprite.tile)?;
+        }
+        for grid in &mut scene.cell_grids {
+            if let Some(texture_id) = &mut grid.texture_id {
+                *texture_id = *texture_ids.get(texture_id).with_context(|| {
+                    format!("scene file is missing atlas texture {texture_id:?}")
+                })?;
+            }
+        }
+
+        let mut renderer = Self::new_offscreen(
+            context,
+            atlas,
+            viewport_size.width.0 as u32,
+            viewport_size.height.0 as u32,
+        );
+        renderer.draw(&scene)?;
+        renderer.capture_frame()
+    }
+
+    /// Applies renderer settings that changed at runtime. The path sample count only applies to
+    /// renderers created afterwards, since the pipelines are built for it.
+    pub fn apply_settings(&mut self, settings: &RendererSettings) {
+        self.rendering_parameters = RenderingParameters::new(
+            settings,
+            self.context.renderer_settings.night_light_temperature(),
+        );
+        match (&mut self.frame_stats, settings.frame_stats_logging) {
+            (Some(frame_stats), Some(logging)) => frame_stats.set_logging(logging),
+            (frame_stats, logging) => *frame_stats = logging.map(FrameStatsLogger::new),
+        }
+        self.context
+            .submission_scheduler
+            .set_eco_mode(self.context.renderer_settings.eco_mode_active());
+
+        let RenderTarget::Surface(surface) = &self.target else {
+            return;
+        };
+        let capabilities = surface.get_capabilities(&self.context.adapter);
+        let present_mode = supported_present_mode(settings.present_mode, &capabilities);
+        if self.surface_configuration.present_mode != present_mode {
+            self.surface_configuration.present_mode = present_mode;
+            self.configure_target();
+        }
+    }
+
+    pub fn set_draw_call_recording(&mut self, enabled: bool) {
+        self.draw_call_recording = enabled;
+        if !enabled {
+            self.last_frame_draw_calls = None;
+        }
+    }
+
+    /// Starts copying presented frames to `recording`'s output, replacing any recording already
+    /// in progress.
+    pub fn start_frame_recording(&mut self, recording: FrameRecording) -> anyhow::Result<()> {
+        if let RenderTarget::Surface(surface) = &self.target {
+            let capabilities = surface.get_capabilities(&self.context.adapter);
+            if !capabilities.usages.contains(wgpu::TextureUsages::COPY_SRC) {
+                anyhow::bail!("this window's surface doesn't support reading back frames");
+            }
+        }
+
+        let recorder = FrameRecorder::new(recording, self.surface_configuration.format)?;
+        self.stop_frame_recording();
+        self.frame_recorder = Some(recorder);
+        if !self
+            .surface_configuration
+            .usage
+            .contains(wgpu::TextureUsages::COPY_SRC)
+        {
+            self.surface_configuration.usage |= wgpu::TextureUsages::COPY_SRC;
+            self.configure_target();
+        }
+        Ok(())
+    }
+
+    /// Stops recording frames, waiting for the frames already captured to be written.
+    pub fn stop_frame_recording(&mut self) {
+        let Some(recorder) = self.frame_recorder.take() else {
+            return;
+        };
+        if let Err(error) = self.context.device.poll(wgpu::PollType::Wait) {
+            log::error!("failed to wait for recorded frames: {error}");
+        }
+        recorder.finish();
+
+        if let RenderTarget::Surface(_) = &self.target {
+            self.surface_configuration.usage -= wgpu::TextureUsages::COPY_SRC;
+            self.configure_target();
+        }
+    }
+
+    /// Sets how often the display the window is on refreshes, which frames have to be drawn within
+    /// for the renderer not to be overloaded.
+    pub fn set_refresh_interval(&mut self, refresh_interval: std::time::Duration) {
+        self.frame_pacer.set_refresh_interval(refresh_interval);
+    }
+
+    /// The shortest time between frames while the renderer can't draw them as fast as the display
+    /// refreshes, or `None` while it keeps up.
+    pub fn overload_frame_interval(&self) -> Option<std::time::Duration> {
+        self.frame_pacer.overload_frame_interval()
+    }
+
+    /// How the frames drawn to the window's surface are reaching the display.
+    pub fn present_stats(&self) -> PresentStats {
+        self.frame_pacer.present_stats()
+    }
+
+    pub fn last_frame_draw_calls(&self) -> Option<FrameDrawCalls> {
+        self.last_frame_draw_calls.clone()
+    }
+
     pub fn sprite_atlas(&self) -> Arc<dyn PlatformAtlas> {
         self.atlas.clone()
     }
@@ -1749,18 +5312,135 @@ impl WgpuRenderer {
         }
     }
 
+    /// The color the main pass clears to: the override if one is set, otherwise fully transparent
+    /// for transparent windows so the desktop shows through, and black for opaque ones.
+    fn main_pass_clear_color(&self) -> wgpu::Color {
+        let Some(clear_color) = self.clear_color else {
+            return if self.transparent {
+                wgpu::Color::TRANSPARENT
+            } else {
+                wgpu::Color::BLACK
+            };
+        };
+
+        let mut rgba = clear_color.to_rgb();
+        if self.view_format.is_srgb() {
+            // Clear colors are linear on sRGB surfaces, which encode them on write.
+            rgba.r = srgb_to_linear(rgba.r);
+            rgba.g = srgb_to_linear(rgba.g);
+            rgba.b = srgb_to_linear(rgba.b);
+        }
+        let alpha = if self.transparent { rgba.a } else { 1.0 };
+        // The shaders blend in premultiplied alpha when the surface composites that way, so the
+        // clear color has to match.
+        let premultiplier = match self.surface_configuration.alpha_mode {
+            wgpu::CompositeAlphaMode::PreMultiplied => alpha,
+            _ => 1.0,
+        };
+        wgpu::Color {
+            r: (rgba.r * premultiplier) as f64,
+            g: (rgba.g * premultiplier) as f64,
+            b: (rgba.b * premultiplier) as f64,
+            a: alpha as f64,
+        }
+    }
+
+    /// Magnifies the frames drawn by [`WgpuRenderer::draw`], or draws them unmagnified again.
+    pub fn set_screen_zoom(&mut self, screen_zoom: Option<ScreenZoom>) {
+        self.screen_zoom = screen_zoom;
+    }
+
+    pub fn screen_zoom(&self) -> Option<ScreenZoom> {
+        self.screen_zoom
+    }
+
+    /// Draws a cursor over the frames drawn by [`WgpuRenderer::draw`], for windows whose cursor
+    /// was moved where the platform can't move the real one, or stops drawing it.
+    pub fn set_software_cursor(&mut self, software_cursor: Option<SoftwareCursor>) {
+        self.software_cursor = software_cursor;
+    }
+
+    /// Transforms the colors of the frames drawn by [`WgpuRenderer::draw`], or draws them
+    /// unfiltered again when `None`.
+    pub fn set_color_filter(&mut self, color_filter: Option<ColorFilter>) {
+        self.color_filter = color_filter;
+        if color_filter.is_none() {
+            self.unfiltered_frame = None;
+        }
+    }
+
+    pub fn color_filter(&self) -> Option<ColorFilter> {
+        self.color_filter
+    }
+
+    /// Sets a callback invoked right before each frame is presented to the window's surface.
+    pub fn set_pre_present_notify(
+        &mut self,
+        pre_present_notify: impl Fn() + Send + Sync + 'static,
+    ) {
+        self.pre_present_notify = Some(Box::new(pre_present_notify));
+    }
+
+    /// The atlas is sampled without filtering while the frame is zoomed, so glyphs stay crisp.
+    fn sprite_sampler(&self) -> SamplerKey {
+        match self.screen_zoom {
+            Some(zoom) if zoom.scale > 1.0 => SamplerKey::NEAREST_MAGNIFIED,
+            _ => SamplerKey::LINEAR,
+        }
+    }
+
+    /// Overrides the color the window is cleared to before each frame, or restores the default
+    /// derived from the window's background appearance when `None`. The alpha is only honored by
+    /// transparent windows.
+    pub fn set_clear_color(&mut self, clear_color: Option<Hsla>) {
+        self.clear_color = clear_color;
+    }
+
+    /// Switches between opaque and transparent compositing. The surface is reconfigured right
+    /// before the next frame is drawn, so the first frame presented in the new alpha mode is
+    /// drawn for it instead of the surface flashing its previous contents.
     pub fn update_transparency(&mut self, transparent: bool) {
-        self.surface_configuration.alpha_mode = if transparent {
+        if self.transparent == transparent {
+            return;
+        }
+        self.transparent = transparent;
+        self.pending_alpha_mode = Some(if transparent {
             wgpu::CompositeAlphaMode::PreMultiplied
         } else {
             // TODO(mdeand): Support for non-X11?
             // wgpu::CompositeAlphaMode::Opaque
             wgpu::CompositeAlphaMode::Inherit
-        };
-        self.surface
-            .configure(&self.context.device, &self.surface_configuration);
+        });
+    }
 
-        // todo!()
+    /// Reconfigures the surface for `alpha_mode` and rebuilds the pipelines, whose blend state
+    /// depends on it, along with everything bound through their layouts.
+    fn apply_alpha_mode(&mut self, alpha_mode: wgpu::CompositeAlphaMode) {
+        if self.surface_configuration.alpha_mode == alpha_mode {
+            return;
+        }
+        self.surface_configuration.alpha_mode = alpha_mode;
+        self.configure_target();
+
+        self.pipelines = WgpuPipelines::new(
+            &self.context,
+            &self.surface_configuration,
+            self.view_format,
+            path_sample_count(&self.context),
+            &self.globals,
+        );
+        self.instance_belt = InstanceBelt::new(
+            &self.context.device,
+            &self.pipelines,
+            self.context.memory.clone(),
+            self.surface_configuration.desired_maximum_frame_latency,
+        );
+        self.surface_bind_groups = Mutex::new(HashMap::new());
+        self.plugin_pipelines.lock().clear();
+        self.resource_pool.clear();
+        self.unfiltered_frame = None;
+        self.clip_stencil = None;
+        self.low_latency_scene = None;
     }
 
     pub fn destroy(&mut self) {
@@ -1777,3 +5457,233 @@ impl WgpuRenderer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::mem::{offset_of, size_of};
+
+    use super::*;
+
+    const SHADERS: &[(&str, &str)] = &[
+        ("cell_grids", include_str!("shaders/cell_grids.wgsl")),
+        ("clips", include_str!("shaders/clips.wgsl")),
+        ("color_filter", include_str!("shaders/color_filter.wgsl")),
+        ("mono_sprites", include_str!("shaders/mono_sprites.wgsl")),
+        ("poly_sprites", include_str!("shaders/poly_sprites.wgsl")),
+        ("quads", include_str!("shaders/quads.wgsl")),
+        ("selections", include_str!("shaders/selections.wgsl")),
+        ("shadows", include_str!("shaders/shadows.wgsl")),
+        ("surfaces", include_str!("shaders/surfaces.wgsl")),
+        ("underlines", include_str!("shaders/underlines.wgsl")),
+    ];
+
+    fn parse_shader(name: &str) -> naga::Module {
+        let (_, source) = SHADERS
+            .iter()
+            .find(|(shader_name, _)| *shader_name == name)
+            .unwrap_or_else(|| panic!("there's no shader named {name}"));
+        parse_composed(source, ShaderFeatures::default())
+    }
+
+    fn parse_composed(source: &str, features: ShaderFeatures) -> naga::Module {
+        let source = shader_preprocessor::preprocess(source, features);
+        naga::front::wgsl::parse_str(&source)
+            .unwrap_or_else(|error| panic!("{}", error.emit_to_string(&source)))
+    }
+
+    #[test]
+    fn test_shaders_compose_for_every_feature() {
+        for premultiplied_alpha in [false, true] {
+            for srgb_output in [false, true] {
+                let features = ShaderFeatures {
+                    premultiplied_alpha,
+                    srgb_output,
+                };
+                for (_, source) in SHADERS {
+                    parse_composed(source, features);
+                }
+            }
+        }
+    }
+
+    /// Asserts that the WGSL struct `name` is `size` bytes and has exactly `members`, by name and
+    /// offset, which are the offsets of the fields of the Rust type it's uploaded as.
+    #[track_caller]
+    fn assert_layout(module: &naga::Module, name: &str, size: usize, members: &[(&str, usize)]) {
+        let (wgsl_members, span) = module
+            .types
+            .iter()
+            .find_map(|(_, ty)| match &ty.inner {
+                naga::TypeInner::Struct { members, span } if ty.name.as_deref() == Some(name) => {
+                    Some((members, *span))
+                }
+                _ => None,
+            })
+            .unwrap_or_else(|| panic!("there's no struct {name}"));
+        let wgsl_members = wgsl_members
+            .iter()
+            .map(|member| {
+                (
+                    member.name.as_deref().unwrap_or_default(),
+                    member.offset as usize,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(wgsl_members, members, "the members of {name} differ");
+        assert_eq!(span as usize, size, "the size of {name} differs");
+    }
+
+    #[test]
+    fn test_globals_layout() {
+        for (name, _) in SHADERS {
+            assert_layout(
+                &parse_shader(name),
+                "Globals",
+                size_of::<GlobalParams>(),
+                &[
+                    ("viewport_size", offset_of!(GlobalParams, viewport_size)),
+                    (
+                        "premultiplied_alpha",
+                        offset_of!(GlobalParams, premultimated_alpha),
+                    ),
+                    ("srgb_output", offset_of!(GlobalParams, srgb_output)),
+                    ("viewport_origin", offset_of!(GlobalParams, viewport_origin)),
+                    ("target_origin", offset_of!(GlobalParams, target_origin)),
+                ],
+            );
+        }
+    }
+
+    #[test]
+    fn test_quad_layout() {
+        let module = parse_shader("quads");
+        assert_layout(
+            &module,
+            "Hsla",
+            size_of::<GpuHsla>(),
+            &[
+                ("h", offset_of!(GpuHsla, h)),
+                ("s", offset_of!(GpuHsla, s)),
+                ("l", offset_of!(GpuHsla, l)),
+                ("a", offset_of!(GpuHsla, a)),
+            ],
+        );
+        assert_layout(
+            &module,
+            "Bounds",
+            size_of::<Bounds>(),
+            &[
+                ("origin", offset_of!(Bounds, origin)),
+                ("size", offset_of!(Bounds, size)),
+            ],
+        );
+        assert_layout(
+            &module,
+            "LinearColorStop",
+            size_of::<GpuLinearColorStop>(),
+            &[
+                ("color", offset_of!(GpuLinearColorStop, color)),
+                ("percentage", offset_of!(GpuLinearColorStop, percentage)),
+            ],
+        );
+        assert_layout(
+            &module,
+            "Background",
+            size_of::<GpuBackground>(),
+            &[
+                ("tag", offset_of!(GpuBackground, tag)),
+                ("color_space", offset_of!(GpuBackground, color_space)),
+                ("solid", offset_of!(GpuBackground, solid)),
+                (
+                    "gradient_angle_or_pattern_height",
+                    offset_of!(GpuBackground, gradient_angle_or_pattern_height),
+                ),
+                ("color0", offset_of!(GpuBackground, colors)),
+                (
+                    "color1",
+                    offset_of!(GpuBackground, colors) + size_of::<GpuLinearColorStop>(),
+                ),
+                ("pad", offset_of!(GpuBackground, pad)),
+            ],
+        );
+        assert_layout(
+            &module,
+            "Quad",
+            size_of::<GpuQuad>(),
+            &[
+                ("order", offset_of!(GpuQuad, order)),
+                ("border_style", offset_of!(GpuQuad, border_style)),
+                ("bounds", offset_of!(GpuQuad, bounds)),
+                ("content_mask", offset_of!(GpuQuad, content_mask)),
+                ("background", offset_of!(GpuQuad, background)),
+                ("border_color", offset_of!(GpuQuad, border_color)),
+                ("corner_radii", offset_of!(GpuQuad, corner_radii)),
+                ("border_widths", offset_of!(GpuQuad, border_widths)),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_monochrome_sprite_layout() {
+        let module = parse_shader("mono_sprites");
+        assert_layout(
+            &module,
+            "AtlasTile",
+            size_of::<GpuAtlasTile>(),
+            &[
+                ("texture_id", offset_of!(GpuAtlasTile, texture_index)),
+                ("tile_id", offset_of!(GpuAtlasTile, tile_id)),
+                ("padding", offset_of!(GpuAtlasTile, padding)),
+                ("bounds", offset_of!(GpuAtlasTile, origin)),
+            ],
+        );
+        assert_layout(
+            &module,
+            "MonochromeSprite",
+            size_of::<GpuMonochromeSprite>(),
+            &[
+                ("order", offset_of!(GpuMonochromeSprite, order)),
+                ("pad", offset_of!(GpuMonochromeSprite, pad)),
+                ("bounds", offset_of!(GpuMonochromeSprite, bounds)),
+                (
+                    "content_mask",
+                    offset_of!(GpuMonochromeSprite, content_mask),
+                ),
+                ("color", offset_of!(GpuMonochromeSprite, color)),
+                ("tile", offset_of!(GpuMonochromeSprite, tile)),
+                (
+                    "transformation",
+                    offset_of!(GpuMonochromeSprite, rotation_scale),
+                ),
+            ],
+        );
+        assert_layout(
+            &module,
+            "TransformationMatrix",
+            size_of::<GpuMonochromeSprite>() - offset_of!(GpuMonochromeSprite, rotation_scale),
+            &[
+                ("rotation_scale", 0),
+                (
+                    "translation",
+                    offset_of!(GpuMonochromeSprite, translation)
+                        - offset_of!(GpuMonochromeSprite, rotation_scale),
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_surface_params_layout() {
+        assert_layout(
+            &parse_shader("surfaces"),
+            "SurfaceParams",
+            size_of::<SurfaceParams>(),
+            &[
+                ("bounds", offset_of!(SurfaceParams, bounds)),
+                ("content_mask", offset_of!(SurfaceParams, content_mask)),
+                ("premultiplied", offset_of!(SurfaceParams, premultiplied)),
+                ("linear", offset_of!(SurfaceParams, linear)),
+            ],
+        );
+    }
+}
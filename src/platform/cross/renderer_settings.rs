@@ -0,0 +1,262 @@
+use parking_lot::{Mutex, RwLock};
+
+use std::time::Duration;
+
+use crate::{
+    AtlasAllocationStrategy, EcoMode, FrameStatsFormat, FrameStatsLogging, PixelSnappingPolicy,
+    PowerState, PresentMode, RendererSettings, WhitePoint,
+};
+
+type SettingsObserver = Box<dyn Fn(&RendererSettings) + Send + Sync>;
+
+/// The renderer settings shared by every window, with the environment variable overrides applied.
+///
+/// Observers are notified whenever the settings change, on the thread that changed them.
+pub(crate) struct RendererSettingsRegistry {
+    settings: RwLock<RendererSettings>,
+    overrides: EnvOverrides,
+    // the color temperature of the OS night light, while it's on
+    night_light_temperature: RwLock<Option<f32>>,
+    power_state: RwLock<PowerState>,
+    observers: Mutex<Vec<SettingsObserver>>,
+}
+
+impl RendererSettingsRegistry {
+    pub(crate) fn from_env() -> Self {
+        let overrides = EnvOverrides::from_env();
+        let mut settings = RendererSettings::default();
+        overrides.apply(&mut settings);
+
+        Self {
+            settings: RwLock::new(settings),
+            overrides,
+            night_light_temperature: RwLock::new(None),
+            power_state: RwLock::new(PowerState::default()),
+            observers: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn get(&self) -> RendererSettings {
+        self.settings.read().clone()
+    }
+
+    /// Replaces the settings, keeping any environment variable overrides, and notifies the
+    /// observers if anything changed.
+    pub(crate) fn set(&self, mut settings: RendererSettings) {
+        self.overrides.apply(&mut settings);
+        {
+            let mut current = self.settings.write();
+            if *current == settings {
+                return;
+            }
+            *current = settings.clone();
+        }
+
+        for observer in self.observers.lock().iter() {
+            observer(&settings);
+        }
+    }
+
+    /// The color temperature of the OS night light, in kelvin, or `None` while it's off or
+    /// unknown. Only tracked while the settings follow [`WhitePoint::System`].
+    pub(crate) fn night_light_temperature(&self) -> Option<f32> {
+        *self.night_light_temperature.read()
+    }
+
+    /// Records the color temperature of the OS night light, notifying the observers if it changed.
+    pub(crate) fn set_night_light_temperature(&self, temperature: Option<f32>) {
+        {
+            let mut current = self.night_light_temperature.write();
+            if *current == temperature {
+                return;
+            }
+            *current = temperature;
+        }
+
+        let settings = self.get();
+        for observer in self.observers.lock().iter() {
+            observer(&settings);
+        }
+    }
+
+    pub(crate) fn power_state(&self) -> PowerState {
+        *self.power_state.read()
+    }
+
+    /// Records the system's power state, notifying the observers and returning true if it
+    /// changed.
+    pub(crate) fn set_power_state(&self, power_state: PowerState) -> bool {
+        {
+            let mut current = self.power_state.write();
+            if *current == power_state {
+                return false;
+            }
+            *current = power_state;
+        }
+
+        let settings = self.get();
+        for observer in self.observers.lock().iter() {
+            observer(&settings);
+        }
+        true
+    }
+
+    /// Whether the renderer should save power, following [`RendererSettings::eco_mode`].
+    pub(crate) fn eco_mode_active(&self) -> bool {
+        match self.settings.read().eco_mode {
+            EcoMode::Automatic => self.power_state.read().is_constrained(),
+            EcoMode::Always => true,
+            EcoMode::Never => false,
+        }
+    }
+
+    pub(crate) fn observe(&self, observer: impl Fn(&RendererSettings) + Send + Sync + 'static) {
+        self.observers.lock().push(Box::new(observer));
+    }
+}
+
+#[derive(Default)]
+struct EnvOverrides {
+    path_sample_count: Option<u32>,
+    fonts_gamma: Option<f32>,
+    grayscale_enhanced_contrast: Option<f32>,
+    present_mode: Option<PresentMode>,
+    monochrome_atlas_strategy: Option<AtlasAllocationStrategy>,
+    polychrome_atlas_strategy: Option<AtlasAllocationStrategy>,
+    white_point: Option<WhitePoint>,
+    eco_mode: Option<EcoMode>,
+    dithering: Option<bool>,
+    pixel_snapping: Option<PixelSnappingPolicy>,
+    frame_stats_interval: Option<Duration>,
+    frame_stats_format: Option<FrameStatsFormat>,
+}
+
+impl EnvOverrides {
+    fn from_env() -> Self {
+        let present_mode = std::env::var("GPUI_PRESENT_MODE")
+            .ok()
+            .and_then(|mode| match mode.to_lowercase().as_str() {
+                "mailbox" => Some(PresentMode::Mailbox),
+                "immediate" => Some(PresentMode::Immediate),
+                "fifo" => Some(PresentMode::Fifo),
+                _ => None,
+            })
+            .or_else(|| {
+                std::env::var("GPUI_DISABLE_VSYNC")
+                    .is_ok()
+                    .then_some(PresentMode::Immediate)
+            });
+
+        Self {
+            path_sample_count: parse_env("ZED_PATH_SAMPLE_COUNT"),
+            fonts_gamma: parse_env("ZED_FONTS_GAMMA"),
+            grayscale_enhanced_contrast: parse_env("ZED_FONTS_GRAYSCALE_ENHANCED_CONTRAST"),
+            present_mode,
+            monochrome_atlas_strategy: parse_atlas_strategy("GPUI_MONOCHROME_ATLAS_STRATEGY"),
+            polychrome_atlas_strategy: parse_atlas_strategy("GPUI_POLYCHROME_ATLAS_STRATEGY"),
+            white_point: parse_white_point("GPUI_WHITE_POINT"),
+            eco_mode: parse_eco_mode("GPUI_ECO_MODE"),
+            dithering: parse_env("GPUI_DITHERING"),
+            pixel_snapping: parse_pixel_snapping("GPUI_PIXEL_SNAPPING"),
+            frame_stats_interval: parse_env::<f32>("GPUI_FRAME_STATS_INTERVAL")
+                .filter(|seconds| seconds.is_finite() && *seconds > 0.0)
+                .map(Duration::from_secs_f32),
+            frame_stats_format: parse_frame_stats_format("GPUI_FRAME_STATS_FORMAT"),
+        }
+    }
+
+    fn apply(&self, settings: &mut RendererSettings) {
+        if let Some(path_sample_count) = self.path_sample_count {
+            settings.path_sample_count = path_sample_count;
+        }
+        if let Some(fonts_gamma) = self.fonts_gamma {
+            settings.fonts_gamma = fonts_gamma;
+        }
+        if let Some(grayscale_enhanced_contrast) = self.grayscale_enhanced_contrast {
+            settings.grayscale_enhanced_contrast = grayscale_enhanced_contrast;
+        }
+        if let Some(present_mode) = self.present_mode {
+            settings.present_mode = present_mode;
+        }
+        if let Some(strategy) = self.monochrome_atlas_strategy {
+            settings.monochrome_atlas_strategy = strategy;
+        }
+        if let Some(strategy) = self.polychrome_atlas_strategy {
+            settings.polychrome_atlas_strategy = strategy;
+        }
+        if let Some(white_point) = self.white_point {
+            settings.white_point = white_point;
+        }
+        if let Some(eco_mode) = self.eco_mode {
+            settings.eco_mode = eco_mode;
+        }
+        if let Some(dithering) = self.dithering {
+            settings.dithering = dithering;
+        }
+        if let Some(pixel_snapping) = self.pixel_snapping {
+            settings.pixel_snapping = pixel_snapping;
+        }
+        // Setting either variable turns the logging on, keeping whatever the other one doesn't set.
+        if self.frame_stats_interval.is_some() || self.frame_stats_format.is_some() {
+            let logging = settings.frame_stats_logging.get_or_insert_default();
+            if let Some(interval) = self.frame_stats_interval {
+                logging.interval = interval;
+            }
+            if let Some(format) = self.frame_stats_format {
+                logging.format = format;
+            }
+        }
+
+        settings.fonts_gamma = settings.fonts_gamma.clamp(1.0, 2.2);
+        settings.grayscale_enhanced_contrast = settings.grayscale_enhanced_contrast.max(0.0);
+        if let WhitePoint::Temperature(temperature) = &mut settings.white_point {
+            *temperature = temperature.clamp(1000.0, 40000.0);
+        }
+    }
+}
+
+fn parse_env<T: std::str::FromStr>(name: &str) -> Option<T> {
+    std::env::var(name).ok()?.parse().ok()
+}
+
+fn parse_atlas_strategy(name: &str) -> Option<AtlasAllocationStrategy> {
+    match std::env::var(name).ok()?.to_lowercase().as_str() {
+        "bucketed" => Some(AtlasAllocationStrategy::Bucketed),
+        "shelf" => Some(AtlasAllocationStrategy::Shelf),
+        _ => None,
+    }
+}
+
+fn parse_white_point(name: &str) -> Option<WhitePoint> {
+    match std::env::var(name).ok()?.to_lowercase().as_str() {
+        "neutral" => Some(WhitePoint::Neutral),
+        "system" => Some(WhitePoint::System),
+        temperature => temperature.parse().ok().map(WhitePoint::Temperature),
+    }
+}
+
+fn parse_eco_mode(name: &str) -> Option<EcoMode> {
+    match std::env::var(name).ok()?.to_lowercase().as_str() {
+        "automatic" => Some(EcoMode::Automatic),
+        "always" => Some(EcoMode::Always),
+        "never" => Some(EcoMode::Never),
+        _ => None,
+    }
+}
+
+fn parse_pixel_snapping(name: &str) -> Option<PixelSnappingPolicy> {
+    match std::env::var(name).ok()?.to_lowercase().as_str() {
+        "never" => Some(PixelSnappingPolicy::Never),
+        "requested" => Some(PixelSnappingPolicy::Requested),
+        "always" => Some(PixelSnappingPolicy::Always),
+        _ => None,
+    }
+}
+
+fn parse_frame_stats_format(name: &str) -> Option<FrameStatsFormat> {
+    match std::env::var(name).ok()?.to_lowercase().as_str() {
+        "text" => Some(FrameStatsFormat::Text),
+        "json" => Some(FrameStatsFormat::Json),
+        _ => None,
+    }
+}
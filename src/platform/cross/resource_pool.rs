@@ -0,0 +1,127 @@
+use collections::FxHashMap;
+use parking_lot::Mutex;
+
+/// How many frames a pooled bind group is kept without being used before it's released, along
+/// with the texture view it keeps alive.
+const UNUSED_FRAMES_BEFORE_RELEASE: u64 = 3;
+
+/// Describes a sampler, which identical samplers are shared by.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct SamplerKey {
+    pub(crate) mag_filter: wgpu::FilterMode,
+    pub(crate) min_filter: wgpu::FilterMode,
+}
+
+impl SamplerKey {
+    /// Filters linearly when magnifying and minifying.
+    pub(crate) const LINEAR: Self = Self {
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+    };
+
+    /// Keeps texels sharp when magnifying, for zoomed frames.
+    pub(crate) const NEAREST_MAGNIFIED: Self = Self {
+        mag_filter: wgpu::FilterMode::Nearest,
+        min_filter: wgpu::FilterMode::Linear,
+    };
+}
+
+/// Describes a bind group of a texture view and a sampler, like the ones sprites are drawn with.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct TextureBindGroupKey {
+    layout: wgpu::BindGroupLayout,
+    view: wgpu::TextureView,
+    sampler: SamplerKey,
+}
+
+struct PooledBindGroup {
+    bind_group: wgpu::BindGroup,
+    last_used_frame: u64,
+}
+
+/// Reuses the samplers and bind groups the renderer would otherwise create again on every frame,
+/// keyed by what they're created from. It's shared by the threads encoding a frame.
+#[derive(Default)]
+pub(crate) struct ResourcePool {
+    samplers: Mutex<FxHashMap<SamplerKey, wgpu::Sampler>>,
+    texture_bind_groups: Mutex<FxHashMap<TextureBindGroupKey, PooledBindGroup>>,
+    frame: u64,
+}
+
+impl ResourcePool {
+    pub(crate) fn sampler(&self, device: &wgpu::Device, key: SamplerKey) -> wgpu::Sampler {
+        self.samplers
+            .lock()
+            .entry(key)
+            .or_insert_with(|| {
+                device.create_sampler(&wgpu::SamplerDescriptor {
+                    label: Some("pooled_sampler"),
+                    mag_filter: key.mag_filter,
+                    min_filter: key.min_filter,
+                    ..Default::default()
+                })
+            })
+            .clone()
+    }
+
+    /// Returns a bind group of `view` at binding 0 and a sampler described by `sampler` at
+    /// binding 1, creating it the first time it's asked for.
+    pub(crate) fn texture_bind_group(
+        &self,
+        device: &wgpu::Device,
+        label: &'static str,
+        layout: &wgpu::BindGroupLayout,
+        view: &wgpu::TextureView,
+        sampler: SamplerKey,
+    ) -> wgpu::BindGroup {
+        let key = TextureBindGroupKey {
+            layout: layout.clone(),
+            view: view.clone(),
+            sampler,
+        };
+        if let Some(pooled) = self.texture_bind_groups.lock().get_mut(&key) {
+            pooled.last_used_frame = self.frame;
+            return pooled.bind_group.clone();
+        }
+
+        // The lock isn't held while the bind group is created, so the sampler can be looked up and
+        // other threads aren't blocked on the driver.
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(label),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler(device, sampler)),
+                },
+            ],
+        });
+        self.texture_bind_groups.lock().insert(
+            key,
+            PooledBindGroup {
+                bind_group: bind_group.clone(),
+                last_used_frame: self.frame,
+            },
+        );
+        bind_group
+    }
+
+    /// Releases the bind groups that haven't been used for a few frames, such as the ones of
+    /// atlas textures that were freed, and starts the next frame.
+    pub(crate) fn end_frame(&mut self) {
+        let frame = self.frame;
+        self.texture_bind_groups
+            .get_mut()
+            .retain(|_, pooled| frame - pooled.last_used_frame < UNUSED_FRAMES_BEFORE_RELEASE);
+        self.frame += 1;
+    }
+
+    /// Releases everything, for when the pipelines the bind groups were created for are replaced.
+    pub(crate) fn clear(&mut self) {
+        self.texture_bind_groups.get_mut().clear();
+    }
+}
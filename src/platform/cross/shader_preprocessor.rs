@@ -0,0 +1,99 @@
+/// The snippets shared by the renderer's shaders, by the name they're included with.
+const SNIPPETS: &[(&str, &str)] = &[
+    ("color", include_str!("shaders/common/color.wgsl")),
+    ("geometry", include_str!("shaders/common/geometry.wgsl")),
+    ("globals", include_str!("shaders/common/globals.wgsl")),
+    ("output", include_str!("shaders/common/output.wgsl")),
+];
+
+/// The features the shaders of a set of pipelines are specialized for, so fragments don't branch
+/// on them at runtime.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct ShaderFeatures {
+    // the target's colors are multiplied by their alpha, `PREMULTIPLIED_ALPHA` in shaders
+    pub premultiplied_alpha: bool,
+    // the target encodes the linear colors written to it as sRGB, `SRGB_OUTPUT` in shaders
+    pub srgb_output: bool,
+}
+
+impl ShaderFeatures {
+    fn is_enabled(&self, name: &str) -> bool {
+        match name {
+            "PREMULTIPLIED_ALPHA" => self.premultiplied_alpha,
+            "SRGB_OUTPUT" => self.srgb_output,
+            _ => {
+                log::error!("a shader tests the unknown feature {name}");
+                false
+            }
+        }
+    }
+}
+
+/// Composes the WGSL of a shader from `source`, which can use these directives, each on a line
+/// of its own:
+///
+/// - `#include "name"` is replaced with the shared snippet of that name. A snippet is included
+///   once however many times the shader and the snippets it includes include it.
+/// - `#if NAME` keeps the lines up to its `#else` or `#endif` only when the feature `NAME` is
+///   enabled, and those from its `#else` to its `#endif` only when it isn't. `#if !NAME` tests
+///   the feature being disabled.
+pub(crate) fn preprocess(source: &str, features: ShaderFeatures) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut included = Vec::new();
+    append(source, features, &mut included, &mut output);
+    output
+}
+
+fn append(
+    source: &str,
+    features: ShaderFeatures,
+    included: &mut Vec<&'static str>,
+    output: &mut String,
+) {
+    // whether the lines around each `#if` block were kept and whether its condition holds,
+    // innermost last
+    let mut blocks: Vec<(bool, bool)> = Vec::new();
+    let mut kept = true;
+    for line in source.lines() {
+        let directive = line.trim();
+        if let Some(condition) = directive.strip_prefix("#if ") {
+            let condition = match condition.trim().strip_prefix('!') {
+                Some(name) => !features.is_enabled(name.trim()),
+                None => features.is_enabled(condition.trim()),
+            };
+            blocks.push((kept, condition));
+            kept = kept && condition;
+        } else if directive == "#else" {
+            match blocks.last() {
+                Some((enclosing_kept, condition)) => kept = *enclosing_kept && !condition,
+                None => log::error!("a shader has an #else outside of an #if"),
+            }
+        } else if directive == "#endif" {
+            match blocks.pop() {
+                Some((enclosing_kept, _)) => kept = enclosing_kept,
+                None => log::error!("a shader has an #endif outside of an #if"),
+            }
+        } else if !kept {
+            continue;
+        } else if let Some(name) = directive.strip_prefix("#include ") {
+            let name = name.trim().trim_matches('"');
+            match SNIPPETS
+                .iter()
+                .find(|(snippet_name, _)| *snippet_name == name)
+            {
+                Some((name, _)) if included.contains(name) => {}
+                Some((name, snippet)) => {
+                    included.push(*name);
+                    append(snippet, features, included, output);
+                }
+                None => log::error!("a shader includes the unknown snippet {name}"),
+            }
+        } else {
+            output.push_str(line);
+            output.push('\n');
+        }
+    }
+    if !blocks.is_empty() {
+        log::error!("a shader has an #if without an #endif");
+    }
+}
@@ -0,0 +1,127 @@
+#include "geometry"
+#include "output"
+
+struct AtlasBounds {
+    origin: vec2<i32>,
+    size: vec2<i32>,
+}
+
+struct CellGrid {
+    origin: vec2<f32>,
+    cell_size: vec2<f32>,
+    content_mask: Bounds,
+    columns: u32,
+    cell_count: u32,
+    glyph_count: u32,
+    opacity: f32,
+}
+
+struct CellGlyph {
+    bounds: Bounds,
+    tile: AtlasBounds,
+}
+
+struct GridCell {
+    foreground: Hsla,
+    background: Hsla,
+    glyph: u32,
+}
+
+// Contrast and gamma correction adapted from https://github.com/microsoft/terminal/blob/1283c0f5b99a2961673249fa77c6b986efb5086c/src/renderer/atlas/dwrite.hlsl
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+fn color_brightness(color: vec3<f32>) -> f32 {
+    // REC. 601 luminance coefficients for perceived brightness
+    return dot(color, vec3<f32>(0.30, 0.59, 0.11));
+}
+
+fn light_on_dark_contrast(enhancedContrast: f32, color: vec3<f32>) -> f32 {
+    let brightness = color_brightness(color);
+    let multiplier = saturate(4.0 * (0.75 - brightness));
+    return enhancedContrast * multiplier;
+}
+
+fn enhance_contrast(alpha: f32, k: f32) -> f32 {
+    return alpha * (k + 1.0) / (alpha * k + 1.0);
+}
+
+fn apply_alpha_correction(a: f32, b: f32, g: vec4<f32>) -> f32 {
+    let brightness_adjustment = g.x * b + g.y;
+    let correction = brightness_adjustment * a + (g.z * b + g.w);
+    return a + a * (1.0 - a) * correction;
+}
+
+fn apply_contrast_and_gamma_correction(sample: f32, color: vec3<f32>, enhanced_contrast_factor: f32, gamma_ratios: vec4<f32>) -> f32 {
+    let enhanced_contrast = light_on_dark_contrast(enhanced_contrast_factor, color);
+    let brightness = color_brightness(color);
+
+    let contrasted = enhance_contrast(sample, enhanced_contrast);
+    return apply_alpha_correction(contrasted, brightness, color_adjustments.gamma_ratios);
+}
+
+@group(1) @binding(0) var<uniform> color_adjustments: ColorAdjustments;
+
+@group(2) @binding(0) var t_sprite: texture_2d<f32>;
+@group(2) @binding(1) var s_sprite: sampler;
+
+@group(3) @binding(0) var<storage, read> grid: CellGrid;
+@group(3) @binding(1) var<storage, read> b_glyphs: array<CellGlyph>;
+@group(3) @binding(2) var<storage, read> b_cells: array<GridCell>;
+
+struct CellGridVarying {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tile_position: vec2<f32>,
+    @location(1) @interpolate(flat) color: vec4<f32>,
+    @location(2) @interpolate(flat) is_glyph: u32,
+    @location(3) clip_distances: vec4<f32>,
+}
+
+// The first `cell_count` instances are the backgrounds of the cells and the rest are their
+// glyphs, so a glyph that overhangs its cell isn't covered by the background of the next one.
+@vertex
+fn vs_cell_grid(@builtin(vertex_index) vertex_id: u32, @builtin(instance_index) instance_id: u32) -> CellGridVarying {
+    let unit_vertex = vec2<f32>(f32(vertex_id & 1u), 0.5 * f32(vertex_id & 2u));
+    let is_glyph = instance_id >= grid.cell_count;
+    let cell_index = select(instance_id, instance_id - grid.cell_count, is_glyph);
+    let cell = b_cells[cell_index];
+    let cell_origin = grid.origin + vec2<f32>(f32(cell_index % grid.columns), f32(cell_index / grid.columns)) * grid.cell_size;
+
+    var out = CellGridVarying();
+    var bounds = Bounds(cell_origin, grid.cell_size);
+    var color = cell.background;
+    if (is_glyph) {
+        if (cell.glyph >= grid.glyph_count) {
+            // Blank cells collapse their glyph to a point, which draws nothing.
+            return out;
+        }
+        let glyph = b_glyphs[cell.glyph];
+        bounds = Bounds(floor(cell_origin) + glyph.bounds.origin, glyph.bounds.size);
+        color = cell.foreground;
+        let atlas_size = vec2<f32>(textureDimensions(t_sprite, 0));
+        out.tile_position = (vec2<f32>(glyph.tile.origin) + unit_vertex * vec2<f32>(glyph.tile.size)) / atlas_size;
+    }
+
+    let position = unit_vertex * bounds.size + bounds.origin;
+    out.position = to_device_position_impl(position);
+    out.color = hsla_to_rgba(color);
+    out.color.a *= grid.opacity;
+    out.is_glyph = u32(is_glyph);
+    out.clip_distances = distance_from_clip_rect_impl(position, grid.content_mask);
+    return out;
+}
+
+@fragment
+fn fs_cell_grid(input: CellGridVarying) -> @location(0) vec4<f32> {
+    let sample = textureSample(t_sprite, s_sprite, input.tile_position).r;
+    var alpha = 1.0;
+    if (input.is_glyph != 0u) {
+        alpha = apply_contrast_and_gamma_correction(sample, input.color.rgb, color_adjustments.grayscale_enhanced_contrast, color_adjustments.gamma_ratios);
+    }
+
+    // Alpha clip after using the derivatives.
+    if (any(input.clip_distances < vec4<f32>(0.0))) {
+        return vec4<f32>(0.0);
+    }
+
+    return blend_color(input.color, alpha);
+}
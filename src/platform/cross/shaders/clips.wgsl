@@ -0,0 +1,47 @@
+#include "geometry"
+
+struct TransformationMatrix {
+    rotation_scale: mat2x2<f32>,
+    translation: vec2<f32>,
+}
+
+struct Clip {
+    bounds: Bounds,
+    corner_radii: Corners,
+    transformation: TransformationMatrix,
+}
+
+struct ClipVarying {
+    @builtin(position) position: vec4<f32>,
+    // the position before the clip's transformation, where its bounds are axis aligned
+    @location(0) local_position: vec2<f32>,
+    @location(1) @interpolate(flat) clip_id: u32,
+}
+
+@group(1) @binding(0) var<storage, read> b_clips: array<Clip>;
+
+@vertex
+fn vs_clip(@builtin(vertex_index) vertex_id: u32, @builtin(instance_index) instance_id: u32) -> ClipVarying {
+    let unit_vertex = vec2<f32>(f32(vertex_id & 1u), 0.5 * f32(vertex_id & 2u));
+    let clip = b_clips[instance_id];
+    let position = unit_vertex * clip.bounds.size + clip.bounds.origin;
+    // The Rust side stores the matrix row-major, so it's transposed here.
+    let transformed = transpose(clip.transformation.rotation_scale) * position + clip.transformation.translation;
+
+    var out = ClipVarying();
+    out.position = to_device_position_impl(transformed);
+    out.local_position = position;
+    out.clip_id = instance_id;
+    return out;
+}
+
+// Only the stencil is written, so the fragments outside of the rounded corners are discarded
+// rather than blended.
+@fragment
+fn fs_clip(input: ClipVarying) -> @location(0) vec4<f32> {
+    let clip = b_clips[input.clip_id];
+    if (quad_sdf(input.local_position, clip.bounds, clip.corner_radii) > 0.0) {
+        discard;
+    }
+    return vec4<f32>(0.0);
+}
@@ -0,0 +1,49 @@
+#include "globals"
+#include "color"
+
+struct ColorFilterParams {
+    red: vec4<f32>,
+    green: vec4<f32>,
+    blue: vec4<f32>,
+}
+
+@group(1) @binding(0) var<uniform> params: ColorFilterParams;
+@group(1) @binding(1) var t_frame: texture_2d<f32>;
+
+@vertex
+fn vs_color_filter(@builtin(vertex_index) vertex_id: u32) -> @builtin(position) vec4<f32> {
+    // A single triangle covering the whole viewport.
+    let unit_vertex = vec2<f32>(f32((vertex_id << 1u) & 2u), f32(vertex_id & 2u));
+    return vec4<f32>(unit_vertex * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+}
+
+@fragment
+fn fs_color_filter(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(t_frame, vec2<i32>(position.xy), 0);
+#if PREMULTIPLIED_ALPHA
+    if (color.a <= 0.0) {
+        return vec4<f32>(0.0);
+    }
+#endif
+
+    // The frame holds sRGB encoded colors unless it's an sRGB texture, which decodes them when
+    // loaded, and the matrix applies to linear colors.
+    var rgb = color.rgb;
+#if PREMULTIPLIED_ALPHA
+    rgb = rgb / color.a;
+#endif
+#if !SRGB_OUTPUT
+    rgb = srgb_to_linear(rgb);
+#endif
+
+    let input = vec4<f32>(rgb, 1.0);
+    rgb = clamp(vec3<f32>(dot(params.red, input), dot(params.green, input), dot(params.blue, input)), vec3<f32>(0.0), vec3<f32>(1.0));
+
+#if !SRGB_OUTPUT
+    rgb = linear_to_srgb(rgb);
+#endif
+#if PREMULTIPLIED_ALPHA
+    rgb = rgb * color.a;
+#endif
+    return vec4<f32>(rgb, color.a);
+}
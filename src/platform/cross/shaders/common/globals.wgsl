@@ -0,0 +1,22 @@
+// The viewport a shader draws into, bound at group 0 by every shader the renderer draws with.
+
+struct Globals {
+    viewport_size: vec2<f32>,
+    premultiplied_alpha: u32,
+    srgb_output: u32,
+    viewport_origin: vec2<f32>,
+    target_origin: vec2<f32>,
+}
+
+@group(0) @binding(0) var<uniform> globals: Globals;
+
+fn to_device_position_impl(position: vec2<f32>) -> vec4<f32> {
+    let device_position = (position - globals.viewport_origin) / globals.viewport_size * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+    return vec4<f32>(device_position, 0.0, 1.0);
+}
+
+// Fragments are at their position in the target, which is offset from their position in the scene
+// when it's drawn into a cached layer.
+fn scene_position(position: vec4<f32>) -> vec2<f32> {
+    return position.xy + globals.target_origin;
+}
@@ -0,0 +1,37 @@
+// The colors a shader writes to its target. Shaders including this bind `color_adjustments`.
+
+#include "color"
+
+struct ColorAdjustments {
+    gamma_ratios: vec4<f32>,
+    grayscale_enhanced_contrast: f32,
+    dither_amplitude: f32,
+    white_point: vec4<f32>,
+}
+
+// The colors are sRGB encoded, which an sRGB surface does on write, so they're decoded for it.
+// The white point is uploaded in the encoding the colors are written in.
+fn output_rgb(color: vec3<f32>) -> vec3<f32> {
+#if SRGB_OUTPUT
+    return srgb_to_linear(color) * color_adjustments.white_point.rgb;
+#else
+    return color * color_adjustments.white_point.rgb;
+#endif
+}
+
+// Abstract away the final color transformation based on the
+// target alpha compositing mode.
+fn blend_color(color: vec4<f32>, alpha_factor: f32) -> vec4<f32> {
+    let alpha = color.a * alpha_factor;
+#if PREMULTIPLIED_ALPHA
+    return vec4<f32>(output_rgb(color.rgb) * alpha, alpha);
+#else
+    return vec4<f32>(output_rgb(color.rgb), alpha);
+#endif
+}
+
+// Interleaved gradient noise, an ordered pattern whose neighboring pixels differ enough for the
+// rounding error to average out between them, centered on zero.
+fn dither_noise(position: vec2<f32>) -> f32 {
+    return fract(52.9829189 * fract(dot(position, vec2<f32>(0.06711056, 0.00583715)))) - 0.5;
+}
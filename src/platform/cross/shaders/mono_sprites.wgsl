@@ -1,20 +1,5 @@
-struct Globals {
-    viewport_size: vec2<f32>,
-    premultiplied_alpha: u32,
-    pad: u32,
-}
-
-struct Bounds {
-    origin: vec2<f32>,
-    size: vec2<f32>,
-}
-
-struct Hsla {
-    h: f32,
-    s: f32,
-    l: f32,
-    a: f32,
-}
+#include "geometry"
+#include "output"
 
 struct AtlasTextureId {
     index: u32,
@@ -48,51 +33,6 @@ struct MonochromeSprite {
     transformation: TransformationMatrix,
 }
 
-struct ColorAdjustments {
-  gamma_ratios: vec4<f32>,
-  grayscale_enhanced_contrast: f32,
-}
-
-fn hsla_to_rgba(hsla: Hsla) -> vec4<f32> {
-    let h = hsla.h * 6.0; // Now, it's an angle but scaled in [0, 6) range
-    let s = hsla.s;
-    let l = hsla.l;
-    let a = hsla.a;
-
-    let c = (1.0 - abs(2.0 * l - 1.0)) * s;
-    let x = c * (1.0 - abs(h % 2.0 - 1.0));
-    let m = l - c / 2.0;
-    var color = vec3<f32>(m);
-
-    if (h >= 0.0 && h < 1.0) {
-        color.r += c;
-        color.g += x;
-    } else if (h >= 1.0 && h < 2.0) {
-        color.r += x;
-        color.g += c;
-    } else if (h >= 2.0 && h < 3.0) {
-        color.g += c;
-        color.b += x;
-    } else if (h >= 3.0 && h < 4.0) {
-        color.g += x;
-        color.b += c;
-    } else if (h >= 4.0 && h < 5.0) {
-        color.r += x;
-        color.b += c;
-    } else {
-        color.r += c;
-        color.b += x;
-    }
-
-    return vec4<f32>(color, a);
-}
-
-fn distance_from_clip_rect_impl(position: vec2<f32>, clip_bounds: Bounds) -> vec4<f32> {
-    let tl = position - clip_bounds.origin;
-    let br = clip_bounds.origin + clip_bounds.size - position;
-    return vec4<f32>(tl.x, br.x, tl.y, br.y);
-}
-
 fn distance_from_clip_rect_transformed(unit_vertex: vec2<f32>, bounds: Bounds, clip_bounds: Bounds, transform: TransformationMatrix) -> vec4<f32> {
     let position = unit_vertex * vec2<f32>(bounds.size) + bounds.origin;
     let transformed = transpose(transform.rotation_scale) * position + transform.translation;
@@ -104,11 +44,6 @@ fn to_tile_position(unit_vertex: vec2<f32>, tile: AtlasTile) -> vec2<f32> {
   return (vec2<f32>(tile.bounds.origin) + unit_vertex * vec2<f32>(tile.bounds.size)) / atlas_size;
 }
 
-fn to_device_position_impl(position: vec2<f32>) -> vec4<f32> {
-    let device_position = position / globals.viewport_size * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
-    return vec4<f32>(device_position, 0.0, 1.0);
-}
-
 fn to_device_position_transformed(unit_vertex: vec2<f32>, bounds: Bounds, transform: TransformationMatrix) -> vec4<f32> {
     let position = unit_vertex * vec2<f32>(bounds.size) + bounds.origin;
     //Note: Rust side stores it as row-major, so transposing here
@@ -116,11 +51,6 @@ fn to_device_position_transformed(unit_vertex: vec2<f32>, bounds: Bounds, transf
     return to_device_position_impl(transformed);
 }
 
-fn distance_from_clip_rect(unit_vertex: vec2<f32>, bounds: Bounds, clip_bounds: Bounds) -> vec4<f32> {
-    let position = unit_vertex * vec2<f32>(bounds.size) + bounds.origin;
-    return distance_from_clip_rect_impl(position, clip_bounds);
-}
-
 // Contrast and gamma correction adapted from https://github.com/microsoft/terminal/blob/1283c0f5b99a2961673249fa77c6b986efb5086c/src/renderer/atlas/dwrite.hlsl
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
@@ -153,16 +83,6 @@ fn apply_contrast_and_gamma_correction(sample: f32, color: vec3<f32>, enhanced_c
     return apply_alpha_correction(contrasted, brightness, color_adjustments.gamma_ratios);
 }
 
-// Abstract away the final color transformation based on the
-// target alpha compositing mode.
-fn blend_color(color: vec4<f32>, alpha_factor: f32) -> vec4<f32> {
-    let alpha = color.a * alpha_factor;
-    let multiplier = select(1.0, alpha, globals.premultiplied_alpha != 0u);
-    return vec4<f32>(color.rgb * multiplier, alpha);
-}
-
-@group(0) @binding(0) var<uniform> globals: Globals; 
-
 @group(1) @binding(0) var<uniform> color_adjustments: ColorAdjustments;
 
 @group(2) @binding(0) var t_sprite: texture_2d<f32>;
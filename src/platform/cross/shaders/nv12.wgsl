@@ -0,0 +1,36 @@
+struct Nv12Varying {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coord: vec2<f32>,
+}
+
+@group(0) @binding(0) var t_luma: texture_2d<f32>;
+@group(0) @binding(1) var t_chroma: texture_2d<f32>;
+@group(0) @binding(2) var s_plane: sampler;
+
+@vertex
+fn vs_nv12(@builtin(vertex_index) vertex_id: u32) -> Nv12Varying {
+    let unit_vertex = vec2<f32>(f32(vertex_id & 1u), 0.5 * f32(vertex_id & 2u));
+
+    var out: Nv12Varying;
+    out.position = vec4<f32>(unit_vertex * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.tex_coord = unit_vertex;
+    return out;
+}
+
+// Converts limited range BT.709 YCbCr to RGB. The result keeps the video's transfer function,
+// which is close enough to sRGB that it's stored as sRGB encoded.
+@fragment
+fn fs_nv12(input: Nv12Varying) -> @location(0) vec4<f32> {
+    let luma = textureSample(t_luma, s_plane, input.tex_coord).r;
+    let chroma = textureSample(t_chroma, s_plane, input.tex_coord).rg;
+
+    let y = (luma - 16.0 / 255.0) * (255.0 / 219.0);
+    let cb = (chroma.x - 128.0 / 255.0) * (255.0 / 224.0);
+    let cr = (chroma.y - 128.0 / 255.0) * (255.0 / 224.0);
+    let rgb = vec3<f32>(
+        y + 1.5748 * cr,
+        y - 0.1873 * cb - 0.4681 * cr,
+        y + 1.8556 * cb,
+    );
+    return vec4<f32>(clamp(rgb, vec3<f32>(0.0), vec3<f32>(1.0)), 1.0);
+}
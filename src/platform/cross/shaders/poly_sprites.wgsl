@@ -1,20 +1,5 @@
-struct Globals {
-    viewport_size: vec2<f32>,
-    premultiplied_alpha: u32,
-    pad: u32,
-}
-
-struct Bounds {
-    origin: vec2<f32>,
-    size: vec2<f32>,
-}
-
-struct Corners {
-    top_left: f32,
-    top_right: f32,
-    bottom_right: f32,
-    bottom_left: f32,
-}
+#include "geometry"
+#include "output"
 
 struct AtlasTextureId {
     index: u32,
@@ -42,9 +27,15 @@ struct PolychromeSprite {
     content_mask: Bounds,
     corner_radii: Corners,
     tile: AtlasTile,
+    // multiplies the sprite's colors, white when it isn't tinted
+    tint: Hsla,
 }
 
 const GRAYSCALE_FACTORS: vec3<f32> = vec3<f32>(0.2126, 0.7152, 0.0722);
+// Set above the grayscale byte for tiles whose colors are premultiplied by their alpha.
+const PREMULTIPLIED_FLAG: u32 = 0x100u;
+// Set above the grayscale byte for sprites sampled from their nearest texel, without filtering.
+const NEAREST_FLAG: u32 = 0x200u;
 
 struct PolySpriteVarying {
     @builtin(position) position: vec4<f32>,
@@ -53,80 +44,16 @@ struct PolySpriteVarying {
     @location(3) clip_distances: vec4<f32>,
 }
 
-@group(0) @binding(0) var<uniform> globals: Globals;
+@group(0) @binding(1) var<uniform> color_adjustments: ColorAdjustments;
 @group(1) @binding(0) var t_sprite: texture_2d<f32>;
 @group(1) @binding(1) var s_sprite: sampler;
 @group(2) @binding(0) var<storage, read> b_poly_sprites: array<PolychromeSprite>;
 
-fn to_device_position_impl(position: vec2<f32>) -> vec4<f32> {
-    let device_position = position / globals.viewport_size * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
-    return vec4<f32>(device_position, 0.0, 1.0);
-}
-
-fn to_device_position(unit_vertex: vec2<f32>, bounds: Bounds) -> vec4<f32> {
-    let position = unit_vertex * vec2<f32>(bounds.size) + bounds.origin;
-    return to_device_position_impl(position);
-}
-
 fn to_tile_position(unit_vertex: vec2<f32>, tile: AtlasTile) -> vec2<f32> {
     let atlas_size = vec2<f32>(textureDimensions(t_sprite, 0));
     return (vec2<f32>(tile.bounds.origin) + unit_vertex * vec2<f32>(tile.bounds.size)) / atlas_size;
 }
 
-fn distance_from_clip_rect_impl(position: vec2<f32>, clip_bounds: Bounds) -> vec4<f32> {
-    let tl = position - clip_bounds.origin;
-    let br = clip_bounds.origin + clip_bounds.size - position;
-    return vec4<f32>(tl.x, br.x, tl.y, br.y);
-}
-
-fn distance_from_clip_rect(unit_vertex: vec2<f32>, bounds: Bounds, clip_bounds: Bounds) -> vec4<f32> {
-    let position = unit_vertex * vec2<f32>(bounds.size) + bounds.origin;
-    return distance_from_clip_rect_impl(position, clip_bounds);
-}
-
-fn blend_color(color: vec4<f32>, alpha_factor: f32) -> vec4<f32> {
-    let alpha = color.a * alpha_factor;
-    let multiplier = select(1.0, alpha, globals.premultiplied_alpha != 0u);
-    return vec4<f32>(color.rgb * multiplier, alpha);
-}
-
-fn pick_corner_radius(center_to_point: vec2<f32>, radii: Corners) -> f32 {
-    if (center_to_point.x < 0.0) {
-        if (center_to_point.y < 0.0) {
-            return radii.top_left;
-        } else {
-            return radii.bottom_left;
-        }
-    } else {
-        if (center_to_point.y < 0.0) {
-            return radii.top_right;
-        } else {
-            return radii.bottom_right;
-        }
-    }
-}
-
-fn quad_sdf_impl(corner_center_to_point: vec2<f32>, corner_radius: f32) -> f32 {
-    if (corner_radius == 0.0) {
-        return max(corner_center_to_point.x, corner_center_to_point.y);
-    } else {
-        let signed_distance_to_inset_quad =
-            length(max(vec2<f32>(0.0), corner_center_to_point)) +
-            min(0.0, max(corner_center_to_point.x, corner_center_to_point.y));
-        return signed_distance_to_inset_quad - corner_radius;
-    }
-}
-
-fn quad_sdf(point: vec2<f32>, bounds: Bounds, corner_radii: Corners) -> f32 {
-    let half_size = bounds.size / 2.0;
-    let center = bounds.origin + half_size;
-    let center_to_point = point - center;
-    let corner_radius = pick_corner_radius(center_to_point, corner_radii);
-    let corner_to_point = abs(center_to_point) - half_size;
-    let corner_center_to_point = corner_to_point + corner_radius;
-    return quad_sdf_impl(corner_center_to_point, corner_radius);
-}
-
 @vertex
 fn vs_poly_sprite(@builtin(vertex_index) vertex_id: u32, @builtin(instance_index) instance_id: u32) -> PolySpriteVarying {
     let unit_vertex = vec2<f32>(f32(vertex_id & 1u), 0.5 * f32(vertex_id & 2u));
@@ -142,18 +69,29 @@ fn vs_poly_sprite(@builtin(vertex_index) vertex_id: u32, @builtin(instance_index
 
 @fragment
 fn fs_poly_sprite(input: PolySpriteVarying) -> @location(0) vec4<f32> {
-    let sample = textureSample(t_sprite, s_sprite, input.tile_position);
+    var sample = textureSample(t_sprite, s_sprite, input.tile_position);
     if (any(input.clip_distances < vec4<f32>(0.0))) {
         return vec4<f32>(0.0);
     }
 
     let sprite = b_poly_sprites[input.sprite_id];
-    let distance = quad_sdf(input.position.xy, sprite.bounds, sprite.corner_radii);
+    if ((sprite.grayscale & NEAREST_FLAG) != 0u) {
+        // The texel is clamped to the tile, so its neighbors in the atlas never show.
+        let texel = vec2<i32>(floor(input.tile_position * vec2<f32>(textureDimensions(t_sprite))));
+        let tile = sprite.tile.bounds;
+        sample = textureLoad(t_sprite, clamp(texel, tile.origin, tile.origin + tile.size - 1), 0);
+    }
+    let distance = quad_sdf(scene_position(input.position), sprite.bounds, sprite.corner_radii);
 
     var color = sample;
+    if ((sprite.grayscale & PREMULTIPLIED_FLAG) != 0u) {
+        color = vec4<f32>(sample.rgb / max(sample.a, 1e-6), sample.a);
+    }
     if ((sprite.grayscale & 0xFFu) != 0u) {
         let grayscale = dot(color.rgb, GRAYSCALE_FACTORS);
         color = vec4<f32>(vec3<f32>(grayscale), sample.a);
     }
+    // The tint applies after grayscale, so a grayscale sprite can be tinted to a single hue.
+    color *= hsla_to_rgba(sprite.tint);
     return blend_color(color, sprite.opacity * saturate(0.5 - distance));
 }
@@ -1,10 +1,7 @@
-const M_PI_F: f32 = 3.1415926;
+#include "geometry"
+#include "output"
 
-struct Globals {
-    viewport_size: vec2<f32>,
-    premultiplied_alpha: u32,
-    pad: u32,
-}
+const M_PI_F: f32 = 3.1415926;
 
 struct GradientColor {
   solid: vec4<f32>,
@@ -12,18 +9,6 @@ struct GradientColor {
   color1: vec4<f32>,
 }
 
-struct Hsla {
-  h: f32,
-  s: f32,
-  l: f32,
-  a: f32,
-}
-
-struct Bounds {
-  origin: vec2<f32>,
-  size: vec2<f32>,
-}
-
 struct LinearColorStop {
   color: Hsla,
   percentage: f32,
@@ -46,13 +31,6 @@ struct Edges {
   left: f32,
 }
 
-struct Corners { 
-  top_left: f32,
-  top_right: f32,
-  bottom_right: f32,
-  bottom_left: f32,
-}
-
 struct Quad {
     order: u32,
     border_style: u32,
@@ -75,7 +53,7 @@ struct QuadVarying {
     @location(5) @interpolate(flat) background_color1: vec4<f32>,
 }
 
-@group(0) @binding(0) var<uniform> globals: Globals;
+@group(0) @binding(1) var<uniform> color_adjustments: ColorAdjustments;
 @group(1) @binding(0) var<storage, read> b_quads: array<Quad>;
 
 /// Convert an Oklab color to linear sRGB space.
@@ -96,48 +74,16 @@ fn oklab_to_linear_srgb(color: vec4<f32>) -> vec4<f32> {
 	);
 }
 
-// https://gamedev.stackexchange.com/questions/92015/optimized-linear-to-srgb-glsl
-fn srgb_to_linear(srgb: vec3<f32>) -> vec3<f32> {
-    let cutoff = srgb < vec3<f32>(0.04045);
-    let higher = pow((srgb + vec3<f32>(0.055)) / vec3<f32>(1.055), vec3<f32>(2.4));
-    let lower = srgb / vec3<f32>(12.92);
-    return select(higher, lower, cutoff);
-}
-
 /// Convert a sRGBA color to linear space.
 fn srgba_to_linear(color: vec4<f32>) -> vec4<f32> {
     return vec4<f32>(srgb_to_linear(color.rgb), color.a);
 }
 
-fn linear_to_srgb(linear: vec3<f32>) -> vec3<f32> {
-    let cutoff = linear < vec3<f32>(0.0031308);
-    let higher = vec3<f32>(1.055) * pow(linear, vec3<f32>(1.0 / 2.4)) - vec3<f32>(0.055);
-    let lower = linear * vec3<f32>(12.92);
-    return select(higher, lower, cutoff);
-}
-
 /// Convert a linear color to sRGBA space.
 fn linear_to_srgba(color: vec4<f32>) -> vec4<f32> {
     return vec4<f32>(linear_to_srgb(color.rgb), color.a);
 }
 
-// Selects corner radius based on quadrant.
-fn pick_corner_radius(center_to_point: vec2<f32>, radii: Corners) -> f32 {
-    if (center_to_point.x < 0.0) {
-        if (center_to_point.y < 0.0) {
-            return radii.top_left;
-        } else {
-            return radii.bottom_left;
-        }
-    } else {
-        if (center_to_point.y < 0.0) {
-            return radii.top_right;
-        } else {
-            return radii.bottom_right;
-        }
-    }
-}
-
 /// Convert a linear sRGB to Oklab space.
 /// Reference: https://bottosson.github.io/posts/oklab/#converting-from-linear-srgb-to-oklab
 fn linear_srgb_to_oklab(color: vec4<f32>) -> vec4<f32> {
@@ -157,51 +103,6 @@ fn linear_srgb_to_oklab(color: vec4<f32>) -> vec4<f32> {
 	);
 }
 
-fn to_device_position_impl(position: vec2<f32>) -> vec4<f32> {
-    let device_position = position / globals.viewport_size * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
-    return vec4<f32>(device_position, 0.0, 1.0);
-}
-
-fn to_device_position(unit_vertex: vec2<f32>, bounds: Bounds) -> vec4<f32> {
-    let position = unit_vertex * vec2<f32>(bounds.size) + bounds.origin;
-    return to_device_position_impl(position);
-}
-
-/// Hsla to linear RGBA conversion.
-fn hsla_to_rgba(hsla: Hsla) -> vec4<f32> {
-    let h = hsla.h * 6.0; // Now, it's an angle but scaled in [0, 6) range
-    let s = hsla.s;
-    let l = hsla.l;
-    let a = hsla.a;
-
-    let c = (1.0 - abs(2.0 * l - 1.0)) * s;
-    let x = c * (1.0 - abs(h % 2.0 - 1.0));
-    let m = l - c / 2.0;
-    var color = vec3<f32>(m);
-
-    if (h >= 0.0 && h < 1.0) {
-        color.r += c;
-        color.g += x;
-    } else if (h >= 1.0 && h < 2.0) {
-        color.r += x;
-        color.g += c;
-    } else if (h >= 2.0 && h < 3.0) {
-        color.g += c;
-        color.b += x;
-    } else if (h >= 3.0 && h < 4.0) {
-        color.g += x;
-        color.b += c;
-    } else if (h >= 4.0 && h < 5.0) {
-        color.r += x;
-        color.b += c;
-    } else {
-        color.r += c;
-        color.b += x;
-    }
-
-    return vec4<f32>(color, a);
-}
-
 fn prepare_gradient_color(tag: u32, color_space: u32,
     solid: Hsla, color0: LinearColorStop, color1: LinearColorStop) -> GradientColor {
     var result = GradientColor();
@@ -302,57 +203,6 @@ fn gradient_color(background: Background, position: vec2<f32>, bounds: Bounds,
     return background_color;
 }
 
-// Abstract away the final color transformation based on the
-// target alpha compositing mode.
-fn blend_color(color: vec4<f32>, alpha_factor: f32) -> vec4<f32> {
-    let alpha = color.a * alpha_factor;
-    let multiplier = select(1.0, alpha, globals.premultiplied_alpha != 0u);
-    return vec4<f32>(color.rgb * multiplier, alpha);
-}
-
-fn distance_from_clip_rect_impl(position: vec2<f32>, clip_bounds: Bounds) -> vec4<f32> {
-    let tl = position - clip_bounds.origin;
-    let br = clip_bounds.origin + clip_bounds.size - position;
-    return vec4<f32>(tl.x, br.x, tl.y, br.y);
-}
-
-fn distance_from_clip_rect(unit_vertex: vec2<f32>, bounds: Bounds, clip_bounds: Bounds) -> vec4<f32> {
-    let position = unit_vertex * vec2<f32>(bounds.size) + bounds.origin;
-    return distance_from_clip_rect_impl(position, clip_bounds);
-}
-
-// Signed distance of the point to the quad's border - positive outside the
-// border, and negative inside.
-//
-// See comments on similar code using `quad_sdf_impl` in `fs_quad` for
-// explanation.
-fn quad_sdf(point: vec2<f32>, bounds: Bounds, corner_radii: Corners) -> f32 {
-    let half_size = bounds.size / 2.0;
-    let center = bounds.origin + half_size;
-    let center_to_point = point - center;
-    let corner_radius = pick_corner_radius(center_to_point, corner_radii);
-    let corner_to_point = abs(center_to_point) - half_size;
-    let corner_center_to_point = corner_to_point + corner_radius;
-    return quad_sdf_impl(corner_center_to_point, corner_radius);
-}
-
-fn quad_sdf_impl(corner_center_to_point: vec2<f32>, corner_radius: f32) -> f32 {
-    if (corner_radius == 0.0) {
-        // Fast path for unrounded corners.
-        return max(corner_center_to_point.x, corner_center_to_point.y);
-    } else {
-        // Signed distance of the point from a quad that is inset by corner_radius.
-        // It is negative inside this quad, and positive outside.
-        let signed_distance_to_inset_quad =
-            // 0 inside the inset quad, and positive outside.
-            length(max(vec2<f32>(0.0), corner_center_to_point)) +
-            // 0 outside the inset quad, and negative inside.
-            min(0.0, max(corner_center_to_point.x, corner_center_to_point.y));
-
-        return signed_distance_to_inset_quad - corner_radius;
-    }
-}
-
 // This approximates distance to the nearest point to a quarter ellipse in a way
 // that is sufficient for anti-aliasing when the ellipse is not very eccentric.
 // The components of `point` are expected to be positive.
@@ -446,8 +296,14 @@ fn fs_quad(input: QuadVarying) -> @location(0) vec4<f32> {
 
     let quad = b_quads[input.quad_id];
 
-    let background_color = gradient_color(quad.background, input.position.xy, quad.bounds,
+    var background_color = gradient_color(quad.background, scene_position(input.position), quad.bounds,
         input.background_solid, input.background_color0, input.background_color1);
+    // Linear gradients are dithered so they don't band once quantized. Solid colors and patterns
+    // are left alone, since they're drawn exactly.
+    if (quad.background.tag == 1u) {
+        let noise = dither_noise(input.position.xy) * color_adjustments.dither_amplitude;
+        background_color = vec4<f32>(background_color.rgb + noise, background_color.a);
+    }
 
     let unrounded = quad.corner_radii.top_left == 0.0 &&
         quad.corner_radii.bottom_left == 0.0 &&
@@ -465,7 +321,7 @@ fn fs_quad(input: QuadVarying) -> @location(0) vec4<f32> {
 
     let size = quad.bounds.size;
     let half_size = size / 2.0;
-    let point = input.position.xy - quad.bounds.origin;
+    let point = scene_position(input.position) - quad.bounds.origin;
     let center_to_point = point - half_size;
 
     // Signed distance field threshold for inclusion of pixels. 0.5 is the
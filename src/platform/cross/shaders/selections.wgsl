@@ -0,0 +1,96 @@
+#include "geometry"
+#include "output"
+
+struct Selection {
+    color: Hsla,
+    content_mask: Bounds,
+    top: f32,
+    line_height: f32,
+    corner_radius: f32,
+    first_line: u32,
+    line_count: u32,
+    pad: array<u32, 3>,
+}
+
+struct SelectionLine {
+    start_x: f32,
+    end_x: f32,
+    selection: u32,
+}
+
+struct SelectionVarying {
+    @builtin(position) position: vec4<f32>,
+    @location(0) @interpolate(flat) color: vec4<f32>,
+    @location(1) @interpolate(flat) bounds: vec4<f32>,
+    @location(2) @interpolate(flat) corner_radii: vec4<f32>,
+    @location(3) clip_distances: vec4<f32>,
+}
+
+@group(0) @binding(1) var<uniform> color_adjustments: ColorAdjustments;
+@group(1) @binding(0) var<storage, read> b_selections: array<Selection>;
+@group(1) @binding(1) var<storage, read> b_lines: array<SelectionLine>;
+
+// Whether a neighboring line of the selection spans the corner at `x`, in which case the corner
+// joins the neighbor's edge and stays square.
+fn is_joined(neighbor: SelectionLine, x: f32) -> bool {
+    return neighbor.start_x <= x && neighbor.end_x >= x && neighbor.end_x > neighbor.start_x;
+}
+
+// Each instance is a line of a selection, laid out below the previous one. The corners that
+// neighboring lines continue from are left square so the lines read as a single shape.
+@vertex
+fn vs_selection(@builtin(vertex_index) vertex_id: u32, @builtin(instance_index) instance_id: u32) -> SelectionVarying {
+    let unit_vertex = vec2<f32>(f32(vertex_id & 1u), 0.5 * f32(vertex_id & 2u));
+    let line = b_lines[instance_id];
+    let selection = b_selections[line.selection];
+    let index = instance_id - selection.first_line;
+
+    let origin = vec2<f32>(line.start_x, selection.top + f32(index) * selection.line_height);
+    let size = vec2<f32>(line.end_x - line.start_x, selection.line_height);
+    let radius = min(selection.corner_radius, 0.5 * min(size.x, size.y));
+
+    var radii = Corners(radius, radius, radius, radius);
+    if (index > 0u) {
+        let above = b_lines[instance_id - 1u];
+        if (is_joined(above, line.start_x)) {
+            radii.top_left = 0.0;
+        }
+        if (is_joined(above, line.end_x)) {
+            radii.top_right = 0.0;
+        }
+    }
+    if (index + 1u < selection.line_count) {
+        let below = b_lines[instance_id + 1u];
+        if (is_joined(below, line.start_x)) {
+            radii.bottom_left = 0.0;
+        }
+        if (is_joined(below, line.end_x)) {
+            radii.bottom_right = 0.0;
+        }
+    }
+
+    let position = unit_vertex * size + origin;
+    var out = SelectionVarying();
+    out.position = to_device_position_impl(position);
+    out.color = hsla_to_rgba(selection.color);
+    out.bounds = vec4<f32>(origin, size);
+    out.corner_radii = vec4<f32>(radii.top_left, radii.top_right, radii.bottom_right, radii.bottom_left);
+    out.clip_distances = distance_from_clip_rect_impl(position, selection.content_mask);
+    return out;
+}
+
+@fragment
+fn fs_selection(input: SelectionVarying) -> @location(0) vec4<f32> {
+    // Alpha clip first, since we don't have `clip_distance`.
+    if (any(input.clip_distances < vec4<f32>(0.0))) {
+        return vec4<f32>(0.0);
+    }
+
+    let half_size = input.bounds.zw / 2.0;
+    let center_to_point = scene_position(input.position) - input.bounds.xy - half_size;
+    let radii = Corners(input.corner_radii.x, input.corner_radii.y, input.corner_radii.z, input.corner_radii.w);
+    let corner_radius = pick_corner_radius(center_to_point, radii);
+    let corner_center_to_point = abs(center_to_point) - half_size + corner_radius;
+    let distance = quad_sdf_impl(corner_center_to_point, corner_radius);
+    return blend_color(input.color, saturate(0.5 - distance));
+}
@@ -1,29 +1,7 @@
-const M_PI_F: f32 = 3.1415926;
-
-struct Globals {
-    viewport_size: vec2<f32>,
-    premultiplied_alpha: u32,
-    pad: u32,
-}
-
-struct Bounds {
-    origin: vec2<f32>,
-    size: vec2<f32>,
-}
-
-struct Hsla {
-    h: f32,
-    s: f32,
-    l: f32,
-    a: f32,
-}
+#include "geometry"
+#include "output"
 
-struct Corners {
-    top_left: f32,
-    top_right: f32,
-    bottom_right: f32,
-    bottom_left: f32,
-}
+const M_PI_F: f32 = 3.1415926;
 
 struct Shadow {
     order: u32,
@@ -41,86 +19,9 @@ struct ShadowVarying {
     @location(3) clip_distances: vec4<f32>,
 }
 
-@group(0) @binding(0) var<uniform> globals: Globals;
+@group(0) @binding(1) var<uniform> color_adjustments: ColorAdjustments;
 @group(1) @binding(0) var<storage, read> b_shadows: array<Shadow>;
 
-fn to_device_position_impl(position: vec2<f32>) -> vec4<f32> {
-    let device_position = position / globals.viewport_size * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
-    return vec4<f32>(device_position, 0.0, 1.0);
-}
-
-fn to_device_position(unit_vertex: vec2<f32>, bounds: Bounds) -> vec4<f32> {
-    let position = unit_vertex * vec2<f32>(bounds.size) + bounds.origin;
-    return to_device_position_impl(position);
-}
-
-fn hsla_to_rgba(hsla: Hsla) -> vec4<f32> {
-    let h = hsla.h * 6.0;
-    let s = hsla.s;
-    let l = hsla.l;
-    let a = hsla.a;
-
-    let c = (1.0 - abs(2.0 * l - 1.0)) * s;
-    let x = c * (1.0 - abs(h % 2.0 - 1.0));
-    let m = l - c / 2.0;
-    var color = vec3<f32>(m);
-
-    if (h >= 0.0 && h < 1.0) {
-        color.r += c;
-        color.g += x;
-    } else if (h >= 1.0 && h < 2.0) {
-        color.r += x;
-        color.g += c;
-    } else if (h >= 2.0 && h < 3.0) {
-        color.g += c;
-        color.b += x;
-    } else if (h >= 3.0 && h < 4.0) {
-        color.g += x;
-        color.b += c;
-    } else if (h >= 4.0 && h < 5.0) {
-        color.r += x;
-        color.b += c;
-    } else {
-        color.r += c;
-        color.b += x;
-    }
-
-    return vec4<f32>(color, a);
-}
-
-fn distance_from_clip_rect_impl(position: vec2<f32>, clip_bounds: Bounds) -> vec4<f32> {
-    let tl = position - clip_bounds.origin;
-    let br = clip_bounds.origin + clip_bounds.size - position;
-    return vec4<f32>(tl.x, br.x, tl.y, br.y);
-}
-
-fn distance_from_clip_rect(unit_vertex: vec2<f32>, bounds: Bounds, clip_bounds: Bounds) -> vec4<f32> {
-    let position = unit_vertex * vec2<f32>(bounds.size) + bounds.origin;
-    return distance_from_clip_rect_impl(position, clip_bounds);
-}
-
-fn blend_color(color: vec4<f32>, alpha_factor: f32) -> vec4<f32> {
-    let alpha = color.a * alpha_factor;
-    let multiplier = select(1.0, alpha, globals.premultiplied_alpha != 0u);
-    return vec4<f32>(color.rgb * multiplier, alpha);
-}
-
-fn pick_corner_radius(center_to_point: vec2<f32>, radii: Corners) -> f32 {
-    if (center_to_point.x < 0.0) {
-        if (center_to_point.y < 0.0) {
-            return radii.top_left;
-        } else {
-            return radii.bottom_left;
-        }
-    } else {
-        if (center_to_point.y < 0.0) {
-            return radii.top_right;
-        } else {
-            return radii.bottom_right;
-        }
-    }
-}
-
 fn gaussian(x: f32, sigma: f32) -> f32 {
     return exp(-(x * x) / (2.0 * sigma * sigma)) / (sqrt(2.0 * M_PI_F) * sigma);
 }
@@ -164,9 +65,15 @@ fn fs_shadow(input: ShadowVarying) -> @location(0) vec4<f32> {
     }
 
     let shadow = b_shadows[input.shadow_id];
+    // An unblurred shadow is the rounded rectangle itself, which the gaussian can't describe.
+    if (shadow.blur_radius == 0.0) {
+        let distance = quad_sdf(scene_position(input.position), shadow.bounds, shadow.corner_radii);
+        return blend_color(input.color, saturate(0.5 - distance));
+    }
+
     let half_size = shadow.bounds.size / 2.0;
     let center = shadow.bounds.origin + half_size;
-    let center_to_point = input.position.xy - center;
+    let center_to_point = scene_position(input.position) - center;
 
     let corner_radius = pick_corner_radius(center_to_point, shadow.corner_radii);
 
@@ -185,5 +92,7 @@ fn fs_shadow(input: ShadowVarying) -> @location(0) vec4<f32> {
         y += step;
     }
 
-    return blend_color(input.color, alpha);
+    // The falloff is dithered so it doesn't band once quantized.
+    alpha += dither_noise(input.position.xy) * color_adjustments.dither_amplitude;
+    return blend_color(input.color, saturate(alpha));
 }
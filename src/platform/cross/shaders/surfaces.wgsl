@@ -1,17 +1,11 @@
-struct Globals {
-    viewport_size: vec2<f32>,
-    premultiplied_alpha: u32,
-    pad: u32,
-}
-
-struct Bounds {
-    origin: vec2<f32>,
-    size: vec2<f32>,
-}
+#include "geometry"
+#include "output"
 
 struct SurfaceParams {
     bounds: Bounds,
     content_mask: Bounds,
+    premultiplied: u32,
+    linear: u32,
 }
 
 struct SurfaceVarying {
@@ -20,16 +14,11 @@ struct SurfaceVarying {
     @location(1) clip_distances: vec4<f32>,
 }
 
-@group(0) @binding(0) var<uniform> globals: Globals;
+@group(0) @binding(1) var<uniform> color_adjustments: ColorAdjustments;
 @group(1) @binding(0) var<uniform> params: SurfaceParams;
 @group(1) @binding(1) var t_surface: texture_2d<f32>;
 @group(1) @binding(2) var s_surface: sampler;
 
-fn to_device_position(position: vec2<f32>) -> vec4<f32> {
-    let device_position = position / globals.viewport_size * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
-    return vec4<f32>(device_position, 0.0, 1.0);
-}
-
 @vertex
 fn vs_surface(@builtin(vertex_index) vertex_id: u32) -> SurfaceVarying {
     let unit_vertex = vec2<f32>(f32(vertex_id & 1u), 0.5 * f32(vertex_id & 2u));
@@ -41,7 +30,7 @@ fn vs_surface(@builtin(vertex_index) vertex_id: u32) -> SurfaceVarying {
     let br = clip_origin + clip_size - position;
 
     var out: SurfaceVarying;
-    out.position = to_device_position(position);
+    out.position = to_device_position_impl(position);
     out.tex_coord = unit_vertex;
     out.clip_distances = vec4<f32>(tl.x, br.x, tl.y, br.y);
     return out;
@@ -53,8 +42,15 @@ fn fs_surface(input: SurfaceVarying) -> @location(0) vec4<f32> {
         return vec4<f32>(0.0);
     }
 
-    let color = textureSample(t_surface, s_surface, input.tex_coord);
-    let alpha = color.a;
-    let multiplier = select(1.0, alpha, globals.premultiplied_alpha != 0u);
-    return vec4<f32>(color.rgb * multiplier, alpha);
+    var color = textureSample(t_surface, s_surface, input.tex_coord);
+    // Premultiplied frames are divided by their alpha first, since blending multiplies by it.
+    if (params.premultiplied != 0u && color.a > 0.0) {
+        color = vec4<f32>(color.rgb / color.a, color.a);
+    }
+    // Linear frames, such as those sampled from sRGB textures, which decode them, are encoded
+    // like the colors of the rest of the frame.
+    if (params.linear != 0u) {
+        color = vec4<f32>(linear_to_srgb(color.rgb), color.a);
+    }
+    return blend_color(color, 1.0);
 }
@@ -0,0 +1,328 @@
+#[cfg(any(unix, target_os = "windows"))]
+use std::time::Duration;
+
+use winit::event_loop::EventLoopProxy;
+
+use crate::platform::cross::dispatcher::CrossEvent;
+
+// a launch that finds another instance waits this long for it to start listening
+#[cfg(any(unix, target_os = "windows"))]
+const CONNECT_ATTEMPTS: u32 = 20;
+#[cfg(any(unix, target_os = "windows"))]
+const CONNECT_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The outcome of claiming to be the only running instance of the application.
+pub(crate) enum Claim {
+    /// No other instance is running, and later launches ask this one to reopen. The claim is
+    /// released when the [`SingleInstance`] is dropped, if there's anything to release.
+    Claimed(Option<SingleInstance>),
+    /// Another instance is running, and has been asked to reopen.
+    AlreadyRunning,
+}
+
+/// The claim of a running instance, which removes its socket when dropped so a later launch
+/// doesn't mistake it for a running instance.
+#[cfg(unix)]
+pub(crate) struct SingleInstance {
+    socket_path: std::path::PathBuf,
+    // held locked until the instance exits, which keeps later launches from binding the socket
+    _lock_file: std::fs::File,
+}
+
+#[cfg(unix)]
+impl Drop for SingleInstance {
+    fn drop(&mut self) {
+        if let Err(error) = std::fs::remove_file(&self.socket_path) {
+            log::debug!("failed to remove the single instance socket: {error}");
+        }
+    }
+}
+
+/// The claim of a running instance. Nothing outlives it on Windows, since the pipe is closed along
+/// with the process.
+#[cfg(not(unix))]
+pub(crate) struct SingleInstance;
+
+/// Listens on the application's single-instance socket, sending [`CrossEvent::Reopen`] whenever
+/// a later launch connects to it. The socket is only bound while holding a lock on the file next
+/// to it, so two launches racing each other can't both claim to be the only instance.
+#[cfg(unix)]
+pub(crate) fn claim(proxy: EventLoopProxy<CrossEvent>) -> Claim {
+    use std::{
+        fs::{OpenOptions, TryLockError},
+        os::unix::{
+            fs::MetadataExt as _,
+            net::{UnixListener, UnixStream},
+        },
+    };
+
+    let (Some(socket_path), Some(lock_path)) = (runtime_path("sock"), runtime_path("lock")) else {
+        return Claim::Claimed(None);
+    };
+    let lock_file = match OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+    {
+        Ok(lock_file) => lock_file,
+        Err(error) => {
+            log::warn!("failed to open the single instance lock file: {error}");
+            return Claim::Claimed(None);
+        }
+    };
+    // Another user who can write to the directory could have created either file first, to
+    // receive this user's launches or to keep them from starting, so neither is trusted then.
+    let lock_owned = lock_file
+        .metadata()
+        .is_ok_and(|metadata| metadata.uid() == current_uid());
+    let socket_foreign = std::fs::symlink_metadata(&socket_path)
+        .is_ok_and(|metadata| metadata.uid() != current_uid());
+    if !lock_owned || socket_foreign {
+        log::warn!(
+            "not claiming the single instance, since {} or {} belongs to another user",
+            lock_path.display(),
+            socket_path.display()
+        );
+        return Claim::Claimed(None);
+    }
+    match lock_file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            ask_to_reopen(|| UnixStream::connect(&socket_path));
+            return Claim::AlreadyRunning;
+        }
+        Err(TryLockError::Error(error)) => {
+            log::warn!("failed to lock the single instance lock file: {error}");
+            return Claim::Claimed(None);
+        }
+    }
+
+    // A socket left behind by an instance that didn't exit cleanly is no longer listened on,
+    // since that instance's lock was released with it.
+    if socket_path.exists() {
+        if let Err(error) = std::fs::remove_file(&socket_path) {
+            log::warn!("failed to remove the stale single instance socket: {error}");
+        }
+    }
+    let listener = match UnixListener::bind(&socket_path) {
+        Ok(listener) => listener,
+        Err(error) => {
+            log::warn!("failed to listen for later launches: {error}");
+            return Claim::Claimed(None);
+        }
+    };
+
+    let spawned = std::thread::Builder::new()
+        .name("gpui-single-instance".into())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(_) => {
+                        if proxy.send_event(CrossEvent::Reopen).is_err() {
+                            break;
+                        }
+                    }
+                    Err(error) => log::debug!("failed to accept a later launch: {error}"),
+                }
+            }
+        });
+    if let Err(error) = spawned {
+        log::error!("failed to listen for later launches: {error}");
+    }
+    Claim::Claimed(Some(SingleInstance {
+        socket_path,
+        _lock_file: lock_file,
+    }))
+}
+
+/// Creates the application's single-instance pipe, sending [`CrossEvent::Reopen`] whenever a
+/// later launch connects to it. Only the first instance of a pipe can be created with
+/// `FILE_FLAG_FIRST_PIPE_INSTANCE`, so two launches racing each other can't both claim it.
+#[cfg(target_os = "windows")]
+pub(crate) fn claim(proxy: EventLoopProxy<CrossEvent>) -> Claim {
+    use ::windows::{
+        Win32::{
+            Foundation::{ERROR_ACCESS_DENIED, ERROR_PIPE_CONNECTED, HANDLE},
+            Storage::FileSystem::{FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_INBOUND},
+            System::Pipes::{
+                ConnectNamedPipe, CreateNamedPipeW, DisconnectNamedPipe,
+                PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_WAIT,
+            },
+        },
+        core::HSTRING,
+    };
+    use std::os::windows::io::{AsRawHandle as _, FromRawHandle as _, OwnedHandle};
+
+    let Some(name) = instance_name() else {
+        return Claim::Claimed(None);
+    };
+    let pipe_path = format!(r"\\.\pipe\gpui-{name}");
+    // SAFETY: The name outlives the call, and no security attributes are passed.
+    let pipe = unsafe {
+        CreateNamedPipeW(
+            &HSTRING::from(pipe_path.as_str()),
+            PIPE_ACCESS_INBOUND | FILE_FLAG_FIRST_PIPE_INSTANCE,
+            PIPE_TYPE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            1,
+            0,
+            0,
+            0,
+            None,
+        )
+    };
+    if pipe.is_invalid() {
+        let error = ::windows::core::Error::from_win32();
+        if error.code() == ERROR_ACCESS_DENIED.to_hresult() {
+            ask_to_reopen(|| std::fs::OpenOptions::new().write(true).open(&pipe_path));
+            return Claim::AlreadyRunning;
+        }
+        log::warn!("failed to listen for later launches: {error}");
+        return Claim::Claimed(None);
+    }
+    // SAFETY: `pipe` is a valid handle that nothing else owns.
+    let pipe = unsafe { OwnedHandle::from_raw_handle(pipe.0) };
+
+    let spawned = std::thread::Builder::new()
+        .name("gpui-single-instance".into())
+        .spawn(move || {
+            let handle = HANDLE(pipe.as_raw_handle());
+            loop {
+                // SAFETY: `handle` stays open for as long as `pipe` is alive.
+                match unsafe { ConnectNamedPipe(handle, None) } {
+                    Ok(()) => {}
+                    Err(error) if error.code() == ERROR_PIPE_CONNECTED.to_hresult() => {}
+                    Err(error) => {
+                        log::debug!("failed to accept a later launch: {error}");
+                        break;
+                    }
+                }
+                if proxy.send_event(CrossEvent::Reopen).is_err() {
+                    break;
+                }
+                // SAFETY: `handle` stays open for as long as `pipe` is alive.
+                if let Err(error) = unsafe { DisconnectNamedPipe(handle) } {
+                    log::debug!("failed to wait for the next launch: {error}");
+                    break;
+                }
+            }
+        });
+    if let Err(error) = spawned {
+        log::error!("failed to listen for later launches: {error}");
+    }
+    Claim::Claimed(Some(SingleInstance))
+}
+
+/// Later launches can't be detected here yet, so every launch is its own instance.
+#[cfg(not(any(unix, target_os = "windows")))]
+pub(crate) fn claim(_proxy: EventLoopProxy<CrossEvent>) -> Claim {
+    log::warn!("single instance applications are not yet supported on this platform");
+    Claim::Claimed(None)
+}
+
+/// Sends [`CrossEvent::Reopen`] when the dock icon is clicked while the application has no
+/// visible windows. Elsewhere, clicking a launcher or taskbar entry either activates an open
+/// window or launches the application again, which reaches the running instance through
+/// [`claim`].
+#[cfg(target_os = "macos")]
+pub(crate) fn watch_dock_activation(proxy: EventLoopProxy<CrossEvent>) {
+    use objc::{
+        Encode as _, class, msg_send,
+        runtime::{BOOL, Class, Imp, NO, Object, Sel, YES, class_addMethod, object_getClass},
+        sel, sel_impl,
+    };
+
+    static PROXY: parking_lot::Mutex<Option<EventLoopProxy<CrossEvent>>> =
+        parking_lot::Mutex::new(None);
+
+    extern "C" fn should_handle_reopen(
+        _this: &Object,
+        _selector: Sel,
+        _application: *mut Object,
+        has_visible_windows: BOOL,
+    ) -> BOOL {
+        if has_visible_windows == NO {
+            if let Some(proxy) = PROXY.lock().as_ref() {
+                if proxy.send_event(CrossEvent::Reopen).is_err() {
+                    log::debug!("the dock icon was clicked after the event loop exited");
+                }
+            }
+        }
+        YES
+    }
+
+    *PROXY.lock() = Some(proxy);
+
+    let Ok(types) = std::ffi::CString::new(format!("{0}@:@{0}", BOOL::encode().as_str())) else {
+        return;
+    };
+    // SAFETY: winit installs its application delegate when the event loop is built, and doesn't
+    // implement `applicationShouldHandleReopen:hasVisibleWindows:`, so adding the method to its
+    // class changes nothing else. The method's signature matches `types`.
+    let added = unsafe {
+        let application: *mut Object = msg_send![class!(NSApplication), sharedApplication];
+        let delegate: *mut Object = msg_send![application, delegate];
+        if delegate.is_null() {
+            NO
+        } else {
+            class_addMethod(
+                object_getClass(delegate) as *mut Class,
+                sel!(applicationShouldHandleReopen:hasVisibleWindows:),
+                std::mem::transmute::<extern "C" fn(&Object, Sel, *mut Object, BOOL) -> BOOL, Imp>(
+                    should_handle_reopen,
+                ),
+                types.as_ptr(),
+            )
+        }
+    };
+    if added == NO {
+        log::warn!("failed to listen for dock icon clicks");
+    }
+}
+
+/// Connects to the running instance so it reopens, retrying for a moment since it may have
+/// claimed the application without listening yet.
+#[cfg(any(unix, target_os = "windows"))]
+fn ask_to_reopen<T>(connect: impl Fn() -> std::io::Result<T>) {
+    for _ in 0..CONNECT_ATTEMPTS {
+        if connect().is_ok() {
+            return;
+        }
+        std::thread::sleep(CONNECT_RETRY_INTERVAL);
+    }
+    log::warn!("the running instance didn't respond to this launch");
+}
+
+/// The name the application's instances find each other by, made of the executable's name and
+/// the user's, so different users each run their own instance.
+#[cfg(unix)]
+fn instance_name() -> Option<String> {
+    let executable = std::env::current_exe().ok()?;
+    let name = executable.file_stem()?.to_string_lossy().into_owned();
+    Some(format!("{name}-{}", current_uid()))
+}
+
+/// The name the application's instances find each other by, made of the executable's name and
+/// the user's, so different users each run their own instance.
+#[cfg(target_os = "windows")]
+fn instance_name() -> Option<String> {
+    let executable = std::env::current_exe().ok()?;
+    let name = executable.file_stem()?.to_string_lossy().into_owned();
+    let user = std::env::var("USERNAME").unwrap_or_default();
+    Some(format!("{name}-{user}"))
+}
+
+#[cfg(unix)]
+fn current_uid() -> libc::uid_t {
+    // SAFETY: `getuid` has no preconditions and always succeeds.
+    unsafe { libc::getuid() }
+}
+
+/// A file kept in the user's runtime directory, or in their cache directory where there's none.
+/// Unlike the shared temporary directory, other users can't create files in either.
+#[cfg(unix)]
+fn runtime_path(extension: &str) -> Option<std::path::PathBuf> {
+    let name = instance_name()?;
+    let directory = dirs::runtime_dir().or_else(dirs::cache_dir)?;
+    Some(directory.join(format!("gpui-{name}.{extension}")))
+}
@@ -0,0 +1,233 @@
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::Arc,
+};
+
+use anyhow::{Context as _, Result};
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    App, Bounds, Entity, Pixels, Point, Render, Size, Window, WindowBounds, WindowOptions,
+    app::NullHttpClient, platform::cross::platform::CrossPlatform,
+};
+
+/// Set to regenerate the golden images instead of comparing against them.
+const UPDATE_SNAPSHOTS_VAR: &str = "UPDATE_SNAPSHOTS";
+
+/// The largest possible difference between two pixels in the YIQ color space.
+const MAX_YIQ_DELTA: f32 = 35215.0;
+
+/// How closely a rendered image has to match its golden image.
+#[derive(Clone, Copy, Debug)]
+pub struct SnapshotComparison {
+    /// How different two pixels may look before they count as differing, from 0 (identical) to
+    /// 1 (anything goes). Small differences come from antialiasing on different GPUs.
+    pub threshold: f32,
+    /// How many differing pixels are tolerated before the snapshot fails.
+    pub max_differing_pixels: usize,
+}
+
+impl Default for SnapshotComparison {
+    fn default() -> Self {
+        Self {
+            threshold: 0.1,
+            max_differing_pixels: 0,
+        }
+    }
+}
+
+/// Opens a window of `size` on a headless platform, lets it settle, and reads back the frame it
+/// drew.
+pub fn render_snapshot<V: 'static + Render>(
+    size: Size<Pixels>,
+    build_root_view: impl FnOnce(&mut Window, &mut App) -> Entity<V>,
+) -> Result<RgbaImage> {
+    let platform = Rc::new(CrossPlatform::headless()?);
+    let app = App::new_app(platform.clone(), Arc::new(()), Arc::new(NullHttpClient));
+
+    app.borrow_mut().open_window(
+        WindowOptions {
+            window_bounds: Some(WindowBounds::Windowed(Bounds::new(Point::default(), size))),
+            ..Default::default()
+        },
+        build_root_view,
+    )?;
+    platform.run_until_parked();
+
+    platform
+        .headless_windows()
+        .first()
+        .context("the snapshot window was closed")?
+        .capture_frame()
+}
+
+/// Compares `image` against the golden image `tests/snapshots/{name}.png` of the crate under test,
+/// panicking if they differ. See [`assert_snapshot_with`].
+#[track_caller]
+pub fn assert_snapshot(name: &str, image: &RgbaImage) {
+    assert_snapshot_with(name, image, SnapshotComparison::default());
+}
+
+/// Compares `image` against the golden image `tests/snapshots/{name}.png` of the crate under test,
+/// panicking if they differ by more than `comparison` allows.
+///
+/// When they differ, the rendered image and a diff highlighting the differing pixels in red are
+/// written next to the golden image. Run with `UPDATE_SNAPSHOTS=1` to replace the golden images
+/// with the rendered ones instead.
+#[track_caller]
+pub fn assert_snapshot_with(name: &str, image: &RgbaImage, comparison: SnapshotComparison) {
+    if let Err(error) = compare_snapshot(name, image, comparison) {
+        panic!("snapshot {name:?} doesn't match: {error:#}");
+    }
+}
+
+fn compare_snapshot(name: &str, image: &RgbaImage, comparison: SnapshotComparison) -> Result<()> {
+    let golden_path = snapshot_dir()?.join(format!("{name}.png"));
+
+    if std::env::var_os(UPDATE_SNAPSHOTS_VAR).is_some() {
+        if let Some(parent) = golden_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        image
+            .save(&golden_path)
+            .with_context(|| format!("failed to write {}", golden_path.display()))?;
+        return Ok(());
+    }
+
+    let golden = image::open(&golden_path)
+        .with_context(|| {
+            format!(
+                "failed to read {}, run with {UPDATE_SNAPSHOTS_VAR}=1 to create it",
+                golden_path.display()
+            )
+        })?
+        .into_rgba8();
+
+    if golden.dimensions() != image.dimensions() {
+        save_failure(&golden_path, image, None)?;
+        anyhow::bail!(
+            "rendered {:?} but the golden image is {:?}",
+            image.dimensions(),
+            golden.dimensions()
+        );
+    }
+
+    let max_delta = MAX_YIQ_DELTA * comparison.threshold * comparison.threshold;
+    let mut diff = RgbaImage::new(image.width(), image.height());
+    let mut differing_pixels = 0;
+    for ((actual, expected), diff_pixel) in
+        image.pixels().zip(golden.pixels()).zip(diff.pixels_mut())
+    {
+        if yiq_delta(*actual, *expected) > max_delta {
+            differing_pixels += 1;
+            *diff_pixel = Rgba([255, 0, 0, 255]);
+        } else {
+            // Matching pixels are drawn as faded grayscale so the differing ones stand out while
+            // the layout stays recognizable.
+            let luma = 255 - ((255 - yiq_luma(*expected) as u8) / 10);
+            *diff_pixel = Rgba([luma, luma, luma, 255]);
+        }
+    }
+
+    if differing_pixels > comparison.max_differing_pixels {
+        save_failure(&golden_path, image, Some(&diff))?;
+        anyhow::bail!(
+            "{differing_pixels} pixels differ, at most {} are allowed",
+            comparison.max_differing_pixels
+        );
+    }
+
+    Ok(())
+}
+
+fn snapshot_dir() -> Result<PathBuf> {
+    let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR")
+        .context("snapshots must be run through cargo, CARGO_MANIFEST_DIR isn't set")?;
+    Ok(Path::new(&manifest_dir).join("tests").join("snapshots"))
+}
+
+fn save_failure(golden_path: &Path, image: &RgbaImage, diff: Option<&RgbaImage>) -> Result<()> {
+    let actual_path = golden_path.with_extension("actual.png");
+    image
+        .save(&actual_path)
+        .with_context(|| format!("failed to write {}", actual_path.display()))?;
+    if let Some(diff) = diff {
+        let diff_path = golden_path.with_extension("diff.png");
+        diff.save(&diff_path)
+            .with_context(|| format!("failed to write {}", diff_path.display()))?;
+    }
+    Ok(())
+}
+
+/// The perceived difference between two pixels, following pixelmatch's weighting of the YIQ
+/// channels. Translucent pixels are blended onto white first.
+fn yiq_delta(first: Rgba<u8>, second: Rgba<u8>) -> f32 {
+    let [first_y, first_i, first_q] = yiq(first);
+    let [second_y, second_i, second_q] = yiq(second);
+    let y = first_y - second_y;
+    let i = first_i - second_i;
+    let q = first_q - second_q;
+    0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q
+}
+
+fn yiq_luma(pixel: Rgba<u8>) -> f32 {
+    yiq(pixel)[0]
+}
+
+fn yiq(pixel: Rgba<u8>) -> [f32; 3] {
+    let Rgba([red, green, blue, alpha]) = pixel;
+    let alpha = alpha as f32 / 255.0;
+    let blend = |channel: u8| 255.0 + (channel as f32 - 255.0) * alpha;
+    let (red, green, blue) = (blend(red), blend(green), blend(blue));
+    [
+        red * 0.29889531 + green * 0.58662247 + blue * 0.11448223,
+        red * 0.59597799 - green * 0.27417610 - blue * 0.32180189,
+        red * 0.21147017 - green * 0.52261711 + blue * 0.31114694,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::{MAX_YIQ_DELTA, assert_snapshot, render_snapshot, yiq_delta};
+    use crate::{
+        AppContext as _, Context, IntoElement, ParentElement as _, Render, Styled as _, Window,
+        div, px, rgb, size, white,
+    };
+
+    /// A red square on a white background.
+    struct QuadView;
+
+    impl Render for QuadView {
+        fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+            div().size_full().bg(white()).child(
+                div()
+                    .absolute()
+                    .left(px(16.))
+                    .top(px(16.))
+                    .size(px(32.))
+                    .bg(rgb(0xff0000)),
+            )
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GPU to draw with"]
+    fn test_quad_matches_its_golden_image() {
+        let image = render_snapshot(size(px(64.), px(64.)), |_, cx| cx.new(|_| QuadView)).unwrap();
+        assert_snapshot("quad", &image);
+    }
+
+    #[test]
+    fn test_yiq_delta() {
+        let black = Rgba([0, 0, 0, 255]);
+        let white = Rgba([255, 255, 255, 255]);
+        assert_eq!(yiq_delta(white, white), 0.0);
+        let black_on_white = yiq_delta(black, white);
+        assert!(black_on_white > MAX_YIQ_DELTA * 0.9 && black_on_white <= MAX_YIQ_DELTA);
+        // Translucent pixels are blended onto white, so a transparent pixel looks white.
+        assert_eq!(yiq_delta(Rgba([0, 0, 0, 0]), white), 0.0);
+    }
+}
@@ -0,0 +1,330 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use parking_lot::{Condvar, Mutex};
+
+use super::surface_registry::SurfaceId;
+
+/// The device features needed to measure producer submissions with timestamp queries. They're
+/// requested when the adapter has them.
+pub(crate) const TIMESTAMP_FEATURES: wgpu::Features =
+    wgpu::Features::TIMESTAMP_QUERY.union(wgpu::Features::TIMESTAMP_QUERY_INSIDE_ENCODERS);
+
+/// The most command buffers submitted at once, so UI frames can be submitted between the chunks
+/// of a large producer submission.
+const MAX_COMMAND_BUFFERS_PER_CHUNK: usize = 4;
+
+/// How long a producer chunk waits for a UI frame that's being encoded before it's submitted
+/// anyway.
+const MAX_UI_FRAME_WAIT: Duration = Duration::from_millis(8);
+
+/// The window over which producers' GPU time is measured and budgeted.
+const BUDGET_WINDOW: Duration = Duration::from_secs(1);
+
+/// Schedules the submissions of `WgpuSurfaceHandle` producers on the queue they share with the
+/// UI, so a heavy producer can't starve UI frames.
+///
+/// Producer submissions are split into chunks, and each chunk waits for a UI frame that's being
+/// encoded. The GPU time of each chunk is measured with timestamp queries where the adapter
+/// supports them, or from submission to completion otherwise, and producers that used up their
+/// budget are held back until the next window. In eco mode, producers whose surfaces weren't
+/// composited during the last window submit at most once per window.
+pub(crate) struct SubmissionScheduler {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    timestamps: bool,
+    eco_mode: AtomicBool,
+    ui_frames: Mutex<usize>,
+    ui_frame_finished: Condvar,
+    clients: Mutex<HashMap<SurfaceId, ClientState>>,
+}
+
+struct ClientState {
+    budget: Option<Duration>,
+    window_start: Instant,
+    window_gpu_time: Duration,
+    last_window_gpu_time: Option<Duration>,
+    last_composited: Option<Instant>,
+    last_submitted: Option<Instant>,
+    measurements: flume::Sender<Measurement>,
+    received_measurements: flume::Receiver<Measurement>,
+}
+
+enum Measurement {
+    // a pair of resolved timestamps, mapped for reading
+    Timestamps(Arc<wgpu::Buffer>),
+    Elapsed(Duration),
+}
+
+/// Held while the UI encodes and submits a frame, holding producer submissions back.
+pub(crate) struct UiFrame(Arc<SubmissionScheduler>);
+
+impl SubmissionScheduler {
+    pub(crate) fn new(device: wgpu::Device, queue: wgpu::Queue) -> Self {
+        Self {
+            timestamps: device.features().contains(TIMESTAMP_FEATURES),
+            device,
+            queue,
+            eco_mode: AtomicBool::new(false),
+            ui_frames: Mutex::new(0),
+            ui_frame_finished: Condvar::new(),
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Marks a UI frame as being encoded until the returned guard is dropped.
+    pub(crate) fn ui_frame(self: &Arc<Self>) -> UiFrame {
+        *self.ui_frames.lock() += 1;
+        UiFrame(self.clone())
+    }
+
+    /// Submits a producer's command buffers in chunks, after waiting for its budget and for any
+    /// UI frame being encoded.
+    pub(crate) fn submit(
+        &self,
+        client: SurfaceId,
+        command_buffers: impl IntoIterator<Item = wgpu::CommandBuffer>,
+    ) {
+        let mut command_buffers = command_buffers.into_iter().peekable();
+        while command_buffers.peek().is_some() {
+            let chunk: Vec<_> = command_buffers
+                .by_ref()
+                .take(MAX_COMMAND_BUFFERS_PER_CHUNK)
+                .collect();
+            self.throttle(client);
+            self.wait_for_ui_frame();
+            self.submit_chunk(client, chunk);
+        }
+    }
+
+    /// Limits the GPU time a producer's submissions may take in each second, or lifts the limit.
+    pub(crate) fn set_budget(&self, client: SurfaceId, budget: Option<Duration>) {
+        self.clients
+            .lock()
+            .entry(client)
+            .or_insert_with(ClientState::new)
+            .budget = budget;
+    }
+
+    /// The budgets set for producers, so they can be carried over to another scheduler.
+    pub(crate) fn budgets(&self) -> Vec<(SurfaceId, Option<Duration>)> {
+        self.clients
+            .lock()
+            .iter()
+            .map(|(client, state)| (*client, state.budget))
+            .collect()
+    }
+
+    /// The GPU time a producer's submissions took during the last full second.
+    pub(crate) fn recent_gpu_time(&self, client: SurfaceId) -> Option<Duration> {
+        let mut clients = self.clients.lock();
+        let state = clients.get_mut(&client)?;
+        state.receive_measurements(&self.queue);
+        state.last_window_gpu_time
+    }
+
+    pub(crate) fn set_eco_mode(&self, eco_mode: bool) {
+        self.eco_mode.store(eco_mode, Ordering::Relaxed);
+    }
+
+    /// Records that the surfaces of these producers were composited into a UI frame.
+    pub(crate) fn record_composited(&self, clients: &[SurfaceId]) {
+        let now = Instant::now();
+        let mut clients_state = self.clients.lock();
+        for client in clients {
+            clients_state
+                .entry(*client)
+                .or_insert_with(ClientState::new)
+                .last_composited = Some(now);
+        }
+    }
+
+    pub(crate) fn remove(&self, client: SurfaceId) {
+        self.clients.lock().remove(&client);
+    }
+
+    /// Blocks until the producer is within its budget for the current window, and in eco mode,
+    /// until a window has passed since its last submission if its surface isn't shown.
+    fn throttle(&self, client: SurfaceId) {
+        if let Err(error) = self.device.poll(wgpu::PollType::Poll) {
+            log::error!("failed to poll for producer GPU times: {error}");
+        }
+
+        let resume_at = {
+            let mut clients = self.clients.lock();
+            let state = clients.entry(client).or_insert_with(ClientState::new);
+            state.receive_measurements(&self.queue);
+            let over_budget_until = match state.budget {
+                Some(budget) if state.window_gpu_time >= budget => {
+                    Some(state.window_start + BUDGET_WINDOW)
+                }
+                _ => None,
+            };
+            let hidden = !state
+                .last_composited
+                .is_some_and(|composited_at| composited_at.elapsed() < BUDGET_WINDOW);
+            let paused_until = state
+                .last_submitted
+                .filter(|_| hidden && self.eco_mode.load(Ordering::Relaxed))
+                .map(|submitted_at| submitted_at + BUDGET_WINDOW);
+            match over_budget_until.max(paused_until) {
+                Some(resume_at) => resume_at,
+                None => return,
+            }
+        };
+        std::thread::sleep(resume_at.saturating_duration_since(Instant::now()));
+    }
+
+    fn wait_for_ui_frame(&self) {
+        let deadline = Instant::now() + MAX_UI_FRAME_WAIT;
+        let mut ui_frames = self.ui_frames.lock();
+        while *ui_frames > 0 {
+            if self
+                .ui_frame_finished
+                .wait_until(&mut ui_frames, deadline)
+                .timed_out()
+            {
+                break;
+            }
+        }
+    }
+
+    fn submit_chunk(&self, client: SurfaceId, chunk: Vec<wgpu::CommandBuffer>) {
+        let measurements = {
+            let mut clients = self.clients.lock();
+            let state = clients.entry(client).or_insert_with(ClientState::new);
+            state.last_submitted = Some(Instant::now());
+            state.measurements.clone()
+        };
+
+        if !self.timestamps {
+            let submitted_at = Instant::now();
+            self.queue.submit(chunk);
+            self.queue.on_submitted_work_done(move || {
+                if measurements
+                    .send(Measurement::Elapsed(submitted_at.elapsed()))
+                    .is_err()
+                {
+                    log::debug!("producer GPU time measured after its surface was dropped");
+                }
+            });
+            return;
+        }
+
+        let query_set = self.device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("producer_gpu_time"),
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        });
+        let timestamps_size = 2 * std::mem::size_of::<u64>() as u64;
+        let resolve_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("producer_gpu_time_resolve"),
+            size: timestamps_size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = Arc::new(self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("producer_gpu_time_readback"),
+            size: timestamps_size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        }));
+
+        let mut start_encoder =
+            self.device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("producer_gpu_time_start"),
+                });
+        start_encoder.write_timestamp(&query_set, 0);
+        let mut end_encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("producer_gpu_time_end"),
+            });
+        end_encoder.write_timestamp(&query_set, 1);
+        end_encoder.resolve_query_set(&query_set, 0..2, &resolve_buffer, 0);
+        end_encoder.copy_buffer_to_buffer(&resolve_buffer, 0, &readback_buffer, 0, timestamps_size);
+
+        self.queue.submit(
+            std::iter::once(start_encoder.finish())
+                .chain(chunk)
+                .chain(std::iter::once(end_encoder.finish())),
+        );
+
+        let mapped_buffer = readback_buffer.clone();
+        readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| match result {
+                Ok(()) => {
+                    if measurements
+                        .send(Measurement::Timestamps(mapped_buffer))
+                        .is_err()
+                    {
+                        log::debug!("producer GPU time measured after its surface was dropped");
+                    }
+                }
+                Err(error) => log::debug!("failed to read back producer GPU time: {error}"),
+            });
+    }
+}
+
+impl ClientState {
+    fn new() -> Self {
+        let (measurements, received_measurements) = flume::unbounded();
+        Self {
+            budget: None,
+            window_start: Instant::now(),
+            window_gpu_time: Duration::ZERO,
+            last_window_gpu_time: None,
+            last_composited: None,
+            last_submitted: None,
+            measurements,
+            received_measurements,
+        }
+    }
+
+    /// Adds the GPU time of the chunks that completed to the current window, starting a new
+    /// window once the current one is over.
+    fn receive_measurements(&mut self, queue: &wgpu::Queue) {
+        let now = Instant::now();
+        if now.duration_since(self.window_start) >= BUDGET_WINDOW {
+            self.last_window_gpu_time = Some(self.window_gpu_time);
+            self.window_gpu_time = Duration::ZERO;
+            self.window_start = now;
+        }
+
+        for measurement in self.received_measurements.try_iter() {
+            let gpu_time = match measurement {
+                Measurement::Elapsed(elapsed) => elapsed,
+                Measurement::Timestamps(buffer) => {
+                    let ticks = {
+                        let data = buffer.slice(..).get_mapped_range();
+                        let timestamps: &[u64] = bytemuck::cast_slice(&data);
+                        timestamps[1].saturating_sub(timestamps[0])
+                    };
+                    buffer.unmap();
+                    Duration::from_nanos(
+                        (ticks as f64 * queue.get_timestamp_period() as f64) as u64,
+                    )
+                }
+            };
+            self.window_gpu_time += gpu_time;
+        }
+    }
+}
+
+impl Drop for UiFrame {
+    fn drop(&mut self) {
+        let mut ui_frames = self.0.ui_frames.lock();
+        *ui_frames = ui_frames.saturating_sub(1);
+        if *ui_frames == 0 {
+            self.0.ui_frame_finished.notify_all();
+        }
+    }
+}
@@ -1,6 +1,18 @@
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+
+use super::{
+    gpu_memory::{GpuMemoryCategory, GpuMemoryTracker},
+    submission_scheduler::SubmissionScheduler,
+};
+use crate::{
+    AnyWindowHandle, SharedString, SurfaceAlphaMode, SurfaceColorSpace, SurfaceInfo,
+    SurfacePresentInfo,
+};
+
+/// How long the presents of a surface are counted for before its frame rate is updated.
+const FRAME_RATE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
 
 /// An opaque identifier for a registered WGPU surface.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -11,27 +23,102 @@ struct DoubleBuffer {
     textures: [wgpu::Texture; 2],
     views: [wgpu::TextureView; 2],
     front: usize,
-    width: u32,
-    height: u32,
+    // the size each buffer was created for, before any downscaling
+    buffer_sizes: [(u32, u32); 2],
+    // the size the producer renders at, which the buffers catch up to as they're handed out
+    requested_width: u32,
+    requested_height: u32,
+    // the size the surface is laid out at, which differs from the requested size while a resize
+    // hasn't been acknowledged
+    display_width: u32,
+    display_height: u32,
+    // changes whenever the textures are recreated, so cached bind groups can be invalidated
+    generation: u64,
     format: wgpu::TextureFormat,
     // true when a present event has been fired but not yet consumed by
     // the renderer.  We coalesce multiple calls to `present()` so the
     // application doesn't flood the event loop at thousands of FPS.
     present_pending: std::sync::atomic::AtomicBool,
+    // how many frames the producer has presented, identifying the one in the front buffer
+    frame: u64,
+    latest_present: Option<SurfacePresentInfo>,
+    // presents recomposite just the surface's region instead of requesting a full frame
+    low_latency: bool,
+    alpha_mode: SurfaceAlphaMode,
+    color_space: SurfaceColorSpace,
+    name: Option<SharedString>,
+    // the window the surface was created for
+    owner: Option<AnyWindowHandle>,
+    // the presents counted since `frame_rate_started_at`, which the frame rate is updated from
+    // once `FRAME_RATE_INTERVAL` has passed
+    frame_rate_started_at: std::time::Instant,
+    frames_since_frame_rate: u64,
+    frames_per_second: f32,
 }
 
+impl DoubleBuffer {
+    fn count_frame(&mut self) {
+        self.frames_since_frame_rate += 1;
+        let elapsed = self.frame_rate_started_at.elapsed();
+        if elapsed >= FRAME_RATE_INTERVAL {
+            self.frames_per_second = self.frames_since_frame_rate as f32 / elapsed.as_secs_f32();
+            self.frame_rate_started_at = std::time::Instant::now();
+            self.frames_since_frame_rate = 0;
+        }
+    }
+
+    fn info(&self) -> SurfaceInfo {
+        // A producer that stopped presenting never updates its frame rate, so it's measured up to
+        // now once the interval has passed.
+        let elapsed = self.frame_rate_started_at.elapsed();
+        let frames_per_second = if elapsed > FRAME_RATE_INTERVAL {
+            self.frames_since_frame_rate as f32 / elapsed.as_secs_f32()
+        } else {
+            self.frames_per_second
+        };
+        let front = &self.textures[self.front];
+        SurfaceInfo {
+            name: self.name.clone(),
+            owner: self.owner,
+            size: (front.width(), front.height()),
+            format: self.format,
+            frames_per_second,
+        }
+    }
+}
+
+/// The device surfaces are rendered with, and what their producers submit through.
+#[derive(Clone)]
+pub(crate) struct SurfaceDevice {
+    pub(crate) device: wgpu::Device,
+    pub(crate) queue: wgpu::Queue,
+    pub(crate) scheduler: Arc<SubmissionScheduler>,
+}
+
+type DeviceLostListener = Arc<dyn Fn(&SurfaceDevice) + Send + Sync>;
+
 /// Thread-safe registry of all active WGPU surfaces.
 /// Maps `SurfaceId` to double-buffered texture pairs.
 pub struct SurfaceRegistry {
     surfaces: Mutex<HashMap<SurfaceId, DoubleBuffer>>,
     next_id: AtomicU64,
+    next_generation: AtomicU64,
+    memory: Arc<GpuMemoryTracker>,
+    // divisor applied to every surface's requested size to relieve memory pressure
+    downscale_factor: AtomicU64,
+    // called when the surfaces move to a new device, so their handles can follow
+    device_lost_listeners: Mutex<HashMap<SurfaceId, DeviceLostListener>>,
 }
 
 impl SurfaceRegistry {
-    pub fn new() -> Self {
+    pub fn new(memory: Arc<GpuMemoryTracker>) -> Self {
         Self {
             surfaces: Mutex::new(HashMap::new()),
             next_id: AtomicU64::new(1),
+            next_generation: AtomicU64::new(1),
+            memory,
+            downscale_factor: AtomicU64::new(1),
+            device_lost_listeners: Mutex::new(HashMap::new()),
         }
     }
 
@@ -44,7 +131,7 @@ impl SurfaceRegistry {
         format: wgpu::TextureFormat,
     ) -> SurfaceId {
         let id = SurfaceId(self.next_id.fetch_add(1, Ordering::Relaxed));
-        let db = Self::create_double_buffer(device, width, height, format);
+        let db = self.create_double_buffer(device, width, height, format);
         self.surfaces.lock().unwrap().insert(id, db);
         id
     }
@@ -53,6 +140,8 @@ impl SurfaceRegistry {
     pub fn swap_buffers(&self, id: SurfaceId) {
         if let Some(db) = self.surfaces.lock().unwrap().get_mut(&id) {
             db.front = 1 - db.front;
+            db.frame += 1;
+            db.count_frame();
         }
     }
 
@@ -66,12 +155,72 @@ impl SurfaceRegistry {
     ) {
         let mut surfaces = self.surfaces.lock().unwrap();
         if let Some(db) = surfaces.get_mut(&id) {
-            if db.width == width && db.height == height {
+            db.display_width = width;
+            db.display_height = height;
+            if db.buffer_sizes == [(width, height); 2] {
+                db.requested_width = width;
+                db.requested_height = height;
                 return;
             }
-            let new_db = Self::create_double_buffer(device, width, height, db.format);
-            *db = new_db;
+            self.recreate_buffers(device, db, width, height);
+        }
+    }
+
+    /// Record the size the surface is laid out at without touching its buffers, which keep
+    /// their size until the producer acknowledges it with [`SurfaceRegistry::acknowledge_resize`].
+    /// Returns false if the surface was already displayed at that size.
+    pub fn request_resize(&self, id: SurfaceId, width: u32, height: u32) -> bool {
+        let mut surfaces = self.surfaces.lock().unwrap();
+        let Some(db) = surfaces.get_mut(&id) else {
+            return false;
+        };
+        if db.display_width == width && db.display_height == height {
+            return false;
         }
+        db.display_width = width;
+        db.display_height = height;
+        true
+    }
+
+    /// Record that the producer renders at the given size from now on. Each buffer is recreated
+    /// at that size the next time it's handed out as the back buffer, so the front buffer keeps
+    /// showing the last frame until one at the new size is presented.
+    pub fn acknowledge_resize(&self, id: SurfaceId, width: u32, height: u32) {
+        if let Some(db) = self.surfaces.lock().unwrap().get_mut(&id) {
+            db.requested_width = width;
+            db.requested_height = height;
+        }
+    }
+
+    /// The size the front buffer was rendered at, if it differs from the size the surface is
+    /// displayed at because a resize is still pending.
+    pub fn stale_front_size(&self, id: SurfaceId) -> Option<(u32, u32)> {
+        let surfaces = self.surfaces.lock().unwrap();
+        let db = surfaces.get(&id)?;
+        let front_size = db.buffer_sizes[db.front];
+        (front_size != (db.display_width, db.display_height)).then_some(front_size)
+    }
+
+    /// Halve the resolution of every surface, down to a quarter of the requested size in each
+    /// dimension. Returns false if the surfaces are already at the minimum resolution.
+    ///
+    /// Callers keep rendering into the back view at whatever size it reports, and the renderer
+    /// stretches the result over the surface's bounds.
+    pub fn downscale(&self, device: &wgpu::Device) -> bool {
+        const MAX_DOWNSCALE_FACTOR: u64 = 4;
+
+        let factor = self.downscale_factor.load(Ordering::Relaxed);
+        if factor >= MAX_DOWNSCALE_FACTOR {
+            return false;
+        }
+        self.downscale_factor.store(factor * 2, Ordering::Relaxed);
+
+        let mut surfaces = self.surfaces.lock().unwrap();
+        for db in surfaces.values_mut() {
+            let (width, height) = (db.requested_width, db.requested_height);
+            self.recreate_buffers(device, db, width, height);
+        }
+        true
     }
 
     /// Get the front buffer's `TextureView` (what the renderer reads from).
@@ -90,26 +239,44 @@ impl SurfaceRegistry {
     }
 
     /// Get the back buffer's `TextureView` for use as a render target.
-    pub fn back_view(&self, id: SurfaceId) -> Option<wgpu::TextureView> {
-        let surfaces = self.surfaces.lock().unwrap();
-        surfaces.get(&id).map(|db| {
-            let back = 1 - db.front;
-            db.views[back].clone()
-        })
+    pub fn back_view(&self, device: &wgpu::Device, id: SurfaceId) -> Option<wgpu::TextureView> {
+        self.lock_and_get_back_with_size(device, id)
+            .map(|(view, _)| view)
     }
 
     /// Atomically retrieve both the back view and the corresponding texture
     /// dimensions. This is useful when a caller needs to create auxiliary
     /// resources (e.g. a depth buffer) that must exactly match the view's size.
+    ///
+    /// The back buffer is recreated first if it doesn't have the requested size.
     pub fn lock_and_get_back_with_size(
         &self,
+        device: &wgpu::Device,
         id: SurfaceId,
     ) -> Option<(wgpu::TextureView, (u32, u32))> {
-        let surfaces = self.surfaces.lock().unwrap();
-        surfaces.get(&id).map(|db| {
-            let back = 1 - db.front;
-            (db.views[back].clone(), (db.width, db.height))
-        })
+        let mut surfaces = self.surfaces.lock().unwrap();
+        let db = surfaces.get_mut(&id)?;
+        let back = 1 - db.front;
+        let requested_size = (db.requested_width, db.requested_height);
+        if db.buffer_sizes[back] != requested_size {
+            self.memory.free(
+                GpuMemoryCategory::Surfaces,
+                Self::texture_bytes(&db.textures[back]),
+            );
+            let (texture, view) = self.create_buffer(
+                device,
+                "surface_buffer",
+                requested_size.0,
+                requested_size.1,
+                db.format,
+            );
+            db.textures[back] = texture;
+            db.views[back] = view;
+            db.buffer_sizes[back] = requested_size;
+            db.generation = self.next_generation.fetch_add(1, Ordering::Relaxed);
+        }
+        let texture = &db.textures[back];
+        Some((db.views[back].clone(), (texture.width(), texture.height())))
     }
 
     /// Get the current front buffer index (0 or 1).
@@ -126,11 +293,20 @@ impl SurfaceRegistry {
             .and_then(|db| db.views.get(idx).cloned())
     }
 
+    /// Get the generation of a surface's textures, which changes whenever they are recreated.
+    pub fn generation(&self, id: SurfaceId) -> Option<u64> {
+        let surfaces = self.surfaces.lock().unwrap();
+        surfaces.get(&id).map(|db| db.generation)
+    }
+
     /// Get the current size of a surface.
     #[allow(dead_code)]
     pub fn size(&self, id: SurfaceId) -> Option<(u32, u32)> {
         let surfaces = self.surfaces.lock().unwrap();
-        surfaces.get(&id).map(|db| (db.width, db.height))
+        surfaces.get(&id).map(|db| {
+            let texture = &db.textures[db.front];
+            (texture.width(), texture.height())
+        })
     }
 
     /// Get the texture format for a surface.
@@ -140,9 +316,167 @@ impl SurfaceRegistry {
         surfaces.get(&id).map(|db| db.format)
     }
 
+    /// Record that the frame in a surface's front buffer was composited into a window frame
+    /// presented at `presented_at`, unless an earlier window frame already showed it. Returns
+    /// whether it was recorded.
+    pub fn record_present(&self, id: SurfaceId, presented_at: std::time::Instant) -> bool {
+        let mut surfaces = self.surfaces.lock().unwrap();
+        let Some(db) = surfaces.get_mut(&id) else {
+            return false;
+        };
+        if db
+            .latest_present
+            .is_some_and(|present| present.frame == db.frame)
+        {
+            return false;
+        }
+        db.latest_present = Some(SurfacePresentInfo {
+            frame: db.frame,
+            presented_at,
+            completed_at: None,
+        });
+        true
+    }
+
+    /// Record when the GPU finished the window frame presented at `presented_at`.
+    pub fn record_completion(
+        &self,
+        id: SurfaceId,
+        presented_at: std::time::Instant,
+        completed_at: std::time::Instant,
+    ) {
+        let mut surfaces = self.surfaces.lock().unwrap();
+        let Some(present) = surfaces
+            .get_mut(&id)
+            .and_then(|db| db.latest_present.as_mut())
+        else {
+            return;
+        };
+        if present.presented_at == presented_at {
+            present.completed_at = Some(completed_at);
+        }
+    }
+
+    /// When the producer's most recent frame to reach the screen was presented.
+    pub fn latest_present(&self, id: SurfaceId) -> Option<SurfacePresentInfo> {
+        let surfaces = self.surfaces.lock().unwrap();
+        surfaces.get(&id).and_then(|db| db.latest_present)
+    }
+
+    /// Set whether presents of a surface recomposite just its region right away.
+    pub fn set_low_latency(&self, id: SurfaceId, low_latency: bool) {
+        if let Some(db) = self.surfaces.lock().unwrap().get_mut(&id) {
+            db.low_latency = low_latency;
+        }
+    }
+
+    pub fn is_low_latency(&self, id: SurfaceId) -> bool {
+        let surfaces = self.surfaces.lock().unwrap();
+        surfaces.get(&id).is_some_and(|db| db.low_latency)
+    }
+
+    /// Set how the colors of a surface's frames relate to their alpha.
+    pub fn set_alpha_mode(&self, id: SurfaceId, alpha_mode: SurfaceAlphaMode) {
+        if let Some(db) = self.surfaces.lock().unwrap().get_mut(&id) {
+            db.alpha_mode = alpha_mode;
+        }
+    }
+
+    pub fn alpha_mode(&self, id: SurfaceId) -> Option<SurfaceAlphaMode> {
+        let surfaces = self.surfaces.lock().unwrap();
+        surfaces.get(&id).map(|db| db.alpha_mode)
+    }
+
+    /// Set the color space of a surface's frames, as the renderer samples them.
+    pub fn set_color_space(&self, id: SurfaceId, color_space: SurfaceColorSpace) {
+        if let Some(db) = self.surfaces.lock().unwrap().get_mut(&id) {
+            db.color_space = color_space;
+        }
+    }
+
+    pub fn color_space(&self, id: SurfaceId) -> Option<SurfaceColorSpace> {
+        let surfaces = self.surfaces.lock().unwrap();
+        surfaces.get(&id).map(|db| db.color_space)
+    }
+
+    /// Set the name a surface can be found by, see [`SurfaceRegistry::find_by_name`].
+    pub fn set_name(&self, id: SurfaceId, name: Option<SharedString>) {
+        if let Some(db) = self.surfaces.lock().unwrap().get_mut(&id) {
+            db.name = name;
+        }
+    }
+
+    pub fn name(&self, id: SurfaceId) -> Option<SharedString> {
+        let surfaces = self.surfaces.lock().unwrap();
+        surfaces.get(&id).and_then(|db| db.name.clone())
+    }
+
+    /// Record the window a surface was created for.
+    pub fn set_owner(&self, id: SurfaceId, owner: AnyWindowHandle) {
+        if let Some(db) = self.surfaces.lock().unwrap().get_mut(&id) {
+            db.owner = Some(owner);
+        }
+    }
+
+    /// Describe every live surface, in the order they were created.
+    pub fn list(&self) -> Vec<SurfaceInfo> {
+        let surfaces = self.surfaces.lock().unwrap();
+        let mut ids = surfaces.keys().copied().collect::<Vec<_>>();
+        ids.sort_by_key(|id| id.0);
+        ids.iter().map(|id| surfaces[id].info()).collect()
+    }
+
+    /// Describe the first surface created with `name` that's still alive.
+    pub fn find_by_name(&self, name: &str) -> Option<SurfaceInfo> {
+        let surfaces = self.surfaces.lock().unwrap();
+        surfaces
+            .iter()
+            .filter(|(_, db)| db.name.as_deref() == Some(name))
+            .min_by_key(|(id, _)| id.0)
+            .map(|(_, db)| db.info())
+    }
+
     /// Remove a surface from the registry.
     pub fn remove(&self, id: SurfaceId) {
-        self.surfaces.lock().unwrap().remove(&id);
+        if let Some(db) = self.surfaces.lock().unwrap().remove(&id) {
+            self.release(&db);
+        }
+        self.device_lost_listeners.lock().unwrap().remove(&id);
+    }
+
+    /// Registers `listener` to be called when the surface moves to a new device.
+    pub(crate) fn on_device_lost(
+        &self,
+        id: SurfaceId,
+        listener: impl Fn(&SurfaceDevice) + Send + Sync + 'static,
+    ) {
+        self.device_lost_listeners
+            .lock()
+            .unwrap()
+            .insert(id, Arc::new(listener));
+    }
+
+    /// Recreates every surface's buffers on `surface_device`, for when the device they were
+    /// created on was lost, and tells their handles. The frames the surfaces showed are gone, so
+    /// they stay blank until their producers present again.
+    pub(crate) fn replace_device(&self, surface_device: SurfaceDevice) {
+        for db in self.surfaces.lock().unwrap().values_mut() {
+            let (width, height) = (db.requested_width, db.requested_height);
+            self.recreate_buffers(&surface_device.device, db, width, height);
+        }
+
+        // The listeners are called without the lock held, since they may call back into the
+        // registry.
+        let listeners = self
+            .device_lost_listeners
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        for listener in listeners {
+            listener(&surface_device);
+        }
     }
 
     /// Set the "present pending" flag for a surface, returning previous value.
@@ -174,45 +508,104 @@ impl SurfaceRegistry {
     }
 
     fn create_double_buffer(
+        &self,
         device: &wgpu::Device,
         width: u32,
         height: u32,
         format: wgpu::TextureFormat,
     ) -> DoubleBuffer {
-        let w = width.max(1);
-        let h = height.max(1);
-
-        let create_texture = |label: &str| {
-            device.create_texture(&wgpu::TextureDescriptor {
-                label: Some(label),
-                size: wgpu::Extent3d {
-                    width: w,
-                    height: h,
-                    depth_or_array_layers: 1,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format,
-                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
-                    | wgpu::TextureUsages::TEXTURE_BINDING,
-                view_formats: &[],
-            })
-        };
-
-        let tex0 = create_texture("surface_buffer_0");
-        let tex1 = create_texture("surface_buffer_1");
-        let view0 = tex0.create_view(&wgpu::TextureViewDescriptor::default());
-        let view1 = tex1.create_view(&wgpu::TextureViewDescriptor::default());
+        let (tex0, view0) = self.create_buffer(device, "surface_buffer_0", width, height, format);
+        let (tex1, view1) = self.create_buffer(device, "surface_buffer_1", width, height, format);
 
         DoubleBuffer {
             textures: [tex0, tex1],
             views: [view0, view1],
             front: 0,
-            width: w,
-            height: h,
+            buffer_sizes: [(width, height); 2],
+            requested_width: width,
+            requested_height: height,
+            display_width: width,
+            display_height: height,
+            generation: self.next_generation.fetch_add(1, Ordering::Relaxed),
             format,
             present_pending: std::sync::atomic::AtomicBool::new(false),
+            frame: 0,
+            latest_present: None,
+            low_latency: false,
+            alpha_mode: SurfaceAlphaMode::default(),
+            color_space: SurfaceColorSpace::of_format(format),
+            name: None,
+            owner: None,
+            frame_rate_started_at: std::time::Instant::now(),
+            frames_since_frame_rate: 0,
+            frames_per_second: 0.,
+        }
+    }
+
+    /// Replace both buffers with new ones of the given size, keeping the size the surface is
+    /// displayed at and the frames it presented.
+    fn recreate_buffers(
+        &self,
+        device: &wgpu::Device,
+        db: &mut DoubleBuffer,
+        width: u32,
+        height: u32,
+    ) {
+        let mut new_db = self.create_double_buffer(device, width, height, db.format);
+        new_db.display_width = db.display_width;
+        new_db.display_height = db.display_height;
+        new_db.frame = db.frame;
+        new_db.latest_present = db.latest_present;
+        new_db.low_latency = db.low_latency;
+        new_db.alpha_mode = db.alpha_mode;
+        new_db.color_space = db.color_space;
+        new_db.name = db.name.take();
+        new_db.owner = db.owner;
+        new_db.frame_rate_started_at = db.frame_rate_started_at;
+        new_db.frames_since_frame_rate = db.frames_since_frame_rate;
+        new_db.frames_per_second = db.frames_per_second;
+        self.release(db);
+        *db = new_db;
+    }
+
+    fn create_buffer(
+        &self,
+        device: &wgpu::Device,
+        label: &str,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> (wgpu::Texture, wgpu::TextureView) {
+        let factor = self.downscale_factor.load(Ordering::Relaxed) as u32;
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: (width / factor).max(1),
+                height: (height / factor).max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.memory
+            .allocate(GpuMemoryCategory::Surfaces, Self::texture_bytes(&texture));
+        (texture, view)
+    }
+
+    fn release(&self, db: &DoubleBuffer) {
+        for texture in &db.textures {
+            self.memory
+                .free(GpuMemoryCategory::Surfaces, Self::texture_bytes(texture));
         }
     }
+
+    fn texture_bytes(texture: &wgpu::Texture) -> u64 {
+        let bytes_per_pixel = texture.format().block_copy_size(None).unwrap_or(4) as u64;
+        texture.width() as u64 * texture.height() as u64 * bytes_per_pixel
+    }
 }
@@ -0,0 +1,205 @@
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use winit::event_loop::EventLoopProxy;
+
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+use crate::platform::cross::gdbus;
+use crate::{BackgroundExecutor, SystemAppearance, platform::cross::dispatcher::CrossEvent};
+
+/// Keeps `appearance` up to date with the OS, sending [`CrossEvent::SystemAppearanceChanged`]
+/// whenever it changes. Linux listens for the desktop portal's change signal, and Windows checks
+/// the settings every few seconds since winit doesn't report accent color changes.
+#[cfg_attr(
+    not(any(target_os = "linux", target_os = "freebsd", target_os = "windows")),
+    allow(unused_variables)
+)]
+pub(crate) fn watch(
+    executor: &BackgroundExecutor,
+    proxy: EventLoopProxy<CrossEvent>,
+    appearance: Arc<Mutex<SystemAppearance>>,
+) {
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    {
+        let spawned = std::thread::Builder::new()
+            .name("gpui-system-appearance".into())
+            .spawn(move || gdbus::watch_portal_settings(|| refresh(&appearance, &proxy)));
+        if let Err(error) = spawned {
+            log::error!("failed to watch the system appearance: {error}");
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+        let timer_executor = executor.clone();
+        executor
+            .spawn(async move {
+                while refresh(&appearance, &proxy) {
+                    timer_executor.timer(POLL_INTERVAL).await;
+                }
+            })
+            .detach();
+    }
+}
+
+/// Reads the system appearance and reports it to the event loop if it changed. Returns false once
+/// the event loop is gone, which stops the watcher.
+#[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "windows"))]
+fn refresh(appearance: &Mutex<SystemAppearance>, proxy: &EventLoopProxy<CrossEvent>) -> bool {
+    let current = read_system_appearance();
+    {
+        let mut appearance = appearance.lock();
+        if *appearance == current {
+            return true;
+        }
+        *appearance = current;
+    }
+    proxy
+        .send_event(CrossEvent::SystemAppearanceChanged)
+        .is_ok()
+}
+
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+fn read_system_appearance() -> SystemAppearance {
+    linux::read_system_appearance()
+}
+
+#[cfg(target_os = "windows")]
+fn read_system_appearance() -> SystemAppearance {
+    windows::read_system_appearance()
+}
+
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+mod linux {
+    use std::path::PathBuf;
+
+    use crate::{Rgba, SystemAppearance, WindowAppearance, platform::cross::gdbus};
+
+    const APPEARANCE_NAMESPACE: &str = "org.freedesktop.appearance";
+
+    pub(super) fn read_system_appearance() -> SystemAppearance {
+        SystemAppearance {
+            accent_color: read_portal_setting("accent-color")
+                .and_then(|reply| parse_portal_color(&reply))
+                .map(Into::into),
+            titlebar_appearance: read_portal_setting("color-scheme").and_then(|reply| {
+                match gdbus::parse_number(&reply)? as u32 {
+                    1 => Some(WindowAppearance::Dark),
+                    2 => Some(WindowAppearance::Light),
+                    _ => None,
+                }
+            }),
+            titlebar_color: read_kde_titlebar_color().map(Into::into),
+        }
+    }
+
+    fn read_portal_setting(key: &str) -> Option<String> {
+        gdbus::read_portal_setting(APPEARANCE_NAMESPACE, key)
+    }
+
+    /// Parses a color printed as `(<(0.2, 0.5, 0.9)>,)`. Components outside of 0 to 1 mean the
+    /// user hasn't picked a color.
+    fn parse_portal_color(reply: &str) -> Option<Rgba> {
+        let start = reply.rfind('(')? + 1;
+        let end = start + reply[start..].find(')')?;
+        let mut components = reply[start..end]
+            .split(',')
+            .map(|component| component.trim().parse::<f32>().ok());
+        let red = components.next()??;
+        let green = components.next()??;
+        let blue = components.next()??;
+        [red, green, blue]
+            .iter()
+            .all(|component| (0.0..=1.0).contains(component))
+            .then_some(Rgba {
+                r: red,
+                g: green,
+                b: blue,
+                a: 1.0,
+            })
+    }
+
+    /// Reads the active title bar color of KDE Plasma's color scheme from `kdeglobals`.
+    fn read_kde_titlebar_color() -> Option<Rgba> {
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| Some(PathBuf::from(std::env::var_os("HOME")?).join(".config")))?;
+        let kdeglobals = std::fs::read_to_string(config_dir.join("kdeglobals")).ok()?;
+
+        let mut in_window_manager_section = false;
+        for line in kdeglobals.lines() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                in_window_manager_section = line == "[WM]";
+                continue;
+            }
+            if !in_window_manager_section {
+                continue;
+            }
+            let Some(value) = line.strip_prefix("activeBackground=") else {
+                continue;
+            };
+            let mut components = value
+                .split(',')
+                .map(|component| component.trim().parse::<u8>().ok());
+            let red = components.next()??;
+            let green = components.next()??;
+            let blue = components.next()??;
+            return Some(Rgba {
+                r: red as f32 / 255.0,
+                g: green as f32 / 255.0,
+                b: blue as f32 / 255.0,
+                a: 1.0,
+            });
+        }
+        None
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod windows {
+    use ::windows::UI::ViewManagement::{UIColorType, UISettings};
+
+    use crate::{Rgba, SystemAppearance, WindowAppearance};
+
+    pub(super) fn read_system_appearance() -> SystemAppearance {
+        let accent_color = UISettings::new()
+            .and_then(|settings| settings.GetColorValue(UIColorType::Accent))
+            .ok()
+            .map(|color| Rgba {
+                r: color.R as f32 / 255.0,
+                g: color.G as f32 / 255.0,
+                b: color.B as f32 / 255.0,
+                a: 1.0,
+            });
+        let read_user_setting = |key: &str, name: &str| {
+            windows_registry::CURRENT_USER
+                .open(key)
+                .and_then(|key| key.get_u32(name))
+                .ok()
+        };
+        // Title bars only take on the accent color when "Show accent color on title bars" is turned
+        // on.
+        let accent_titlebars =
+            read_user_setting("Software\\Microsoft\\Windows\\DWM", "ColorPrevalence") == Some(1);
+        let titlebar_appearance = read_user_setting(
+            "Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize",
+            "AppsUseLightTheme",
+        )
+        .map(|light| {
+            if light == 0 {
+                WindowAppearance::Dark
+            } else {
+                WindowAppearance::Light
+            }
+        });
+
+        SystemAppearance {
+            accent_color: accent_color.map(Into::into),
+            titlebar_appearance,
+            titlebar_color: accent_color.filter(|_| accent_titlebars).map(Into::into),
+        }
+    }
+}
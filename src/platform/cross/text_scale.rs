@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use winit::event_loop::EventLoopProxy;
+
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+use crate::platform::cross::gdbus;
+use crate::{BackgroundExecutor, platform::cross::dispatcher::CrossEvent};
+
+/// Keeps `text_scale_factor` up to date with the OS's text size preference, sending
+/// [`CrossEvent::TextScaleFactorChanged`] whenever it changes. Linux listens for the desktop
+/// portal's change signal, and Windows checks the setting every few seconds.
+#[cfg_attr(
+    not(any(target_os = "linux", target_os = "freebsd", target_os = "windows")),
+    allow(unused_variables)
+)]
+pub(crate) fn watch(
+    executor: &BackgroundExecutor,
+    proxy: EventLoopProxy<CrossEvent>,
+    text_scale_factor: Arc<Mutex<f32>>,
+) {
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    {
+        let spawned = std::thread::Builder::new()
+            .name("gpui-text-scale".into())
+            .spawn(move || gdbus::watch_portal_settings(|| refresh(&text_scale_factor, &proxy)));
+        if let Err(error) = spawned {
+            log::error!("failed to watch the text scale factor: {error}");
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+        let timer_executor = executor.clone();
+        executor
+            .spawn(async move {
+                while refresh(&text_scale_factor, &proxy) {
+                    timer_executor.timer(POLL_INTERVAL).await;
+                }
+            })
+            .detach();
+    }
+}
+
+/// Reads the text scale factor and reports it to the event loop if it changed. Returns false once
+/// the event loop is gone, which stops the watcher.
+#[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "windows"))]
+fn refresh(text_scale_factor: &Mutex<f32>, proxy: &EventLoopProxy<CrossEvent>) -> bool {
+    let current = read_text_scale_factor();
+    {
+        let mut text_scale_factor = text_scale_factor.lock();
+        if *text_scale_factor == current {
+            return true;
+        }
+        *text_scale_factor = current;
+    }
+    proxy.send_event(CrossEvent::TextScaleFactorChanged).is_ok()
+}
+
+/// GNOME's text scaling factor, such as 1.25 when "Large Text" is turned on.
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+fn read_text_scale_factor() -> f32 {
+    gdbus::read_portal_setting("org.gnome.desktop.interface", "text-scaling-factor")
+        .and_then(|reply| gdbus::parse_number(&reply))
+        .filter(|factor| *factor > 0.0)
+        .unwrap_or(1.0)
+}
+
+/// The "Text size" accessibility setting, stored as a percentage from 100 to 225.
+#[cfg(target_os = "windows")]
+fn read_text_scale_factor() -> f32 {
+    windows_registry::CURRENT_USER
+        .open("Software\\Microsoft\\Accessibility")
+        .and_then(|key| key.get_u32("TextScaleFactor"))
+        .ok()
+        .map(|percentage| percentage as f32 / 100.0)
+        .filter(|factor| *factor > 0.0)
+        .unwrap_or(1.0)
+}
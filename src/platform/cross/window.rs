@@ -1,31 +1,52 @@
 use crate::{
-    Bounds, Capslock, Modifiers, Pixels, PlatformInputHandler, PlatformWindow, Point, Size,
-    WgpuSurfaceHandle, WindowAppearance, WindowBackgroundAppearance, WindowBounds,
+    Bounds, Capslock, Decorations, DevicePixels, Modifiers, Pixels, PlatformInputHandler,
+    PlatformWindow, Point, ResizeEdge, Size, SurfaceFormatPreference, Tiling, WgpuSurfaceHandle,
+    WindowAppearance, WindowBackgroundAppearance, WindowBounds, WindowDecorations,
     platform::cross::{
         atlas::WgpuAtlas,
         dispatcher::CrossEvent,
+        display::CrossDisplay,
+        frame_pacing::DEFAULT_REFRESH_INTERVAL,
         render_context::WgpuContext,
-        renderer::WgpuRenderer,
+        render_thread::RenderThread,
+        renderer::{PreparedFrame, SoftwareCursor, WgpuRenderer},
+        surface_registry::SurfaceDevice,
     },
+    point,
 };
+use anyhow::Context as _;
 use std::{
     cell::{Cell, OnceCell, RefCell},
-    sync::Arc,
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
 };
 use winit::event_loop::EventLoopProxy;
 
+/// The shortest time between frames in eco mode, capping the frame rate at 30 frames per second.
+const ECO_MODE_FRAME_INTERVAL: Duration = Duration::from_micros(33_333);
+
 #[derive(Clone)]
 pub struct CrossWindow(pub(crate) Arc<CrossWindowInner>);
 
 pub(crate) struct CrossWindowInner {
-    pub(crate) winit_window: OnceCell<Arc<winit::window::Window>>,
-    pub(crate) renderer: OnceCell<RefCell<WgpuRenderer>>,
-    pub(crate) wgpu_context: Arc<WgpuContext>,
+    // shared with the handles of WGPU surfaces, which can be created before the window is
+    // initialized
+    pub(crate) winit_window: Arc<OnceLock<Arc<winit::window::Window>>>,
+    pub(crate) renderer: OnceCell<WindowRenderer>,
+    // replaced when the renderer moves to a new device after the previous one was lost
+    pub(crate) wgpu_context: RefCell<Arc<WgpuContext>>,
     pub(crate) sprite_atlas: Arc<WgpuAtlas>,
     pub(crate) event_loop_proxy: EventLoopProxy<CrossEvent>,
     pub(crate) state: CrossWindowState,
 }
 
+pub(crate) enum WindowRenderer {
+    /// Draws on the event loop thread.
+    Inline(RefCell<WgpuRenderer>),
+    /// Draws on a dedicated thread, see [`crate::WindowOptions::render_thread`].
+    Threaded(RenderThread),
+}
+
 #[derive(Default)]
 pub(crate) struct CrossWindowState {
     pub(crate) callbacks: Callbacks,
@@ -33,6 +54,55 @@ pub(crate) struct CrossWindowState {
     pub(crate) mouse_position: Cell<Point<Pixels>>,
     pub(crate) modifiers: Cell<Modifiers>,
     pub(crate) capslock: Cell<Capslock>,
+    // set when the next frame must be rebuilt from scratch rather than reusing cached primitives
+    pub(crate) force_render: Cell<bool>,
+    pub(crate) input_passthrough: RefCell<InputPassthrough>,
+    // whether winit's cursor hit testing is currently disabled for the window
+    pub(crate) passing_through: Cell<bool>,
+    // the width of the resize border around the content of an undecorated window
+    pub(crate) client_inset: Cell<Pixels>,
+    // the edge whose resize cursor is currently shown
+    pub(crate) resize_edge: Cell<Option<ResizeEdge>>,
+    pub(crate) screen_zoom: Cell<Option<crate::ScreenZoom>>,
+    // where the renderer draws the cursor relative to the real one, while it draws the cursor
+    // because the platform couldn't move the real one, see `CrossWindow::set_mouse_position`
+    pub(crate) software_cursor_offset: Cell<Option<Point<Pixels>>>,
+    pub(crate) color_filter: Cell<Option<crate::ColorFilter>>,
+    // whether the window is kept hidden until its first frame is presented
+    pub(crate) show_after_first_frame: Cell<bool>,
+    pub(crate) presented_first_frame: Cell<bool>,
+    // the errors of frames that failed to draw inline, reported before the next frame
+    pub(crate) renderer_errors: RefCell<Vec<crate::RendererError>>,
+    // whether the frame overload callback was last told the renderer is overloaded
+    pub(crate) reported_overloaded: Cell<bool>,
+    // the refresh interval of the display the renderer was last told the window is on
+    pub(crate) refresh_interval: Cell<Option<Duration>>,
+    // when GPUI was last asked to draw a frame, to cap the frame rate in eco mode
+    pub(crate) last_frame_requested_at: Cell<Option<Instant>>,
+    // the formats the window's surface was created with, to create it again on a new device
+    pub(crate) surface_format: RefCell<SurfaceFormatPreference>,
+    // whether the window's renderer draws on its own thread, for a renderer created after the
+    // window
+    pub(crate) render_thread: Cell<bool>,
+    // set when GPUI was asked for a frame before the window's renderer was created, or while the
+    // app was suspended
+    pub(crate) pending_frame_request: Cell<bool>,
+    // whether the window's surface is released while the app is suspended
+    pub(crate) suspended: Cell<bool>,
+    // the group of windows whose frames are presented together, see
+    // `Platform::synchronize_presents`
+    pub(crate) present_group: Cell<Option<u64>>,
+    // the frame drawn for a synchronized window, waiting to be presented with its group's
+    pub(crate) prepared_frame: RefCell<Option<PreparedFrame>>,
+}
+
+/// Where mouse input falls through the window to whatever is underneath it.
+#[derive(Default)]
+pub(crate) enum InputPassthrough {
+    #[default]
+    None,
+    Window,
+    Regions(Vec<Bounds<Pixels>>),
 }
 
 #[derive(Default)]
@@ -49,6 +119,9 @@ pub(crate) struct Callbacks {
         Cell<Option<Box<dyn FnMut() -> Option<crate::WindowControlArea>>>>,
     pub(crate) on_close: Cell<Option<Box<dyn FnOnce()>>>,
     pub(crate) on_appearance_changed: Cell<Option<Box<dyn FnMut()>>>,
+    pub(crate) on_first_frame: Cell<Option<Box<dyn FnOnce()>>>,
+    pub(crate) on_renderer_error: Cell<Option<Box<dyn FnMut(crate::RendererError)>>>,
+    pub(crate) on_frame_overload: Cell<Option<Box<dyn FnMut(bool)>>>,
 }
 
 impl Callbacks {
@@ -70,8 +143,8 @@ impl CrossWindow {
         event_loop_proxy: EventLoopProxy<CrossEvent>,
     ) -> Self {
         Self(Arc::new(CrossWindowInner {
-            winit_window: OnceCell::new(),
-            wgpu_context: wgpu_context.clone(),
+            winit_window: Arc::new(OnceLock::new()),
+            wgpu_context: RefCell::new(wgpu_context.clone()),
             renderer: OnceCell::new(),
             sprite_atlas: Arc::new(WgpuAtlas::new(wgpu_context.clone())),
             event_loop_proxy,
@@ -79,30 +152,452 @@ impl CrossWindow {
         }))
     }
 
-    pub(crate) fn initialize(&self, winit_window: winit::window::Window) {
+    pub(crate) fn initialize(
+        &self,
+        winit_window: winit::window::Window,
+        render_thread: bool,
+        surface_format: &SurfaceFormatPreference,
+    ) -> anyhow::Result<()> {
         let initial_size = winit_window.inner_size();
+        let winit_window = Arc::new(winit_window);
 
-        self.0
-            .winit_window
-            .set(Arc::new(winit_window))
-            .expect("winit_window already initialized");
+        if self.0.winit_window.set(winit_window).is_err() {
+            anyhow::bail!("window already initialized");
+        }
+        self.0.state.surface_format.replace(surface_format.clone());
+        self.0.state.render_thread.set(render_thread);
+        #[cfg(target_os = "windows")]
+        crate::platform::cross::window_controls::install(self, self.get_raw_handle());
 
+        // Windows can start out with no size, such as on Wayland before the compositor configures
+        // them, in which case the renderer is created once they're first resized, see
+        // `resize_renderer`.
         if initial_size.width > 0 && initial_size.height > 0 {
-            let renderer = WgpuRenderer::new(
-                self.0.wgpu_context.clone(),
-                self.window(),
-                self.0.sprite_atlas.clone(),
-                initial_size.width,
-                initial_size.height,
-                4,
+            self.create_renderer(initial_size.width, initial_size.height)?;
+            self.window().request_redraw();
+        }
+        Ok(())
+    }
+
+    /// Creates the window's renderer with a surface of the given size.
+    fn create_renderer(&self, width: u32, height: u32) -> anyhow::Result<()> {
+        let Some(winit_window) = self.0.winit_window.get().cloned() else {
+            anyhow::bail!("window not initialized");
+        };
+        let surface_format = self.0.state.surface_format.borrow().clone();
+        let mut renderer = WgpuRenderer::new(
+            self.0.wgpu_context.borrow().clone(),
+            winit_window.clone(),
+            self.0.sprite_atlas.clone(),
+            width,
+            height,
+            &surface_format,
+        )?;
+        let refresh_interval = display_refresh_interval(&winit_window);
+        renderer.set_refresh_interval(refresh_interval);
+        self.0.state.refresh_interval.set(Some(refresh_interval));
+        renderer.set_pre_present_notify({
+            let winit_window = winit_window.clone();
+            move || winit_window.pre_present_notify()
+        });
+
+        let renderer = if self.0.state.render_thread.get() {
+            WindowRenderer::Threaded(
+                RenderThread::spawn(renderer, winit_window)
+                    .context("failed to spawn the render thread")?,
+            )
+        } else {
+            WindowRenderer::Inline(RefCell::new(renderer))
+        };
+
+        if self.0.renderer.set(renderer).is_err() {
+            log::error!("window renderer already initialized");
+        }
+        Ok(())
+    }
+
+    /// Moves the window's renderer and atlas onto `context` after the device they were created on
+    /// was lost, and redraws the window from scratch. Glyphs and images are rasterized into the
+    /// atlas again as they're drawn.
+    pub(crate) fn recover_from_device_loss(&self, context: Arc<WgpuContext>) -> anyhow::Result<()> {
+        self.0.wgpu_context.replace(context.clone());
+        self.0.state.prepared_frame.take();
+        self.0.sprite_atlas.handle_device_lost(context.clone());
+
+        let Some(winit_window) = self.0.winit_window.get().cloned() else {
+            return Ok(());
+        };
+        let surface_format = self.0.state.surface_format.borrow().clone();
+        self.with_renderer(|renderer| renderer.recreate(context, winit_window, &surface_format))
+            .transpose()?;
+        self.0.state.force_render.set(true);
+        self.window().request_redraw();
+        Ok(())
+    }
+
+    /// Releases the window's surface while the app is suspended, keeping its renderer and atlas
+    /// so it's drawn as it was once resumed.
+    pub(crate) fn suspend(&self) {
+        if self.0.state.suspended.replace(true) {
+            return;
+        }
+        self.0.state.prepared_frame.take();
+        self.with_renderer(|renderer| renderer.suspend());
+    }
+
+    /// Creates the window's surface again after [`CrossWindow::suspend`] and redraws it.
+    pub(crate) fn resume(&self) {
+        if !self.0.state.suspended.replace(false) {
+            return;
+        }
+        let Some(winit_window) = self.0.winit_window.get().cloned() else {
+            return;
+        };
+        if let Some(Err(error)) = self.with_renderer(|renderer| renderer.resume(winit_window)) {
+            log::error!("failed to create the window's surface again: {error:#}");
+        }
+        self.window().request_redraw();
+    }
+
+    /// Redraws the regions of the window's low latency surfaces that presented, without waiting
+    /// for the next frame. Returns false if a full frame has to be drawn instead.
+    pub(crate) fn recomposite_low_latency_surfaces(&self) -> bool {
+        self.with_renderer(|renderer| renderer.recomposite_low_latency_surfaces())
+            .unwrap_or(false)
+    }
+
+    /// Presents the frame a synchronized window drew, if it drew one since the last call.
+    pub(crate) fn present_prepared_frame(&self) {
+        if let Some(frame) = self.0.state.prepared_frame.take() {
+            self.with_renderer(|renderer| renderer.present(frame));
+        }
+    }
+
+    /// Runs `f` with the window's renderer, if it has been created. For a threaded renderer,
+    /// this waits for the frame in flight.
+    pub(crate) fn with_renderer<R>(&self, f: impl FnOnce(&mut WgpuRenderer) -> R) -> Option<R> {
+        match self.0.renderer.get()? {
+            WindowRenderer::Inline(renderer) => Some(f(&mut renderer.borrow_mut())),
+            WindowRenderer::Threaded(render_thread) => Some(render_thread.with_renderer(f)),
+        }
+    }
+
+    /// Resizes the window's renderer, or creates it if the window had no size until now. A frame
+    /// GPUI was asked for in the meantime is requested again once it exists.
+    pub(crate) fn resize_renderer(&self, size: Size<DevicePixels>) {
+        match self.0.renderer.get() {
+            Some(WindowRenderer::Inline(renderer)) => {
+                renderer.borrow_mut().update_drawable_size(size)
+            }
+            Some(WindowRenderer::Threaded(render_thread)) => render_thread.resize(size),
+            None => {
+                if let Err(error) = self.create_renderer(size.width.0 as u32, size.height.0 as u32)
+                {
+                    log::error!("failed to create the window's renderer: {error:#}");
+                    return;
+                }
+                if self.0.state.pending_frame_request.take() {
+                    self.window().request_redraw();
+                }
+            }
+        }
+    }
+
+    /// Asks GPUI to draw a frame, then reports the window's first frame if it was just presented.
+    /// In eco mode, frames are skipped to stay within [`ECO_MODE_FRAME_INTERVAL`].
+    pub(crate) fn request_frame(&self) {
+        if self.0.renderer.get().is_none() || self.0.state.suspended.get() {
+            self.0.state.pending_frame_request.set(true);
+            return;
+        }
+        let now = Instant::now();
+        self.report_renderer_errors();
+        let overload_frame_interval = self.overload_frame_interval();
+        self.report_frame_overload(overload_frame_interval.is_some());
+
+        // While the renderer is overloaded, the requests that come in before it could draw another
+        // frame are skipped, so the next frame draws the latest state instead of falling further
+        // behind.
+        let eco_mode_frame_interval = self
+            .0
+            .wgpu_context
+            .borrow()
+            .renderer_settings
+            .eco_mode_active()
+            .then_some(ECO_MODE_FRAME_INTERVAL);
+        if let Some(frame_interval) = eco_mode_frame_interval.max(overload_frame_interval) {
+            let last_frame_requested_at = self.0.state.last_frame_requested_at.get();
+            if last_frame_requested_at
+                .is_some_and(|requested_at| now - requested_at < frame_interval)
+            {
+                return;
+            }
+        }
+        self.0.state.last_frame_requested_at.set(Some(now));
+
+        self.0
+            .state
+            .callbacks
+            .invoke_mut(&self.0.state.callbacks.on_request_frame, |cb| {
+                cb(crate::RequestFrameOptions {
+                    force_render: self.take_force_render(),
+                    require_presentation: true,
+                });
+            });
+
+        // The frame is presented while GPUI is drawing, when the app can't be updated, so the
+        // callback waits until GPUI is done.
+        if self.0.state.presented_first_frame.get() {
+            if let Some(callback) = self.0.state.callbacks.on_first_frame.take() {
+                callback();
+            }
+        }
+    }
+
+    /// Reports the frames that failed to draw since the last frame to the window's renderer error
+    /// callback, or logs them when there's none. They're reported here rather than as they
+    /// happen, since frames are drawn while GPUI is drawing, when the app can't be updated.
+    fn report_renderer_errors(&self) {
+        let mut errors = self.0.state.renderer_errors.take();
+        if let Some(WindowRenderer::Threaded(render_thread)) = self.0.renderer.get() {
+            errors.extend(render_thread.take_errors());
+        }
+        let callbacks = &self.0.state.callbacks;
+        for error in errors {
+            match callbacks.on_renderer_error.take() {
+                Some(mut callback) => {
+                    callback(error);
+                    callbacks.on_renderer_error.set(Some(callback));
+                }
+                None => log::error!("failed to draw a frame: {error}"),
+            }
+        }
+    }
+
+    /// The shortest time between frames while the window's renderer is overloaded. See
+    /// [`WgpuRenderer::overload_frame_interval`].
+    fn overload_frame_interval(&self) -> Option<Duration> {
+        match self.0.renderer.get()? {
+            WindowRenderer::Inline(renderer) => renderer.borrow().overload_frame_interval(),
+            WindowRenderer::Threaded(render_thread) => render_thread.overload_frame_interval(),
+        }
+    }
+
+    /// Tells the window's frame overload callback when the renderer starts or stops being
+    /// overloaded.
+    fn report_frame_overload(&self, overloaded: bool) {
+        if self.0.state.reported_overloaded.replace(overloaded) == overloaded {
+            return;
+        }
+        if overloaded {
+            log::warn!("the renderer can't keep up with the display, so frames are skipped");
+        }
+        self.0
+            .state
+            .callbacks
+            .invoke_mut(&self.0.state.callbacks.on_frame_overload, |callback| {
+                callback(overloaded)
+            });
+    }
+
+    /// Tells the renderer the refresh interval of the display the window is on, if it changed
+    /// since the window was created or last moved.
+    pub(crate) fn update_refresh_interval(&self) {
+        let refresh_interval = display_refresh_interval(self.window());
+        let state = &self.0.state;
+        if state.refresh_interval.replace(Some(refresh_interval)) != Some(refresh_interval) {
+            self.with_renderer(|renderer| renderer.set_refresh_interval(refresh_interval));
+        }
+    }
+
+    /// Returns true if the next frame must be rebuilt from scratch, because the renderer evicted
+    /// atlas tiles that previous scenes reference.
+    pub(crate) fn take_force_render(&self) -> bool {
+        let threaded = match self.0.renderer.get() {
+            Some(WindowRenderer::Threaded(render_thread)) => render_thread.take_needs_full_redraw(),
+            _ => false,
+        };
+        self.0.state.force_render.take() || threaded
+    }
+
+    /// Enables or disables cursor hit testing for a cursor at `position`. winit can only make the
+    /// whole window pass input through, so passthrough regions are applied as the cursor moves.
+    pub(crate) fn update_cursor_hittest(&self, position: Point<Pixels>) {
+        let passes_through = match &*self.0.state.input_passthrough.borrow() {
+            InputPassthrough::None => false,
+            InputPassthrough::Window => true,
+            InputPassthrough::Regions(regions) => {
+                regions.iter().any(|region| region.contains(&position))
+            }
+        };
+
+        if self.0.state.passing_through.replace(passes_through) != passes_through
+            && let Err(error) = self.window().set_cursor_hittest(!passes_through)
+        {
+            log::error!("failed to change cursor hit testing: {error}");
+        }
+    }
+
+    /// Returns true if the cursor is over a passthrough region, where the window receives no
+    /// cursor events of its own.
+    pub(crate) fn is_passing_through_region(&self) -> bool {
+        self.0.state.passing_through.get()
+            && matches!(
+                *self.0.state.input_passthrough.borrow(),
+                InputPassthrough::Regions(_)
             )
-            .expect("Failed to create renderer");
+    }
+
+    /// Returns the edge that dragging at `position` resizes. Only undecorated windows with a
+    /// client inset have a resize border, and only while they're neither maximized nor fullscreen.
+    pub(crate) fn resize_edge(&self, position: Point<Pixels>) -> Option<ResizeEdge> {
+        let window = self.window();
+        let inset = self.0.state.client_inset.get();
+        if window.is_decorated()
+            || !window.is_resizable()
+            || window.is_maximized()
+            || window.fullscreen().is_some()
+            || inset <= Pixels::ZERO
+        {
+            return None;
+        }
+
+        let size = self.content_size();
+        let top = position.y < inset;
+        let bottom = position.y >= size.height - inset;
+        let left = position.x < inset;
+        let right = position.x >= size.width - inset;
+        match (top, bottom, left, right) {
+            (true, _, true, _) => Some(ResizeEdge::TopLeft),
+            (true, _, _, true) => Some(ResizeEdge::TopRight),
+            (_, true, true, _) => Some(ResizeEdge::BottomLeft),
+            (_, true, _, true) => Some(ResizeEdge::BottomRight),
+            (true, _, _, _) => Some(ResizeEdge::Top),
+            (_, true, _, _) => Some(ResizeEdge::Bottom),
+            (_, _, true, _) => Some(ResizeEdge::Left),
+            (_, _, _, true) => Some(ResizeEdge::Right),
+            _ => None,
+        }
+    }
 
-            let _ = self.0.renderer.set(RefCell::new(renderer));
+    /// Shows the resize cursor for the edge under a cursor at `position`, or restores the default
+    /// cursor once it leaves the resize border.
+    pub(crate) fn update_resize_cursor(&self, position: Point<Pixels>) {
+        let edge = self.resize_edge(position);
+        if self.0.state.resize_edge.replace(edge) != edge {
+            let icon = edge.map_or(winit::window::CursorIcon::Default, resize_cursor_icon);
+            self.window().set_cursor(icon);
+        }
+    }
+
+    /// Maps a position in the window to the position of the unmagnified frame shown there, which
+    /// is where input lands while the window is zoomed.
+    pub(crate) fn unzoomed_position(&self, position: Point<Pixels>) -> Point<Pixels> {
+        let Some(zoom) = self.0.state.screen_zoom.get() else {
+            return position;
+        };
+        let content_size = self.content_size();
+        let position = zoom.window_to_frame(
+            point(position.x.0, position.y.0),
+            crate::size(content_size.width.0, content_size.height.0),
+        );
+        point(Pixels(position.x), Pixels(position.y))
+    }
+
+    /// Asks GPUI which of the window controls it draws, such as in a custom titlebar, is under the
+    /// mouse, if any.
+    pub(crate) fn hit_test_window_control(&self) -> Option<crate::WindowControlArea> {
+        let callbacks = &self.0.state.callbacks;
+        let mut callback = callbacks.on_hit_test_window_control.take()?;
+        let area = callback();
+        callbacks.on_hit_test_window_control.set(Some(callback));
+        area
+    }
+
+    /// Maps `position` in the possibly magnified frame to where it's shown in the window, the
+    /// inverse of [`CrossWindow::unzoomed_position`].
+    pub(crate) fn zoomed_position(&self, position: Point<Pixels>) -> Point<Pixels> {
+        let Some(zoom) = self.0.state.screen_zoom.get() else {
+            return position;
+        };
+        let content_size = self.content_size();
+        let position = zoom.frame_to_window(
+            point(position.x.0, position.y.0),
+            crate::size(content_size.width.0, content_size.height.0),
+        );
+        point(Pixels(position.x), Pixels(position.y))
+    }
+
+    /// Moves the cursor to `position` in the window. Where the platform doesn't let apps move the
+    /// cursor, such as on Wayland, the real cursor is hidden and the renderer draws one at
+    /// `position` instead, which follows the mouse from there until it leaves the window.
+    pub fn set_mouse_position(&self, position: Point<Pixels>) {
+        let window_position = self.zoomed_position(position);
+        let moved = self
+            .window()
+            .set_cursor_position(winit::dpi::LogicalPosition::new(
+                window_position.x.0 as f64,
+                window_position.y.0 as f64,
+            ));
+        let state = &self.0.state;
+        match moved {
+            Ok(()) => self.set_software_cursor_offset(None),
+            Err(error) => {
+                log::debug!("drawing the cursor, since it can't be moved: {error}");
+                let offset = state.software_cursor_offset.get().unwrap_or_default();
+                let real_position = state.mouse_position.get() - offset;
+                self.set_software_cursor_offset(Some(position - real_position));
+            }
+        }
+        state.mouse_position.set(position);
+        self.move_software_cursor();
+    }
+
+    /// Starts drawing the cursor at `offset` from the real one, hiding the real one, or stops
+    /// drawing it and shows the real one again.
+    pub(crate) fn set_software_cursor_offset(&self, offset: Option<Point<Pixels>>) {
+        let previous_offset = self.0.state.software_cursor_offset.replace(offset);
+        if previous_offset.is_none() && offset.is_none() {
+            return;
+        }
+        self.window().set_cursor_visible(offset.is_none());
+        if offset.is_none() {
+            self.with_renderer(|renderer| renderer.set_software_cursor(None));
             self.window().request_redraw();
         }
     }
 
+    /// Moves the cursor the renderer draws to the window's mouse position, if it draws one.
+    pub(crate) fn move_software_cursor(&self) {
+        if self.0.state.software_cursor_offset.get().is_none() {
+            return;
+        }
+        let scale_factor = self.window().scale_factor() as f32;
+        let cursor = SoftwareCursor {
+            position: self.0.state.mouse_position.get().scale(scale_factor),
+            scale_factor,
+        };
+        self.with_renderer(|renderer| renderer.set_software_cursor(Some(cursor)));
+        self.window().request_redraw();
+    }
+
+    /// Returns the native handle of the winit window, for integrating native libraries with it.
+    /// See [`crate::Window::raw_window_handle`].
+    pub fn raw_window_handle(
+        &self,
+    ) -> Result<raw_window_handle::RawWindowHandle, raw_window_handle::HandleError> {
+        use raw_window_handle::HasWindowHandle as _;
+        Ok(self.window().window_handle()?.as_raw())
+    }
+
+    /// Returns the native handle of the display connection the winit window belongs to.
+    pub fn raw_display_handle(
+        &self,
+    ) -> Result<raw_window_handle::RawDisplayHandle, raw_window_handle::HandleError> {
+        use raw_window_handle::HasDisplayHandle as _;
+        Ok(self.window().display_handle()?.as_raw())
+    }
+
     pub(crate) fn window(&self) -> &winit::window::Window {
         &*self
             .0
@@ -185,8 +680,8 @@ impl PlatformWindow for CrossWindow {
     }
 
     fn display(&self) -> Option<std::rc::Rc<dyn crate::PlatformDisplay>> {
-        // TODO(mdeand): Add support for querying the display.
-        None
+        let monitor = self.window().current_monitor()?;
+        Some(std::rc::Rc::new(CrossDisplay::new(&monitor)))
     }
 
     fn mouse_position(&self) -> Point<Pixels> {
@@ -240,8 +735,15 @@ impl PlatformWindow for CrossWindow {
         self.window().set_title(title);
     }
 
-    fn set_background_appearance(&self, _background_appearance: WindowBackgroundAppearance) {
-        // TODO(mdeand): Add support for setting the background appearance.
+    fn set_background_appearance(&self, background_appearance: WindowBackgroundAppearance) {
+        let transparent = background_appearance != WindowBackgroundAppearance::Opaque;
+        self.window().set_transparent(transparent);
+        self.window()
+            .set_blur(background_appearance == WindowBackgroundAppearance::Blurred);
+        self.with_renderer(|renderer| renderer.update_transparency(transparent));
+        // The renderer switches alpha modes when it draws the next frame.
+        self.0.state.force_render.set(true);
+        self.window().request_redraw();
     }
 
     fn minimize(&self) {
@@ -323,9 +825,75 @@ impl PlatformWindow for CrossWindow {
             .set(Some(callback));
     }
 
+    fn on_first_frame(&self, callback: Box<dyn FnOnce()>) {
+        self.0.state.callbacks.on_first_frame.set(Some(callback));
+    }
+
+    fn on_renderer_error(&self, callback: Box<dyn FnMut(crate::RendererError)>) {
+        self.0.state.callbacks.on_renderer_error.set(Some(callback));
+    }
+
+    fn on_frame_overload(&self, callback: Box<dyn FnMut(bool)>) {
+        self.0.state.callbacks.on_frame_overload.set(Some(callback));
+    }
+
     fn draw(&self, scene: &crate::Scene) {
-        if let Some(renderer) = self.0.renderer.get() {
-            renderer.borrow().draw(scene);
+        match self.0.renderer.get() {
+            // A synchronized window only draws and submits its frame here. It's presented with the
+            // frames of the rest of its group once the event loop has drawn every window, see
+            // `present_prepared_frame`.
+            Some(_)
+                if self.0.state.present_group.get().is_some()
+                    && !self.0.state.show_after_first_frame.get() =>
+            {
+                let needs_full_redraw = self.with_renderer(|renderer| {
+                    match renderer.prepare(scene) {
+                        Ok(frame) => {
+                            self.0.state.prepared_frame.replace(Some(frame));
+                        }
+                        Err(error) => self.0.state.renderer_errors.borrow_mut().push(error),
+                    }
+                    renderer.take_needs_full_redraw()
+                });
+                if needs_full_redraw == Some(true) {
+                    self.0.state.force_render.set(true);
+                    self.window().request_redraw();
+                }
+            }
+            Some(WindowRenderer::Inline(renderer)) => {
+                let mut renderer = renderer.borrow_mut();
+                if let Err(error) = renderer.draw(scene) {
+                    self.0.state.renderer_errors.borrow_mut().push(error);
+                }
+                if renderer.take_needs_full_redraw() {
+                    self.0.state.force_render.set(true);
+                    self.window().request_redraw();
+                }
+            }
+            // A window waiting for its first frame is shown as soon as this returns, so that frame
+            // is presented before returning.
+            Some(WindowRenderer::Threaded(render_thread))
+                if self.0.state.show_after_first_frame.get() =>
+            {
+                let needs_full_redraw = render_thread.with_renderer(|renderer| {
+                    if let Err(error) = renderer.draw(scene) {
+                        self.0.state.renderer_errors.borrow_mut().push(error);
+                    }
+                    renderer.take_needs_full_redraw()
+                });
+                if needs_full_redraw {
+                    self.0.state.force_render.set(true);
+                    self.window().request_redraw();
+                }
+            }
+            Some(WindowRenderer::Threaded(render_thread)) => render_thread.draw(scene),
+            None => return,
+        }
+
+        if !self.0.state.presented_first_frame.replace(true)
+            && self.0.state.show_after_first_frame.take()
+        {
+            self.window().set_visible(true);
         }
     }
 
@@ -335,43 +903,55 @@ impl PlatformWindow for CrossWindow {
         height: u32,
         format: wgpu::TextureFormat,
     ) -> Option<WgpuSurfaceHandle> {
-        let ctx = &self.0.wgpu_context;
+        let ctx = self.0.wgpu_context.borrow().clone();
         let registry = ctx.surface_registry.clone();
         let surface_id = registry.create(&ctx.device, width, height, format);
 
         // Build the present trigger: sends a CrossEvent to wake the event loop
         // and request a redraw for this window.
+        // The window is looked up when the surface presents, since the surface can be created
+        // before the window is initialized.
         let proxy = self.0.event_loop_proxy.clone();
-        let window_id = self
-            .0
-            .winit_window
-            .get()
-            .map(|w| w.id());
+        let winit_window = self.0.winit_window.clone();
         let present_trigger: Arc<dyn Fn() + Send + Sync> = Arc::new(move || {
-            if let Some(wid) = window_id {
-                let _ = proxy.send_event(CrossEvent::SurfacePresent(wid));
+            if let Some(window) = winit_window.get() {
+                if proxy
+                    .send_event(CrossEvent::SurfacePresent(window.id()))
+                    .is_err()
+                {
+                    log::debug!("a surface presented after the event loop exited");
+                }
             }
         });
 
-        // capture winit window Arc so handle can request redraw directly
-        let winit_arc = self
-            .0
-            .winit_window
-            .get()
-            .cloned();
         Some(WgpuSurfaceHandle::new(
-            ctx.device.clone(),
-            ctx.queue.clone(),
+            SurfaceDevice {
+                device: ctx.device.clone(),
+                queue: ctx.queue.clone(),
+                scheduler: ctx.submission_scheduler.clone(),
+            },
             surface_id,
             registry,
             present_trigger,
-            winit_arc,
+            self.0.winit_window.clone(),
             width,
             height,
             format,
         ))
     }
 
+    fn wgpu_surfaces(&self) -> Vec<crate::SurfaceInfo> {
+        self.0.wgpu_context.borrow().surface_registry.list()
+    }
+
+    fn find_wgpu_surface(&self, name: &str) -> Option<crate::SurfaceInfo> {
+        self.0
+            .wgpu_context
+            .borrow()
+            .surface_registry
+            .find_by_name(name)
+    }
+
     fn sprite_atlas(&self) -> std::sync::Arc<dyn crate::PlatformAtlas> {
         self.0.sprite_atlas.clone()
     }
@@ -383,6 +963,182 @@ impl PlatformWindow for CrossWindow {
 
     fn update_ime_position(&self, _bounds: crate::Bounds<crate::Pixels>) {}
 
+    fn request_decorations(&self, decorations: WindowDecorations) {
+        let window = self.window();
+        window.set_decorations(decorations == WindowDecorations::Server);
+        // Windows can keep the system drop shadow on undecorated windows, so the application
+        // doesn't need to draw one of its own.
+        #[cfg(target_os = "windows")]
+        {
+            use winit::platform::windows::WindowExtWindows as _;
+            window.set_undecorated_shadow(decorations == WindowDecorations::Client);
+        }
+    }
+
+    fn window_decorations(&self) -> Decorations {
+        let window = self.window();
+        if window.is_decorated() {
+            return Decorations::Server;
+        }
+        let tiling = if window.is_maximized() || window.fullscreen().is_some() {
+            Tiling::tiled()
+        } else {
+            Tiling::default()
+        };
+        Decorations::Client { tiling }
+    }
+
+    fn show_window_menu(&self, position: Point<Pixels>) {
+        self.window()
+            .show_window_menu(winit::dpi::LogicalPosition::new(position.x.0, position.y.0));
+    }
+
+    fn start_window_move(&self) {
+        if let Err(error) = self.window().drag_window() {
+            log::error!("failed to start moving the window: {error}");
+        }
+    }
+
+    fn start_window_resize(&self, edge: ResizeEdge) {
+        if let Err(error) = self.window().drag_resize_window(resize_direction(edge)) {
+            log::error!("failed to start resizing the window: {error}");
+        }
+    }
+
+    fn set_client_inset(&self, inset: Pixels) {
+        self.0.state.client_inset.set(inset);
+        self.update_resize_cursor(self.0.state.mouse_position.get());
+    }
+
+    fn set_input_passthrough(&self, passthrough: bool) {
+        let passthrough = if passthrough {
+            InputPassthrough::Window
+        } else {
+            InputPassthrough::None
+        };
+        self.0.state.input_passthrough.replace(passthrough);
+        self.update_cursor_hittest(self.0.state.mouse_position.get());
+    }
+
+    fn set_input_passthrough_regions(&self, regions: Vec<Bounds<Pixels>>) {
+        let passthrough = if regions.is_empty() {
+            InputPassthrough::None
+        } else {
+            InputPassthrough::Regions(regions)
+        };
+        self.0.state.input_passthrough.replace(passthrough);
+        self.update_cursor_hittest(self.0.state.mouse_position.get());
+    }
+
+    fn set_draw_call_recording(&self, enabled: bool) {
+        self.with_renderer(|renderer| renderer.set_draw_call_recording(enabled));
+    }
+
+    fn last_frame_draw_calls(&self) -> Option<crate::FrameDrawCalls> {
+        self.with_renderer(|renderer| renderer.last_frame_draw_calls())
+            .flatten()
+    }
+
+    fn present_stats(&self) -> Option<crate::PresentStats> {
+        match self.0.renderer.get()? {
+            WindowRenderer::Inline(renderer) => Some(renderer.borrow().present_stats()),
+            WindowRenderer::Threaded(render_thread) => Some(render_thread.present_stats()),
+        }
+    }
+
+    fn start_frame_recording(&self, recording: crate::FrameRecording) -> anyhow::Result<()> {
+        self.with_renderer(|renderer| renderer.start_frame_recording(recording))
+            .unwrap_or_else(|| {
+                Err(anyhow::anyhow!(
+                    "the window's renderer hasn't been created yet"
+                ))
+            })
+    }
+
+    fn stop_frame_recording(&self) {
+        self.with_renderer(|renderer| renderer.stop_frame_recording());
+    }
+
+    fn set_mouse_position(&self, position: Point<Pixels>) {
+        CrossWindow::set_mouse_position(self, position);
+    }
+
+    fn set_clear_color(&self, color: Option<crate::Hsla>) {
+        self.with_renderer(|renderer| renderer.set_clear_color(color));
+        self.0.state.force_render.set(true);
+        self.window().request_redraw();
+    }
+
+    fn set_screen_zoom(&self, zoom: Option<crate::ScreenZoom>) {
+        self.0.state.screen_zoom.set(zoom);
+        self.with_renderer(|renderer| renderer.set_screen_zoom(zoom));
+        self.0.state.force_render.set(true);
+        self.window().request_redraw();
+    }
+
+    fn screen_zoom(&self) -> Option<crate::ScreenZoom> {
+        self.0.state.screen_zoom.get()
+    }
+
+    fn set_color_filter(&self, color_filter: Option<crate::ColorFilter>) {
+        self.0.state.color_filter.set(color_filter);
+        self.with_renderer(|renderer| renderer.set_color_filter(color_filter));
+        self.0.state.force_render.set(true);
+        self.window().request_redraw();
+    }
+
+    fn color_filter(&self) -> Option<crate::ColorFilter> {
+        self.0.state.color_filter.get()
+    }
+
+    fn draw_region(
+        &self,
+        scene: &crate::Scene,
+        viewport: crate::SceneViewport,
+        target: &wgpu::Texture,
+    ) -> anyhow::Result<()> {
+        self.with_renderer(|renderer| renderer.draw_region(scene, viewport, target))
+            .unwrap_or_else(|| {
+                Err(anyhow::anyhow!(
+                    "the window's renderer hasn't been created yet"
+                ))
+            })
+    }
+
+    fn draw_region_to_view(
+        &self,
+        scene: &crate::Scene,
+        viewport: crate::SceneViewport,
+        target: &wgpu::TextureView,
+        target_size: Size<DevicePixels>,
+    ) -> anyhow::Result<()> {
+        self.with_renderer(|renderer| {
+            renderer.draw_region_to_view(scene, viewport, target, target_size)
+        })
+        .unwrap_or_else(|| {
+            Err(anyhow::anyhow!(
+                "the window's renderer hasn't been created yet"
+            ))
+        })
+    }
+
+    fn xr_swapchain_format(&self) -> Option<wgpu::TextureFormat> {
+        self.with_renderer(|renderer| renderer.xr_swapchain_format())
+    }
+
+    fn draw_to_xr_swapchain(
+        &self,
+        scene: &crate::Scene,
+        target: &crate::XrSwapchainTarget,
+    ) -> anyhow::Result<()> {
+        self.with_renderer(|renderer| renderer.draw_to_xr_swapchain(scene, target))
+            .unwrap_or_else(|| {
+                Err(anyhow::anyhow!(
+                    "the window's renderer hasn't been created yet"
+                ))
+            })
+    }
+
     #[cfg(target_os = "windows")]
     fn get_raw_handle(&self) -> windows::Win32::Foundation::HWND {
         use raw_window_handle::{HasWindowHandle, RawWindowHandle};
@@ -395,6 +1151,45 @@ impl PlatformWindow for CrossWindow {
     }
 }
 
+fn resize_direction(edge: ResizeEdge) -> winit::window::ResizeDirection {
+    use winit::window::ResizeDirection;
+    match edge {
+        ResizeEdge::Top => ResizeDirection::North,
+        ResizeEdge::TopRight => ResizeDirection::NorthEast,
+        ResizeEdge::Right => ResizeDirection::East,
+        ResizeEdge::BottomRight => ResizeDirection::SouthEast,
+        ResizeEdge::Bottom => ResizeDirection::South,
+        ResizeEdge::BottomLeft => ResizeDirection::SouthWest,
+        ResizeEdge::Left => ResizeDirection::West,
+        ResizeEdge::TopLeft => ResizeDirection::NorthWest,
+    }
+}
+
+fn resize_cursor_icon(edge: ResizeEdge) -> winit::window::CursorIcon {
+    use winit::window::CursorIcon;
+    match edge {
+        ResizeEdge::Top => CursorIcon::NResize,
+        ResizeEdge::TopRight => CursorIcon::NeResize,
+        ResizeEdge::Right => CursorIcon::EResize,
+        ResizeEdge::BottomRight => CursorIcon::SeResize,
+        ResizeEdge::Bottom => CursorIcon::SResize,
+        ResizeEdge::BottomLeft => CursorIcon::SwResize,
+        ResizeEdge::Left => CursorIcon::WResize,
+        ResizeEdge::TopLeft => CursorIcon::NwResize,
+    }
+}
+
+/// How often the display `window` is on refreshes, or 60 Hz when that can't be read.
+fn display_refresh_interval(window: &winit::window::Window) -> Duration {
+    window
+        .current_monitor()
+        .and_then(|monitor| monitor.refresh_rate_millihertz())
+        .filter(|millihertz| *millihertz > 0)
+        .map_or(DEFAULT_REFRESH_INTERVAL, |millihertz| {
+            Duration::from_secs_f64(1000.0 / millihertz as f64)
+        })
+}
+
 impl raw_window_handle::HasDisplayHandle for CrossWindow {
     fn display_handle(
         &self,
@@ -0,0 +1,179 @@
+use std::{
+    cell::Cell,
+    sync::{Arc, Weak},
+};
+
+use windows::Win32::{
+    Foundation::{HWND, LPARAM, LRESULT, POINT, WPARAM},
+    Graphics::Gdi::ScreenToClient,
+    UI::{
+        Shell::{DefSubclassProc, RemoveWindowSubclass, SetWindowSubclass},
+        WindowsAndMessaging::{
+            HTCAPTION, HTCLIENT, HTCLOSE, HTMAXBUTTON, HTMINBUTTON, PostMessageW, WM_CLOSE,
+            WM_NCDESTROY, WM_NCHITTEST, WM_NCLBUTTONDOWN, WM_NCLBUTTONUP, WM_NCMOUSEMOVE,
+        },
+    },
+};
+
+use crate::{
+    MouseMoveEvent, Pixels, PlatformInput, WindowControlArea,
+    platform::cross::window::{CrossWindow, CrossWindowInner},
+    point,
+};
+
+/// Identifies the subclass among any others installed on the window.
+const SUBCLASS_ID: usize = 0x6770_7569;
+
+/// What the subclass of a window keeps, freed along with the window.
+struct WindowControls {
+    window: Weak<CrossWindowInner>,
+    // the window control button the left mouse button was pressed on, which it has to be released
+    // on too for the button to be clicked
+    pressed_button: Cell<Option<u32>>,
+}
+
+/// Reports the window controls GPUI draws, such as in a custom titlebar, to Windows as the
+/// window's caption and caption buttons. Windows then shows the system cursors and, on Windows 11,
+/// the snap layouts of the maximize button for them, and drags the window by its caption.
+pub(crate) fn install(window: &CrossWindow, hwnd: HWND) {
+    let controls = Box::into_raw(Box::new(WindowControls {
+        window: Arc::downgrade(&window.0),
+        pressed_button: Cell::new(None),
+    }));
+    // SAFETY: The subclass frees `controls` when the window is destroyed, which is the last
+    // message it receives.
+    let installed =
+        unsafe { SetWindowSubclass(hwnd, Some(subclass_proc), SUBCLASS_ID, controls as usize) };
+    if !installed.as_bool() {
+        // SAFETY: `controls` was never handed to the window.
+        drop(unsafe { Box::from_raw(controls) });
+        log::error!("failed to report the window controls to Windows");
+    }
+}
+
+unsafe extern "system" fn subclass_proc(
+    hwnd: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    _subclass_id: usize,
+    data: usize,
+) -> LRESULT {
+    // SAFETY: `data` is the `WindowControls` the subclass was installed with, freed only below.
+    let controls = unsafe { &*(data as *const WindowControls) };
+    let hit_area = wparam.0 as u32;
+    match message {
+        WM_NCHITTEST => {
+            let hit_test = unsafe { DefSubclassProc(hwnd, message, wparam, lparam) };
+            if hit_test.0 as u32 != HTCLIENT {
+                return hit_test;
+            }
+            let area = controls
+                .window()
+                .and_then(|window| window.hit_test_window_control());
+            match area {
+                Some(area) => LRESULT(hit_test_result(area) as isize),
+                None => hit_test,
+            }
+        }
+        // Windows reports the mouse over the caption and its buttons as outside the client area, so
+        // GPUI is told about it to keep hovering them.
+        WM_NCMOUSEMOVE if is_window_control(hit_area) => {
+            controls.move_mouse(hwnd, lparam);
+            unsafe { DefSubclassProc(hwnd, message, wparam, lparam) }
+        }
+        // The default handling of the caption buttons draws the buttons of a decorated window over
+        // GPUI's, so they're clicked here instead.
+        WM_NCLBUTTONDOWN if is_button(hit_area) => {
+            controls.pressed_button.set(Some(hit_area));
+            LRESULT(0)
+        }
+        WM_NCLBUTTONUP if is_button(hit_area) => {
+            if controls.pressed_button.take() == Some(hit_area) {
+                controls.click(hwnd, hit_area);
+            }
+            LRESULT(0)
+        }
+        WM_NCDESTROY => unsafe {
+            if !RemoveWindowSubclass(hwnd, Some(subclass_proc), SUBCLASS_ID).as_bool() {
+                log::error!("failed to remove the window controls subclass");
+            }
+            drop(Box::from_raw(data as *mut WindowControls));
+            DefSubclassProc(hwnd, message, wparam, lparam)
+        },
+        _ => unsafe { DefSubclassProc(hwnd, message, wparam, lparam) },
+    }
+}
+
+impl WindowControls {
+    fn window(&self) -> Option<CrossWindow> {
+        self.window.upgrade().map(CrossWindow)
+    }
+
+    /// Tells GPUI the mouse moved to the screen position in `lparam`.
+    fn move_mouse(&self, hwnd: HWND, lparam: LPARAM) {
+        let Some(window) = self.window() else {
+            return;
+        };
+        let mut position = POINT {
+            x: (lparam.0 & 0xFFFF) as i16 as i32,
+            y: ((lparam.0 >> 16) & 0xFFFF) as i16 as i32,
+        };
+        if !unsafe { ScreenToClient(hwnd, &mut position) }.as_bool() {
+            return;
+        }
+        let scale_factor = window.window().scale_factor() as f32;
+        let position = window.unzoomed_position(point(
+            Pixels(position.x as f32 / scale_factor),
+            Pixels(position.y as f32 / scale_factor),
+        ));
+        let state = &window.0.state;
+        state.mouse_position.set(position);
+        let event = PlatformInput::MouseMove(MouseMoveEvent {
+            position,
+            pressed_button: None,
+            modifiers: state.modifiers.get(),
+        });
+        state
+            .callbacks
+            .invoke_mut(&state.callbacks.on_input, |callback| {
+                callback(event);
+            });
+    }
+
+    fn click(&self, hwnd: HWND, button: u32) {
+        let Some(window) = self.window() else {
+            return;
+        };
+        let winit_window = window.window();
+        match button {
+            HTMINBUTTON => winit_window.set_minimized(true),
+            HTMAXBUTTON => winit_window.set_maximized(!winit_window.is_maximized()),
+            // Closing goes through winit, so the app can still refuse it.
+            _ => {
+                if let Err(error) =
+                    unsafe { PostMessageW(Some(hwnd), WM_CLOSE, WPARAM(0), LPARAM(0)) }
+                {
+                    log::error!("failed to close the window: {error}");
+                }
+            }
+        }
+    }
+}
+
+fn hit_test_result(area: WindowControlArea) -> u32 {
+    match area {
+        WindowControlArea::Drag => HTCAPTION,
+        WindowControlArea::Close => HTCLOSE,
+        WindowControlArea::Max => HTMAXBUTTON,
+        WindowControlArea::Min => HTMINBUTTON,
+    }
+}
+
+fn is_button(hit_area: u32) -> bool {
+    matches!(hit_area, HTCLOSE | HTMAXBUTTON | HTMINBUTTON)
+}
+
+fn is_window_control(hit_area: u32) -> bool {
+    hit_area == HTCAPTION || is_button(hit_area)
+}
@@ -1048,7 +1048,7 @@ impl Platform for MacPlatform {
                         ClipboardEntry::Image(image) => {
                             self.write_image_to_clipboard(image);
                         }
-                        ClipboardEntry::ExternalPaths(_) => {}
+                        ClipboardEntry::ExternalPaths(_) | ClipboardEntry::Html(_) => {}
                     },
                     None => {
                         // Writing an empty list of entries just clears the clipboard.
@@ -582,6 +582,9 @@ impl MacWindow {
             display_id,
             window_min_size,
             tabbing_identifier,
+            render_thread: _,
+            surface_format: _,
+            show_after_first_frame: _,
         }: WindowParams,
         executor: ForegroundExecutor,
         renderer_context: renderer::Context,
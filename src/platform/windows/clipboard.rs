@@ -156,7 +156,7 @@ fn write_to_clipboard_inner(item: ClipboardItem) -> Result<()> {
             ClipboardEntry::Image(image) => {
                 write_image_to_clipboard(image)?;
             }
-            ClipboardEntry::ExternalPaths(_) => {}
+            ClipboardEntry::ExternalPaths(_) | ClipboardEntry::Html(_) => {}
         },
         None => {
             // Writing an empty list of entries just clears the clipboard.
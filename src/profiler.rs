@@ -4,7 +4,7 @@ use std::{
     hash::{DefaultHasher, Hash},
     sync::Arc,
     thread::ThreadId,
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 use serde::{Deserialize, Serialize};
@@ -54,6 +54,38 @@ impl ThreadTaskTimings {
     }
 }
 
+/// How long tasks dispatched to the main thread waited before they started running, as reported
+/// by [`PlatformDispatcher::main_thread_dispatch_latency`](crate::PlatformDispatcher).
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct DispatchLatency {
+    /// The number of tasks measured.
+    pub count: u64,
+    /// The time all of them waited together.
+    pub total: Duration,
+    /// The longest time a single task waited.
+    pub max: Duration,
+    /// The time the most recent task waited.
+    pub last: Duration,
+}
+
+impl DispatchLatency {
+    /// The average time a task waited.
+    pub fn mean(&self) -> Duration {
+        match u32::try_from(self.count) {
+            Ok(0) => Duration::ZERO,
+            Ok(count) => self.total / count,
+            Err(_) => Duration::from_secs_f64(self.total.as_secs_f64() / self.count as f64),
+        }
+    }
+
+    pub(crate) fn record(&mut self, latency: Duration) {
+        self.count += 1;
+        self.total += latency;
+        self.max = self.max.max(latency);
+        self.last = latency;
+    }
+}
+
 /// Serializable variant of [`core::panic::Location`]
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct SerializedLocation<'a> {
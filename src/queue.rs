@@ -126,6 +126,13 @@ impl<T> Clone for PriorityQueueReceiver<T> {
 
 pub(crate) struct SendError<T>(T);
 
+impl<T> SendError<T> {
+    /// The item that couldn't be sent.
+    pub(crate) fn into_inner(self) -> T {
+        self.0
+    }
+}
+
 impl<T: fmt::Debug> fmt::Debug for SendError<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_tuple("SendError").field(&self.0).finish()
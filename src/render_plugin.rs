@@ -0,0 +1,74 @@
+use std::{any::TypeId, sync::Arc};
+
+use collections::FxHashMap;
+use parking_lot::RwLock;
+
+use crate::PluginPrimitive;
+
+/// Draws a kind of primitive the renderer doesn't know about, such as the cells of a terminal
+/// grid or the series of a chart, so downstream crates can add primitives without patching the
+/// renderer. Register it with [`crate::App::register_render_pipeline_plugin`] and paint its
+/// primitives with [`crate::Window::paint_plugin_primitive`].
+pub trait RenderPipelinePlugin: Send + Sync + 'static {
+    /// Creates the pipelines that draw the plugin's primitives into a renderer's frames. Every
+    /// renderer creates its own the first time it draws one of the primitives, and creates them
+    /// again for each format it draws in, such as the format of cached layers.
+    fn create_pipelines(&self, context: &RenderPipelineContext<'_>) -> Box<dyn PluginPipelines>;
+}
+
+/// The pipelines a [`RenderPipelinePlugin`] created for one renderer.
+pub trait PluginPipelines: Send {
+    /// Draws a batch of the plugin's primitives into `pass`. The renderer sets its own pipeline
+    /// and bind groups again for the batches that follow.
+    fn encode_batch(&mut self, batch: &PluginBatch<'_>, pass: &mut wgpu::RenderPass<'_>);
+}
+
+/// What a [`RenderPipelinePlugin`] creates its pipelines for.
+pub struct RenderPipelineContext<'a> {
+    /// The device the renderer draws with.
+    pub device: &'a wgpu::Device,
+    /// The queue the renderer submits to.
+    pub queue: &'a wgpu::Queue,
+    /// The color target of the renderer's own pipelines, with the format frames are drawn in and
+    /// the blending their alpha mode requires.
+    pub color_target: wgpu::ColorTargetState,
+    /// The stencil test the renderer's own pipelines clip primitives with. Pipelines that don't
+    /// use it aren't compatible with the renderer's passes.
+    pub depth_stencil: wgpu::DepthStencilState,
+    /// The layout of the renderer's globals, which its shaders bind at group 0: the viewport as
+    /// the `Globals` struct of its WGSL shaders at binding 0, with a dynamic offset, and its color
+    /// adjustments at binding 1.
+    pub globals_bind_group_layout: &'a wgpu::BindGroupLayout,
+}
+
+/// A run of a [`RenderPipelinePlugin`]'s primitives, consecutive in draw order and in the same
+/// clip, so they can be drawn together.
+pub struct PluginBatch<'a> {
+    /// The primitives to draw, in draw order.
+    pub primitives: &'a [PluginPrimitive],
+    /// The renderer's globals, see [`RenderPipelineContext::globals_bind_group_layout`].
+    pub globals_bind_group: &'a wgpu::BindGroup,
+    /// The dynamic offset of the frame's globals, which `globals_bind_group` is bound with.
+    pub globals_offset: u32,
+    /// The device the renderer draws with.
+    pub device: &'a wgpu::Device,
+    /// The queue the renderer submits to. Data written with it lands before the frame is drawn,
+    /// so each batch of a frame needs its own buffers or region of a buffer.
+    pub queue: &'a wgpu::Queue,
+}
+
+/// The render pipeline plugins registered with the app, which every renderer draws with.
+#[derive(Default)]
+pub(crate) struct RenderPluginRegistry {
+    plugins: RwLock<FxHashMap<TypeId, Arc<dyn RenderPipelinePlugin>>>,
+}
+
+impl RenderPluginRegistry {
+    pub(crate) fn register(&self, plugin_type: TypeId, plugin: Arc<dyn RenderPipelinePlugin>) {
+        self.plugins.write().insert(plugin_type, plugin);
+    }
+
+    pub(crate) fn get(&self, plugin_type: TypeId) -> Option<Arc<dyn RenderPipelinePlugin>> {
+        self.plugins.read().get(&plugin_type).cloned()
+    }
+}
@@ -1,19 +1,25 @@
 // todo("windows"): remove
 #![cfg_attr(windows, allow(dead_code))]
 
+use anyhow::{Context as _, Result};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    AtlasTextureId, AtlasTile, Background, Bounds, ContentMask, Corners, Edges, Hsla, Pixels,
-    Point, Radians, ScaledPixels, Size, bounds_tree::BoundsTree,
-    platform::cross::surface_registry::SurfaceId, point,
+    AtlasTextureContents, AtlasTextureId, AtlasTextureKind, AtlasTile, Background, BackgroundTag,
+    Bounds, ContentMask, Corners, CulledPrimitives, DevicePixels, Edges, GridCell, Hsla,
+    IsZero as _, Pixels, PlatformAtlas, Point, Radians, ScaledPixels, Size,
+    bounds_tree::BoundsTree, platform::cross::surface_registry::SurfaceId, point,
 };
 use std::{
+    any::{Any, TypeId},
+    borrow::Cow,
     fmt::Debug,
+    io::Cursor,
     iter::Peekable,
     ops::{Add, Range, Sub},
     slice,
+    sync::Arc,
 };
 
 #[allow(non_camel_case_types, unused)]
@@ -21,18 +27,36 @@ pub(crate) type PathVertex_ScaledPixels = PathVertex<ScaledPixels>;
 
 pub(crate) type DrawOrder = u32;
 
+/// Identifies a clip pushed onto a scene's clip stack, counting from 1 in the order the clips were
+/// pushed. Primitives outside of every clip have the id 0.
+pub(crate) type ClipId = u32;
+
+/// The most opaque quads that primitives are tested against when culling. The largest are kept,
+/// since they're the panels and modals that cover the most.
+const MAX_OCCLUDERS: usize = 16;
+
 #[derive(Default)]
 pub(crate) struct Scene {
     pub(crate) paint_operations: Vec<PaintOperation>,
     primitive_bounds: BoundsTree<ScaledPixels>,
     layer_stack: Vec<DrawOrder>,
+    clip_stack: Vec<ClipId>,
+    pub(crate) clips: Vec<Clip>,
     pub(crate) shadows: Vec<Shadow>,
     pub(crate) quads: Vec<Quad>,
     pub(crate) paths: Vec<Path<ScaledPixels>>,
     pub(crate) underlines: Vec<Underline>,
     pub(crate) monochrome_sprites: Vec<MonochromeSprite>,
     pub(crate) polychrome_sprites: Vec<PolychromeSprite>,
+    pub(crate) cell_grids: Vec<CellGrid>,
+    pub(crate) selections: Vec<Selection>,
     pub(crate) surfaces: Vec<PaintSurface>,
+    pub(crate) plugin_primitives: Vec<PluginPrimitive>,
+    // the cached layers painted into this scene, rendered into the atlas before it's drawn
+    pub(crate) layer_renders: Vec<LayerRender>,
+    // the layers whose atlas tiles are kept while this scene is shown
+    pub(crate) retained_layers: Vec<RenderLayerParams>,
+    pub(crate) culled: CulledPrimitives,
 }
 
 impl Scene {
@@ -40,13 +64,21 @@ impl Scene {
         self.paint_operations.clear();
         self.primitive_bounds.clear();
         self.layer_stack.clear();
+        self.clip_stack.clear();
+        self.clips.clear();
         self.paths.clear();
         self.shadows.clear();
         self.quads.clear();
         self.underlines.clear();
         self.monochrome_sprites.clear();
         self.polychrome_sprites.clear();
+        self.cell_grids.clear();
+        self.selections.clear();
         self.surfaces.clear();
+        self.plugin_primitives.clear();
+        self.layer_renders.clear();
+        self.retained_layers.clear();
+        self.culled = CulledPrimitives::default();
     }
 
     pub fn len(&self) -> usize {
@@ -65,6 +97,42 @@ impl Scene {
         self.paint_operations.push(PaintOperation::EndLayer);
     }
 
+    /// Clips the primitives inserted until the matching [`Scene::pop_clip`] to `clip`, within
+    /// the clips already on the stack.
+    pub fn push_clip(&mut self, mut clip: Clip) {
+        clip.parent = self.current_clip();
+        self.clips.push(clip.clone());
+        self.clip_stack.push(self.clips.len() as ClipId);
+        self.paint_operations.push(PaintOperation::PushClip(clip));
+    }
+
+    pub fn pop_clip(&mut self) {
+        self.clip_stack.pop();
+        self.paint_operations.push(PaintOperation::PopClip);
+    }
+
+    /// Keeps the atlas tiles of `layers` while this scene is shown, and while the scenes that
+    /// replay this operation are.
+    pub fn retain_layers(&mut self, layers: Vec<RenderLayerParams>) {
+        if layers.is_empty() {
+            return;
+        }
+        self.retained_layers.extend(layers.iter().cloned());
+        self.paint_operations
+            .push(PaintOperation::RetainLayers(layers));
+    }
+
+    /// Composites an opacity group, whose layer is rendered before this scene is drawn.
+    pub fn insert_opacity_group(&mut self, group: OpacityGroup) {
+        self.retain_layers(group.layers);
+        self.layer_renders.push(group.render);
+        self.insert_primitive(group.sprite);
+    }
+
+    fn current_clip(&self) -> ClipId {
+        self.clip_stack.last().copied().unwrap_or(0)
+    }
+
     pub fn insert_primitive(&mut self, primitive: impl Into<Primitive>) {
         let mut primitive = primitive.into();
         let clipped_bounds = primitive
@@ -80,36 +148,59 @@ impl Scene {
             .last()
             .copied()
             .unwrap_or_else(|| self.primitive_bounds.insert(clipped_bounds));
+        let clip = self.current_clip();
         match &mut primitive {
             Primitive::Shadow(shadow) => {
                 shadow.order = order;
+                shadow.clip = clip;
                 self.shadows.push(shadow.clone());
             }
             Primitive::Quad(quad) => {
                 quad.order = order;
+                quad.clip = clip;
                 self.quads.push(quad.clone());
             }
             Primitive::Path(path) => {
                 path.order = order;
+                path.clip = clip;
                 path.id = PathId(self.paths.len());
                 self.paths.push(path.clone());
             }
             Primitive::Underline(underline) => {
                 underline.order = order;
+                underline.clip = clip;
                 self.underlines.push(underline.clone());
             }
             Primitive::MonochromeSprite(sprite) => {
                 sprite.order = order;
+                sprite.clip = clip;
                 self.monochrome_sprites.push(sprite.clone());
             }
             Primitive::PolychromeSprite(sprite) => {
                 sprite.order = order;
+                sprite.clip = clip;
                 self.polychrome_sprites.push(sprite.clone());
             }
+            Primitive::CellGrid(grid) => {
+                grid.order = order;
+                grid.clip = clip;
+                self.cell_grids.push(grid.clone());
+            }
+            Primitive::Selection(selection) => {
+                selection.order = order;
+                selection.clip = clip;
+                self.selections.push(selection.clone());
+            }
             Primitive::Surface(surface) => {
                 surface.order = order;
+                surface.clip = clip;
                 self.surfaces.push(surface.clone());
             }
+            Primitive::Plugin(primitive) => {
+                primitive.order = order;
+                primitive.clip = clip;
+                self.plugin_primitives.push(primitive.clone());
+            }
         }
         self.paint_operations
             .push(PaintOperation::Primitive(primitive));
@@ -121,20 +212,220 @@ impl Scene {
                 PaintOperation::Primitive(primitive) => self.insert_primitive(primitive.clone()),
                 PaintOperation::StartLayer(bounds) => self.push_layer(*bounds),
                 PaintOperation::EndLayer => self.pop_layer(),
+                PaintOperation::PushClip(clip) => self.push_clip(clip.clone()),
+                PaintOperation::PopClip => self.pop_clip(),
+                PaintOperation::RetainLayers(layers) => self.retain_layers(layers.clone()),
             }
         }
     }
 
+    /// Copies the primitives of a finished scene, without the state used to build it, so it
+    /// can be drawn on another thread.
+    pub(crate) fn clone_primitives(&self) -> Scene {
+        let mut scene = Scene::default();
+        scene.clone_primitives_from(self);
+        scene
+    }
+
+    /// Replaces the primitives of this scene with copies of `source`'s, reusing the memory of the
+    /// ones it held before.
+    pub(crate) fn clone_primitives_from(&mut self, source: &Scene) {
+        self.clips.clone_from(&source.clips);
+        self.shadows.clone_from(&source.shadows);
+        self.quads.clone_from(&source.quads);
+        self.paths.clone_from(&source.paths);
+        self.underlines.clone_from(&source.underlines);
+        self.monochrome_sprites
+            .clone_from(&source.monochrome_sprites);
+        self.polychrome_sprites
+            .clone_from(&source.polychrome_sprites);
+        self.cell_grids.clone_from(&source.cell_grids);
+        self.selections.clone_from(&source.selections);
+        self.surfaces.clone_from(&source.surfaces);
+        self.plugin_primitives.clone_from(&source.plugin_primitives);
+        self.layer_renders.clone_from(&source.layer_renders);
+    }
+
+    /// Serializes the primitives of a finished scene along with the atlas textures its sprites
+    /// sample from, so the frame can be drawn again without the app that built it. Surfaces are
+    /// left out, since their contents only exist on the GPU, and so are the primitives of render
+    /// pipeline plugins, whose data only the plugins understand.
+    pub(crate) fn serialize(
+        &self,
+        viewport_size: Size<DevicePixels>,
+        atlas: &dyn PlatformAtlas,
+    ) -> Result<Vec<u8>> {
+        let mut texture_ids = Vec::new();
+        let sampled_textures = self
+            .monochrome_sprites
+            .iter()
+            .map(|sprite| sprite.tile.texture_id)
+            .chain(
+                self.polychrome_sprites
+                    .iter()
+                    .map(|sprite| sprite.tile.texture_id),
+            )
+            .chain(self.cell_grids.iter().filter_map(|grid| grid.texture_id));
+        for texture_id in sampled_textures {
+            if !texture_ids.contains(&texture_id) {
+                texture_ids.push(texture_id);
+            }
+        }
+
+        let atlas_textures = texture_ids
+            .into_iter()
+            .map(|id| encode_atlas_texture(atlas.read_texture(id)?))
+            .collect::<Result<Vec<_>>>()?;
+
+        let scene_file = SceneFile {
+            viewport_size,
+            clips: Cow::Borrowed(&self.clips),
+            shadows: Cow::Borrowed(&self.shadows),
+            quads: Cow::Borrowed(&self.quads),
+            paths: Cow::Borrowed(&self.paths),
+            underlines: Cow::Borrowed(&self.underlines),
+            monochrome_sprites: Cow::Borrowed(&self.monochrome_sprites),
+            polychrome_sprites: Cow::Borrowed(&self.polychrome_sprites),
+            cell_grids: Cow::Borrowed(&self.cell_grids),
+            selections: Cow::Borrowed(&self.selections),
+            atlas_textures,
+        };
+        Ok(serde_json::to_vec(&scene_file)?)
+    }
+
+    /// Reads a scene written by [`Scene::serialize`].
+    pub(crate) fn deserialize(bytes: &[u8]) -> Result<SavedScene> {
+        let scene_file: SceneFile = serde_json::from_slice(bytes).context("invalid scene file")?;
+        let atlas_textures = scene_file
+            .atlas_textures
+            .into_iter()
+            .map(decode_atlas_texture)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(SavedScene {
+            scene: Scene {
+                clips: scene_file.clips.into_owned(),
+                shadows: scene_file.shadows.into_owned(),
+                quads: scene_file.quads.into_owned(),
+                paths: scene_file.paths.into_owned(),
+                underlines: scene_file.underlines.into_owned(),
+                monochrome_sprites: scene_file.monochrome_sprites.into_owned(),
+                polychrome_sprites: scene_file.polychrome_sprites.into_owned(),
+                cell_grids: scene_file.cell_grids.into_owned(),
+                selections: scene_file.selections.into_owned(),
+                ..Scene::default()
+            },
+            viewport_size: scene_file.viewport_size,
+            atlas_textures,
+        })
+    }
+
     pub fn finish(&mut self) {
-        self.shadows.sort_by_key(|shadow| shadow.order);
-        self.quads.sort_by_key(|quad| quad.order);
-        self.paths.sort_by_key(|path| path.order);
-        self.underlines.sort_by_key(|underline| underline.order);
+        self.shadows
+            .sort_by_key(|shadow| (shadow.order, shadow.clip));
+        self.quads.sort_by_key(|quad| (quad.order, quad.clip));
+        self.paths.sort_by_key(|path| (path.order, path.clip));
+        self.underlines
+            .sort_by_key(|underline| (underline.order, underline.clip));
         self.monochrome_sprites
-            .sort_by_key(|sprite| (sprite.order, sprite.tile.tile_id));
+            .sort_by_key(|sprite| (sprite.order, sprite.clip, sprite.tile.tile_id));
         self.polychrome_sprites
-            .sort_by_key(|sprite| (sprite.order, sprite.tile.tile_id));
-        self.surfaces.sort_by_key(|surface| surface.order);
+            .sort_by_key(|sprite| (sprite.order, sprite.clip, sprite.tile.tile_id));
+        self.cell_grids.sort_by_key(|grid| (grid.order, grid.clip));
+        self.selections
+            .sort_by_key(|selection| (selection.order, selection.clip));
+        self.surfaces
+            .sort_by_key(|surface| (surface.order, surface.clip));
+        self.plugin_primitives
+            .sort_by_key(|primitive| (primitive.order, primitive.clip));
+    }
+
+    /// Removes the primitives that can't be seen, because they're outside of `viewport` or
+    /// entirely covered by an opaque quad drawn after them, so they're neither uploaded nor drawn.
+    /// Replaying the scene is unaffected, since it replays the paint operations.
+    pub(crate) fn cull(&mut self, viewport: Bounds<ScaledPixels>) {
+        let mut culler = Culler {
+            viewport,
+            occluders: self.occluders(viewport),
+            culled: CulledPrimitives::default(),
+        };
+        self.shadows.retain(|shadow| {
+            // The blur is drawn up to three times its radius outside of the shadow's bounds.
+            let blurred_bounds = shadow.bounds.dilate(shadow.blur_radius * 3.0);
+            culler.is_visible(shadow.order, &blurred_bounds, &shadow.content_mask)
+        });
+        self.quads
+            .retain(|quad| culler.is_visible(quad.order, &quad.bounds, &quad.content_mask));
+        self.paths
+            .retain(|path| culler.is_visible(path.order, &path.bounds, &path.content_mask));
+        self.underlines.retain(|underline| {
+            culler.is_visible(underline.order, &underline.bounds, &underline.content_mask)
+        });
+        self.monochrome_sprites
+            .retain(|sprite| culler.is_visible(sprite.order, &sprite.bounds, &sprite.content_mask));
+        self.polychrome_sprites
+            .retain(|sprite| culler.is_visible(sprite.order, &sprite.bounds, &sprite.content_mask));
+        self.cell_grids
+            .retain(|grid| culler.is_visible(grid.order, &grid.bounds, &grid.content_mask));
+        self.selections.retain(|selection| {
+            culler.is_visible(selection.order, &selection.bounds, &selection.content_mask)
+        });
+        self.surfaces.retain(|surface| {
+            culler.is_visible(surface.order, &surface.bounds, &surface.content_mask)
+        });
+        self.plugin_primitives.retain(|primitive| {
+            culler.is_visible(primitive.order, &primitive.bounds, &primitive.content_mask)
+        });
+        self.culled = culler.culled;
+    }
+
+    /// Collapses runs of quads that are stacked vertically and look the same, such as the
+    /// backgrounds of a list's rows, into single taller quads. Must be called after
+    /// [`Scene::finish`], since only quads next to each other in draw order are merged.
+    pub(crate) fn merge_adjacent_quads(&mut self) {
+        let mut merged: Vec<Quad> = Vec::with_capacity(self.quads.len());
+        for quad in self.quads.drain(..) {
+            match merged.last_mut() {
+                Some(previous) if continues_vertically(previous, &quad) => {
+                    previous.bounds.size.height =
+                        quad.bounds.origin.y + quad.bounds.size.height - previous.bounds.origin.y;
+                }
+                _ => merged.push(quad),
+            }
+        }
+        self.quads = merged;
+    }
+
+    /// The largest opaque quads in `viewport`, with the area each one is sure to cover.
+    fn occluders(&self, viewport: Bounds<ScaledPixels>) -> Vec<(DrawOrder, Bounds<ScaledPixels>)> {
+        let mut occluders = self
+            .quads
+            .iter()
+            .filter(|quad| quad.clip == 0 && quad.background.is_opaque())
+            .filter_map(|quad| {
+                // Rounded corners leave the area within the largest radius of the edges partly
+                // uncovered.
+                let radius = quad
+                    .corner_radii
+                    .top_left
+                    .max(quad.corner_radii.top_right)
+                    .max(quad.corner_radii.bottom_right)
+                    .max(quad.corner_radii.bottom_left);
+                let covered = Bounds {
+                    origin: point(quad.bounds.origin.x + radius, quad.bounds.origin.y + radius),
+                    size: Size {
+                        width: quad.bounds.size.width - radius * 2.0,
+                        height: quad.bounds.size.height - radius * 2.0,
+                    },
+                }
+                .intersect(&quad.content_mask.bounds)
+                .intersect(&viewport);
+                (!covered.is_empty()).then_some((quad.order, covered))
+            })
+            .collect::<Vec<_>>();
+        occluders.sort_by(|(_, a), (_, b)| area(b).total_cmp(&area(a)));
+        occluders.truncate(MAX_OCCLUDERS);
+        occluders
     }
 
     #[cfg_attr(
@@ -164,13 +455,151 @@ impl Scene {
             polychrome_sprites: &self.polychrome_sprites,
             polychrome_sprites_start: 0,
             polychrome_sprites_iter: self.polychrome_sprites.iter().peekable(),
+            cell_grids: &self.cell_grids,
+            cell_grids_start: 0,
+            cell_grids_iter: self.cell_grids.iter().peekable(),
+            selections: &self.selections,
+            selections_start: 0,
+            selections_iter: self.selections.iter().peekable(),
             surfaces: &self.surfaces,
             surfaces_start: 0,
             surfaces_iter: self.surfaces.iter().peekable(),
+            plugin_primitives: &self.plugin_primitives,
+            plugin_primitives_start: 0,
+            plugin_primitives_iter: self.plugin_primitives.iter().peekable(),
         }
     }
 }
 
+struct Culler {
+    viewport: Bounds<ScaledPixels>,
+    // opaque quads, with the area each one is sure to cover
+    occluders: Vec<(DrawOrder, Bounds<ScaledPixels>)>,
+    culled: CulledPrimitives,
+}
+
+impl Culler {
+    /// Whether a primitive can be seen, counting it as culled when it can't.
+    fn is_visible(
+        &mut self,
+        order: DrawOrder,
+        bounds: &Bounds<ScaledPixels>,
+        content_mask: &ContentMask<ScaledPixels>,
+    ) -> bool {
+        let visible_bounds = bounds.intersect(&content_mask.bounds);
+        if !visible_bounds.intersects(&self.viewport) {
+            self.culled.outside_viewport += 1;
+            return false;
+        }
+        let occluded = self.occluders.iter().any(|(occluder_order, covered)| {
+            *occluder_order > order && covers(covered, &visible_bounds)
+        });
+        if occluded {
+            self.culled.occluded += 1;
+        }
+        !occluded
+    }
+}
+
+/// Whether `next` starts where `previous` ends and is drawn the same way, so they can be drawn as
+/// one quad. Quads with borders, rounded corners or gradients look different when merged.
+fn continues_vertically(previous: &Quad, next: &Quad) -> bool {
+    previous.order == next.order
+        && previous.bounds.origin.x == next.bounds.origin.x
+        && previous.bounds.size.width == next.bounds.size.width
+        && previous.bounds.origin.y + previous.bounds.size.height == next.bounds.origin.y
+        && previous.background.tag == BackgroundTag::Solid
+        && previous.background == next.background
+        && previous.corner_radii.is_zero()
+        && next.corner_radii.is_zero()
+        && previous.border_widths == Edges::default()
+        && next.border_widths == Edges::default()
+        && previous.content_mask == next.content_mask
+        && previous.clip == next.clip
+        && previous.snapping == next.snapping
+}
+
+/// Whether `outer` contains all of `inner`, including its edges.
+fn covers(outer: &Bounds<ScaledPixels>, inner: &Bounds<ScaledPixels>) -> bool {
+    inner.origin.x >= outer.origin.x
+        && inner.origin.y >= outer.origin.y
+        && inner.origin.x + inner.size.width <= outer.origin.x + outer.size.width
+        && inner.origin.y + inner.size.height <= outer.origin.y + outer.size.height
+}
+
+fn area(bounds: &Bounds<ScaledPixels>) -> f32 {
+    bounds.size.width.0 * bounds.size.height.0
+}
+
+/// A scene read back from a file written by [`Scene::serialize`]. Its sprites still refer to the
+/// ids the atlas textures had when it was saved.
+pub(crate) struct SavedScene {
+    pub(crate) scene: Scene,
+    pub(crate) viewport_size: Size<DevicePixels>,
+    pub(crate) atlas_textures: Vec<AtlasTextureContents>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SceneFile<'a> {
+    viewport_size: Size<DevicePixels>,
+    #[serde(default)]
+    clips: Cow<'a, [Clip]>,
+    shadows: Cow<'a, [Shadow]>,
+    quads: Cow<'a, [Quad]>,
+    paths: Cow<'a, [Path<ScaledPixels>]>,
+    underlines: Cow<'a, [Underline]>,
+    monochrome_sprites: Cow<'a, [MonochromeSprite]>,
+    polychrome_sprites: Cow<'a, [PolychromeSprite]>,
+    #[serde(default)]
+    cell_grids: Cow<'a, [CellGrid]>,
+    #[serde(default)]
+    selections: Cow<'a, [Selection]>,
+    atlas_textures: Vec<SavedAtlasTexture>,
+}
+
+/// An atlas texture in a scene file, compressed as a PNG since atlases are mostly empty.
+#[derive(Serialize, Deserialize)]
+struct SavedAtlasTexture {
+    id: AtlasTextureId,
+    size: Size<DevicePixels>,
+    png: Vec<u8>,
+}
+
+fn encode_atlas_texture(contents: AtlasTextureContents) -> Result<SavedAtlasTexture> {
+    let width = contents.size.width.0 as u32;
+    let height = contents.size.height.0 as u32;
+    let image: image::DynamicImage = match contents.id.kind {
+        AtlasTextureKind::Monochrome => {
+            image::GrayImage::from_raw(width, height, contents.bytes).map(image::DynamicImage::from)
+        }
+        AtlasTextureKind::Polychrome => {
+            image::RgbaImage::from_raw(width, height, contents.bytes).map(image::DynamicImage::from)
+        }
+    }
+    .context("atlas texture has the wrong number of pixels")?;
+
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)?;
+    Ok(SavedAtlasTexture {
+        id: contents.id,
+        size: contents.size,
+        png,
+    })
+}
+
+fn decode_atlas_texture(texture: SavedAtlasTexture) -> Result<AtlasTextureContents> {
+    let image = image::load_from_memory_with_format(&texture.png, image::ImageFormat::Png)?;
+    let bytes = match texture.id.kind {
+        AtlasTextureKind::Monochrome => image.into_luma8().into_raw(),
+        AtlasTextureKind::Polychrome => image.into_rgba8().into_raw(),
+    };
+    Ok(AtlasTextureContents {
+        id: texture.id,
+        size: texture.size,
+        bytes,
+    })
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Default)]
 #[cfg_attr(
     all(
@@ -187,13 +616,19 @@ pub(crate) enum PrimitiveKind {
     Underline,
     MonochromeSprite,
     PolychromeSprite,
+    CellGrid,
+    Selection,
     Surface,
+    Plugin,
 }
 
 pub(crate) enum PaintOperation {
     Primitive(Primitive),
     StartLayer(Bounds<ScaledPixels>),
     EndLayer,
+    PushClip(Clip),
+    PopClip,
+    RetainLayers(Vec<RenderLayerParams>),
 }
 
 #[derive(Clone)]
@@ -204,7 +639,10 @@ pub(crate) enum Primitive {
     Underline(Underline),
     MonochromeSprite(MonochromeSprite),
     PolychromeSprite(PolychromeSprite),
+    CellGrid(CellGrid),
+    Selection(Selection),
     Surface(PaintSurface),
+    Plugin(PluginPrimitive),
 }
 
 impl Primitive {
@@ -216,7 +654,10 @@ impl Primitive {
             Primitive::Underline(underline) => &underline.bounds,
             Primitive::MonochromeSprite(sprite) => &sprite.bounds,
             Primitive::PolychromeSprite(sprite) => &sprite.bounds,
+            Primitive::CellGrid(grid) => &grid.bounds,
+            Primitive::Selection(selection) => &selection.bounds,
             Primitive::Surface(surface) => &surface.bounds,
+            Primitive::Plugin(primitive) => &primitive.bounds,
         }
     }
 
@@ -228,7 +669,10 @@ impl Primitive {
             Primitive::Underline(underline) => &underline.content_mask,
             Primitive::MonochromeSprite(sprite) => &sprite.content_mask,
             Primitive::PolychromeSprite(sprite) => &sprite.content_mask,
+            Primitive::CellGrid(grid) => &grid.content_mask,
+            Primitive::Selection(selection) => &selection.content_mask,
             Primitive::Surface(surface) => &surface.content_mask,
+            Primitive::Plugin(primitive) => &primitive.content_mask,
         }
     }
 }
@@ -259,9 +703,18 @@ struct BatchIterator<'a> {
     polychrome_sprites: &'a [PolychromeSprite],
     polychrome_sprites_start: usize,
     polychrome_sprites_iter: Peekable<slice::Iter<'a, PolychromeSprite>>,
+    cell_grids: &'a [CellGrid],
+    cell_grids_start: usize,
+    cell_grids_iter: Peekable<slice::Iter<'a, CellGrid>>,
+    selections: &'a [Selection],
+    selections_start: usize,
+    selections_iter: Peekable<slice::Iter<'a, Selection>>,
     surfaces: &'a [PaintSurface],
     surfaces_start: usize,
     surfaces_iter: Peekable<slice::Iter<'a, PaintSurface>>,
+    plugin_primitives: &'a [PluginPrimitive],
+    plugin_primitives_start: usize,
+    plugin_primitives_iter: Peekable<slice::Iter<'a, PluginPrimitive>>,
 }
 
 impl<'a> Iterator for BatchIterator<'a> {
@@ -270,36 +723,67 @@ impl<'a> Iterator for BatchIterator<'a> {
     fn next(&mut self) -> Option<Self::Item> {
         let mut orders_and_kinds = [
             (
-                self.shadows_iter.peek().map(|s| s.order),
+                self.shadows_iter.peek().map(|s| (s.order, s.clip)),
                 PrimitiveKind::Shadow,
             ),
-            (self.quads_iter.peek().map(|q| q.order), PrimitiveKind::Quad),
-            (self.paths_iter.peek().map(|q| q.order), PrimitiveKind::Path),
             (
-                self.underlines_iter.peek().map(|u| u.order),
+                self.quads_iter.peek().map(|q| (q.order, q.clip)),
+                PrimitiveKind::Quad,
+            ),
+            (
+                self.paths_iter.peek().map(|q| (q.order, q.clip)),
+                PrimitiveKind::Path,
+            ),
+            (
+                self.underlines_iter.peek().map(|u| (u.order, u.clip)),
                 PrimitiveKind::Underline,
             ),
             (
-                self.monochrome_sprites_iter.peek().map(|s| s.order),
+                self.monochrome_sprites_iter
+                    .peek()
+                    .map(|s| (s.order, s.clip)),
                 PrimitiveKind::MonochromeSprite,
             ),
             (
-                self.polychrome_sprites_iter.peek().map(|s| s.order),
+                self.polychrome_sprites_iter
+                    .peek()
+                    .map(|s| (s.order, s.clip)),
                 PrimitiveKind::PolychromeSprite,
             ),
             (
-                self.surfaces_iter.peek().map(|s| s.order),
+                self.cell_grids_iter.peek().map(|g| (g.order, g.clip)),
+                PrimitiveKind::CellGrid,
+            ),
+            (
+                self.selections_iter.peek().map(|s| (s.order, s.clip)),
+                PrimitiveKind::Selection,
+            ),
+            (
+                self.surfaces_iter.peek().map(|s| (s.order, s.clip)),
                 PrimitiveKind::Surface,
             ),
+            (
+                self.plugin_primitives_iter
+                    .peek()
+                    .map(|p| (p.order, p.clip)),
+                PrimitiveKind::Plugin,
+            ),
         ];
-        orders_and_kinds.sort_by_key(|(order, kind)| (order.unwrap_or(u32::MAX), *kind));
+        orders_and_kinds.sort_by_key(|(order_and_clip, kind)| {
+            (order_and_clip.unwrap_or((u32::MAX, ClipId::MAX)), *kind)
+        });
 
+        // Primitives in different clips are never batched together, since the renderer switches the
+        // stencil between batches.
         let first = orders_and_kinds[0];
         let second = orders_and_kinds[1];
-        let (batch_kind, max_order_and_kind) = if first.0.is_some() {
-            (first.1, (second.0.unwrap_or(u32::MAX), second.1))
-        } else {
-            return None;
+        let (batch_kind, batch_clip, max_order_and_kind) = match first.0 {
+            Some((_, clip)) => (
+                first.1,
+                clip,
+                (second.0.unwrap_or((u32::MAX, ClipId::MAX)), second.1),
+            ),
+            None => return None,
         };
 
         match batch_kind {
@@ -309,7 +793,10 @@ impl<'a> Iterator for BatchIterator<'a> {
                 self.shadows_iter.next();
                 while self
                     .shadows_iter
-                    .next_if(|shadow| (shadow.order, batch_kind) < max_order_and_kind)
+                    .next_if(|shadow| {
+                        ((shadow.order, shadow.clip), batch_kind) < max_order_and_kind
+                            && shadow.clip == batch_clip
+                    })
                     .is_some()
                 {
                     shadows_end += 1;
@@ -325,7 +812,10 @@ impl<'a> Iterator for BatchIterator<'a> {
                 self.quads_iter.next();
                 while self
                     .quads_iter
-                    .next_if(|quad| (quad.order, batch_kind) < max_order_and_kind)
+                    .next_if(|quad| {
+                        ((quad.order, quad.clip), batch_kind) < max_order_and_kind
+                            && quad.clip == batch_clip
+                    })
                     .is_some()
                 {
                     quads_end += 1;
@@ -339,7 +829,10 @@ impl<'a> Iterator for BatchIterator<'a> {
                 self.paths_iter.next();
                 while self
                     .paths_iter
-                    .next_if(|path| (path.order, batch_kind) < max_order_and_kind)
+                    .next_if(|path| {
+                        ((path.order, path.clip), batch_kind) < max_order_and_kind
+                            && path.clip == batch_clip
+                    })
                     .is_some()
                 {
                     paths_end += 1;
@@ -353,7 +846,10 @@ impl<'a> Iterator for BatchIterator<'a> {
                 self.underlines_iter.next();
                 while self
                     .underlines_iter
-                    .next_if(|underline| (underline.order, batch_kind) < max_order_and_kind)
+                    .next_if(|underline| {
+                        ((underline.order, underline.clip), batch_kind) < max_order_and_kind
+                            && underline.clip == batch_clip
+                    })
                     .is_some()
                 {
                     underlines_end += 1;
@@ -371,7 +867,8 @@ impl<'a> Iterator for BatchIterator<'a> {
                 while self
                     .monochrome_sprites_iter
                     .next_if(|sprite| {
-                        (sprite.order, batch_kind) < max_order_and_kind
+                        ((sprite.order, sprite.clip), batch_kind) < max_order_and_kind
+                            && sprite.clip == batch_clip
                             && sprite.tile.texture_id == texture_id
                     })
                     .is_some()
@@ -392,7 +889,8 @@ impl<'a> Iterator for BatchIterator<'a> {
                 while self
                     .polychrome_sprites_iter
                     .next_if(|sprite| {
-                        (sprite.order, batch_kind) < max_order_and_kind
+                        ((sprite.order, sprite.clip), batch_kind) < max_order_and_kind
+                            && sprite.clip == batch_clip
                             && sprite.tile.texture_id == texture_id
                     })
                     .is_some()
@@ -405,13 +903,54 @@ impl<'a> Iterator for BatchIterator<'a> {
                     sprites: &self.polychrome_sprites[sprites_start..sprites_end],
                 })
             }
+            PrimitiveKind::CellGrid => {
+                let grids_start = self.cell_grids_start;
+                let mut grids_end = grids_start + 1;
+                self.cell_grids_iter.next();
+                while self
+                    .cell_grids_iter
+                    .next_if(|grid| {
+                        ((grid.order, grid.clip), batch_kind) < max_order_and_kind
+                            && grid.clip == batch_clip
+                    })
+                    .is_some()
+                {
+                    grids_end += 1;
+                }
+                self.cell_grids_start = grids_end;
+                Some(PrimitiveBatch::CellGrids(
+                    &self.cell_grids[grids_start..grids_end],
+                ))
+            }
+            PrimitiveKind::Selection => {
+                let selections_start = self.selections_start;
+                let mut selections_end = selections_start + 1;
+                self.selections_iter.next();
+                while self
+                    .selections_iter
+                    .next_if(|selection| {
+                        ((selection.order, selection.clip), batch_kind) < max_order_and_kind
+                            && selection.clip == batch_clip
+                    })
+                    .is_some()
+                {
+                    selections_end += 1;
+                }
+                self.selections_start = selections_end;
+                Some(PrimitiveBatch::Selections(
+                    &self.selections[selections_start..selections_end],
+                ))
+            }
             PrimitiveKind::Surface => {
                 let surfaces_start = self.surfaces_start;
                 let mut surfaces_end = surfaces_start + 1;
                 self.surfaces_iter.next();
                 while self
                     .surfaces_iter
-                    .next_if(|surface| (surface.order, batch_kind) < max_order_and_kind)
+                    .next_if(|surface| {
+                        ((surface.order, surface.clip), batch_kind) < max_order_and_kind
+                            && surface.clip == batch_clip
+                    })
                     .is_some()
                 {
                     surfaces_end += 1;
@@ -421,6 +960,28 @@ impl<'a> Iterator for BatchIterator<'a> {
                     &self.surfaces[surfaces_start..surfaces_end],
                 ))
             }
+            PrimitiveKind::Plugin => {
+                let plugin = self.plugin_primitives_iter.peek()?.plugin;
+                let primitives_start = self.plugin_primitives_start;
+                let mut primitives_end = primitives_start + 1;
+                self.plugin_primitives_iter.next();
+                while self
+                    .plugin_primitives_iter
+                    .next_if(|primitive| {
+                        ((primitive.order, primitive.clip), batch_kind) < max_order_and_kind
+                            && primitive.clip == batch_clip
+                            && primitive.plugin == plugin
+                    })
+                    .is_some()
+                {
+                    primitives_end += 1;
+                }
+                self.plugin_primitives_start = primitives_end;
+                Some(PrimitiveBatch::Plugin {
+                    plugin,
+                    primitives: &self.plugin_primitives[primitives_start..primitives_end],
+                })
+            }
         }
     }
 }
@@ -446,10 +1007,46 @@ pub(crate) enum PrimitiveBatch<'a> {
         texture_id: AtlasTextureId,
         sprites: &'a [PolychromeSprite],
     },
+    CellGrids(&'a [CellGrid]),
+    Selections(&'a [Selection]),
     Surfaces(&'a [PaintSurface]),
+    Plugin {
+        plugin: TypeId,
+        primitives: &'a [PluginPrimitive],
+    },
+}
+
+impl PrimitiveBatch<'_> {
+    /// The clip every primitive in the batch is drawn in.
+    pub(crate) fn clip(&self) -> ClipId {
+        match self {
+            PrimitiveBatch::Shadows(shadows) => shadows.first().map_or(0, |shadow| shadow.clip),
+            PrimitiveBatch::Quads(quads) => quads.first().map_or(0, |quad| quad.clip),
+            PrimitiveBatch::Paths(paths) => paths.first().map_or(0, |path| path.clip),
+            PrimitiveBatch::Underlines(underlines) => {
+                underlines.first().map_or(0, |underline| underline.clip)
+            }
+            PrimitiveBatch::MonochromeSprites { sprites, .. } => {
+                sprites.first().map_or(0, |sprite| sprite.clip)
+            }
+            PrimitiveBatch::PolychromeSprites { sprites, .. } => {
+                sprites.first().map_or(0, |sprite| sprite.clip)
+            }
+            PrimitiveBatch::CellGrids(grids) => grids.first().map_or(0, |grid| grid.clip),
+            PrimitiveBatch::Selections(selections) => {
+                selections.first().map_or(0, |selection| selection.clip)
+            }
+            PrimitiveBatch::Surfaces(surfaces) => {
+                surfaces.first().map_or(0, |surface| surface.clip)
+            }
+            PrimitiveBatch::Plugin { primitives, .. } => {
+                primitives.first().map_or(0, |primitive| primitive.clip)
+            }
+        }
+    }
 }
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 #[repr(C)]
 pub(crate) struct Quad {
     pub order: DrawOrder,
@@ -460,6 +1057,10 @@ pub(crate) struct Quad {
     pub border_color: Hsla,
     pub corner_radii: Corners<ScaledPixels>,
     pub border_widths: Edges<ScaledPixels>,
+    #[serde(default)]
+    pub clip: ClipId,
+    #[serde(default)]
+    pub snapping: PixelSnapping,
 }
 
 impl From<Quad> for Primitive {
@@ -468,7 +1069,7 @@ impl From<Quad> for Primitive {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[repr(C)]
 pub(crate) struct Underline {
     pub order: DrawOrder,
@@ -477,7 +1078,22 @@ pub(crate) struct Underline {
     pub content_mask: ContentMask<ScaledPixels>,
     pub color: Hsla,
     pub thickness: ScaledPixels,
-    pub wavy: u32,
+    pub pattern: UnderlinePattern,
+    #[serde(default)]
+    pub clip: ClipId,
+    #[serde(default)]
+    pub snapping: PixelSnapping,
+}
+
+/// How an underline primitive's line is drawn, matching the patterns in `underlines.wgsl`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[repr(u32)]
+pub(crate) enum UnderlinePattern {
+    Solid = 0,
+    Wavy = 1,
+    Dotted = 2,
+    Dashed = 3,
+    Double = 4,
 }
 
 impl From<Underline> for Primitive {
@@ -486,7 +1102,7 @@ impl From<Underline> for Primitive {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[repr(C)]
 pub(crate) struct Shadow {
     pub order: DrawOrder,
@@ -495,6 +1111,8 @@ pub(crate) struct Shadow {
     pub corner_radii: Corners<ScaledPixels>,
     pub content_mask: ContentMask<ScaledPixels>,
     pub color: Hsla,
+    #[serde(default)]
+    pub clip: ClipId,
 }
 
 impl From<Shadow> for Primitive {
@@ -503,6 +1121,54 @@ impl From<Shadow> for Primitive {
     }
 }
 
+/// A rounded rectangle that the primitives inserted while it's on the clip stack are clipped to.
+/// Unlike a content mask, it can be rotated and scaled, so it clips transformed content exactly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Clip {
+    // the clip this one is nested in, which it's intersected with
+    pub parent: ClipId,
+    pub bounds: Bounds<ScaledPixels>,
+    pub corner_radii: Corners<ScaledPixels>,
+    pub transformation: TransformationMatrix,
+}
+
+/// Identifies a layer drawn into the sprite atlas.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum LayerId {
+    // a layer its painter keeps across frames
+    Cached(u64),
+    // an opacity group, drawn into a new tile whenever it's painted
+    OpacityGroup(u64),
+}
+
+/// Identifies the contents of a layer in the sprite atlas. A new version or size is rendered
+/// into a new tile.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) struct RenderLayerParams {
+    pub(crate) layer_id: LayerId,
+    pub(crate) version: u64,
+    pub(crate) size: Size<DevicePixels>,
+}
+
+/// A cached layer whose subtree was painted into its own scene, which the renderer draws into
+/// the layer's atlas tile before drawing the scene it was painted in.
+#[derive(Clone)]
+pub(crate) struct LayerRender {
+    pub tile: AtlasTile,
+    // the region of the window the layer covers, which is drawn into the tile
+    pub source: Bounds<ScaledPixels>,
+    pub scene: Arc<Scene>,
+}
+
+/// A subtree drawn into a layer and composited with a single opacity, so its overlapping
+/// children don't show through each other the way they do when each is faded on its own.
+pub(crate) struct OpacityGroup {
+    // the group's layer and the layers nested in it, whose tiles are kept while it's shown
+    pub layers: Vec<RenderLayerParams>,
+    pub render: LayerRender,
+    pub sprite: PolychromeSprite,
+}
+
 /// The style of a border.
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 #[repr(C)]
@@ -514,8 +1180,45 @@ pub enum BorderStyle {
     Dashed = 1,
 }
 
+/// How an image is sampled when it's drawn at another size than its pixels.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum ImageFilter {
+    /// Blends the nearest pixels, which suits photos and illustrations.
+    #[default]
+    Linear,
+    /// Takes the nearest pixel, which keeps pixel art and magnified screenshots sharp.
+    Nearest,
+}
+
+/// Which edges of a primitive are aligned to device pixels before it's drawn, so thin borders and
+/// hairlines stay crisp at fractional scale factors. Whether it's honored depends on
+/// [`RendererSettings::pixel_snapping`](crate::RendererSettings::pixel_snapping).
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub struct PixelSnapping {
+    /// Rounds the edges of the primitive's bounds to the nearest device pixel, keeping it at
+    /// least a pixel wide and tall.
+    pub bounds: bool,
+    /// Rounds the widths of the primitive's borders to whole device pixels, keeping borders at
+    /// least a pixel wide.
+    pub borders: bool,
+}
+
+impl PixelSnapping {
+    /// Leaves the primitive where it's painted.
+    pub const NONE: Self = Self {
+        bounds: false,
+        borders: false,
+    };
+
+    /// Snaps both the primitive's bounds and its borders.
+    pub const ALL: Self = Self {
+        bounds: true,
+        borders: true,
+    };
+}
+
 /// A data type representing a 2 dimensional transformation that can be applied to an element.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 #[repr(C)]
 pub struct TransformationMatrix {
     /// 2x2 matrix containing rotation and scale,
@@ -617,7 +1320,7 @@ impl Default for TransformationMatrix {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[repr(C)]
 pub(crate) struct MonochromeSprite {
     pub order: DrawOrder,
@@ -627,6 +1330,8 @@ pub(crate) struct MonochromeSprite {
     pub color: Hsla,
     pub tile: AtlasTile,
     pub transformation: TransformationMatrix,
+    #[serde(default)]
+    pub clip: ClipId,
 }
 
 impl From<MonochromeSprite> for Primitive {
@@ -635,7 +1340,7 @@ impl From<MonochromeSprite> for Primitive {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[repr(C)]
 pub(crate) struct PolychromeSprite {
     pub order: DrawOrder,
@@ -646,6 +1351,16 @@ pub(crate) struct PolychromeSprite {
     pub content_mask: ContentMask<ScaledPixels>,
     pub corner_radii: Corners<ScaledPixels>,
     pub tile: AtlasTile,
+    #[serde(default)]
+    pub clip: ClipId,
+    // whether the tile's colors are premultiplied by their alpha, as cached layers are
+    #[serde(default)]
+    pub premultiplied: bool,
+    #[serde(default)]
+    pub filter: ImageFilter,
+    // the color the sprite's colors are multiplied by, if any
+    #[serde(default)]
+    pub tint: Option<Hsla>,
 }
 
 impl From<PolychromeSprite> for Primitive {
@@ -654,6 +1369,61 @@ impl From<PolychromeSprite> for Primitive {
     }
 }
 
+/// A grid of cells drawn with a single draw call, painted with [`crate::Window::paint_cell_grid`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct CellGrid {
+    pub order: DrawOrder,
+    #[serde(default)]
+    pub clip: ClipId,
+    pub bounds: Bounds<ScaledPixels>,
+    pub content_mask: ContentMask<ScaledPixels>,
+    pub cell_size: Size<ScaledPixels>,
+    pub columns: u32,
+    pub opacity: f32,
+    // the atlas texture the glyphs are sampled from, None when none of them draws anything
+    pub texture_id: Option<AtlasTextureId>,
+    // the glyph table the cells index into
+    pub glyphs: Vec<CellGlyph>,
+    pub cells: Arc<[GridCell]>,
+}
+
+impl From<CellGrid> for Primitive {
+    fn from(grid: CellGrid) -> Self {
+        Primitive::CellGrid(grid)
+    }
+}
+
+/// A glyph of a [`CellGrid`]'s glyph table.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct CellGlyph {
+    // where the glyph is drawn relative to the top left corner of its cell, empty when the glyph
+    // doesn't draw anything
+    pub bounds: Bounds<ScaledPixels>,
+    // the glyph's tile in the grid's atlas texture
+    pub tile_bounds: Bounds<DevicePixels>,
+}
+
+/// A selection highlight over consecutive lines, painted with [`crate::Window::paint_selection`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct Selection {
+    pub order: DrawOrder,
+    #[serde(default)]
+    pub clip: ClipId,
+    pub bounds: Bounds<ScaledPixels>,
+    pub content_mask: ContentMask<ScaledPixels>,
+    pub color: Hsla,
+    pub line_height: ScaledPixels,
+    pub corner_radius: ScaledPixels,
+    // the horizontal extent of the selection on each line, from the top of its bounds down
+    pub lines: Vec<Range<ScaledPixels>>,
+}
+
+impl From<Selection> for Primitive {
+    fn from(selection: Selection) -> Self {
+        Primitive::Selection(selection)
+    }
+}
+
 /// The backing content for a painted surface.
 #[derive(Clone, Debug)]
 pub(crate) enum SurfaceContent {
@@ -670,6 +1440,7 @@ pub(crate) struct PaintSurface {
     pub bounds: Bounds<ScaledPixels>,
     pub content_mask: ContentMask<ScaledPixels>,
     pub content: SurfaceContent,
+    pub clip: ClipId,
 }
 
 impl From<PaintSurface> for Primitive {
@@ -678,14 +1449,44 @@ impl From<PaintSurface> for Primitive {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+/// A primitive drawn by a [`crate::RenderPipelinePlugin`], painted with
+/// [`crate::Window::paint_plugin_primitive`].
+#[derive(Clone, Debug)]
+pub struct PluginPrimitive {
+    pub(crate) order: DrawOrder,
+    pub(crate) clip: ClipId,
+    // the type of the plugin that draws the primitive
+    pub(crate) plugin: TypeId,
+    /// The bounds of the primitive, in device pixels.
+    pub bounds: Bounds<ScaledPixels>,
+    /// The region the primitive is clipped to, in device pixels.
+    pub content_mask: ContentMask<ScaledPixels>,
+    pub(crate) data: Arc<dyn Any + Send + Sync>,
+}
+
+impl PluginPrimitive {
+    /// The data the primitive was painted with, if it's a `T`.
+    pub fn data<T: Any>(&self) -> Option<&T> {
+        self.data.downcast_ref()
+    }
+}
+
+impl From<PluginPrimitive> for Primitive {
+    fn from(primitive: PluginPrimitive) -> Self {
+        Primitive::Plugin(primitive)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub(crate) struct PathId(pub(crate) usize);
 
 /// A line made up of a series of vertices and control points.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Path<P: Clone + Debug + Default + PartialEq> {
     pub(crate) id: PathId,
     pub(crate) order: DrawOrder,
+    #[serde(default)]
+    pub(crate) clip: ClipId,
     pub(crate) bounds: Bounds<P>,
     pub(crate) content_mask: ContentMask<P>,
     pub(crate) vertices: Vec<PathVertex<P>>,
@@ -701,6 +1502,7 @@ impl Path<Pixels> {
         Self {
             id: PathId(0),
             order: DrawOrder::default(),
+            clip: 0,
             vertices: Vec::new(),
             start,
             current: start,
@@ -719,6 +1521,7 @@ impl Path<Pixels> {
         Path {
             id: self.id,
             order: self.order,
+            clip: self.clip,
             bounds: self.bounds.scale(factor),
             content_mask: self.content_mask.scale(factor),
             vertices: self
@@ -824,7 +1627,7 @@ impl From<Path<ScaledPixels>> for Primitive {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[repr(C)]
 pub(crate) struct PathVertex<P: Clone + Debug + Default + PartialEq> {
     pub(crate) xy_position: Point<P>,
@@ -841,3 +1644,180 @@ impl PathVertex<Pixels> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{black, solid_background};
+
+    fn bounds(x: f32, y: f32, width: f32, height: f32) -> Bounds<ScaledPixels> {
+        Bounds {
+            origin: point(ScaledPixels(x), ScaledPixels(y)),
+            size: Size {
+                width: ScaledPixels(width),
+                height: ScaledPixels(height),
+            },
+        }
+    }
+
+    fn viewport() -> Bounds<ScaledPixels> {
+        bounds(0., 0., 100., 100.)
+    }
+
+    /// An opaque quad drawn at `order` within `bounds`, masked by the viewport.
+    fn quad(order: DrawOrder, bounds: Bounds<ScaledPixels>) -> Quad {
+        Quad {
+            order,
+            bounds,
+            content_mask: ContentMask { bounds: viewport() },
+            background: solid_background(black()),
+            ..Default::default()
+        }
+    }
+
+    fn culled_scene(quads: Vec<Quad>) -> Scene {
+        let mut scene = Scene {
+            quads,
+            ..Default::default()
+        };
+        scene.cull(viewport());
+        scene
+    }
+
+    fn quad_orders(scene: &Scene) -> Vec<DrawOrder> {
+        scene.quads.iter().map(|quad| quad.order).collect()
+    }
+
+    #[test]
+    fn test_cull_quad_occluded_by_later_opaque_quad() {
+        let scene = culled_scene(vec![
+            quad(1, bounds(10., 10., 20., 20.)),
+            quad(2, bounds(0., 0., 100., 100.)),
+        ]);
+        assert_eq!(quad_orders(&scene), [2]);
+        assert_eq!(scene.culled.occluded, 1);
+    }
+
+    #[test]
+    fn test_cull_keeps_quad_drawn_over_opaque_quad() {
+        let scene = culled_scene(vec![
+            quad(1, bounds(0., 0., 100., 100.)),
+            quad(2, bounds(10., 10., 20., 20.)),
+        ]);
+        assert_eq!(quad_orders(&scene), [1, 2]);
+        assert_eq!(scene.culled.total(), 0);
+    }
+
+    #[test]
+    fn test_cull_quad_outside_viewport() {
+        let mut outside = quad(1, bounds(150., 150., 20., 20.));
+        outside.content_mask = ContentMask {
+            bounds: bounds(0., 0., 200., 200.),
+        };
+        let scene = culled_scene(vec![outside, quad(2, bounds(10., 10., 20., 20.))]);
+        assert_eq!(quad_orders(&scene), [2]);
+        assert_eq!(scene.culled.outside_viewport, 1);
+    }
+
+    #[test]
+    fn test_cull_rounded_occluder_only_covers_within_its_radius() {
+        let mut rounded = quad(3, bounds(0., 0., 100., 100.));
+        rounded.corner_radii = Corners::all(ScaledPixels(10.));
+        let scene = culled_scene(vec![
+            quad(1, bounds(2., 2., 6., 6.)),
+            quad(2, bounds(20., 20., 20., 20.)),
+            rounded,
+        ]);
+        assert_eq!(quad_orders(&scene), [1, 3]);
+        assert_eq!(scene.culled.occluded, 1);
+    }
+
+    #[test]
+    fn test_cull_ignores_clipped_and_translucent_occluders() {
+        let mut clipped = quad(2, bounds(0., 0., 100., 100.));
+        clipped.clip = 1;
+        let mut translucent = quad(3, bounds(0., 0., 100., 100.));
+        translucent.background = solid_background(black().opacity(0.5));
+        let scene = culled_scene(vec![
+            quad(1, bounds(10., 10., 20., 20.)),
+            clipped,
+            translucent,
+        ]);
+        assert_eq!(quad_orders(&scene), [1, 2, 3]);
+        assert_eq!(scene.culled.total(), 0);
+    }
+
+    #[test]
+    fn test_covers_includes_edges() {
+        let outer = bounds(0., 0., 10., 10.);
+        assert!(covers(&outer, &outer));
+        assert!(covers(&outer, &bounds(2., 2., 8., 8.)));
+        assert!(!covers(&outer, &bounds(2., 2., 9., 8.)));
+        assert!(!covers(&outer, &bounds(-1., 0., 5., 5.)));
+    }
+
+    fn merged_scene(quads: Vec<Quad>) -> Scene {
+        let mut scene = Scene {
+            quads,
+            ..Default::default()
+        };
+        scene.merge_adjacent_quads();
+        scene
+    }
+
+    #[test]
+    fn test_merge_vertically_adjacent_quads() {
+        let scene = merged_scene(vec![
+            quad(1, bounds(0., 0., 50., 10.)),
+            quad(1, bounds(0., 10., 50., 10.)),
+            quad(1, bounds(0., 20., 50., 15.)),
+        ]);
+        assert_eq!(scene.quads.len(), 1);
+        assert_eq!(scene.quads[0].bounds, bounds(0., 0., 50., 35.));
+    }
+
+    #[test]
+    fn test_merge_refuses_quads_that_dont_line_up() {
+        let shifted = bounds(5., 10., 50., 10.);
+        let gapped = bounds(0., 11., 50., 10.);
+        let narrower = bounds(0., 10., 40., 10.);
+        for next_bounds in [shifted, gapped, narrower] {
+            let scene = merged_scene(vec![
+                quad(1, bounds(0., 0., 50., 10.)),
+                quad(1, next_bounds),
+            ]);
+            assert_eq!(scene.quads.len(), 2, "merged a quad at {next_bounds:?}");
+        }
+    }
+
+    #[test]
+    fn test_merge_refuses_quads_that_look_different() {
+        let variations: [(&str, fn(&mut Quad)); 5] = [
+            ("border", |quad| {
+                quad.border_widths = Edges::all(ScaledPixels(1.))
+            }),
+            ("radius", |quad| {
+                quad.corner_radii = Corners::all(ScaledPixels(2.))
+            }),
+            ("clip", |quad| quad.clip = 1),
+            ("content mask", |quad| {
+                quad.content_mask = ContentMask {
+                    bounds: bounds(0., 0., 50., 50.),
+                }
+            }),
+            ("background", |quad| {
+                quad.background = solid_background(black().opacity(0.5))
+            }),
+        ];
+        for (difference, vary) in variations {
+            let mut next = quad(1, bounds(0., 10., 50., 10.));
+            vary(&mut next);
+            let scene = merged_scene(vec![quad(1, bounds(0., 0., 50., 10.)), next]);
+            assert_eq!(
+                scene.quads.len(),
+                2,
+                "merged quads with a different {difference}"
+            );
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use util::ResultExt as _;
+
+use crate::{App, Bounds, KeyBinding, Point, Size, actions, point, size};
+
+actions!(
+    screen_zoom,
+    [
+        /// Magnifies the active window's frame by another step, around the mouse.
+        ZoomIn,
+        /// Reduces the magnification of the active window's frame by a step.
+        ZoomOut,
+        /// Shows the active window's frame unmagnified again.
+        ResetZoom,
+    ]
+);
+
+/// How much each [`ZoomIn`] or [`ZoomOut`] changes the magnification.
+const ZOOM_STEP: f32 = 1.25;
+
+/// The strongest magnification a window can be zoomed to.
+const MAX_ZOOM_SCALE: f32 = 16.0;
+
+/// A magnification of a window's presented frame, set with
+/// [`Window::set_screen_zoom`](crate::Window::set_screen_zoom).
+///
+/// The zoom is applied when the finished frame is composed, so layout is unaffected and input is
+/// mapped back to the unmagnified frame: the app keeps working as if nothing was zoomed. Shapes are
+/// drawn at the magnified resolution and stay sharp, while glyphs and images are sampled without
+/// filtering so their pixels stay crisp rather than blurring.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScreenZoom {
+    /// How much the frame is magnified, between 1 and 16.
+    pub scale: f32,
+    /// The point of the frame shown in the middle of the window, as a fraction of the window's
+    /// size, so `(0.5, 0.5)` magnifies around the window's center. Near the edges, the magnified
+    /// region is moved inwards so it always covers the window.
+    pub focus: Point<f32>,
+}
+
+impl ScreenZoom {
+    /// Magnifies the frame by `scale` around the window's center.
+    pub fn new(scale: f32) -> Self {
+        Self {
+            scale,
+            focus: point(0.5, 0.5),
+        }
+    }
+
+    /// Sets [`ScreenZoom::focus`].
+    pub fn with_focus(mut self, focus: Point<f32>) -> Self {
+        self.focus = focus;
+        self
+    }
+
+    /// The region of a frame of `frame_size` that fills the window, in the units of `frame_size`.
+    pub(crate) fn visible_region(&self, frame_size: Size<f32>) -> Bounds<f32> {
+        let scale = self.scale.clamp(1.0, MAX_ZOOM_SCALE);
+        let visible_size = size(frame_size.width / scale, frame_size.height / scale);
+        let origin = point(
+            (self.focus.x * frame_size.width - visible_size.width / 2.0)
+                .clamp(0.0, frame_size.width - visible_size.width),
+            (self.focus.y * frame_size.height - visible_size.height / 2.0)
+                .clamp(0.0, frame_size.height - visible_size.height),
+        );
+        Bounds::new(origin, visible_size)
+    }
+
+    /// Maps `position` in a window of `frame_size` to the position of the unmagnified frame shown
+    /// there.
+    pub(crate) fn window_to_frame(
+        &self,
+        position: Point<f32>,
+        frame_size: Size<f32>,
+    ) -> Point<f32> {
+        if frame_size.width <= 0.0 || frame_size.height <= 0.0 {
+            return position;
+        }
+        let region = self.visible_region(frame_size);
+        point(
+            region.origin.x + position.x * region.size.width / frame_size.width,
+            region.origin.y + position.y * region.size.height / frame_size.height,
+        )
+    }
+
+    /// Maps `position` in the unmagnified frame to where it's shown in a window of `frame_size`,
+    /// the inverse of [`ScreenZoom::window_to_frame`].
+    pub(crate) fn frame_to_window(
+        &self,
+        position: Point<f32>,
+        frame_size: Size<f32>,
+    ) -> Point<f32> {
+        if frame_size.width <= 0.0 || frame_size.height <= 0.0 {
+            return position;
+        }
+        let region = self.visible_region(frame_size);
+        point(
+            (position.x - region.origin.x) * frame_size.width / region.size.width,
+            (position.y - region.origin.y) * frame_size.height / region.size.height,
+        )
+    }
+}
+
+/// Handles [`ZoomIn`], [`ZoomOut`] and [`ResetZoom`] in the active window and binds them to
+/// `ctrl-alt-=`, `ctrl-alt--` and `ctrl-alt-0`. Call it once at startup to offer screen zoom.
+pub fn init(cx: &mut App) {
+    cx.on_action(|_: &ZoomIn, cx| zoom_active_window(cx, ZOOM_STEP));
+    cx.on_action(|_: &ZoomOut, cx| zoom_active_window(cx, 1.0 / ZOOM_STEP));
+    cx.on_action(|_: &ResetZoom, cx| {
+        if let Some(window) = cx.active_window() {
+            window
+                .update(cx, |_, window, _| window.set_screen_zoom(None))
+                .log_err();
+        }
+    });
+    cx.bind_keys([
+        KeyBinding::new("ctrl-alt-=", ZoomIn, None),
+        KeyBinding::new("ctrl-alt--", ZoomOut, None),
+        KeyBinding::new("ctrl-alt-0", ResetZoom, None),
+    ]);
+}
+
+fn zoom_active_window(cx: &mut App, factor: f32) {
+    let Some(window) = cx.active_window() else {
+        return;
+    };
+    window
+        .update(cx, |_, window, _| {
+            let scale = window.screen_zoom().map_or(1.0, |zoom| zoom.scale) * factor;
+            if scale <= 1.0 {
+                window.set_screen_zoom(None);
+                return;
+            }
+
+            let viewport_size = window.viewport_size();
+            let mouse_position = window.mouse_position();
+            let focus = if viewport_size.width.0 > 0.0 && viewport_size.height.0 > 0.0 {
+                point(
+                    mouse_position.x / viewport_size.width,
+                    mouse_position.y / viewport_size.height,
+                )
+            } else {
+                point(0.5, 0.5)
+            };
+            window.set_screen_zoom(Some(
+                ScreenZoom::new(scale.min(MAX_ZOOM_SCALE)).with_focus(focus),
+            ));
+        })
+        .log_err();
+}
@@ -786,7 +786,8 @@ impl Default for Style {
     Refineable, Copy, Clone, Default, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema,
 )]
 pub struct UnderlineStyle {
-    /// The thickness of the underline.
+    /// The thickness of the underline. Zero draws it as thick as the font suggests, so it
+    /// scales with the font's size and weight.
     pub thickness: Pixels,
 
     /// The color of the underline.
@@ -794,6 +795,23 @@ pub struct UnderlineStyle {
 
     /// Whether the underline should be wavy, like in a spell checker.
     pub wavy: bool,
+
+    /// How the line is drawn when the underline isn't wavy.
+    pub stroke: UnderlineStroke,
+}
+
+/// How the line of a straight underline is drawn.
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum UnderlineStroke {
+    /// A single solid line.
+    #[default]
+    Solid,
+    /// A line of round dots.
+    Dotted,
+    /// A line of dashes.
+    Dashed,
+    /// Two solid lines.
+    Double,
 }
 
 /// The properties that can be applied to a strikethrough.
@@ -801,7 +819,8 @@ pub struct UnderlineStyle {
     Refineable, Copy, Clone, Default, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema,
 )]
 pub struct StrikethroughStyle {
-    /// The thickness of the strikethrough.
+    /// The thickness of the strikethrough. Zero draws it as thick as the font suggests, so it
+    /// scales with the font's size and weight.
     pub thickness: Pixels,
 
     /// The color of the strikethrough.
@@ -1321,6 +1340,7 @@ mod tests {
                 thickness: px(2.),
                 color: Some(red()),
                 wavy: true,
+                stroke: UnderlineStroke::Solid,
             }),
         };
         let expected_style = style_b;
@@ -1353,6 +1373,7 @@ mod tests {
                 thickness: px(4.),
                 color: None,
                 wavy: false,
+                stroke: UnderlineStroke::Solid,
             }),
         };
 
@@ -1371,6 +1392,7 @@ mod tests {
                 thickness: px(4.),
                 color: None,
                 wavy: false,
+                stroke: UnderlineStroke::Solid,
             }),
         };
 
@@ -2,8 +2,8 @@ use crate::{
     self as gpui, AbsoluteLength, AlignContent, AlignItems, BorderStyle, CursorStyle,
     DefiniteLength, Display, Fill, FlexDirection, FlexWrap, Font, FontFeatures, FontStyle,
     FontWeight, GridPlacement, Hsla, JustifyContent, Length, SharedString, StrikethroughStyle,
-    StyleRefinement, TextAlign, TextOverflow, TextStyleRefinement, UnderlineStyle, WhiteSpace, px,
-    relative, rems,
+    StyleRefinement, TextAlign, TextOverflow, TextStyleRefinement, UnderlineStroke, UnderlineStyle,
+    WhiteSpace, px, relative, rems,
 };
 pub use gpui_macros::{
     border_style_methods, box_shadow_style_methods, cursor_style_methods, margin_style_methods,
@@ -524,10 +524,7 @@ pub trait Styled: Sized {
     /// [Docs](https://tailwindcss.com/docs/text-decoration-line#underling-text)
     fn underline(mut self) -> Self {
         let style = self.text_style().get_or_insert_with(Default::default);
-        style.underline = Some(UnderlineStyle {
-            thickness: px(1.),
-            ..Default::default()
-        });
+        style.underline = Some(UnderlineStyle::default());
         self
     }
 
@@ -535,10 +532,7 @@ pub trait Styled: Sized {
     /// [Docs](https://tailwindcss.com/docs/text-decoration-line#adding-a-line-through-text)
     fn line_through(mut self) -> Self {
         let style = self.text_style().get_or_insert_with(Default::default);
-        style.strikethrough = Some(StrikethroughStyle {
-            thickness: px(1.),
-            ..Default::default()
-        });
+        style.strikethrough = Some(StrikethroughStyle::default());
         self
     }
 
@@ -566,6 +560,37 @@ pub trait Styled: Sized {
         let style = self.text_style().get_or_insert_with(Default::default);
         let underline = style.underline.get_or_insert_with(Default::default);
         underline.wavy = false;
+        underline.stroke = UnderlineStroke::Solid;
+        self
+    }
+
+    /// Sets the text decoration style to a double line.
+    /// [Docs](https://tailwindcss.com/docs/text-decoration-style)
+    fn text_decoration_double(mut self) -> Self {
+        let style = self.text_style().get_or_insert_with(Default::default);
+        let underline = style.underline.get_or_insert_with(Default::default);
+        underline.wavy = false;
+        underline.stroke = UnderlineStroke::Double;
+        self
+    }
+
+    /// Sets the text decoration style to a dotted line.
+    /// [Docs](https://tailwindcss.com/docs/text-decoration-style)
+    fn text_decoration_dotted(mut self) -> Self {
+        let style = self.text_style().get_or_insert_with(Default::default);
+        let underline = style.underline.get_or_insert_with(Default::default);
+        underline.wavy = false;
+        underline.stroke = UnderlineStroke::Dotted;
+        self
+    }
+
+    /// Sets the text decoration style to a dashed line.
+    /// [Docs](https://tailwindcss.com/docs/text-decoration-style)
+    fn text_decoration_dashed(mut self) -> Self {
+        let style = self.text_style().get_or_insert_with(Default::default);
+        let underline = style.underline.get_or_insert_with(Default::default);
+        underline.wavy = false;
+        underline.stroke = UnderlineStroke::Dashed;
         self
     }
 
@@ -578,7 +603,7 @@ pub trait Styled: Sized {
         self
     }
 
-    /// Sets the text decoration to be 0px thick.
+    /// Sets the text decoration's thickness to zero, which draws it as thick as the font suggests.
     /// [Docs](https://tailwindcss.com/docs/text-decoration-thickness)
     fn text_decoration_0(mut self) -> Self {
         let style = self.text_style().get_or_insert_with(Default::default);
@@ -210,6 +210,7 @@ fn paint_line(
         let mut wraps = wrap_boundaries.iter().peekable();
         let mut run_end = 0;
         let mut color = black();
+        let mut decoration_run: Option<&DecorationRun> = None;
         let mut current_underline: Option<(Point<Pixels>, UnderlineStyle)> = None;
         let mut current_strikethrough: Option<(Point<Pixels>, StrikethroughStyle)> = None;
         let text_system = cx.text_system().clone();
@@ -229,6 +230,8 @@ fn paint_line(
         let mut first_glyph_x = origin.x;
         for (run_ix, run) in layout.runs.iter().enumerate() {
             max_glyph_size = text_system.bounding_box(run.font_id, layout.font_size).size;
+            let font_line_thickness =
+                text_system.underline_thickness(run.font_id, layout.font_size);
 
             for (glyph_ix, glyph) in run.glyphs.iter().enumerate() {
                 glyph_origin.x += glyph.position.x - prev_glyph_position.x;
@@ -279,7 +282,11 @@ fn paint_line(
 
                 let mut finished_underline: Option<(Point<Pixels>, UnderlineStyle)> = None;
                 let mut finished_strikethrough: Option<(Point<Pixels>, StrikethroughStyle)> = None;
+                // Lines without a thickness of their own are as thick as the font of each run
+                // suggests, so they're also restarted where the font changes.
+                let mut decorations_changed = glyph_ix == 0;
                 if glyph.index >= run_end {
+                    decorations_changed = true;
                     let mut style_run = decoration_runs.next();
 
                     // ignore style runs that apply to a partial glyph
@@ -291,51 +298,67 @@ fn paint_line(
                         style_run = decoration_runs.next();
                     }
 
+                    decoration_run = style_run;
                     if let Some(style_run) = style_run {
-                        if let Some((_, underline_style)) = &mut current_underline
-                            && style_run.underline.as_ref() != Some(underline_style)
-                        {
-                            finished_underline = current_underline.take();
-                        }
-                        if let Some(run_underline) = style_run.underline.as_ref() {
-                            current_underline.get_or_insert((
-                                point(
-                                    glyph_origin.x,
-                                    glyph_origin.y + baseline_offset.y + (layout.descent * 0.618),
-                                ),
-                                UnderlineStyle {
-                                    color: Some(run_underline.color.unwrap_or(style_run.color)),
-                                    thickness: run_underline.thickness,
-                                    wavy: run_underline.wavy,
-                                },
-                            ));
-                        }
-                        if let Some((_, strikethrough_style)) = &mut current_strikethrough
-                            && style_run.strikethrough.as_ref() != Some(strikethrough_style)
-                        {
-                            finished_strikethrough = current_strikethrough.take();
-                        }
-                        if let Some(run_strikethrough) = style_run.strikethrough.as_ref() {
-                            current_strikethrough.get_or_insert((
-                                point(
-                                    glyph_origin.x,
-                                    glyph_origin.y
-                                        + (((layout.ascent * 0.5) + baseline_offset.y) * 0.5),
-                                ),
-                                StrikethroughStyle {
-                                    color: Some(run_strikethrough.color.unwrap_or(style_run.color)),
-                                    thickness: run_strikethrough.thickness,
-                                },
-                            ));
-                        }
-
                         run_end += style_run.len as usize;
                         color = style_run.color;
                     } else {
                         run_end = layout.len;
+                    }
+                }
+
+                if decorations_changed {
+                    let underline = decoration_run.and_then(|decoration_run| {
+                        let underline = decoration_run.underline.as_ref()?;
+                        Some(UnderlineStyle {
+                            color: Some(underline.color.unwrap_or(decoration_run.color)),
+                            thickness: line_thickness(underline.thickness, font_line_thickness),
+                            wavy: underline.wavy,
+                            stroke: underline.stroke,
+                        })
+                    });
+                    if current_underline.as_ref().map(|(_, style)| style) != underline.as_ref() {
                         finished_underline = current_underline.take();
+                    }
+                    if let Some(underline) = underline {
+                        current_underline.get_or_insert((
+                            point(
+                                glyph_origin.x,
+                                glyph_origin.y + baseline_offset.y + (layout.descent * 0.618),
+                            ),
+                            underline,
+                        ));
+                    }
+
+                    let strikethrough = decoration_run.and_then(|decoration_run| {
+                        let strikethrough = decoration_run.strikethrough.as_ref()?;
+                        Some(StrikethroughStyle {
+                            color: Some(strikethrough.color.unwrap_or(decoration_run.color)),
+                            thickness: line_thickness(strikethrough.thickness, font_line_thickness),
+                        })
+                    });
+                    if current_strikethrough.as_ref().map(|(_, style)| style)
+                        != strikethrough.as_ref()
+                    {
                         finished_strikethrough = current_strikethrough.take();
                     }
+                    if let Some(strikethrough) = strikethrough {
+                        // The line is centered halfway up the font's lowercase letters, or halfway
+                        // up its ascent when the font doesn't report an x-height.
+                        let mut x_height = text_system.x_height(run.font_id, layout.font_size);
+                        if x_height <= px(0.) {
+                            x_height = layout.ascent * 0.5;
+                        }
+                        current_strikethrough.get_or_insert((
+                            point(
+                                glyph_origin.x,
+                                glyph_origin.y + baseline_offset.y
+                                    - x_height.half()
+                                    - strikethrough.thickness.half(),
+                            ),
+                            strikethrough,
+                        ));
+                    }
                 }
 
                 if let Some((mut underline_origin, underline_style)) = finished_underline {
@@ -423,6 +446,18 @@ fn paint_line(
     })
 }
 
+/// The thickness of a decoration line that asked for `thickness`, which is the one the font
+/// suggests, `font_thickness`, when it's zero.
+fn line_thickness(thickness: Pixels, font_thickness: Pixels) -> Pixels {
+    if thickness > px(0.) {
+        thickness
+    } else if font_thickness > px(0.) {
+        font_thickness
+    } else {
+        px(1.)
+    }
+}
+
 fn paint_line_background(
     origin: Point<Pixels>,
     layout: &LineLayout,
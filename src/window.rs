@@ -3,20 +3,24 @@ use crate::Inspector;
 use crate::{
     Action, AnyDrag, AnyElement, AnyImageCache, AnyTooltip, AnyView, App, AppContext, Arena, Asset,
     AsyncWindowContext, AvailableSpace, Background, BorderStyle, Bounds, BoxShadow, Capslock,
-    Context, Corners, CursorStyle, Decorations, DevicePixels, DispatchActionListener,
-    DispatchNodeId, DispatchTree, DisplayId, Edges, Effect, Entity, EntityId, EventEmitter,
-    FileDropEvent, FontId, Global, GlobalElementId, GlyphId, GpuSpecs, Hsla, InputHandler, IsZero,
-    KeyBinding, KeyContext, KeyDownEvent, KeyEvent, Keystroke, KeystrokeEvent, LayoutId,
-    LineLayoutIndex, Modifiers, ModifiersChangedEvent, MonochromeSprite, MouseButton, MouseEvent,
-    MouseMoveEvent, MouseUpEvent, Path, Pixels, PlatformAtlas, PlatformDisplay, PlatformInput,
-    PlatformInputHandler, PlatformWindow, Point, PolychromeSprite, Priority, PromptButton,
-    PromptLevel, Quad, Render, RenderGlyphParams, RenderImage, RenderImageParams, RenderSvgParams,
-    Replay, ResizeEdge, SMOOTH_SVG_SCALE_FACTOR, SUBPIXEL_VARIANTS_X, SUBPIXEL_VARIANTS_Y,
-    ScaledPixels, Scene, Shadow, SharedString, Size, StrikethroughStyle, Style, SubscriberSet,
-    Subscription, SystemWindowTab, SystemWindowTabController, TabStopMap, TaffyLayoutEngine, Task,
-    TextStyle, TextStyleRefinement, TransformationMatrix, Underline, UnderlineStyle,
-    WindowAppearance, WindowBackgroundAppearance, WindowBounds, WindowControls, WindowDecorations,
-    WindowOptions, WindowParams, WindowTextSystem, point, prelude::*, px, rems, size,
+    CellGlyph, CellGrid, Clip, ColorFilter, Context, Corners, CursorStyle, Decorations,
+    DevicePixels, DispatchActionListener, DispatchNodeId, DispatchTree, DisplayId, Edges, Effect,
+    Entity, EntityId, EventEmitter, FileDropEvent, FontId, FrameDrawCalls, FrameRecording, Global,
+    GlobalElementId, GlyphId, GpuSpecs, Hsla, ImageFilter, InputHandler, IsZero, KeyBinding,
+    KeyContext, KeyDownEvent, KeyEvent, Keystroke, KeystrokeEvent, LayerId, LayerRender, LayoutId,
+    LineLayout, LineLayoutIndex, Modifiers, ModifiersChangedEvent, MonochromeSprite, MouseButton,
+    MouseEvent, MouseMoveEvent, MouseUpEvent, OpacityGroup, Path, PixelSnapping, Pixels,
+    PlatformAtlas, PlatformDisplay, PlatformInput, PlatformInputHandler, PlatformWindow,
+    PluginPrimitive, Point, PolychromeSprite, PresentStats, Priority, PromptButton, PromptLevel,
+    Quad, Render, RenderGlyphParams, RenderImage, RenderImageParams, RenderLayerParams,
+    RenderPipelinePlugin, RenderSvgParams, RendererError, Replay, ResizeEdge,
+    SMOOTH_SVG_SCALE_FACTOR, SUBPIXEL_VARIANTS_X, SUBPIXEL_VARIANTS_Y, ScaledPixels, Scene,
+    SceneViewport, ScreenZoom, Selection, Shadow, SharedString, Size, StrikethroughStyle, Style,
+    SubscriberSet, Subscription, SystemWindowTab, SystemWindowTabController, TabStopMap,
+    TaffyLayoutEngine, Task, TextStyle, TextStyleRefinement, TransformationMatrix, Underline,
+    UnderlinePattern, UnderlineStroke, UnderlineStyle, WindowAppearance,
+    WindowBackgroundAppearance, WindowBounds, WindowControls, WindowDecorations, WindowOptions,
+    WindowParams, WindowTextSystem, XrSwapchainTarget, point, prelude::*, px, rems, size,
     transparent_black,
 };
 use anyhow::{Context as _, Result, anyhow};
@@ -29,8 +33,11 @@ use futures::channel::oneshot;
 use itertools::FoldWhile::{Continue, Done};
 use itertools::Itertools;
 use parking_lot::RwLock;
-use raw_window_handle::{HandleError, HasDisplayHandle, HasWindowHandle};
+use raw_window_handle::{
+    HandleError, HasDisplayHandle, HasWindowHandle, RawDisplayHandle, RawWindowHandle,
+};
 use refineable::Refineable;
+use serde::{Deserialize, Serialize};
 use slotmap::SlotMap;
 use smallvec::SmallVec;
 use std::{
@@ -837,8 +844,13 @@ pub struct Window {
     pub(crate) platform_window: Box<dyn PlatformWindow>,
     display_id: Option<DisplayId>,
     sprite_atlas: Arc<dyn PlatformAtlas>,
+    // the atlas key of each cached layer's contents, so stale contents are removed from the atlas
+    cached_layers: FxHashMap<u64, RenderLayerParams>,
+    next_opacity_group_id: u64,
     text_system: Arc<WindowTextSystem>,
     rem_size: Pixels,
+    // the user's preferred text size, which the rem size is multiplied by
+    pub(crate) text_scale_factor: f32,
     /// The stack of override values for the window's rem size.
     ///
     /// This is used by `with_rem_size` to allow rendering an element tree with
@@ -887,6 +899,10 @@ pub struct Window {
     pub(crate) pending_input_observers: SubscriberSet<(), AnyObserver>,
     prompt: Option<RenderablePromptHandle>,
     pub(crate) client_inset: Option<Pixels>,
+    window_shadow: Option<(BoxShadow, Pixels)>,
+    atlas_viewer_visible: bool,
+    draw_call_viewer_visible: bool,
+    surface_viewer_visible: bool,
     #[cfg(any(feature = "inspector", debug_assertions))]
     inspector: Option<Entity<Inspector>>,
 }
@@ -919,6 +935,11 @@ pub(crate) struct ElementStateBox {
     pub(crate) type_name: &'static str,
 }
 
+/// The bytes of a fully transparent tile of the given size, which layers are drawn over.
+fn transparent_pixels(size: Size<DevicePixels>) -> Cow<'static, [u8]> {
+    Cow::Owned(vec![0; size.width.0 as usize * size.height.0 as usize * 4])
+}
+
 fn default_bounds(display_id: Option<DisplayId>, cx: &mut App) -> WindowBounds {
     // TODO, BUG: if you open a window with the currently active window
     // on the stack, this will erroneously fallback to `None`
@@ -1006,6 +1027,9 @@ impl Window {
             window_decorations,
             #[cfg_attr(not(target_os = "macos"), allow(unused_variables))]
             tabbing_identifier,
+            render_thread,
+            surface_format,
+            show_after_first_frame,
         } = options;
 
         let window_bounds = window_bounds.unwrap_or_else(|| default_bounds(display_id, cx));
@@ -1024,6 +1048,9 @@ impl Window {
                 window_min_size,
                 #[cfg(target_os = "macos")]
                 tabbing_identifier,
+                render_thread,
+                surface_format,
+                show_after_first_frame,
             },
         )?;
 
@@ -1099,6 +1126,9 @@ impl Window {
                     measure("frame duration", || {
                         handle
                             .update(&mut cx, |_, window, cx| {
+                                if request_frame_options.force_render {
+                                    window.refresh();
+                                }
                                 let arena_clear_needed = window.draw(cx);
                                 window.present();
                                 // drop the arena elements after present to reduce latency
@@ -1265,8 +1295,11 @@ impl Window {
             platform_window,
             display_id,
             sprite_atlas,
+            cached_layers: FxHashMap::default(),
+            next_opacity_group_id: 0,
             text_system,
             rem_size: px(16.),
+            text_scale_factor: cx.platform.text_scale_factor(),
             rem_size_override_stack: SmallVec::new(),
             viewport_size: content_size,
             layout_engine: Some(TaffyLayoutEngine::new()),
@@ -1310,6 +1343,10 @@ impl Window {
             pending_input_observers: SubscriberSet::new(),
             prompt: None,
             client_inset: None,
+            window_shadow: None,
+            atlas_viewer_visible: false,
+            draw_call_viewer_visible: false,
+            surface_viewer_visible: false,
             image_cache_stack: Vec::new(),
             #[cfg(any(feature = "inspector", debug_assertions))]
             inspector: None,
@@ -1333,7 +1370,7 @@ pub(crate) struct DispatchEventResult {
 /// Indicates which region of the window is visible. Content falling outside of this mask will not be
 /// rendered. Currently, only rectangular content masks are supported, but we give the mask its own type
 /// to leave room to support more complex shapes in the future.
-#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(C)]
 pub struct ContentMask<P: Clone + Debug + Default + PartialEq> {
     /// The bounds
@@ -1841,6 +1878,14 @@ impl Window {
         self.client_inset
     }
 
+    /// Draws a drop shadow in the client inset around the content of a window with client side
+    /// decorations, which loses the shadow the compositor would otherwise draw. `corner_radius`
+    /// should match the rounding of the content. Tiled edges get no shadow.
+    pub fn set_window_shadow(&mut self, shadow: Option<BoxShadow>, corner_radius: Pixels) {
+        self.window_shadow = shadow.map(|shadow| (shadow, corner_radius));
+        self.refresh();
+    }
+
     /// Returns whether the title bar window controls need to be rendered by the application (Wayland and X11)
     pub fn window_decorations(&self) -> Decorations {
         self.platform_window.window_decorations()
@@ -1867,6 +1912,51 @@ impl Window {
             .set_background_appearance(background_appearance);
     }
 
+    /// Sets the color the window is cleared to before each frame is drawn, which shows wherever
+    /// the rendered elements don't cover the window, typically the app's theme background. Pass
+    /// `None` to go back to the default: transparent for windows with a transparent background
+    /// appearance and black otherwise.
+    pub fn set_clear_color(&self, color: Option<Hsla>) {
+        self.platform_window.set_clear_color(color);
+    }
+
+    /// Magnifies the frames this window presents, or shows them unmagnified again when `None`.
+    /// Layout is unaffected, and mouse input is mapped back to the unmagnified frame. See
+    /// [`screen_zoom::init`](crate::screen_zoom::init) for keyboard shortcuts that control it.
+    pub fn set_screen_zoom(&self, zoom: Option<ScreenZoom>) {
+        self.platform_window.set_screen_zoom(zoom);
+    }
+
+    /// Returns how this window's frames are magnified, if they are.
+    pub fn screen_zoom(&self) -> Option<ScreenZoom> {
+        self.platform_window.screen_zoom()
+    }
+
+    /// Transforms the colors of the frames this window presents, for color blindness simulation,
+    /// daltonization or a grayscale mode, or presents them unfiltered again when `None`.
+    pub fn set_color_filter(&self, color_filter: Option<ColorFilter>) {
+        self.platform_window.set_color_filter(color_filter);
+    }
+
+    /// Returns the color filter applied to this window's frames, if any.
+    pub fn color_filter(&self) -> Option<ColorFilter> {
+        self.platform_window.color_filter()
+    }
+
+    /// Lets mouse input fall through the whole window to the applications underneath it, as
+    /// overlay and HUD windows do. This replaces any regions set with
+    /// [`Window::set_input_passthrough_regions`].
+    pub fn set_input_passthrough(&self, passthrough: bool) {
+        self.platform_window.set_input_passthrough(passthrough);
+    }
+
+    /// Lets mouse input fall through the given regions of the window, in window coordinates,
+    /// while the rest of the window stays interactive. An empty list makes the whole window
+    /// interactive again.
+    pub fn set_input_passthrough_regions(&self, regions: Vec<Bounds<Pixels>>) {
+        self.platform_window.set_input_passthrough_regions(regions);
+    }
+
     /// Mark the window as dirty at the platform level.
     pub fn set_window_edited(&mut self, edited: bool) {
         self.platform_window.set_edited(edited);
@@ -1893,16 +1983,19 @@ impl Window {
     }
 
     /// The size of an em for the base font of the application. Adjusting this value allows the
-    /// UI to scale, just like zooming a web page.
+    /// UI to scale, just like zooming a web page. Unless it's overridden with
+    /// [`Window::with_rem_size`], it's multiplied by the system's text scale factor, so UIs honor
+    /// the user's text size preference.
     pub fn rem_size(&self) -> Pixels {
         self.rem_size_override_stack
             .last()
             .copied()
-            .unwrap_or(self.rem_size)
+            .unwrap_or(self.rem_size * self.text_scale_factor)
     }
 
-    /// Sets the size of an em for the base font of the application. Adjusting this value allows the
-    /// UI to scale, just like zooming a web page.
+    /// Sets the size of an em for the base font of the application, before it's multiplied by
+    /// the system's text scale factor. Adjusting this value allows the UI to scale, just like
+    /// zooming a web page.
     pub fn set_rem_size(&mut self, rem_size: impl Into<Pixels>) {
         self.rem_size = rem_size.into();
     }
@@ -1974,6 +2067,15 @@ impl Window {
         self.mouse_position
     }
 
+    /// Moves the mouse cursor to `position` in the window, such as to keep it in place while a
+    /// color picker or camera control is dragged. Where the platform doesn't let apps move the
+    /// cursor, such as on Wayland, a cursor is drawn at `position` in place of the real one and
+    /// follows the mouse from there until it leaves the window.
+    pub fn set_mouse_position(&mut self, position: Point<Pixels>) {
+        self.platform_window.set_mouse_position(position);
+        self.mouse_position = position;
+    }
+
     /// The current state of the keyboard's modifiers
     pub fn modifiers(&self) -> Modifiers {
         self.modifiers
@@ -2023,11 +2125,15 @@ impl Window {
         self.layout_engine.as_mut().unwrap().clear();
         self.text_system().finish_frame();
         self.next_frame.finish(&mut self.rendered_frame);
+        let viewport = Bounds::new(Point::default(), self.viewport_size).scale(self.scale_factor());
+        self.next_frame.scene.cull(viewport);
+        self.next_frame.scene.merge_adjacent_quads();
 
         self.invalidator.set_phase(DrawPhase::Focus);
         let previous_focus_path = self.rendered_frame.focus_path();
         let previous_window_active = self.rendered_frame.window_active;
         mem::swap(&mut self.rendered_frame, &mut self.next_frame);
+        self.remove_unretained_layers();
         self.next_frame.clear();
         let current_focus_path = self.rendered_frame.focus_path();
         let current_window_active = self.rendered_frame.window_active;
@@ -2041,6 +2147,8 @@ impl Window {
                     .retain(&(), |listener| listener(self, cx));
             }
 
+            cx.platform.reset_cursor_blink();
+
             let event = WindowFocusEvent {
                 previous_focus_path: if previous_window_active {
                     previous_focus_path
@@ -2068,6 +2176,16 @@ impl Window {
         ArenaClearNeeded
     }
 
+    /// Removes the tiles of the layers the previous frame retained and the new one doesn't from
+    /// the sprite atlas.
+    fn remove_unretained_layers(&mut self) {
+        for layer in &self.next_frame.scene.retained_layers {
+            if !self.rendered_frame.scene.retained_layers.contains(layer) {
+                self.sprite_atlas.remove(&layer.clone().into());
+            }
+        }
+    }
+
     fn record_entities_accessed(&mut self, cx: &mut App) {
         let mut entities_ref = cx.entities.accessed_entities.borrow_mut();
         let mut entities = mem::take(entities_ref.deref_mut());
@@ -2150,10 +2268,27 @@ impl Window {
             tooltip_element = self.prepaint_tooltip(cx);
         }
 
+        let atlas_viewer_element = self.atlas_viewer_visible.then(|| {
+            let mut element = crate::atlas_viewer().into_any_element();
+            element.prepaint_as_root(Point::default(), root_size.into(), self, cx);
+            element
+        });
+        let draw_call_viewer_element = self.draw_call_viewer_visible.then(|| {
+            let mut element = crate::draw_call_viewer().into_any_element();
+            element.prepaint_as_root(Point::default(), root_size.into(), self, cx);
+            element
+        });
+        let surface_viewer_element = self.surface_viewer_visible.then(|| {
+            let mut element = crate::surface_viewer().into_any_element();
+            element.prepaint_as_root(Point::default(), root_size.into(), self, cx);
+            element
+        });
+
         self.mouse_hit_test = self.next_frame.hit_test(self.mouse_position);
 
         // Now actually paint the elements.
         self.invalidator.set_phase(DrawPhase::Paint);
+        self.paint_window_shadow();
         root_element.paint(self, cx);
 
         #[cfg(any(feature = "inspector", debug_assertions))]
@@ -2169,10 +2304,53 @@ impl Window {
             tooltip_element.paint(self, cx);
         }
 
+        if let Some(mut atlas_viewer_element) = atlas_viewer_element {
+            atlas_viewer_element.paint(self, cx);
+        }
+        if let Some(mut draw_call_viewer_element) = draw_call_viewer_element {
+            draw_call_viewer_element.paint(self, cx);
+        }
+        if let Some(mut surface_viewer_element) = surface_viewer_element {
+            surface_viewer_element.paint(self, cx);
+        }
+
         #[cfg(any(feature = "inspector", debug_assertions))]
         self.paint_inspector_hitbox(cx);
     }
 
+    fn paint_window_shadow(&mut self) {
+        let Some((shadow, corner_radius)) = self.window_shadow.clone() else {
+            return;
+        };
+        let Some(inset) = self.client_inset else {
+            return;
+        };
+        let Decorations::Client { tiling } = self.window_decorations() else {
+            return;
+        };
+
+        let mut bounds = Bounds {
+            origin: Point::default(),
+            size: self.viewport_size,
+        };
+        if !tiling.top {
+            bounds.origin.y += inset;
+            bounds.size.height -= inset;
+        }
+        if !tiling.bottom {
+            bounds.size.height -= inset;
+        }
+        if !tiling.left {
+            bounds.origin.x += inset;
+            bounds.size.width -= inset;
+        }
+        if !tiling.right {
+            bounds.size.width -= inset;
+        }
+
+        self.paint_shadows(bounds, Corners::all(corner_radius), &[shadow]);
+    }
+
     fn prepaint_tooltip(&mut self, cx: &mut App) -> Option<AnyElement> {
         // Use indexing instead of iteration to avoid borrowing self for the duration of the loop.
         for tooltip_request_index in (0..self.next_frame.tooltip_requests.len()).rev() {
@@ -2892,6 +3070,32 @@ impl Window {
         result
     }
 
+    /// Clips everything painted by `f` to `bounds`, with the given corner radii, after the clip is
+    /// transformed by `transformation`. Unlike a content mask, the clip follows rotated and scaled
+    /// content, and nested clips are intersected exactly by the renderer's stencil buffer.
+    ///
+    /// This method should only be called as part of the paint phase of element drawing.
+    pub fn with_clip<R>(
+        &mut self,
+        bounds: Bounds<Pixels>,
+        corner_radii: Corners<Pixels>,
+        transformation: TransformationMatrix,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        self.invalidator.debug_assert_paint();
+
+        let scale_factor = self.scale_factor();
+        self.next_frame.scene.push_clip(Clip {
+            parent: 0,
+            bounds: bounds.scale(scale_factor),
+            corner_radii: corner_radii.scale(scale_factor),
+            transformation,
+        });
+        let result = f(self);
+        self.next_frame.scene.pop_clip();
+        result
+    }
+
     /// Paint one or more drop shadows into the scene for the next frame at the current z-index.
     ///
     /// This method should only be called as part of the paint phase of element drawing.
@@ -2908,13 +3112,23 @@ impl Window {
         let opacity = self.element_opacity();
         for shadow in shadows {
             let shadow_bounds = (bounds + shadow.offset).dilate(shadow.spread_radius);
+            // Rounded corners grow and shrink with the spread, like CSS box shadows, while square
+            // corners stay square.
+            let shadow_corner_radii = corner_radii.map(|radius| {
+                if radius.is_zero() {
+                    *radius
+                } else {
+                    (*radius + shadow.spread_radius).max(Pixels::ZERO)
+                }
+            });
             self.next_frame.scene.insert_primitive(Shadow {
                 order: 0,
                 blur_radius: shadow.blur_radius.scale(scale_factor),
                 bounds: shadow_bounds.scale(scale_factor),
                 content_mask: content_mask.scale(scale_factor),
-                corner_radii: corner_radii.scale(scale_factor),
+                corner_radii: shadow_corner_radii.scale(scale_factor),
                 color: shadow.color.opacity(opacity),
+                clip: 0,
             });
         }
     }
@@ -2943,6 +3157,8 @@ impl Window {
             corner_radii: quad.corner_radii.scale(scale_factor),
             border_widths: quad.border_widths.scale(scale_factor),
             border_style: quad.border_style,
+            clip: 0,
+            snapping: quad.snapping,
         });
     }
 
@@ -2975,10 +3191,19 @@ impl Window {
         self.invalidator.debug_assert_paint();
 
         let scale_factor = self.scale_factor();
-        let height = if style.wavy {
-            style.thickness * 3.
-        } else {
-            style.thickness
+        let pattern = match (style.wavy, style.stroke) {
+            (true, _) => UnderlinePattern::Wavy,
+            (false, UnderlineStroke::Solid) => UnderlinePattern::Solid,
+            (false, UnderlineStroke::Dotted) => UnderlinePattern::Dotted,
+            (false, UnderlineStroke::Dashed) => UnderlinePattern::Dashed,
+            (false, UnderlineStroke::Double) => UnderlinePattern::Double,
+        };
+        // Waves and double lines are drawn over three times the line's thickness.
+        let height = match pattern {
+            UnderlinePattern::Wavy | UnderlinePattern::Double => style.thickness * 3.,
+            UnderlinePattern::Solid | UnderlinePattern::Dotted | UnderlinePattern::Dashed => {
+                style.thickness
+            }
         };
         let bounds = Bounds {
             origin,
@@ -2994,7 +3219,9 @@ impl Window {
             content_mask: content_mask.scale(scale_factor),
             color: style.color.unwrap_or_default().opacity(element_opacity),
             thickness: style.thickness.scale(scale_factor),
-            wavy: if style.wavy { 1 } else { 0 },
+            pattern,
+            clip: 0,
+            snapping: PixelSnapping::NONE,
         });
     }
 
@@ -3025,7 +3252,9 @@ impl Window {
             content_mask: content_mask.scale(scale_factor),
             thickness: style.thickness.scale(scale_factor),
             color: style.color.unwrap_or_default().opacity(opacity),
-            wavy: 0,
+            pattern: UnderlinePattern::Solid,
+            clip: 0,
+            snapping: PixelSnapping::NONE,
         });
     }
 
@@ -3066,31 +3295,253 @@ impl Window {
 
         let raster_bounds = self.text_system().raster_bounds(&params)?;
         if !raster_bounds.is_zero() {
+            let origin = glyph_origin.map(|px| px.floor()) + raster_bounds.origin.map(Into::into);
+            let content_mask = self.content_mask().scale(scale_factor);
+            let color = color.opacity(element_opacity);
             let tile = self
                 .sprite_atlas
                 .get_or_insert_with(&params.clone().into(), &mut || {
                     let (size, bytes) = self.text_system().rasterize_glyph(&params)?;
                     Ok(Some((size, Cow::Owned(bytes))))
-                })?
-                .expect("Callback above only errors or returns Some");
+                });
+            let tile = match tile {
+                Ok(tile) => tile.expect("Callback above only errors or returns Some"),
+                // A glyph that can't be rasterized or added to the atlas is drawn as a box so the
+                // rest of its line is still painted. When the atlas ran out of memory it's evicted
+                // after the frame and the frame redrawn.
+                Err(error) => {
+                    log::error!(
+                        "failed to paint glyph {glyph_id:?}, drawing a box instead: {error}"
+                    );
+                    self.next_frame.scene.insert_primitive(Quad {
+                        order: 0,
+                        bounds: Bounds {
+                            origin,
+                            size: raster_bounds.size.map(Into::into),
+                        },
+                        content_mask,
+                        background: transparent_black().into(),
+                        border_color: color,
+                        corner_radii: Corners::default(),
+                        border_widths: Edges::all(ScaledPixels(1.)),
+                        border_style: BorderStyle::Solid,
+                        clip: 0,
+                        snapping: PixelSnapping::NONE,
+                    });
+                    return Ok(());
+                }
+            };
             let bounds = Bounds {
-                origin: glyph_origin.map(|px| px.floor()) + raster_bounds.origin.map(Into::into),
+                origin,
                 size: tile.bounds.size.map(Into::into),
             };
-            let content_mask = self.content_mask().scale(scale_factor);
             self.next_frame.scene.insert_primitive(MonochromeSprite {
                 order: 0,
                 pad: 0,
                 bounds,
                 content_mask,
-                color: color.opacity(element_opacity),
+                color,
                 tile,
                 transformation: TransformationMatrix::unit(),
+                clip: 0,
             });
         }
         Ok(())
     }
 
+    /// Paints a grid of cells into the scene for the next frame at the current z-index. The
+    /// backgrounds and glyphs of all the cells are drawn with a single draw call, instead of a
+    /// quad and a sprite for each cell, which keeps grids of thousands of cells such as an
+    /// embedded terminal cheap to draw.
+    ///
+    /// This method should only be called as part of the paint phase of element drawing.
+    pub fn paint_cell_grid(&mut self, grid: PaintCellGrid) -> Result<()> {
+        self.invalidator.debug_assert_paint();
+
+        if grid.cells.is_empty() {
+            return Ok(());
+        }
+        let element_opacity = self.element_opacity();
+        let scale_factor = self.scale_factor();
+        let origin = grid.origin.scale(scale_factor);
+        let cell_size = grid.cell_size.scale(scale_factor);
+        let columns = grid.columns.max(1);
+        let rows = grid.cells.len().div_ceil(columns);
+        let glyph_origin = point(ScaledPixels(0.), grid.baseline.scale(scale_factor).floor());
+
+        let mut texture_id = None;
+        let mut glyphs = Vec::with_capacity(grid.glyphs.len());
+        // the glyphs that landed in another atlas texture than the first one, which are painted
+        // as sprites instead, since the grid samples a single texture
+        let mut stray_glyphs = FxHashMap::default();
+        for (index, glyph_id) in grid.glyphs.iter().enumerate() {
+            let params = RenderGlyphParams {
+                font_id: grid.font_id,
+                glyph_id: *glyph_id,
+                font_size: grid.font_size,
+                subpixel_variant: Point::default(),
+                scale_factor,
+                is_emoji: false,
+            };
+            let raster_bounds = self.text_system().raster_bounds(&params)?;
+            if raster_bounds.is_zero() {
+                glyphs.push(CellGlyph::default());
+                continue;
+            }
+            let tile = self
+                .sprite_atlas
+                .get_or_insert_with(&params.clone().into(), &mut || {
+                    let (size, bytes) = self.text_system().rasterize_glyph(&params)?;
+                    Ok(Some((size, Cow::Owned(bytes))))
+                })?;
+            let Some(tile) = tile else {
+                glyphs.push(CellGlyph::default());
+                continue;
+            };
+            let bounds = Bounds {
+                origin: glyph_origin + raster_bounds.origin.map(Into::into),
+                size: tile.bounds.size.map(Into::into),
+            };
+            match texture_id {
+                Some(texture_id) if texture_id != tile.texture_id => {
+                    stray_glyphs.insert(index as u32, (tile, bounds));
+                    glyphs.push(CellGlyph::default());
+                }
+                _ => {
+                    texture_id = Some(tile.texture_id);
+                    glyphs.push(CellGlyph {
+                        bounds,
+                        tile_bounds: tile.bounds,
+                    });
+                }
+            }
+        }
+
+        let content_mask = self.content_mask().scale(scale_factor);
+        self.next_frame.scene.insert_primitive(CellGrid {
+            order: 0,
+            clip: 0,
+            bounds: Bounds {
+                origin,
+                size: size(
+                    cell_size.width * columns as f32,
+                    cell_size.height * rows as f32,
+                ),
+            },
+            content_mask: content_mask.clone(),
+            cell_size,
+            columns: columns as u32,
+            opacity: element_opacity,
+            texture_id,
+            glyphs,
+            cells: grid.cells.clone(),
+        });
+
+        if !stray_glyphs.is_empty() {
+            for (index, cell) in grid.cells.iter().enumerate() {
+                let Some((tile, bounds)) = cell.glyph.and_then(|glyph| stray_glyphs.get(&glyph))
+                else {
+                    continue;
+                };
+                let cell_origin = point(
+                    origin.x + cell_size.width * (index % columns) as f32,
+                    origin.y + cell_size.height * (index / columns) as f32,
+                );
+                self.next_frame.scene.insert_primitive(MonochromeSprite {
+                    order: 0,
+                    pad: 0,
+                    bounds: Bounds {
+                        origin: cell_origin.map(|px| px.floor()) + bounds.origin,
+                        size: bounds.size,
+                    },
+                    content_mask: content_mask.clone(),
+                    color: cell.foreground.opacity(element_opacity),
+                    tile: tile.clone(),
+                    transformation: TransformationMatrix::unit(),
+                    clip: 0,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Paints a selection highlight into the scene for the next frame at the current z-index. All
+    /// of its lines are a single primitive, which is expanded into a rectangle for each line when
+    /// it's drawn, instead of a quad for each line.
+    ///
+    /// This method should only be called as part of the paint phase of element drawing.
+    pub fn paint_selection(&mut self, selection: PaintSelection) {
+        self.invalidator.debug_assert_paint();
+
+        let scale_factor = self.scale_factor();
+        let lines = selection
+            .lines
+            .iter()
+            .map(|line| line.start_x.scale(scale_factor)..line.end_x.scale(scale_factor))
+            .collect::<Vec<_>>();
+        let (Some(left), Some(right)) = (
+            lines.iter().map(|line| line.start).min(),
+            lines.iter().map(|line| line.end).max(),
+        ) else {
+            return;
+        };
+        let line_height = selection.line_height.scale(scale_factor);
+        let content_mask = self.content_mask().scale(scale_factor);
+        self.next_frame.scene.insert_primitive(Selection {
+            order: 0,
+            clip: 0,
+            bounds: Bounds {
+                origin: point(left, selection.start_y.scale(scale_factor)),
+                size: size(right - left, line_height * lines.len() as f32),
+            },
+            content_mask,
+            color: selection.color.opacity(self.element_opacity()),
+            line_height,
+            corner_radius: selection.corner_radius.scale(scale_factor),
+            lines,
+        });
+    }
+
+    /// Rasterizes the glyphs of `layout` into the sprite atlas at `scale_factor` ahead of time.
+    ///
+    /// Glyphs are rasterized separately for each scale factor, so calling this before the window
+    /// moves to a display with a different scale factor avoids a frame of missing or blurry text
+    /// while the glyphs are rasterized for it.
+    pub fn prewarm_glyphs(&self, layout: &LineLayout, scale_factor: f32) -> Result<()> {
+        for run in &layout.runs {
+            for glyph in &run.glyphs {
+                // Emojis are rendered without subpixel variants.
+                let subpixel_variants = if glyph.is_emoji {
+                    vec![Point::default()]
+                } else {
+                    (0..SUBPIXEL_VARIANTS_X)
+                        .flat_map(|x| (0..SUBPIXEL_VARIANTS_Y).map(move |y| point(x, y)))
+                        .collect()
+                };
+
+                for subpixel_variant in subpixel_variants {
+                    let params = RenderGlyphParams {
+                        font_id: run.font_id,
+                        glyph_id: glyph.id,
+                        font_size: layout.font_size,
+                        subpixel_variant,
+                        scale_factor,
+                        is_emoji: glyph.is_emoji,
+                    };
+                    if self.text_system().raster_bounds(&params)?.is_zero() {
+                        continue;
+                    }
+                    self.sprite_atlas
+                        .get_or_insert_with(&params.clone().into(), &mut || {
+                            let (size, bytes) = self.text_system().rasterize_glyph(&params)?;
+                            Ok(Some((size, Cow::Owned(bytes))))
+                        })?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Paints an emoji glyph into the scene for the next frame at the current z-index.
     ///
     /// The y component of the origin is the baseline of the glyph.
@@ -3146,6 +3597,10 @@ impl Window {
                 content_mask,
                 tile,
                 opacity,
+                clip: 0,
+                premultiplied: false,
+                filter: ImageFilter::Linear,
+                tint: None,
             });
         }
         Ok(())
@@ -3211,6 +3666,7 @@ impl Window {
             color: color.opacity(element_opacity),
             tile,
             transformation,
+            clip: 0,
         });
 
         Ok(())
@@ -3227,19 +3683,63 @@ impl Window {
         data: Arc<RenderImage>,
         frame_index: usize,
         grayscale: bool,
+    ) -> Result<()> {
+        self.paint_image_with_options(
+            bounds,
+            corner_radii,
+            data,
+            frame_index,
+            ImageOptions {
+                grayscale,
+                ..ImageOptions::default()
+            },
+        )
+    }
+
+    /// Paint an image into the scene for the next frame at the current z-index, sampled and
+    /// colored as `options` describes. This method will panic if the frame_index is not valid
+    ///
+    /// This method should only be called as part of the paint phase of element drawing.
+    pub fn paint_image_with_options(
+        &mut self,
+        bounds: Bounds<Pixels>,
+        corner_radii: Corners<Pixels>,
+        data: Arc<RenderImage>,
+        frame_index: usize,
+        options: ImageOptions,
     ) -> Result<()> {
         self.invalidator.debug_assert_paint();
 
         let scale_factor = self.scale_factor();
         let bounds = bounds.scale(scale_factor);
+        let mip_level = if options.mipmaps {
+            let image_size = data.size(frame_index);
+            // The level is the number of halvings that keep the image at least as large as it's
+            // drawn, so it's still minified, but by less than half.
+            let minification = (image_size.width.0 as f32 / bounds.size.width.0)
+                .min(image_size.height.0 as f32 / bounds.size.height.0);
+            match minification.log2().floor() {
+                levels if levels >= 1.0 => (levels as u32).min(data.max_mip_level(frame_index)),
+                _ => 0,
+            }
+        } else {
+            0
+        };
         let params = RenderImageParams {
             image_id: data.id,
             frame_index,
+            mip_level,
         };
 
         let tile = self
             .sprite_atlas
             .get_or_insert_with(&params.into(), &mut || {
+                if mip_level > 0 {
+                    let (size, bytes) = data
+                        .mip_level(frame_index, mip_level)
+                        .expect("It's the caller's job to pass a valid frame index");
+                    return Ok(Some((size, Cow::Owned(bytes))));
+                }
                 Ok(Some((
                     data.size(frame_index),
                     Cow::Borrowed(
@@ -3256,7 +3756,7 @@ impl Window {
         self.next_frame.scene.insert_primitive(PolychromeSprite {
             order: 0,
             pad: 0,
-            grayscale,
+            grayscale: options.grayscale,
             bounds: bounds
                 .map_origin(|origin| origin.floor())
                 .map_size(|size| size.ceil()),
@@ -3264,10 +3764,183 @@ impl Window {
             corner_radii,
             tile,
             opacity,
+            clip: 0,
+            premultiplied: false,
+            filter: options.filter,
+            tint: options.tint,
         });
         Ok(())
     }
 
+    /// Paint what `paint` draws within `bounds` into a texture, and composite the texture into the
+    /// scene for the next frame at the current z-index. The texture is reused without calling
+    /// `paint` again until the layer is painted with another `version` or size, which suits
+    /// static content that's expensive to draw. Whatever `paint` registers, such as mouse
+    /// listeners, is only registered on the frames the layer is painted.
+    ///
+    /// This method should only be called as part of the paint phase of element drawing.
+    pub fn paint_cached_layer(
+        &mut self,
+        layer_id: u64,
+        version: u64,
+        bounds: Bounds<Pixels>,
+        paint: impl FnOnce(&mut Self),
+    ) -> Result<()> {
+        self.invalidator.debug_assert_paint();
+
+        let Some((scaled_bounds, tile_size)) = self.layer_bounds(bounds) else {
+            return Ok(());
+        };
+        let params = RenderLayerParams {
+            layer_id: LayerId::Cached(layer_id),
+            version,
+            size: tile_size,
+        };
+        match self.cached_layers.insert(layer_id, params.clone()) {
+            Some(previous) if previous != params => self.sprite_atlas.remove(&previous.into()),
+            _ => {}
+        }
+
+        let mut is_new = false;
+        let tile = self
+            .sprite_atlas
+            .get_or_insert_with(&params.into(), &mut || {
+                is_new = true;
+                Ok(Some((tile_size, transparent_pixels(tile_size))))
+            })?
+            .expect("Callback above only returns Some");
+
+        if is_new {
+            let mut scene = self.paint_layer_scene(bounds, paint);
+            let nested_layers = mem::take(&mut scene.retained_layers);
+            self.next_frame.scene.retain_layers(nested_layers);
+            self.next_frame.scene.layer_renders.push(LayerRender {
+                tile: tile.clone(),
+                source: scaled_bounds,
+                scene: Arc::new(scene),
+            });
+        }
+
+        let sprite = self.layer_sprite(scaled_bounds, tile, self.element_opacity());
+        self.next_frame.scene.insert_primitive(sprite);
+        Ok(())
+    }
+
+    /// Paint what `paint` draws within `bounds` into a texture, and composite the texture into the
+    /// scene for the next frame at the current z-index with `opacity`. Unlike
+    /// [`Window::with_element_opacity`], which fades each primitive on its own, overlapping
+    /// children don't show through each other.
+    ///
+    /// This method should only be called as part of the paint phase of element drawing.
+    pub fn paint_opacity_group(
+        &mut self,
+        bounds: Bounds<Pixels>,
+        opacity: f32,
+        paint: impl FnOnce(&mut Self),
+    ) -> Result<()> {
+        self.invalidator.debug_assert_paint();
+
+        let Some((scaled_bounds, tile_size)) = self.layer_bounds(bounds) else {
+            return Ok(());
+        };
+        let params = RenderLayerParams {
+            layer_id: LayerId::OpacityGroup(self.next_opacity_group_id),
+            version: 0,
+            size: tile_size,
+        };
+        self.next_opacity_group_id += 1;
+        let tile = self
+            .sprite_atlas
+            .get_or_insert_with(&params.clone().into(), &mut || {
+                Ok(Some((tile_size, transparent_pixels(tile_size))))
+            })?
+            .expect("Callback above only returns Some");
+
+        let mut scene = self.paint_layer_scene(bounds, paint);
+        let mut layers = mem::take(&mut scene.retained_layers);
+        layers.push(params);
+        let sprite = self.layer_sprite(
+            scaled_bounds,
+            tile.clone(),
+            opacity * self.element_opacity(),
+        );
+        self.next_frame.scene.insert_opacity_group(OpacityGroup {
+            layers,
+            render: LayerRender {
+                tile,
+                source: scaled_bounds,
+                scene: Arc::new(scene),
+            },
+            sprite,
+        });
+        Ok(())
+    }
+
+    /// The device pixels a layer covering `bounds` is drawn into, and the size of its tile, or
+    /// None when it's empty.
+    fn layer_bounds(
+        &self,
+        bounds: Bounds<Pixels>,
+    ) -> Option<(Bounds<ScaledPixels>, Size<DevicePixels>)> {
+        let scaled_bounds = bounds
+            .scale(self.scale_factor())
+            .map_origin(|origin| origin.floor())
+            .map_size(|size| size.ceil());
+        let tile_size = size(
+            DevicePixels::from(scaled_bounds.size.width),
+            DevicePixels::from(scaled_bounds.size.height),
+        );
+        (tile_size.width.0 > 0 && tile_size.height.0 > 0).then_some((scaled_bounds, tile_size))
+    }
+
+    /// Paints what `paint` draws within `bounds` into a scene of its own, to be drawn into a
+    /// layer. The layers nested in it are drawn before the scene for the next frame.
+    fn paint_layer_scene(
+        &mut self,
+        bounds: Bounds<Pixels>,
+        paint: impl FnOnce(&mut Self),
+    ) -> Scene {
+        let mut scene = mem::take(&mut self.next_frame.scene);
+        // The layer is drawn on its own, so it's only masked to its bounds, and the opacity of its
+        // ancestors is applied when it's composited.
+        self.content_mask_stack.push(ContentMask { bounds });
+        let opacity = mem::replace(&mut self.element_opacity, 1.0);
+        paint(self);
+        self.element_opacity = opacity;
+        self.content_mask_stack.pop();
+        mem::swap(&mut self.next_frame.scene, &mut scene);
+
+        scene.finish();
+        // Nested layers are drawn before the layers they're composited into.
+        self.next_frame
+            .scene
+            .layer_renders
+            .append(&mut scene.layer_renders);
+        scene
+    }
+
+    fn layer_sprite(
+        &self,
+        bounds: Bounds<ScaledPixels>,
+        tile: crate::AtlasTile,
+        opacity: f32,
+    ) -> PolychromeSprite {
+        PolychromeSprite {
+            order: 0,
+            pad: 0,
+            grayscale: false,
+            bounds,
+            content_mask: self.content_mask().scale(self.scale_factor()),
+            corner_radii: Corners::default(),
+            tile,
+            opacity,
+            clip: 0,
+            premultiplied: true,
+            filter: ImageFilter::Linear,
+            tint: None,
+        }
+    }
+
     /// Paint a macOS CoreVideo surface into the scene for the next frame at the current z-index.
     ///
     /// This method should only be called as part of the paint phase of element drawing.
@@ -3285,6 +3958,7 @@ impl Window {
             bounds,
             content_mask,
             content: SurfaceContent::CoreVideo(image_buffer),
+            clip: 0,
         });
     }
 
@@ -3311,10 +3985,36 @@ impl Window {
                 bounds,
                 content_mask,
                 content: SurfaceContent::Wgpu(surface_id),
+                clip: 0,
             });
         }
     }
 
+    /// Paints a primitive drawn by the render pipeline plugin `P` into the scene for the next
+    /// frame, which the plugin reads `data` back from with [`PluginPrimitive::data`]. Nothing is
+    /// drawn unless `P` has been registered with [`App::register_render_pipeline_plugin`].
+    ///
+    /// This method should only be called as part of the paint phase of element drawing.
+    pub fn paint_plugin_primitive<P: RenderPipelinePlugin>(
+        &mut self,
+        bounds: Bounds<Pixels>,
+        data: impl Any + Send + Sync,
+    ) {
+        self.invalidator.debug_assert_paint();
+
+        let scale_factor = self.scale_factor();
+        let bounds = bounds.scale(scale_factor);
+        let content_mask = self.content_mask().scale(scale_factor);
+        self.next_frame.scene.insert_primitive(PluginPrimitive {
+            order: 0,
+            clip: 0,
+            plugin: TypeId::of::<P>(),
+            bounds,
+            content_mask,
+            data: Arc::new(data),
+        });
+    }
+
     /// Create a double-buffered WGPU surface handle for external GPU rendering.
     ///
     /// Returns `None` on platforms that don't use the WGPU renderer.
@@ -3327,23 +4027,160 @@ impl Window {
         height: u32,
         format: wgpu::TextureFormat,
     ) -> Option<crate::WgpuSurfaceHandle> {
-        self.platform_window.create_wgpu_surface(width, height, format)
+        let surface = self
+            .platform_window
+            .create_wgpu_surface(width, height, format)?;
+        surface.set_owner(self.handle);
+        Some(surface)
+    }
+
+    /// Create a WGPU surface handle like [`Window::create_wgpu_surface`], with a name it can be
+    /// found by with [`Window::find_wgpu_surface`] and is shown with in the surface viewer, see
+    /// [`Window::toggle_surface_viewer`].
+    pub fn create_named_wgpu_surface(
+        &self,
+        name: impl Into<SharedString>,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> Option<crate::WgpuSurfaceHandle> {
+        let surface = self.create_wgpu_surface(width, height, format)?;
+        surface.set_name(name.into());
+        Some(surface)
+    }
+
+    /// Describes the live WGPU surfaces on this window's device, including those created for
+    /// other windows, in the order they were created.
+    pub fn wgpu_surfaces(&self) -> Vec<crate::SurfaceInfo> {
+        self.platform_window.wgpu_surfaces()
+    }
+
+    /// Describes the first live WGPU surface on this window's device that was created with
+    /// `name`, see [`Window::create_named_wgpu_surface`].
+    pub fn find_wgpu_surface(&self, name: &str) -> Option<crate::SurfaceInfo> {
+        self.platform_window.find_wgpu_surface(name)
+    }
+
+    /// Returns the native handle of this window, for handing the window to native libraries such
+    /// as OpenXR runtimes, video overlays or native menus.
+    ///
+    /// The handle stays valid while the window is open. Fails if the platform can't provide a
+    /// handle right now, for example while an Android app is suspended, or if the window is
+    /// headless.
+    pub fn raw_window_handle(&self) -> Result<RawWindowHandle, HandleError> {
+        Ok(HasWindowHandle::window_handle(self)?.as_raw())
+    }
+
+    /// Returns the native handle of the display connection this window belongs to, such as the
+    /// X11 or Wayland display, to pair with [`Window::raw_window_handle`].
+    pub fn raw_display_handle(&self) -> Result<RawDisplayHandle, HandleError> {
+        Ok(self.display_handle()?.as_raw())
+    }
+
+    /// Paints an entire sprite atlas texture, stretched to the given bounds.
+    ///
+    /// This is used by the atlas viewer and should only be called as part of the paint phase.
+    pub(crate) fn paint_atlas_texture(
+        &mut self,
+        bounds: Bounds<Pixels>,
+        snapshot: &crate::AtlasTextureSnapshot,
+    ) {
+        self.invalidator.debug_assert_paint();
+
+        let scale_factor = self.scale_factor();
+        let bounds = bounds.scale(scale_factor);
+        let content_mask = self.content_mask().scale(scale_factor);
+        let tile = crate::AtlasTile {
+            texture_id: snapshot.id,
+            tile_id: crate::TileId(0),
+            padding: 0,
+            bounds: Bounds {
+                origin: Point::default(),
+                size: snapshot.size,
+            },
+        };
+
+        match snapshot.id.kind {
+            crate::AtlasTextureKind::Monochrome => {
+                self.next_frame.scene.insert_primitive(MonochromeSprite {
+                    order: 0,
+                    pad: 0,
+                    bounds,
+                    content_mask,
+                    color: crate::white(),
+                    tile,
+                    transformation: TransformationMatrix::unit(),
+                    clip: 0,
+                });
+            }
+            crate::AtlasTextureKind::Polychrome => {
+                self.next_frame.scene.insert_primitive(PolychromeSprite {
+                    order: 0,
+                    pad: 0,
+                    grayscale: false,
+                    opacity: 1.0,
+                    bounds,
+                    content_mask,
+                    corner_radii: Corners::default(),
+                    tile,
+                    clip: 0,
+                    premultiplied: false,
+                    filter: ImageFilter::Linear,
+                    tint: None,
+                });
+            }
+        }
+    }
+
+    /// Returns the allocation state of this window's sprite atlas textures.
+    pub(crate) fn atlas_texture_snapshots(&self) -> Vec<crate::AtlasTextureSnapshot> {
+        self.sprite_atlas.texture_snapshots()
+    }
+
+    /// Returns how full and fragmented this window's sprite atlas is, to help choose an
+    /// [`AtlasAllocationStrategy`](crate::AtlasAllocationStrategy) for the app's mix of glyphs
+    /// and images.
+    pub fn atlas_stats(&self) -> crate::AtlasStats {
+        self.sprite_atlas.allocation_stats()
+    }
+
+    /// Saves the last frame this window drew to `path`, along with the atlas textures it samples
+    /// from. [`replay_scene`](crate::replay_scene) draws it again without the app, so it can be
+    /// attached to bug reports about rendering artifacts.
+    pub fn save_scene(&self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        let path = path.as_ref();
+        let viewport_size = self.viewport_size.to_device_pixels(self.scale_factor);
+        let bytes = self
+            .rendered_frame
+            .scene
+            .serialize(viewport_size, self.sprite_atlas.as_ref())?;
+        std::fs::write(path, bytes).with_context(|| format!("failed to write {}", path.display()))
     }
 
     /// Removes an image from the sprite atlas.
     pub fn drop_image(&mut self, data: Arc<RenderImage>) -> Result<()> {
         for frame_index in 0..data.frame_count() {
-            let params = RenderImageParams {
-                image_id: data.id,
-                frame_index,
-            };
+            for mip_level in 0..=data.max_mip_level(frame_index) {
+                let params = RenderImageParams {
+                    image_id: data.id,
+                    frame_index,
+                    mip_level,
+                };
 
-            self.sprite_atlas.remove(&params.clone().into());
+                self.sprite_atlas.remove(&params.clone().into());
+            }
         }
 
         Ok(())
     }
 
+    /// Removes a cached layer's contents from the sprite atlas.
+    pub fn drop_cached_layer(&mut self, layer_id: u64) {
+        if let Some(params) = self.cached_layers.remove(&layer_id) {
+            self.sprite_atlas.remove(&params.into());
+        }
+    }
+
     /// Add a node to the layout tree for the current frame. Takes the `Style` of the element for which
     /// layout is being requested, along with the layout ids of any children. This method is called during
     /// calls to the [`Element::request_layout`] trait method and enables any element to participate in layout.
@@ -3761,6 +4598,21 @@ impl Window {
                 self.modifiers = scroll_wheel.modifiers;
                 PlatformInput::ScrollWheel(scroll_wheel)
             }
+            PlatformInput::Pinch(pinch) => {
+                self.mouse_position = pinch.position;
+                self.modifiers = pinch.modifiers;
+                PlatformInput::Pinch(pinch)
+            }
+            PlatformInput::Rotation(rotation) => {
+                self.mouse_position = rotation.position;
+                self.modifiers = rotation.modifiers;
+                PlatformInput::Rotation(rotation)
+            }
+            PlatformInput::PrimaryPaste(primary_paste) => {
+                self.mouse_position = primary_paste.position;
+                self.modifiers = primary_paste.modifiers;
+                PlatformInput::PrimaryPaste(primary_paste)
+            }
             // Translate dragging and dropping of external files from the operating system
             // to internal drag and drop events.
             PlatformInput::FileDrop(file_drop) => match file_drop {
@@ -4520,6 +5372,47 @@ impl Window {
         }))
     }
 
+    /// Register a callback to be invoked once the window's first frame has been presented, such as
+    /// to start work that shouldn't delay the window appearing.
+    pub fn on_first_frame(&self, cx: &App, f: impl FnOnce(&mut Window, &mut App) + 'static) {
+        let mut cx = self.to_async(cx);
+        self.platform_window.on_first_frame(Box::new(move || {
+            cx.update(|window, cx| f(window, cx)).log_err();
+        }))
+    }
+
+    /// Register a callback to be invoked when a frame fails to draw, such as when the window's
+    /// surface is lost, so the app can tell the user, retry or fall back to drawing less. The
+    /// failed frame is skipped and the window redrawn. Without a callback, the error is logged.
+    pub fn on_renderer_error(
+        &self,
+        cx: &App,
+        mut f: impl FnMut(RendererError, &mut Window, &mut App) + 'static,
+    ) {
+        let mut cx = self.to_async(cx);
+        self.platform_window
+            .on_renderer_error(Box::new(move |error| {
+                cx.update(|window, cx| f(error, window, cx)).log_err();
+            }))
+    }
+
+    /// Register a callback to be invoked when the renderer starts or stops being overloaded, with
+    /// whether it is. The renderer is overloaded while its frames take longer to draw than the
+    /// display takes to refresh, and it skips frames to draw only the latest state until it
+    /// keeps up again, which the app can help with by reducing its workload, such as pausing
+    /// animations.
+    pub fn on_frame_overload(
+        &self,
+        cx: &App,
+        mut f: impl FnMut(bool, &mut Window, &mut App) + 'static,
+    ) {
+        let mut cx = self.to_async(cx);
+        self.platform_window
+            .on_frame_overload(Box::new(move |overloaded| {
+                cx.update(|window, cx| f(overloaded, window, cx)).log_err();
+            }))
+    }
+
     /// Register an action listener on this node for the next frame. The type of action
     /// is determined by the first parameter of the given listener. When the next frame is rendered
     /// the listener will be cleared.
@@ -4618,6 +5511,119 @@ impl Window {
             .set_tabbing_identifier(tabbing_identifier)
     }
 
+    /// Toggles the atlas viewer overlay, which shows the textures in this window's sprite atlas
+    /// along with their tile allocations.
+    pub fn toggle_atlas_viewer(&mut self) {
+        self.atlas_viewer_visible = !self.atlas_viewer_visible;
+        self.refresh();
+    }
+
+    /// Returns true if the atlas viewer overlay is visible.
+    pub fn is_atlas_viewer_visible(&self) -> bool {
+        self.atlas_viewer_visible
+    }
+
+    /// Enables or disables recording of the draw calls the renderer issues for each frame.
+    /// Disabling recording discards the last recorded frame.
+    pub fn set_draw_call_recording(&self, enabled: bool) {
+        self.platform_window.set_draw_call_recording(enabled)
+    }
+
+    /// Returns the draw calls issued for the most recently rendered frame, if draw call
+    /// recording is enabled and supported by the renderer.
+    pub fn last_frame_draw_calls(&self) -> Option<FrameDrawCalls> {
+        self.platform_window.last_frame_draw_calls()
+    }
+
+    /// Returns how the window's frames are reaching the display as of the last frame it
+    /// presented: the time between its last two frames, the display refreshes its frames missed
+    /// and the display's refresh interval. Returns `None` on platforms that don't track it.
+    pub fn present_stats(&self) -> Option<PresentStats> {
+        self.platform_window.present_stats()
+    }
+
+    /// Starts recording the frames this window presents to an image sequence or an encoder
+    /// process, replacing any recording already in progress. Fails if the renderer can't read
+    /// back frames or the output can't be opened.
+    pub fn start_frame_recording(&self, recording: FrameRecording) -> Result<()> {
+        self.platform_window.start_frame_recording(recording)
+    }
+
+    /// Stops recording frames, waiting for the frames already captured to be written.
+    pub fn stop_frame_recording(&self) {
+        self.platform_window.stop_frame_recording()
+    }
+
+    /// Draws the `viewport.source` region of the most recently rendered frame into
+    /// `viewport.destination` of `target`, for magnifier lenses, minimaps or picture-in-picture
+    /// previews of this window that don't rebuild its scene. The target must be a render
+    /// attachment on the window's device with the format returned by
+    /// [`Window::xr_swapchain_format`]. The destination isn't cleared first.
+    pub fn draw_region(&self, viewport: SceneViewport, target: &wgpu::Texture) -> Result<()> {
+        self.platform_window
+            .draw_region(&self.rendered_frame.scene, viewport, target)
+    }
+
+    /// Draws the `viewport.source` region of the most recently rendered frame into
+    /// `viewport.destination` of the `target_size` texture `target` views, like
+    /// [`Window::draw_region`], for targets only available as views, such as the frames of a host
+    /// GPUI is embedded in. The view must have the format returned by
+    /// [`Window::xr_swapchain_format`].
+    pub fn draw_region_to_view(
+        &self,
+        viewport: SceneViewport,
+        target: &wgpu::TextureView,
+        target_size: Size<DevicePixels>,
+    ) -> Result<()> {
+        self.platform_window.draw_region_to_view(
+            &self.rendered_frame.scene,
+            viewport,
+            target,
+            target_size,
+        )
+    }
+
+    /// Returns the format an OpenXR swapchain has to be created with for
+    /// [`Window::draw_to_xr_swapchain`] to draw into it, or `None` if the renderer can't draw to
+    /// OpenXR swapchains.
+    pub fn xr_swapchain_format(&self) -> Option<wgpu::TextureFormat> {
+        self.platform_window.xr_swapchain_format()
+    }
+
+    /// Draws the most recently rendered frame of this window into each eye of an OpenXR
+    /// swapchain image, for showing the window as a 2D panel in a headset. Call it once per XR
+    /// frame, between acquiring and releasing the swapchain image. The window's own surface is
+    /// left untouched.
+    pub fn draw_to_xr_swapchain(&self, target: &XrSwapchainTarget) -> Result<()> {
+        self.platform_window
+            .draw_to_xr_swapchain(&self.rendered_frame.scene, target)
+    }
+
+    /// Toggles the draw call viewer overlay, which lists the draw calls issued for the previous
+    /// frame. Draw call recording is enabled while the overlay is visible.
+    pub fn toggle_draw_call_viewer(&mut self) {
+        self.draw_call_viewer_visible = !self.draw_call_viewer_visible;
+        self.set_draw_call_recording(self.draw_call_viewer_visible);
+        self.refresh();
+    }
+
+    /// Returns true if the draw call viewer overlay is visible.
+    pub fn is_draw_call_viewer_visible(&self) -> bool {
+        self.draw_call_viewer_visible
+    }
+
+    /// Toggles the surface viewer overlay, which lists the live WGPU surfaces with their size,
+    /// format, frame rate and owner.
+    pub fn toggle_surface_viewer(&mut self) {
+        self.surface_viewer_visible = !self.surface_viewer_visible;
+        self.refresh();
+    }
+
+    /// Returns true if the surface viewer overlay is visible.
+    pub fn is_surface_viewer_visible(&self) -> bool {
+        self.surface_viewer_visible
+    }
+
     /// Toggles the inspector mode on this window.
     #[cfg(any(feature = "inspector", debug_assertions))]
     pub fn toggle_inspector(&mut self, cx: &mut App) {
@@ -5206,6 +6212,22 @@ impl From<&'static core::panic::Location<'static>> for ElementId {
     }
 }
 
+/// How an image painted with [`Window::paint_image_with_options`] is sampled and colored.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ImageOptions {
+    /// Whether the image is drawn in grayscale.
+    pub grayscale: bool,
+    /// A color the image's colors are multiplied by, after it's made grayscale. White leaves
+    /// the image as it is, and a translucent tint dims it along with its opacity.
+    pub tint: Option<Hsla>,
+    /// How the image's pixels are filtered.
+    pub filter: ImageFilter,
+    /// Whether the image is drawn from a downscaled copy when it's drawn at less than half its
+    /// size, which keeps it from shimmering. The copies are made on the CPU the first time
+    /// they're needed and kept in the sprite atlas with the image.
+    pub mipmaps: bool,
+}
+
 /// A rectangle to be rendered in the window at the given position and size.
 /// Passed as an argument [`Window::paint_quad`].
 #[derive(Clone)]
@@ -5222,6 +6244,8 @@ pub struct PaintQuad {
     pub border_color: Hsla,
     /// The style of the quad's borders.
     pub border_style: BorderStyle,
+    /// Which of the quad's edges are aligned to device pixels.
+    pub snapping: PixelSnapping,
 }
 
 impl PaintQuad {
@@ -5256,6 +6280,11 @@ impl PaintQuad {
             ..self
         }
     }
+
+    /// Sets which of the quad's edges are aligned to device pixels.
+    pub fn snapping(self, snapping: PixelSnapping) -> Self {
+        PaintQuad { snapping, ..self }
+    }
 }
 
 /// Creates a quad with the given parameters.
@@ -5274,6 +6303,7 @@ pub fn quad(
         border_widths: border_widths.into(),
         border_color: border_color.into(),
         border_style,
+        snapping: PixelSnapping::NONE,
     }
 }
 
@@ -5286,6 +6316,7 @@ pub fn fill(bounds: impl Into<Bounds<Pixels>>, background: impl Into<Background>
         border_widths: (0.).into(),
         border_color: transparent_black(),
         border_style: BorderStyle::default(),
+        snapping: PixelSnapping::NONE,
     }
 }
 
@@ -5302,5 +6333,65 @@ pub fn outline(
         border_widths: (1.).into(),
         border_color: border_color.into(),
         border_style,
+        snapping: PixelSnapping::NONE,
     }
 }
+
+/// A grid of uniformly sized cells, each with a background and an optional glyph, such as the
+/// screen of a terminal. Passed as an argument to [`Window::paint_cell_grid`].
+#[derive(Clone)]
+pub struct PaintCellGrid {
+    /// The top left corner of the grid within the window.
+    pub origin: Point<Pixels>,
+    /// The size of each cell.
+    pub cell_size: Size<Pixels>,
+    /// The number of cells in each row.
+    pub columns: usize,
+    /// The font the glyphs are rasterized from.
+    pub font_id: FontId,
+    /// The size the glyphs are rasterized at.
+    pub font_size: Pixels,
+    /// The distance from the top of a cell to the baseline of its glyph.
+    pub baseline: Pixels,
+    /// The glyphs the cells draw, which [`GridCell::glyph`] indexes into.
+    pub glyphs: Vec<GlyphId>,
+    /// The cells of the grid, row by row.
+    pub cells: Arc<[GridCell]>,
+}
+
+/// A cell of a [`PaintCellGrid`].
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GridCell {
+    /// The index of the cell's glyph in [`PaintCellGrid::glyphs`], or `None` for a blank cell.
+    pub glyph: Option<u32>,
+    /// The color of the cell's glyph.
+    pub foreground: Hsla,
+    /// The color the cell is filled with behind its glyph.
+    pub background: Hsla,
+}
+
+/// A selection highlight spanning consecutive lines of text, such as an editor's selection.
+/// Passed as an argument to [`Window::paint_selection`].
+#[derive(Clone, Debug)]
+pub struct PaintSelection {
+    /// The top of the first line.
+    pub start_y: Pixels,
+    /// The height of each line.
+    pub line_height: Pixels,
+    /// The extent of the selection on each line, from the first line down.
+    pub lines: Vec<SelectionLine>,
+    /// The color of the selection.
+    pub color: Hsla,
+    /// The radius of the selection's outer corners. Corners where a line meets the line above or
+    /// below it aren't rounded.
+    pub corner_radius: Pixels,
+}
+
+/// The extent of a [`PaintSelection`] on one line.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SelectionLine {
+    /// Where the selection starts on the line.
+    pub start_x: Pixels,
+    /// Where the selection ends on the line.
+    pub end_x: Pixels,
+}